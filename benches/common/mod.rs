@@ -0,0 +1,45 @@
+//! Representative inputs shared by the benchmarks.
+
+/// All zero bytes, like sparse files or padding in firmware images.
+pub fn zeros(len: usize) -> Vec<u8> {
+    vec![0u8; len]
+}
+
+/// Pseudo random bytes, roughly like a compressed or encrypted file.
+pub fn random(len: usize) -> Vec<u8> {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 56) as u8
+        })
+        .collect()
+}
+
+/// Plain ASCII text, where colors change only at spaces and punctuation.
+pub fn text(len: usize) -> Vec<u8> {
+    let text = b"The quick brown fox jumps over the lazy dog. 0123456789\n";
+    text.iter().copied().cycle().take(len).collect()
+}
+
+/// A real executable, uses the benchmark binary itself (an ELF file on Linux).
+///
+/// The content is repeated if the executable is smaller than `len`.
+pub fn executable(len: usize) -> Vec<u8> {
+    let exe = std::env::current_exe()
+        .and_then(std::fs::read)
+        .unwrap_or_else(|_| random(len));
+    exe.iter().copied().cycle().take(len).collect()
+}
+
+/// All inputs with their names, each `len` bytes long.
+pub fn inputs(len: usize) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("zeros", zeros(len)),
+        ("random", random(len)),
+        ("text", text(len)),
+        ("elf", executable(len)),
+    ]
+}
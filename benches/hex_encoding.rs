@@ -0,0 +1,70 @@
+//! Compares the vectorized `LineWriter::write_line` against the scalar reference.
+//!
+//! Run with `cargo bench --bench hex_encoding`.
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hexler::hex_formatter::HexFormatter;
+use hexler::line_writer::LineWriter;
+use hexler::simd::{Backend, BLOCK};
+use std::hint::black_box;
+
+fn bench_write_line(c: &mut Criterion) {
+    const LEN: usize = 64 * 1024;
+    let inputs = common::inputs(LEN);
+
+    let mut group = c.benchmark_group("write_line_simd_vs_scalar");
+    group.throughput(Throughput::Bytes(LEN as u64));
+    for (name, data) in &inputs {
+        let line_writer = LineWriter::new_bytes(32).unwrap();
+        let mut buffer = Vec::with_capacity(LEN * 16);
+
+        group.bench_with_input(BenchmarkId::new("simd", name), data, |b, data| {
+            b.iter(|| {
+                buffer.clear();
+                for (idx, line) in data.chunks(32).enumerate() {
+                    line_writer.write_line(&mut buffer, idx * 32, line);
+                }
+                black_box(buffer.len())
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("scalar", name), data, |b, data| {
+            b.iter(|| {
+                buffer.clear();
+                for (idx, line) in data.chunks(32).enumerate() {
+                    line_writer.write_line_scalar(&mut buffer, idx * 32, line);
+                }
+                black_box(buffer.len())
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_write_hex_bytes(c: &mut Criterion) {
+    const LEN: usize = 64 * 1024;
+    let data = common::random(LEN);
+    let mut out = vec![0u8; BLOCK * 3];
+
+    let mut group = c.benchmark_group("write_hex_bytes");
+    group.throughput(Throughput::Bytes(LEN as u64));
+    for backend in [Backend::Scalar, Backend::Ssse3, Backend::Avx2] {
+        if !backend.is_supported() {
+            continue;
+        }
+        let formatter = HexFormatter::with_backend(backend);
+        group.bench_function(format!("{:?}", backend), |b| {
+            b.iter(|| {
+                for block in data.chunks(BLOCK) {
+                    formatter.write_hex_bytes(block, &mut out[..block.len() * 3]);
+                    black_box(&out);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_write_line, bench_write_hex_bytes);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+//! Throughput of the complete dump pipeline and its hot functions.
+//!
+//! All output goes to a discarding sink, so only formatting is measured.
+//! Run with `cargo bench --bench throughput`.
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hexler::hex_formatter::HexFormatter;
+use hexler::line_writer::LineWriter;
+use std::hint::black_box;
+
+const BYTES_PER_LINE: [usize; 4] = [8, 16, 32, 64];
+
+fn bench_dump(c: &mut Criterion) {
+    const LEN: usize = 4 * 1024 * 1024;
+
+    let mut group = c.benchmark_group("dump");
+    group.throughput(Throughput::Bytes(LEN as u64));
+    group.sample_size(20);
+    for (name, data) in common::inputs(LEN) {
+        for bytes_per_line in BYTES_PER_LINE {
+            let mut line_writer = LineWriter::new_bytes(bytes_per_line).unwrap();
+            let id = BenchmarkId::new(name, bytes_per_line);
+            group.bench_with_input(id, &data, |b, data| {
+                b.iter(|| {
+                    let reader = std::io::Cursor::new(data.as_slice());
+                    hexler::dump("bench", reader, &mut line_writer, std::io::sink()).unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_write_line(c: &mut Criterion) {
+    const LEN: usize = 256 * 1024;
+
+    let mut group = c.benchmark_group("write_line");
+    group.throughput(Throughput::Bytes(LEN as u64));
+    for (name, data) in common::inputs(LEN) {
+        for bytes_per_line in BYTES_PER_LINE {
+            let line_writer = LineWriter::new_bytes(bytes_per_line).unwrap();
+            let mut buffer = Vec::with_capacity(LEN * 16);
+            let id = BenchmarkId::new(name, bytes_per_line);
+            group.bench_with_input(id, &data, |b, data| {
+                b.iter(|| {
+                    buffer.clear();
+                    for (idx, line) in data.chunks(bytes_per_line).enumerate() {
+                        line_writer.write_line(&mut buffer, idx * bytes_per_line, line);
+                    }
+                    black_box(buffer.len())
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_write_offset(c: &mut Criterion) {
+    const NUM_OFFSETS: usize = 64 * 1024;
+
+    let formatter = HexFormatter::new();
+    let mut buffer = Vec::with_capacity(NUM_OFFSETS * 20);
+
+    let mut group = c.benchmark_group("write_offset");
+    group.throughput(Throughput::Elements(NUM_OFFSETS as u64));
+    for bytes_per_line in BYTES_PER_LINE {
+        group.bench_function(BenchmarkId::from_parameter(bytes_per_line), |b| {
+            b.iter(|| {
+                buffer.clear();
+                for line in 0..NUM_OFFSETS {
+                    formatter.write_offset(&mut buffer, line * bytes_per_line);
+                }
+                black_box(buffer.len())
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_dump, bench_write_line, bench_write_offset);
+criterion_main!(benches);
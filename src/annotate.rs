@@ -0,0 +1,278 @@
+use crate::renderer::Renderer;
+
+/// Source of labelled separator rows in the hex dump, e.g. signatures found in the data.
+///
+/// Like `Renderer::write_line_in_context`, `annotate` is called concurrently for lines in any
+/// order, so it must not depend on any state.
+pub trait Annotator: Sync {
+    /// Number of bytes before and after each line that `annotate` needs to see.
+    fn context_len(&self) -> usize {
+        0
+    }
+
+    /// Appends the rows shown in front of the line `window[line]`, which starts at
+    /// `byte_offset`. Each row is the offset it refers to and its label.
+    fn annotate(
+        &self,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+        rows: &mut Vec<(usize, String)>,
+    );
+}
+
+impl<A: Annotator> Annotator for Option<A> {
+    fn context_len(&self) -> usize {
+        self.as_ref().map_or(0, A::context_len)
+    }
+
+    fn annotate(
+        &self,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+        rows: &mut Vec<(usize, String)>,
+    ) {
+        if let Some(annotator) = self {
+            annotator.annotate(byte_offset, window, line, rows);
+        }
+    }
+}
+
+/// Combines the rows of two annotators, sorted by their offsets.
+impl<A: Annotator, B: Annotator> Annotator for (A, B) {
+    fn context_len(&self) -> usize {
+        self.0.context_len().max(self.1.context_len())
+    }
+
+    fn annotate(
+        &self,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+        rows: &mut Vec<(usize, String)>,
+    ) {
+        let start = rows.len();
+        self.0.annotate(byte_offset, window, line.clone(), rows);
+        self.1.annotate(byte_offset, window, line, rows);
+        rows[start..].sort_by_key(|(offset, _)| *offset);
+    }
+}
+
+/// Separator rows at known offsets, e.g. the sections of an executable.
+#[derive(Debug, Clone, Default)]
+pub struct Boundaries {
+    rows: Vec<(usize, String)>,
+}
+
+impl Boundaries {
+    /// Creates the rows, rows with the same offset keep their order.
+    pub fn new(mut rows: Vec<(usize, String)>) -> Self {
+        rows.sort_by_key(|(offset, _)| *offset);
+        Self { rows }
+    }
+
+    /// Whether there are no rows at all.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Adds a row after the existing rows with the same offset.
+    pub fn add(&mut self, offset: usize, label: String) {
+        let idx = self
+            .rows
+            .partition_point(|(row_offset, _)| *row_offset <= offset);
+        self.rows.insert(idx, (offset, label));
+    }
+}
+
+impl Annotator for Boundaries {
+    fn annotate(
+        &self,
+        byte_offset: usize,
+        _window: &[u8],
+        line: std::ops::Range<usize>,
+        rows: &mut Vec<(usize, String)>,
+    ) {
+        let end = byte_offset + line.len();
+        let first = self
+            .rows
+            .partition_point(|(offset, _)| *offset < byte_offset);
+        let len = self.rows[first..].partition_point(|(offset, _)| *offset < end);
+        rows.extend_from_slice(&self.rows[first..first + len]);
+    }
+}
+
+/// Renderer that adds the separator rows of an `Annotator` to the lines of another renderer.
+///
+/// ```text
+/// 00000000 │ 78 9c 4b cc  ...
+/// 00001200 ├── gzip: gzip compressed data, name "notes.txt" ─────────────────
+/// 00001200 │ 1f 8b 08 08  ...
+/// ```
+pub struct AnnotatedRenderer<R, A> {
+    renderer: R,
+    annotator: A,
+}
+
+impl<R: Renderer, A: Annotator> AnnotatedRenderer<R, A> {
+    /// Creates a renderer that writes the rows of `annotator` with `renderer`.
+    pub fn new(renderer: R, annotator: A) -> Self {
+        Self {
+            renderer,
+            annotator,
+        }
+    }
+}
+
+impl<R: Renderer, A: Annotator> Renderer for AnnotatedRenderer<R, A> {
+    fn bytes_per_line(&self) -> usize {
+        self.renderer.bytes_per_line()
+    }
+
+    fn start_offset(&self) -> usize {
+        self.renderer.start_offset()
+    }
+
+    fn context_len(&self) -> usize {
+        self.renderer
+            .context_len()
+            .max(self.annotator.context_len())
+    }
+
+    fn squeeze(&self) -> bool {
+        self.renderer.squeeze()
+    }
+
+    fn write_header(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()> {
+        self.renderer.write_header(buffer, title)
+    }
+
+    fn write_line(&self, buffer: &mut Vec<u8>, byte_offset: usize, line_data: &[u8]) {
+        self.write_line_in_context(buffer, byte_offset, line_data, 0..line_data.len());
+    }
+
+    fn write_line_in_context(
+        &self,
+        buffer: &mut Vec<u8>,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+    ) {
+        let mut rows = Vec::new();
+        self.annotator
+            .annotate(byte_offset, window, line.clone(), &mut rows);
+        for (offset, label) in &rows {
+            self.renderer.write_separator(buffer, *offset, label);
+        }
+
+        // The wrapped renderer gets the context it asked for
+        let context_len = self.renderer.context_len();
+        let start = line.start.saturating_sub(context_len);
+        let end = (line.end + context_len).min(window.len());
+        self.renderer.write_line_in_context(
+            buffer,
+            byte_offset,
+            &window[start..end],
+            line.start - start..line.end - start,
+        );
+    }
+
+    fn write_squeezed(&self, buffer: &mut Vec<u8>, byte_offset: usize) {
+        self.renderer.write_squeezed(buffer, byte_offset)
+    }
+
+    fn write_separator(&self, buffer: &mut Vec<u8>, byte_offset: usize, label: &str) {
+        self.renderer.write_separator(buffer, byte_offset, label)
+    }
+
+    fn write_footer(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()> {
+        self.renderer.write_footer(buffer, title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_to_color::{ByteToColor, Theme};
+    use crate::hex_formatter::HexFormatter;
+    use crate::line_writer::LineWriter;
+    use crate::renderer::context_window;
+
+    /// Marks every `!` byte.
+    struct Exclamations;
+
+    impl Annotator for Exclamations {
+        fn annotate(
+            &self,
+            byte_offset: usize,
+            window: &[u8],
+            line: std::ops::Range<usize>,
+            rows: &mut Vec<(usize, String)>,
+        ) {
+            for (idx, &byte) in window[line].iter().enumerate() {
+                if byte == b'!' {
+                    rows.push((byte_offset + idx, "bang".to_string()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_boundaries_with_signatures() {
+        let boundaries = Boundaries::new(vec![
+            (9, "second".to_string()),
+            (2, "first".to_string()),
+            (100, "later".to_string()),
+        ]);
+        let annotator = (boundaries, Some(Exclamations));
+
+        let mut rows = Vec::new();
+        annotator.annotate(0, b"0123!5678!", 0..10, &mut rows);
+        let offsets: Vec<usize> = rows.iter().map(|(offset, _)| *offset).collect();
+        assert_eq!(offsets, vec![2, 4, 9, 9]);
+        assert_eq!(rows[2].1, "second");
+
+        let mut rows = Vec::new();
+        (Boundaries::default(), None::<Exclamations>).annotate(0, b"!", 0..1, &mut rows);
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn test_boundaries_add() {
+        let mut boundaries = Boundaries::new(vec![(2, "a".to_string()), (9, "b".to_string())]);
+        boundaries.add(9, "c".to_string());
+        boundaries.add(0, "d".to_string());
+
+        let mut rows = Vec::new();
+        boundaries.annotate(0, b"0123456789", 0..10, &mut rows);
+        let labels: Vec<&str> = rows.iter().map(|(_, label)| label.as_str()).collect();
+        assert_eq!(labels, vec!["d", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_rows_before_line() {
+        let line_writer = LineWriter::new_bytes(8)
+            .unwrap()
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap());
+        let renderer = AnnotatedRenderer::new(line_writer, Exclamations);
+
+        let data = b"hello!!, world!";
+        let mut output = Vec::new();
+        for start in (0..data.len()).step_by(8) {
+            let end = (start + 8).min(data.len());
+            let (window, line) = context_window(data, start..end, renderer.context_len());
+            renderer.write_line_in_context(&mut output, start, window, line);
+        }
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("00000005 ├── bang ──"));
+        assert!(lines[1].starts_with("00000006 ├── bang ──"));
+        assert!(lines[2].starts_with("00000000 │"));
+        assert!(lines[3].starts_with("0000000e ├── bang ──"));
+        assert!(lines[4].starts_with("00000008 │"));
+    }
+}
@@ -0,0 +1,831 @@
+use crate::error::{HexlerError, Result};
+use crate::executable::printable_name;
+use crate::hex_formatter::HexFormatter;
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use size::Size;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// How the data of an archive member is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Stored,
+    Deflate {
+        compressed_size: u64,
+    },
+    /// A zip compression method other than stored or deflate
+    Unsupported(u16),
+    Encrypted,
+}
+
+impl Compression {
+    /// Name of the compression, as shown in the listing.
+    pub fn name(self) -> String {
+        match self {
+            Compression::Stored => "stored".to_string(),
+            Compression::Deflate { .. } => "deflate".to_string(),
+            Compression::Unsupported(method) => format!("method {}", method),
+            Compression::Encrypted => "encrypted".to_string(),
+        }
+    }
+}
+
+/// A file in an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    /// Size of the data after decompression
+    pub size: u64,
+    pub modified: Option<DateTime<Local>>,
+    /// Offset of the data in the archive, compressed data for deflate
+    pub offset: u64,
+    pub compression: Compression,
+}
+
+/// Archive formats whose members can be listed and dumped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+}
+
+/// The regular files of a zip or tar archive. Directories, links and other special members are
+/// left out.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    pub kind: ArchiveKind,
+    pub members: Vec<Member>,
+}
+
+impl Archive {
+    /// Maximum size of the zip central directory and of tar extended headers that are read.
+    pub const MAX_HEADER_SIZE: u64 = 16 * 1024 * 1024;
+
+    /// Reads the members of a zip or tar archive. Returns `None` for other files.
+    pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<Option<Self>> {
+        let mut block = [0u8; tar::BLOCK];
+        reader.seek(SeekFrom::Start(0))?;
+        let len = crate::read_full(reader, &mut block)?;
+
+        if block[..len].starts_with(b"PK\x03\x04") || block[..len].starts_with(b"PK\x05\x06") {
+            return Ok(Some(Self {
+                kind: ArchiveKind::Zip,
+                members: zip::members(reader)?,
+            }));
+        }
+        if len == tar::BLOCK && tar::is_header(&block) {
+            return Ok(Some(Self {
+                kind: ArchiveKind::Tar,
+                members: tar::members(reader)?,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Returns the member called `name`, a leading `./` of tar member names is optional.
+    pub fn member(&self, name: &str, archive: &Path) -> Result<&Member> {
+        self.members
+            .iter()
+            .find(|member| member.name == name || member.name.strip_prefix("./") == Some(name))
+            .ok_or_else(|| HexlerError::MemberNotFound {
+                name: name.to_string(),
+                archive: archive.display().to_string(),
+            })
+    }
+
+    /// Returns a reader of the decompressed data of `member`, read from `reader`.
+    pub fn open_member<'a, R: Read + Seek + 'a>(
+        &self,
+        member: &Member,
+        mut reader: R,
+    ) -> Result<Box<dyn Read + 'a>> {
+        reader.seek(SeekFrom::Start(member.offset))?;
+        match member.compression {
+            Compression::Stored => Ok(Box::new(reader.take(member.size))),
+            Compression::Deflate { compressed_size } => {
+                let compressed = std::io::BufReader::new(reader.take(compressed_size));
+                Ok(Box::new(
+                    flate2::bufread::DeflateDecoder::new(compressed).take(member.size),
+                ))
+            }
+            compression => Err(HexlerError::InvalidArchive(format!(
+                "{} is {}, only stored and deflate members can be read",
+                member.name,
+                match compression {
+                    Compression::Encrypted => "encrypted".to_string(),
+                    _ => format!("compressed with {}", compression.name()),
+                }
+            ))),
+        }
+    }
+}
+
+/// Splits `archive.zip:path/in/archive` into the archive and the member name.
+///
+/// Returns `None` when `path` exists, or when no part in front of a `:` is a file.
+pub fn split_member_path(path: &Path) -> Option<(PathBuf, String)> {
+    if path.exists() {
+        return None;
+    }
+    let text = path.to_str()?;
+    text.match_indices(':').find_map(|(pos, _)| {
+        let archive = Path::new(&text[..pos]);
+        archive
+            .is_file()
+            .then(|| (archive.to_path_buf(), text[pos + 1..].to_string()))
+    })
+}
+
+/// Prints the members of an archive with the offsets of their data.
+///
+/// ```text
+/// 0000002b │   12.3 KiB  18 Oct 2026 20:12:40  deflate  lib/foo.so
+/// ```
+pub struct ArchiveListing {
+    hex_formatter: HexFormatter,
+}
+
+impl ArchiveListing {
+    /// Creates a listing with colored offsets.
+    pub fn new() -> Self {
+        Self {
+            hex_formatter: HexFormatter::new(),
+        }
+    }
+
+    /// Replaces the hex formatter used for the offsets.
+    pub fn with_hex_formatter(mut self, hex_formatter: HexFormatter) -> Self {
+        self.hex_formatter = hex_formatter;
+        self
+    }
+
+    /// Writes the title and the members of `archive`.
+    pub fn write<W: std::io::Write>(
+        &self,
+        title: &str,
+        archive: &Archive,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(title.as_bytes());
+        buffer.push(b'\n');
+
+        for member in &archive.members {
+            self.hex_formatter
+                .write_offset(&mut buffer, member.offset as usize);
+            let modified = member
+                .modified
+                .map(|time| time.format("%-d %b %Y %H:%M:%S").to_string())
+                .unwrap_or_default();
+            let row = format!(
+                " │ {:>12}  {:<20}  {:<9} {}\n",
+                Size::from_bytes(member.size).to_string(),
+                modified,
+                member.compression.name(),
+                member.name
+            );
+            buffer.extend_from_slice(row.as_bytes());
+        }
+        if archive.members.is_empty() {
+            buffer.extend_from_slice(b"no files in the archive\n");
+        }
+
+        writer.write_all(&buffer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Default for ArchiveListing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn invalid(message: String) -> HexlerError {
+    HexlerError::InvalidArchive(message)
+}
+
+/// Reads `len` bytes at `offset`, `what` is used in the error if they are not in the file.
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: u64, what: &str) -> Result<Vec<u8>> {
+    if len > Archive::MAX_HEADER_SIZE {
+        return Err(invalid(format!("{} of {} bytes is too large", what, len)));
+    }
+    let mut data = vec![0u8; len as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    if crate::read_full(reader, &mut data)? < data.len() {
+        return Err(invalid(format!(
+            "{} at 0x{:x} ends after the end of the file",
+            what, offset
+        )));
+    }
+    Ok(data)
+}
+
+fn le16(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn le32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn le64(data: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+fn unix_time(secs: i64) -> Option<DateTime<Local>> {
+    DateTime::from_timestamp(secs, 0).map(|time| time.with_timezone(&Local))
+}
+
+mod zip {
+    use super::*;
+
+    const END_OF_CENTRAL_DIRECTORY: &[u8] = b"PK\x05\x06";
+    const ZIP64_LOCATOR: &[u8] = b"PK\x06\x07";
+    const ZIP64_END_OF_CENTRAL_DIRECTORY: &[u8] = b"PK\x06\x06";
+    const CENTRAL_DIRECTORY_ENTRY: &[u8] = b"PK\x01\x02";
+
+    /// The end of central directory record is 22 bytes, followed by a comment of up to 64 KiB.
+    const MAX_END_LEN: u64 = 22 + 0xffff;
+
+    /// Reads the members from the central directory at the end of the archive.
+    pub(super) fn members<R: Read + Seek>(reader: &mut R) -> Result<Vec<Member>> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        let tail_len = file_len.min(MAX_END_LEN);
+        let tail = read_at(reader, file_len - tail_len, tail_len, "end of the zip file")?;
+        let end_pos = (0..tail.len().saturating_sub(21))
+            .rev()
+            .find(|&pos| tail[pos..].starts_with(END_OF_CENTRAL_DIRECTORY))
+            .ok_or_else(|| invalid("no end of central directory record".to_string()))?;
+        let end = &tail[end_pos..];
+
+        let mut num_entries = le16(end, 10) as u64;
+        let mut directory_size = le32(end, 12) as u64;
+        let mut directory_offset = le32(end, 16) as u64;
+        if num_entries == 0xffff || directory_size == 0xffff_ffff || directory_offset == 0xffff_ffff
+        {
+            // Zip64: the real values are in another record, found by the locator in front
+            let end_offset = file_len - tail_len + end_pos as u64;
+            let locator = read_at(
+                reader,
+                end_offset.saturating_sub(20),
+                20,
+                "zip64 end of central directory locator",
+            )?;
+            if !locator.starts_with(ZIP64_LOCATOR) {
+                return Err(invalid(
+                    "no zip64 end of central directory locator".to_string(),
+                ));
+            }
+            let end64 = read_at(
+                reader,
+                le64(&locator, 8),
+                56,
+                "zip64 end of central directory record",
+            )?;
+            if !end64.starts_with(ZIP64_END_OF_CENTRAL_DIRECTORY) {
+                return Err(invalid(
+                    "no zip64 end of central directory record".to_string(),
+                ));
+            }
+            num_entries = le64(&end64, 32);
+            directory_size = le64(&end64, 40);
+            directory_offset = le64(&end64, 48);
+        }
+
+        let directory = read_at(
+            reader,
+            directory_offset,
+            directory_size,
+            "central directory",
+        )?;
+        let mut members = Vec::new();
+        let mut pos = 0;
+        for _ in 0..num_entries {
+            let entry = directory
+                .get(pos..pos + 46)
+                .filter(|entry| entry.starts_with(CENTRAL_DIRECTORY_ENTRY))
+                .ok_or_else(|| invalid(format!("broken central directory entry at {}", pos)))?;
+            let name_len = le16(entry, 28) as usize;
+            let extra_len = le16(entry, 30) as usize;
+            let comment_len = le16(entry, 32) as usize;
+            let end = pos + 46 + name_len + extra_len + comment_len;
+            if end > directory.len() {
+                return Err(invalid(format!(
+                    "central directory entry at {} ends after the directory",
+                    pos
+                )));
+            }
+            let name = printable_name(&directory[pos + 46..pos + 46 + name_len]);
+            let extra = &directory[pos + 46 + name_len..pos + 46 + name_len + extra_len];
+            if !name.ends_with('/') {
+                members.push(member(reader, entry, name, extra)?);
+            }
+            pos = end;
+        }
+        Ok(members)
+    }
+
+    /// Creates the member of a central directory entry, reading its local header for the
+    /// offset of the data.
+    fn member<R: Read + Seek>(
+        reader: &mut R,
+        entry: &[u8],
+        name: String,
+        extra: &[u8],
+    ) -> Result<Member> {
+        let flags = le16(entry, 8);
+        let method = le16(entry, 10);
+        let mut compressed_size = le32(entry, 20) as u64;
+        let mut size = le32(entry, 24) as u64;
+        let mut header_offset = le32(entry, 42) as u64;
+        let mut modified = dos_time(le16(entry, 14), le16(entry, 12));
+
+        let mut pos = 0;
+        while pos + 4 <= extra.len() {
+            let id = le16(extra, pos);
+            let data = &extra[pos + 4..extra.len().min(pos + 4 + le16(extra, pos + 2) as usize)];
+            match id {
+                // Zip64 sizes and offset, only the ones that don't fit into 32 bits
+                0x0001 => {
+                    let mut values = data.chunks_exact(8).map(|value| le64(value, 0));
+                    for field in [&mut size, &mut compressed_size, &mut header_offset] {
+                        if *field == 0xffff_ffff {
+                            *field = values.next().unwrap_or(*field);
+                        }
+                    }
+                }
+                // Extended timestamp, the modification time in UTC
+                0x5455 if data.len() >= 5 && data[0] & 1 != 0 => {
+                    modified = unix_time(le32(data, 1) as i32 as i64).or(modified);
+                }
+                _ => {}
+            }
+            pos += 4 + data.len();
+        }
+
+        let header = read_at(
+            reader,
+            header_offset,
+            30,
+            &format!("local header of {}", name),
+        )?;
+        if !header.starts_with(b"PK\x03\x04") {
+            return Err(invalid(format!("broken local header of {}", name)));
+        }
+        let offset = header_offset + 30 + le16(&header, 26) as u64 + le16(&header, 28) as u64;
+
+        let compression = match (flags & 1, method) {
+            (1, _) => Compression::Encrypted,
+            (_, 0) => Compression::Stored,
+            (_, 8) => Compression::Deflate { compressed_size },
+            (_, method) => Compression::Unsupported(method),
+        };
+        Ok(Member {
+            name,
+            size,
+            modified,
+            offset,
+            compression,
+        })
+    }
+
+    /// MS-DOS date and time in local time, with a resolution of 2 seconds.
+    fn dos_time(date: u16, time: u16) -> Option<DateTime<Local>> {
+        let date = NaiveDate::from_ymd_opt(
+            1980 + (date >> 9) as i32,
+            ((date >> 5) & 0xf) as u32,
+            (date & 0x1f) as u32,
+        )?;
+        let time = date.and_hms_opt(
+            (time >> 11) as u32,
+            ((time >> 5) & 0x3f) as u32,
+            2 * (time & 0x1f) as u32,
+        )?;
+        Local.from_local_datetime(&time).earliest()
+    }
+}
+
+mod tar {
+    use super::*;
+
+    pub(super) const BLOCK: usize = 512;
+
+    /// Whether `block` is a tar header: the checksum matches and the name is not empty.
+    pub(super) fn is_header(block: &[u8]) -> bool {
+        let Some(checksum) = octal(&block[148..156]) else {
+            return false;
+        };
+        let sum: u64 = block
+            .iter()
+            .enumerate()
+            .map(|(pos, &byte)| match pos {
+                148..=155 => b' ' as u64,
+                _ => byte as u64,
+            })
+            .sum();
+        block[0] != 0 && sum == checksum
+    }
+
+    /// Reads all headers of the archive, skipping over the data of the members.
+    pub(super) fn members<R: Read + Seek>(reader: &mut R) -> Result<Vec<Member>> {
+        let mut members = Vec::new();
+        let mut pos = 0u64;
+        // Name, size and modification time of the next member from GNU and pax headers
+        let mut long_name: Option<String> = None;
+        let mut pax: Vec<(String, String)> = Vec::new();
+
+        let mut block = [0u8; BLOCK];
+        loop {
+            reader.seek(SeekFrom::Start(pos))?;
+            if crate::read_full(reader, &mut block)? < BLOCK || block.iter().all(|&b| b == 0) {
+                break;
+            }
+            if !is_header(&block) {
+                return Err(invalid(format!("broken tar header at 0x{:x}", pos)));
+            }
+            let size = number(&block[124..136])
+                .ok_or_else(|| invalid(format!("broken size in tar header at 0x{:x}", pos)))?;
+            let offset = pos + BLOCK as u64;
+            pos = size
+                .checked_add(BLOCK as u64 - 1)
+                .and_then(|size| offset.checked_add(size / BLOCK as u64 * BLOCK as u64))
+                .ok_or_else(|| invalid(format!("tar member at 0x{:x} is too large", offset)))?;
+
+            match block[156] {
+                b'L' => {
+                    let data = read_at(reader, offset, size, "GNU long name")?;
+                    long_name = Some(text(&data));
+                }
+                b'x' => {
+                    let data = read_at(reader, offset, size, "pax header")?;
+                    pax = pax_records(&data);
+                }
+                b'0' | b'\0' | b'7' => {
+                    let pax_value = |key: &str| {
+                        pax.iter()
+                            .find(|(k, _)| k == key)
+                            .map(|(_, value)| value.as_str())
+                    };
+                    let name = match (pax_value("path"), long_name.take()) {
+                        (Some(path), _) => printable_name(path.as_bytes()),
+                        (None, Some(name)) => name,
+                        (None, None) => header_name(&block),
+                    };
+                    let size = pax_value("size")
+                        .and_then(|size| size.parse().ok())
+                        .unwrap_or(size);
+                    let mtime = pax_value("mtime")
+                        .and_then(|mtime| mtime.split('.').next()?.parse().ok())
+                        .or_else(|| number(&block[136..148]).map(|mtime| mtime as i64));
+                    members.push(Member {
+                        name,
+                        size,
+                        modified: mtime.and_then(unix_time),
+                        offset,
+                        compression: Compression::Stored,
+                    });
+                    pax.clear();
+                }
+                _ => {
+                    long_name = None;
+                    pax.clear();
+                }
+            }
+        }
+        Ok(members)
+    }
+
+    /// The name of a ustar header has a prefix, GNU headers use that space for other fields.
+    fn header_name(block: &[u8]) -> String {
+        let name = text(&block[..100]);
+        if &block[257..263] == b"ustar\0" && block[345] != 0 {
+            format!("{}/{}", text(&block[345..500]), name)
+        } else {
+            name
+        }
+    }
+
+    fn text(data: &[u8]) -> String {
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        printable_name(&data[..end])
+    }
+
+    fn octal(field: &[u8]) -> Option<u64> {
+        let text = std::str::from_utf8(field).ok()?;
+        let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+        match text.is_empty() {
+            true => Some(0),
+            false => u64::from_str_radix(text, 8).ok(),
+        }
+    }
+
+    /// Numbers are octal text, or big endian binary with the high bit set when they are large.
+    fn number(field: &[u8]) -> Option<u64> {
+        if field[0] & 0x80 == 0 {
+            return octal(field);
+        }
+        let (first, rest) = field.split_first()?;
+        let mut value = (first & 0x7f) as u64;
+        for &byte in rest {
+            value = value.checked_mul(256)? | byte as u64;
+        }
+        Some(value)
+    }
+
+    /// Records of a pax header are `<len> <key>=<value>\n`.
+    fn pax_records(data: &[u8]) -> Vec<(String, String)> {
+        let mut records = Vec::new();
+        let mut rest = data;
+        while let Some(space) = rest.iter().position(|&b| b == b' ') {
+            let Some(len) = std::str::from_utf8(&rest[..space])
+                .ok()
+                .and_then(|len| len.parse::<usize>().ok())
+                .filter(|&len| len > space && len <= rest.len())
+            else {
+                break;
+            };
+            let record = String::from_utf8_lossy(&rest[space + 1..len]);
+            if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+                records.push((key.to_string(), value.to_string()));
+            }
+            rest = &rest[len..];
+        }
+        records
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_number() {
+            assert_eq!(number(b"00000001750\0"), Some(0o1750));
+            assert_eq!(number(b"           \0"), Some(0));
+            assert_eq!(number(b"\x80\0\0\0\0\0\0\x01\0\0\0\0"), Some(1 << 32));
+            assert_eq!(number(b"12x4\0"), None);
+        }
+
+        #[test]
+        fn test_pax_records() {
+            assert_eq!(
+                pax_records(b"30 mtime=1700000000.123456789\n14 path=a/b/c\n"),
+                vec![
+                    ("mtime".to_string(), "1700000000.123456789".to_string()),
+                    ("path".to_string(), "a/b/c".to_string())
+                ]
+            );
+            assert!(pax_records(b"99 path=x\n").is_empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    /// Tar header of a member, without its data.
+    fn tar_header(name: &str, size: u64, typeflag: u8) -> Vec<u8> {
+        let mut header = vec![0u8; tar::BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+        header[136..148].copy_from_slice(format!("{:011o}\0", 1_700_000_000).as_bytes());
+        header[156] = typeflag;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].copy_from_slice(b"        ");
+        let sum: u32 = header.iter().map(|&b| b as u32).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+        header
+    }
+
+    fn tar_member(data: &mut Vec<u8>, name: &str, content: &[u8], typeflag: u8) {
+        data.extend(tar_header(name, content.len() as u64, typeflag));
+        data.extend_from_slice(content);
+        data.resize((data.len() + tar::BLOCK - 1) / tar::BLOCK * tar::BLOCK, 0);
+    }
+
+    fn tiny_tar() -> Vec<u8> {
+        let mut data = Vec::new();
+        tar_member(&mut data, "./etc/", b"", b'5');
+        tar_member(&mut data, "./etc/passwd", b"root:x:0:0\n", b'0');
+        let long_name = format!("{}/file.txt", "d".repeat(120));
+        tar_member(&mut data, "././@LongLink", long_name.as_bytes(), b'L');
+        tar_member(&mut data, "truncated", b"long", b'0');
+        data.extend([0u8; 2 * tar::BLOCK]);
+        data
+    }
+
+    /// A zip file with a stored and a deflated member, and a directory.
+    fn tiny_zip() -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut directory = Vec::new();
+        let mut add = |name: &str, content: &[u8], deflate: bool| {
+            let stored = match deflate {
+                true => {
+                    let mut encoder = flate2::write::DeflateEncoder::new(
+                        Vec::new(),
+                        flate2::Compression::default(),
+                    );
+                    encoder.write_all(content).unwrap();
+                    encoder.finish().unwrap()
+                }
+                false => content.to_vec(),
+            };
+            let method: u16 = if deflate { 8 } else { 0 };
+            // 2023-11-14 22:13:20 in MS-DOS format
+            let (time, date) = (22u16 << 11 | 13 << 5 | 10, 43u16 << 9 | 11 << 5 | 14);
+
+            let header_offset = data.len() as u32;
+            data.extend_from_slice(b"PK\x03\x04\x14\x00\x00\x00");
+            data.extend_from_slice(&method.to_le_bytes());
+            data.extend_from_slice(&time.to_le_bytes());
+            data.extend_from_slice(&date.to_le_bytes());
+            data.extend_from_slice(&[0; 4]);
+            data.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            data.extend_from_slice(&3u16.to_le_bytes());
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(b"xyz");
+            data.extend_from_slice(&stored);
+
+            directory.extend_from_slice(b"PK\x01\x02\x14\x00\x14\x00\x00\x00");
+            directory.extend_from_slice(&method.to_le_bytes());
+            directory.extend_from_slice(&time.to_le_bytes());
+            directory.extend_from_slice(&date.to_le_bytes());
+            directory.extend_from_slice(&[0; 4]);
+            directory.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&header_offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        };
+        add("lib/", b"", false);
+        add("lib/foo.so", b"\x7fELF stored", false);
+        add("README", &b"deflated text ".repeat(20), true);
+
+        let directory_offset = data.len() as u32;
+        data.extend_from_slice(&directory);
+        data.extend_from_slice(b"PK\x05\x06\0\0\0\0\x03\x00\x03\x00");
+        data.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        data.extend_from_slice(&directory_offset.to_le_bytes());
+        data.extend_from_slice(b"\x05\x00hello");
+        data
+    }
+
+    fn read_member(archive: &Archive, data: &[u8], name: &str) -> Vec<u8> {
+        let member = archive.member(name, Path::new("test")).unwrap();
+        let mut content = Vec::new();
+        archive
+            .open_member(member, Cursor::new(data))
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_zip() {
+        let data = tiny_zip();
+        let archive = Archive::parse(&mut Cursor::new(&data)).unwrap().unwrap();
+        assert_eq!(archive.kind, ArchiveKind::Zip);
+
+        let names: Vec<&str> = archive.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["lib/foo.so", "README"]);
+        assert_eq!(archive.members[0].compression, Compression::Stored);
+        assert!(matches!(
+            archive.members[1].compression,
+            Compression::Deflate { .. }
+        ));
+        assert_eq!(
+            archive.members[0]
+                .modified
+                .unwrap()
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            "2023-11-14 22:13:20"
+        );
+
+        assert_eq!(
+            read_member(&archive, &data, "lib/foo.so"),
+            b"\x7fELF stored"
+        );
+        assert_eq!(
+            read_member(&archive, &data, "README"),
+            b"deflated text ".repeat(20)
+        );
+        let err = archive
+            .member("lib/bar.so", Path::new("a.zip"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no member lib/bar.so in a.zip");
+    }
+
+    #[test]
+    fn test_broken_zip() {
+        let mut data = tiny_zip();
+        let len = data.len();
+        data[len - 11] = 0xff;
+        let err = Archive::parse(&mut Cursor::new(&data)).unwrap_err();
+        assert!(err.to_string().contains("central directory"), "{}", err);
+
+        let err =
+            Archive::parse(&mut Cursor::new(b"PK\x03\x04 no directory".to_vec())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid archive: no end of central directory record"
+        );
+    }
+
+    #[test]
+    fn test_tar() {
+        let data = tiny_tar();
+        let archive = Archive::parse(&mut Cursor::new(&data)).unwrap().unwrap();
+        assert_eq!(archive.kind, ArchiveKind::Tar);
+
+        let names: Vec<&str> = archive.members.iter().map(|m| m.name.as_str()).collect();
+        let long_name = format!("{}/file.txt", "d".repeat(120));
+        assert_eq!(names, ["./etc/passwd", long_name.as_str()]);
+        assert_eq!(archive.members[0].offset, 2 * 512);
+        assert_eq!(
+            archive.members[0].modified,
+            DateTime::from_timestamp(1_700_000_000, 0).map(|time| time.with_timezone(&Local))
+        );
+
+        assert_eq!(read_member(&archive, &data, "etc/passwd"), b"root:x:0:0\n");
+        assert_eq!(read_member(&archive, &data, &long_name), b"long");
+    }
+
+    #[test]
+    fn test_control_characters_in_names() {
+        let mut data = Vec::new();
+        tar_member(&mut data, "evil\x1b]0;pwned\x07\x1b[2J.txt", b"", b'0');
+        tar_member(&mut data, "pax", b"14 path=a\x1b[Kb\n", b'x');
+        tar_member(&mut data, "overridden", b"", b'0');
+        data.extend([0u8; 2 * tar::BLOCK]);
+        let archive = Archive::parse(&mut Cursor::new(&data)).unwrap().unwrap();
+
+        let names: Vec<&str> = archive.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["evil\\u{1b}]0;pwned\\u{7}\\u{1b}[2J.txt", "a\\u{1b}[Kb"]
+        );
+    }
+
+    #[test]
+    fn test_broken_tar() {
+        let mut data = tiny_tar();
+        data[3 * 512 + 10] ^= 1;
+        let err = Archive::parse(&mut Cursor::new(&data)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid archive: broken tar header at 0x600"
+        );
+    }
+
+    #[test]
+    fn test_other_files() {
+        assert!(Archive::parse(&mut Cursor::new(b"hello".to_vec()))
+            .unwrap()
+            .is_none());
+        assert!(Archive::parse(&mut Cursor::new(vec![0u8; 2048]))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_split_member_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("bundle.zip");
+        std::fs::write(&archive, tiny_zip()).unwrap();
+
+        let path = PathBuf::from(format!("{}:lib/foo.so", archive.display()));
+        assert_eq!(
+            split_member_path(&path),
+            Some((archive.clone(), "lib/foo.so".to_string()))
+        );
+        assert_eq!(split_member_path(&archive), None);
+        assert_eq!(split_member_path(&dir.path().join("missing:x")), None);
+    }
+
+    #[test]
+    fn test_listing() {
+        let data = tiny_zip();
+        let archive = Archive::parse(&mut Cursor::new(&data)).unwrap().unwrap();
+        let listing =
+            ArchiveListing::new().with_hex_formatter(HexFormatter::new().with_color(false));
+
+        let mut output = Vec::new();
+        listing.write("title", &archive, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "title");
+        assert!(lines[1].starts_with("00000050 │     11 bytes  14 Nov 2023 22:13:20"));
+        assert!(lines[1].ends_with("  stored    lib/foo.so"));
+        assert!(lines[2].ends_with("  deflate   README"));
+    }
+}
@@ -1,48 +1,352 @@
-/// Handles rendering bytes as CodePage 437 characters.
+/// Character encoding used for the text panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Encoding {
+    /// CodePage 437 of the original IBM PC, every byte has a distinct glyph
+    #[default]
+    Cp437,
+    /// Printable ASCII only, everything else is shown as `.`
+    Ascii,
+    /// ISO-8859-1
+    Latin1,
+    /// Windows-1252
+    Cp1252,
+    /// EBCDIC code page 037
+    Ebcdic,
+    /// UTF-8, multibyte characters are shown at their first byte
+    Utf8,
+    /// UTF-16 little endian, characters are shown at the first byte of their code unit
+    Utf16le,
+    /// UTF-16 big endian, characters are shown at the first byte of their code unit
+    Utf16be,
+}
+
+impl Encoding {
+    /// Whether characters span multiple bytes, so glyphs depend on the surrounding bytes.
+    pub fn is_multibyte(self) -> bool {
+        matches!(self, Encoding::Utf8 | Encoding::Utf16le | Encoding::Utf16be)
+    }
+
+    /// The byte that is shown as the printable ASCII character `c`, e.g. for typing text.
+    /// `None` for other characters, and for UTF-16 where every character takes two bytes.
+    pub fn encode_ascii(self, c: char) -> Option<u8> {
+        if !(' '..='~').contains(&c) {
+            return None;
+        }
+        match self {
+            Encoding::Utf16le | Encoding::Utf16be => None,
+            // Below 0x40 are control characters, their placeholders would match `.`
+            Encoding::Ebcdic => {
+                (0x40..=0xff).find(|&byte| EBCDIC_037[byte as usize] == c.encode_utf8(&mut [0; 4]))
+            }
+            _ => Some(c as u8),
+        }
+    }
+}
+
+/// Placeholder for bytes that have no printable character in the selected encoding.
+const PLACEHOLDER: &str = ".";
+
+/// Shown for the bytes of a multibyte character that follow the cell with the character.
+const CONTINUATION: &str = "·";
+
+/// Shown for bytes that are not part of a valid multibyte character.
+const INVALID: &str = "\u{fffd}";
+
+/// CodePage 437, with visible glyphs for NUL (`⋄`) and 0xFF (`ﬀ`) instead of blanks.
+#[rustfmt::skip]
+const CODEPAGE_437: [&str; 256] = [
+    "⋄", "☺", "☻", "♥", "♦", "♣", "♠", "•", "◘", "○", "◙", "♂", "♀", "♪", "♫", "☼", // 00-0f
+    "►", "◄", "↕", "‼", "¶", "§", "▬", "↨", "↑", "↓", "→", "←", "∟", "↔", "▲", "▼", // 10-1f
+    " ", "!", "\"", "#", "$", "%", "&", "'", "(", ")", "*", "+", ",", "-", ".", "/", // 20-2f
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", ":", ";", "<", "=", ">", "?", // 30-3f
+    "@", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", // 40-4f
+    "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "[", "\\", "]", "^", "_", // 50-5f
+    "`", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", // 60-6f
+    "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "{", "|", "}", "~", "⌂", // 70-7f
+    "Ç", "ü", "é", "â", "ä", "à", "å", "ç", "ê", "ë", "è", "ï", "î", "ì", "Ä", "Å", // 80-8f
+    "É", "æ", "Æ", "ô", "ö", "ò", "û", "ù", "ÿ", "Ö", "Ü", "¢", "£", "¥", "₧", "ƒ", // 90-9f
+    "á", "í", "ó", "ú", "ñ", "Ñ", "ª", "º", "¿", "⌐", "¬", "½", "¼", "¡", "«", "»", // a0-af
+    "░", "▒", "▓", "│", "┤", "╡", "╢", "╖", "╕", "╣", "║", "╗", "╝", "╜", "╛", "┐", // b0-bf
+    "└", "┴", "┬", "├", "─", "┼", "╞", "╟", "╚", "╔", "╩", "╦", "╠", "═", "╬", "╧", // c0-cf
+    "╨", "╤", "╥", "╙", "╘", "╒", "╓", "╫", "╪", "┘", "┌", "█", "▄", "▌", "▐", "▀", // d0-df
+    "α", "ß", "Γ", "π", "Σ", "σ", "µ", "τ", "Φ", "Θ", "Ω", "δ", "∞", "φ", "ε", "∩", // e0-ef
+    "≡", "±", "≥", "≤", "⌠", "⌡", "÷", "≈", "°", "∙", "·", "√", "ⁿ", "²", "■", "ﬀ", // f0-ff
+];
+
+/// ISO-8859-1. C1 control characters 0x80-0x9F have no glyph.
+#[rustfmt::skip]
+const LATIN_1: [&str; 256] = [
+    "⋄", "☺", "☻", "♥", "♦", "♣", "♠", "•", "◘", "○", "◙", "♂", "♀", "♪", "♫", "☼", // 00-0f
+    "►", "◄", "↕", "‼", "¶", "§", "▬", "↨", "↑", "↓", "→", "←", "∟", "↔", "▲", "▼", // 10-1f
+    " ", "!", "\"", "#", "$", "%", "&", "'", "(", ")", "*", "+", ",", "-", ".", "/", // 20-2f
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", ":", ";", "<", "=", ">", "?", // 30-3f
+    "@", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", // 40-4f
+    "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "[", "\\", "]", "^", "_", // 50-5f
+    "`", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", // 60-6f
+    "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "{", "|", "}", "~", "⌂", // 70-7f
+    ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", // 80-8f
+    ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", ".", // 90-9f
+    " ", "¡", "¢", "£", "¤", "¥", "¦", "§", "¨", "©", "ª", "«", "¬", "-", "®", "¯", // a0-af
+    "°", "±", "²", "³", "´", "µ", "¶", "·", "¸", "¹", "º", "»", "¼", "½", "¾", "¿", // b0-bf
+    "À", "Á", "Â", "Ã", "Ä", "Å", "Æ", "Ç", "È", "É", "Ê", "Ë", "Ì", "Í", "Î", "Ï", // c0-cf
+    "Ð", "Ñ", "Ò", "Ó", "Ô", "Õ", "Ö", "×", "Ø", "Ù", "Ú", "Û", "Ü", "Ý", "Þ", "ß", // d0-df
+    "à", "á", "â", "ã", "ä", "å", "æ", "ç", "è", "é", "ê", "ë", "ì", "í", "î", "ï", // e0-ef
+    "ð", "ñ", "ò", "ó", "ô", "õ", "ö", "÷", "ø", "ù", "ú", "û", "ü", "ý", "þ", "ÿ", // f0-ff
+];
+
+/// Windows-1252, Latin-1 with printable characters in most of 0x80-0x9F.
+#[rustfmt::skip]
+const WINDOWS_1252: [&str; 256] = [
+    "⋄", "☺", "☻", "♥", "♦", "♣", "♠", "•", "◘", "○", "◙", "♂", "♀", "♪", "♫", "☼", // 00-0f
+    "►", "◄", "↕", "‼", "¶", "§", "▬", "↨", "↑", "↓", "→", "←", "∟", "↔", "▲", "▼", // 10-1f
+    " ", "!", "\"", "#", "$", "%", "&", "'", "(", ")", "*", "+", ",", "-", ".", "/", // 20-2f
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", ":", ";", "<", "=", ">", "?", // 30-3f
+    "@", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", // 40-4f
+    "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "[", "\\", "]", "^", "_", // 50-5f
+    "`", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", // 60-6f
+    "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "{", "|", "}", "~", "⌂", // 70-7f
+    "€", ".", "‚", "ƒ", "„", "…", "†", "‡", "ˆ", "‰", "Š", "‹", "Œ", ".", "Ž", ".", // 80-8f
+    ".", "‘", "’", "“", "”", "•", "–", "—", "˜", "™", "š", "›", "œ", ".", "ž", "Ÿ", // 90-9f
+    " ", "¡", "¢", "£", "¤", "¥", "¦", "§", "¨", "©", "ª", "«", "¬", "-", "®", "¯", // a0-af
+    "°", "±", "²", "³", "´", "µ", "¶", "·", "¸", "¹", "º", "»", "¼", "½", "¾", "¿", // b0-bf
+    "À", "Á", "Â", "Ã", "Ä", "Å", "Æ", "Ç", "È", "É", "Ê", "Ë", "Ì", "Í", "Î", "Ï", // c0-cf
+    "Ð", "Ñ", "Ò", "Ó", "Ô", "Õ", "Ö", "×", "Ø", "Ù", "Ú", "Û", "Ü", "Ý", "Þ", "ß", // d0-df
+    "à", "á", "â", "ã", "ä", "å", "æ", "ç", "è", "é", "ê", "ë", "ì", "í", "î", "ï", // e0-ef
+    "ð", "ñ", "ò", "ó", "ô", "õ", "ö", "÷", "ø", "ù", "ú", "û", "ü", "ý", "þ", "ÿ", // f0-ff
+];
+
+/// EBCDIC code page 037 (US/Canada), as used for mainframe records.
+///
+/// Control characters that have an ASCII equivalent use the same symbols as CodePage 437.
+#[rustfmt::skip]
+const EBCDIC_037: [&str; 256] = [
+    "⋄", "☺", "☻", "♥", ".", "○", ".", "⌂", ".", ".", ".", "♂", "♀", "♪", "♫", "☼", // 00-0f
+    "►", "◄", "↕", "‼", ".", ".", "◘", ".", "↑", "↓", ".", ".", "∟", "↔", "▲", "▼", // 10-1f
+    ".", ".", ".", ".", ".", "◙", "↨", "←", ".", ".", ".", ".", ".", "♣", "♠", "•", // 20-2f
+    ".", ".", "▬", ".", ".", ".", ".", "♦", ".", ".", ".", ".", "¶", "§", ".", "→", // 30-3f
+    " ", " ", "â", "ä", "à", "á", "ã", "å", "ç", "ñ", "¢", ".", "<", "(", "+", "|", // 40-4f
+    "&", "é", "ê", "ë", "è", "í", "î", "ï", "ì", "ß", "!", "$", "*", ")", ";", "¬", // 50-5f
+    "-", "/", "Â", "Ä", "À", "Á", "Ã", "Å", "Ç", "Ñ", "¦", ",", "%", "_", ">", "?", // 60-6f
+    "ø", "É", "Ê", "Ë", "È", "Í", "Î", "Ï", "Ì", "`", ":", "#", "@", "'", "=", "\"", // 70-7f
+    "Ø", "a", "b", "c", "d", "e", "f", "g", "h", "i", "«", "»", "ð", "ý", "þ", "±", // 80-8f
+    "°", "j", "k", "l", "m", "n", "o", "p", "q", "r", "ª", "º", "æ", "¸", "Æ", "¤", // 90-9f
+    "µ", "~", "s", "t", "u", "v", "w", "x", "y", "z", "¡", "¿", "Ð", "Ý", "Þ", "®", // a0-af
+    "^", "£", "¥", "·", "©", "§", "¶", "¼", "½", "¾", "[", "]", "¯", "¨", "´", "×", // b0-bf
+    "{", "A", "B", "C", "D", "E", "F", "G", "H", "I", "-", "ô", "ö", "ò", "ó", "õ", // c0-cf
+    "}", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "¹", "û", "ü", "ù", "ú", "ÿ", // d0-df
+    "\\", "÷", "S", "T", "U", "V", "W", "X", "Y", "Z", "²", "Ô", "Ö", "Ò", "Ó", "Õ", // e0-ef
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "³", "Û", "Ü", "Ù", "Ú", ".", // f0-ff
+];
+
+/// Handles rendering bytes as characters of a single-byte encoding, CodePage 437 by default.
 ///
 /// CodePage 437 is the character encoding used by the original IBM PC.
 /// It maps all 256 byte values to printable characters, including special
 /// symbols for control characters (0x00-0x1F) and extended ASCII (0x80-0xFF).
+/// The other encodings use the same symbols for control characters where possible, and
+/// a `.` placeholder for bytes without a printable character.
+///
+/// For the multibyte encodings UTF-8 and UTF-16 a glyph depends on the neighbouring bytes, these
+/// are rendered with `render_in_context`.
 pub struct AsciiRenderer {
-    codepage_437_bytes: [&'static [u8]; 256], // Pre-computed bytes for performance
+    glyphs: [([u8; 4], usize); 256], // Zero padded UTF-8 and its length
+    encoding: Encoding,
+}
+
+/// A valid character decoded from a multibyte encoding.
+struct Sequence {
+    start: usize,
+    len: usize,
+    ch: char,
 }
 
 impl AsciiRenderer {
     /// Creates a new AsciiRenderer with the full CodePage 437 character set.
     pub fn new() -> Self {
-        #[rustfmt::skip]
-        let codepage_437 = [
-            "⋄", "☺", "☻", "♥", "♦", "♣", "♠", "•", "◘", "○", "◙", "♂", "♀", "♪", "♫", "☼", // 00-0f
-            "►", "◄", "↕", "‼", "¶", "§", "▬", "↨", "↑", "↓", "→", "←", "∟", "↔", "▲", "▼", // 10-1f
-            " ", "!", "\"", "#", "$", "%", "&", "'", "(", ")", "*", "+", ",", "-", ".", "/", // 20-2f
-            "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", ":", ";", "<", "=", ">", "?", // 30-3f
-            "@", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", // 40-4f
-            "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "[", "\\", "]", "^", "_", // 50-5f
-            "`", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", // 60-6f
-            "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "{", "|", "}", "~", "⌂", // 70-7f
-            "Ç", "ü", "é", "â", "ä", "à", "å", "ç", "ê", "ë", "è", "ï", "î", "ì", "Ä", "Å", // 80-8f
-            "É", "æ", "Æ", "ô", "ö", "ò", "û", "ù", "ÿ", "Ö", "Ü", "¢", "£", "¥", "₧", "ƒ", // 90-9f
-            "á", "í", "ó", "ú", "ñ", "Ñ", "ª", "º", "¿", "⌐", "¬", "½", "¼", "¡", "«", "»", // a0-af
-            "░", "▒", "▓", "│", "┤", "╡", "╢", "╖", "╕", "╣", "║", "╗", "╝", "╜", "╛", "┐", // b0-bf
-            "└", "┴", "┬", "├", "─", "┼", "╞", "╟", "╚", "╔", "╩", "╦", "╠", "═", "╬", "╧", // c0-cf
-            "╨", "╤", "╥", "╙", "╘", "╒", "╓", "╫", "╪", "┘", "┌", "█", "▄", "▌", "▐", "▀", // d0-df
-            "α", "ß", "Γ", "π", "Σ", "σ", "µ", "τ", "Φ", "Θ", "Ω", "δ", "∞", "φ", "ε", "∩", // e0-ef
-            "≡", "±", "≥", "≤", "⌠", "⌡", "÷", "≈", "°", "∙", "·", "√", "ⁿ", "²", "■", "ﬀ", // f0-ff
-        ];
-
-        let mut codepage_437_bytes = ["".as_bytes(); 256];
-        for i in 0..256 {
-            codepage_437_bytes[i] = codepage_437[i].as_bytes();
+        Self::with_encoding(Encoding::Cp437)
+    }
+
+    /// Creates a new AsciiRenderer for the given encoding.
+    pub fn with_encoding(encoding: Encoding) -> Self {
+        let mut glyphs = [([0u8; 4], 0); 256];
+        for (i, glyph) in glyphs.iter_mut().enumerate() {
+            let text = match encoding {
+                Encoding::Cp437 => CODEPAGE_437[i],
+                Encoding::Ascii => match i {
+                    0x20..=0x7e => CODEPAGE_437[i],
+                    _ => PLACEHOLDER,
+                },
+                Encoding::Latin1 => LATIN_1[i],
+                Encoding::Cp1252 => WINDOWS_1252[i],
+                Encoding::Ebcdic => EBCDIC_037[i],
+                Encoding::Utf8 | Encoding::Utf16le | Encoding::Utf16be => match i {
+                    0x00..=0x7f => CODEPAGE_437[i],
+                    _ => PLACEHOLDER,
+                },
+            };
+            glyph.0[..text.len()].copy_from_slice(text.as_bytes());
+            glyph.1 = text.len();
+        }
+
+        Self { glyphs, encoding }
+    }
+
+    /// Number of bytes before and after a line that `render_in_context` looks at.
+    ///
+    /// This is enough to decode any UTF-8 or UTF-16 character that crosses a line boundary.
+    pub fn context_len(&self) -> usize {
+        if self.is_multibyte() {
+            3
+        } else {
+            0
         }
+    }
 
-        Self { codepage_437_bytes }
+    /// Whether `render_in_context` has to be used instead of the single byte methods.
+    #[inline]
+    pub fn is_multibyte(&self) -> bool {
+        self.encoding.is_multibyte()
     }
 
-    /// Returns the CodePage 437 character representation as bytes.
+    /// Returns the character representation as bytes.
     /// This is more efficient than calling render().as_bytes() in hot loops.
     #[inline]
-    pub fn render_bytes(&self, byte: u8) -> &'static [u8] {
-        self.codepage_437_bytes[byte as usize]
+    pub fn render_bytes(&self, byte: u8) -> &[u8] {
+        let (bytes, len) = &self.glyphs[byte as usize];
+        &bytes[..*len]
+    }
+
+    /// Returns the character zero padded to 4 bytes, and its actual length.
+    ///
+    /// Every character fits into 4 bytes of UTF-8, so hot loops can always copy 4 bytes and
+    /// then advance by the returned length.
+    #[inline]
+    pub fn render_padded(&self, byte: u8) -> (&[u8; 4], usize) {
+        let (bytes, len) = &self.glyphs[byte as usize];
+        (bytes, *len)
+    }
+
+    /// Returns the zero padded glyph for `window[pos]` in a multibyte encoding, and its length.
+    ///
+    /// `window[line]` is the line that is rendered, `pos` must be inside of it. The bytes before
+    /// and after the line are only used to decode characters that cross the line boundaries.
+    /// UTF-16 code units are aligned to the start of the line.
+    ///
+    /// A character is shown in the cell of its first byte, the cells of the following bytes show
+    /// `·`. Wide characters also use the next cell, which is then empty (length 0). When that
+    /// cell is on the next line, the character is shown at the start of the next line instead.
+    /// Bytes that are not part of a valid character show `�`.
+    pub fn render_in_context(
+        &self,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+        pos: usize,
+    ) -> ([u8; 4], usize) {
+        use unicode_width::UnicodeWidthChar;
+
+        let Some(sequence) = self.sequence_covering(window, line.start, pos) else {
+            return Self::padded(INVALID);
+        };
+
+        let ch = sequence.ch;
+        let width = if (ch as u32) < 0x80 {
+            1
+        } else {
+            ch.width().unwrap_or(0)
+        };
+
+        let anchor = if sequence.start >= line.start && (width < 2 || sequence.start + 1 < line.end)
+        {
+            Some(sequence.start)
+        } else if width == 2 && sequence.start + 1 == line.start {
+            // The first byte was the last cell of the previous line, where there is no room
+            Some(line.start)
+        } else {
+            None
+        };
+
+        match anchor {
+            Some(anchor) if anchor == pos => {
+                if (ch as u32) < 0x80 {
+                    let (bytes, len) = self.glyphs[ch as usize];
+                    (bytes, len)
+                } else if width == 0 {
+                    // Control characters and combining marks would mess up the columns
+                    Self::padded(PLACEHOLDER)
+                } else {
+                    let mut bytes = [0u8; 4];
+                    let len = ch.encode_utf8(&mut bytes).len();
+                    (bytes, len)
+                }
+            }
+            Some(anchor) if width == 2 && anchor + 1 == pos => ([0u8; 4], 0),
+            _ => Self::padded(CONTINUATION),
+        }
+    }
+
+    /// Finds the valid character that contains `window[pos]`.
+    fn sequence_covering(&self, window: &[u8], line_start: usize, pos: usize) -> Option<Sequence> {
+        match self.encoding {
+            Encoding::Utf16le | Encoding::Utf16be => {
+                let unit_start = pos - ((pos - line_start) & 1);
+                if let Some(sequence) = self.sequence_at(window, unit_start) {
+                    return Some(sequence);
+                }
+                // Second half of a surrogate pair
+                let sequence = self.sequence_at(window, unit_start.checked_sub(2)?)?;
+                (sequence.len == 4).then_some(sequence)
+            }
+            _ => (0..4)
+                .filter_map(|back| {
+                    let start = pos.checked_sub(back)?;
+                    self.sequence_at(window, start)
+                        .filter(|sequence| sequence.len > back)
+                })
+                .next(),
+        }
+    }
+
+    /// Decodes the character that starts at `window[start]`, if it is valid.
+    fn sequence_at(&self, window: &[u8], start: usize) -> Option<Sequence> {
+        match self.encoding {
+            Encoding::Utf16le | Encoding::Utf16be => {
+                let unit = |at: usize| {
+                    let bytes = [*window.get(at)?, *window.get(at + 1)?];
+                    Some(match self.encoding {
+                        Encoding::Utf16le => u16::from_le_bytes(bytes),
+                        _ => u16::from_be_bytes(bytes),
+                    })
+                };
+                let first = unit(start)?;
+                let (ch, len) = match first {
+                    0xd800..=0xdbff => {
+                        let units = [first, unit(start + 2)?];
+                        (char::decode_utf16(units).next()?.ok()?, 4)
+                    }
+                    _ => (char::decode_utf16([first]).next()?.ok()?, 2),
+                };
+                Some(Sequence { start, len, ch })
+            }
+            _ => {
+                let len = match window[start] {
+                    0x00..=0x7f => 1,
+                    0xc2..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf4 => 4,
+                    _ => return None,
+                };
+                let text = std::str::from_utf8(window.get(start..start + len)?).ok()?;
+                let ch = text.chars().next()?;
+                Some(Sequence { start, len, ch })
+            }
+        }
+    }
+
+    /// Zero pads a single character to 4 bytes.
+    fn padded(text: &str) -> ([u8; 4], usize) {
+        let mut bytes = [0u8; 4];
+        bytes[..text.len()].copy_from_slice(text.as_bytes());
+        (bytes, text.len())
     }
 }
 
@@ -58,7 +362,7 @@ mod tests {
 
     // Helper function for tests to get character as &str
     fn render(renderer: &AsciiRenderer, byte: u8) -> &str {
-        // Safe because codepage_437_bytes always contains valid UTF-8
+        // Safe because the glyphs always contain valid UTF-8
         unsafe { std::str::from_utf8_unchecked(renderer.render_bytes(byte)) }
     }
 
@@ -99,6 +403,179 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_render_padded_matches_render_bytes() {
+        let renderer = AsciiRenderer::new();
+        for byte in 0..=255u8 {
+            let (padded, len) = renderer.render_padded(byte);
+            assert_eq!(&padded[..len], renderer.render_bytes(byte));
+        }
+    }
+
+    #[test]
+    fn test_ascii_placeholders() {
+        let renderer = AsciiRenderer::with_encoding(Encoding::Ascii);
+        assert_eq!(render(&renderer, 0x00), ".");
+        assert_eq!(render(&renderer, 0x0a), ".");
+        assert_eq!(render(&renderer, 0x41), "A");
+        assert_eq!(render(&renderer, 0x7f), ".");
+        assert_eq!(render(&renderer, 0xe9), ".");
+    }
+
+    #[test]
+    fn test_latin1_and_cp1252() {
+        let latin1 = AsciiRenderer::with_encoding(Encoding::Latin1);
+        let cp1252 = AsciiRenderer::with_encoding(Encoding::Cp1252);
+        assert_eq!(render(&latin1, 0xe9), "é");
+        assert_eq!(render(&cp1252, 0xe9), "é");
+        assert_eq!(render(&latin1, 0x80), ".");
+        assert_eq!(render(&cp1252, 0x80), "€");
+        assert_eq!(render(&cp1252, 0x81), "."); // undefined in Windows-1252
+        assert_eq!(render(&latin1, 0x00), "⋄");
+    }
+
+    #[test]
+    fn test_ebcdic() {
+        let renderer = AsciiRenderer::with_encoding(Encoding::Ebcdic);
+        let text: String = [0xc8, 0x85, 0x93, 0x93, 0x96, 0x40, 0xf1, 0xf2]
+            .iter()
+            .map(|&b| render(&renderer, b))
+            .collect();
+        assert_eq!(text, "Hello 12");
+    }
+
+    #[test]
+    fn test_encode_ascii() {
+        for encoding in [
+            Encoding::Cp437,
+            Encoding::Ascii,
+            Encoding::Latin1,
+            Encoding::Cp1252,
+            Encoding::Ebcdic,
+            Encoding::Utf8,
+        ] {
+            let renderer = AsciiRenderer::with_encoding(encoding);
+            for c in ' '..='~' {
+                let byte = encoding.encode_ascii(c).unwrap();
+                assert_eq!(render(&renderer, byte), c.to_string(), "{:?}", encoding);
+            }
+        }
+        assert_eq!(Encoding::Ebcdic.encode_ascii('A'), Some(0xc1));
+        assert_eq!(Encoding::Ebcdic.encode_ascii('.'), Some(0x4b));
+        assert_eq!(Encoding::Latin1.encode_ascii('ä'), None);
+        assert_eq!(Encoding::Utf16le.encode_ascii('A'), None);
+    }
+
+    #[test]
+    fn test_utf8_ascii_range() {
+        let renderer = AsciiRenderer::with_encoding(Encoding::Utf8);
+        assert_eq!(render(&renderer, 0x41), "A");
+        assert_eq!(render(&renderer, 0xc3), ".");
+    }
+
+    /// Renders all cells of `window[line]`, separated by `|` so empty cells are visible.
+    fn render_line(encoding: Encoding, window: &[u8], line: std::ops::Range<usize>) -> String {
+        let renderer = AsciiRenderer::with_encoding(encoding);
+        line.clone()
+            .map(|pos| {
+                let (bytes, len) = renderer.render_in_context(window, line.clone(), pos);
+                String::from_utf8(bytes[..len].to_vec()).unwrap()
+            })
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
+    #[test]
+    fn test_utf8_sequences() {
+        let text = "aé€😀".as_bytes();
+        assert_eq!(
+            render_line(Encoding::Utf8, text, 0..text.len()),
+            "a|é|·|€|·|·|😀||·|·"
+        );
+    }
+
+    #[test]
+    fn test_utf8_invalid() {
+        // Lone continuation byte, truncated sequence, overlong encoding and 0xff
+        let data = [0x80, 0xe2, 0x82, b'x', 0xc0, 0xaf, 0xff];
+        assert_eq!(
+            render_line(Encoding::Utf8, &data, 0..data.len()),
+            "�|�|�|x|�|�|�"
+        );
+    }
+
+    #[test]
+    fn test_utf8_across_lines() {
+        // "€" starts in the last cell of the first line
+        let data = [b'a', b'b', 0xe2, 0x82, 0xac, b'c'];
+        assert_eq!(render_line(Encoding::Utf8, &data, 0..3), "a|b|€");
+        assert_eq!(render_line(Encoding::Utf8, &data, 3..6), "·|·|c");
+
+        // Without the next line the sequence can't be decoded
+        assert_eq!(render_line(Encoding::Utf8, &data[..3], 0..3), "a|b|�");
+    }
+
+    #[test]
+    fn test_utf8_wide_across_lines() {
+        // "日" is two columns wide, it doesn't fit into the last cell of the first line
+        let data = [b'a', 0xe6, 0x97, 0xa5, b'b', b'c'];
+        assert_eq!(render_line(Encoding::Utf8, &data, 0..2), "a|·");
+        assert_eq!(render_line(Encoding::Utf8, &data, 2..4), "日|");
+        assert_eq!(render_line(Encoding::Utf8, &data, 0..4), "a|日||·");
+    }
+
+    #[test]
+    fn test_utf8_zero_width() {
+        // Combining acute accent and a C1 control character
+        let data = [0xcc, 0x81, 0xc2, 0x85];
+        assert_eq!(render_line(Encoding::Utf8, &data, 0..4), ".|·|.|·");
+    }
+
+    #[test]
+    fn test_utf16() {
+        let le: Vec<u8> = "a€😀".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(
+            render_line(Encoding::Utf16le, &le, 0..le.len()),
+            "a|·|€|·|😀||·|·"
+        );
+        let be: Vec<u8> = "a€😀".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(
+            render_line(Encoding::Utf16be, &be, 0..be.len()),
+            "a|·|€|·|😀||·|·"
+        );
+    }
+
+    #[test]
+    fn test_utf16_surrogates() {
+        let le: Vec<u8> = "😀".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(render_line(Encoding::Utf16le, &le, 0..2), "😀|");
+        assert_eq!(render_line(Encoding::Utf16le, &le, 2..4), "·|·");
+
+        // Unpaired surrogates and a truncated code unit
+        let data = [0x3d, 0xd8, b'a', 0, 0x00, 0xde, b'b'];
+        assert_eq!(
+            render_line(Encoding::Utf16le, &data, 0..data.len()),
+            "�|�|a|·|�|�|�"
+        );
+    }
+
+    #[test]
+    fn test_all_encodings_single_char() {
+        use clap::ValueEnum;
+        for encoding in Encoding::value_variants() {
+            let renderer = AsciiRenderer::with_encoding(*encoding);
+            for byte in 0..=255u8 {
+                assert_eq!(
+                    render(&renderer, byte).chars().count(),
+                    1,
+                    "{:?} byte {:02x}",
+                    encoding,
+                    byte
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_default_trait() {
         let renderer1 = AsciiRenderer::new();
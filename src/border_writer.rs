@@ -10,6 +10,9 @@ impl BorderWriter {
     const CONNECTOR_TOP: &'static [u8] = "┬".as_bytes();
     const CONNECTOR_BOTTOM: &'static [u8] = "┴".as_bytes();
 
+    /// Width of the offset column used by `write_header` and `write_footer`.
+    pub const DEFAULT_OFFSET_WIDTH: usize = 8;
+
     /// Writes a header border with an optional title above it.
     ///
     /// Format: `title\n─────┬─────┬─────\n`
@@ -18,7 +21,29 @@ impl BorderWriter {
         title: &str,
         bytes_per_line: usize,
     ) -> std::io::Result<()> {
-        Self::write_border(writer, title, bytes_per_line, Self::CONNECTOR_TOP, true)
+        Self::write_header_with_offset_width(
+            writer,
+            title,
+            bytes_per_line,
+            Self::DEFAULT_OFFSET_WIDTH,
+        )
+    }
+
+    /// Writes a header border for an offset column that is `offset_width` characters wide.
+    pub fn write_header_with_offset_width<W: std::io::Write>(
+        writer: &mut W,
+        title: &str,
+        bytes_per_line: usize,
+        offset_width: usize,
+    ) -> std::io::Result<()> {
+        Self::write_border(
+            writer,
+            title,
+            bytes_per_line,
+            offset_width,
+            Self::CONNECTOR_TOP,
+            true,
+        )
     }
 
     /// Writes a footer border with an optional title below it.
@@ -29,19 +54,42 @@ impl BorderWriter {
         title: &str,
         bytes_per_line: usize,
     ) -> std::io::Result<()> {
-        Self::write_border(writer, title, bytes_per_line, Self::CONNECTOR_BOTTOM, false)
+        Self::write_footer_with_offset_width(
+            writer,
+            title,
+            bytes_per_line,
+            Self::DEFAULT_OFFSET_WIDTH,
+        )
+    }
+
+    /// Writes a footer border for an offset column that is `offset_width` characters wide.
+    pub fn write_footer_with_offset_width<W: std::io::Write>(
+        writer: &mut W,
+        title: &str,
+        bytes_per_line: usize,
+        offset_width: usize,
+    ) -> std::io::Result<()> {
+        Self::write_border(
+            writer,
+            title,
+            bytes_per_line,
+            offset_width,
+            Self::CONNECTOR_BOTTOM,
+            false,
+        )
     }
 
     /// Internal method to write a border line with proper spacing.
     ///
     /// The border is divided into three sections:
-    /// 1. Left: 9 characters for the offset column (8 hex digits + space)
+    /// 1. Left: offset_width + 1 characters for the offset column (by default 8 hex digits + space)
     /// 2. Middle: Variable length for hex bytes (groups of 8 bytes each)
     /// 3. Right: Variable length for ASCII representation (bytes_per_line + 1)
     fn write_border<W: std::io::Write>(
         writer: &mut W,
         title: &str,
         bytes_per_line: usize,
+        offset_width: usize,
         connector: &[u8],
         title_first: bool,
     ) -> std::io::Result<()> {
@@ -52,8 +100,8 @@ impl BorderWriter {
             writeln!(writer, "{}", title)?;
         }
 
-        // Left section (offset column): 9 chars (8 hex + 1 space)
-        for _ in 0..9 {
+        // Left section (offset column): offset digits + 1 space
+        for _ in 0..(offset_width + 1) {
             writer.write_all(Self::HORIZONTAL)?;
         }
         writer.write_all(connector)?;
@@ -69,10 +117,9 @@ impl BorderWriter {
             writer.write_all(Self::HORIZONTAL)?;
         }
 
+        writeln!(writer)?;
         if !title_first && !title.is_empty() {
             writeln!(writer, "{}", title)?;
-        } else {
-            writeln!(writer)?;
         }
 
         Ok(())
@@ -100,8 +147,7 @@ mod tests {
         BorderWriter::write_footer(&mut output, "Test Footer", 8).unwrap();
 
         let result = String::from_utf8_lossy(&output);
-        assert!(result.contains("Test Footer"));
-        assert!(result.contains("─"));
+        assert!(result.ends_with("─\nTest Footer\n"));
         assert!(result.contains("┴"));
     }
 
@@ -115,6 +161,14 @@ mod tests {
         assert!(result.contains("┬"));
     }
 
+    #[test]
+    fn test_offset_width() {
+        let mut output = Vec::new();
+        BorderWriter::write_header_with_offset_width(&mut output, "", 8, 10).unwrap();
+        let result = String::from_utf8_lossy(&output);
+        assert!(result.starts_with(&format!("{}┬", "─".repeat(11))));
+    }
+
     #[test]
     fn test_different_widths() {
         let mut output = Vec::new();
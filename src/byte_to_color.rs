@@ -1,8 +1,152 @@
+use crate::error::{HexlerError, Result};
 use std::collections::HashMap;
 
+/// What the colors of the bytes are based on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ColorBy {
+    /// Byte class like whitespace, symbols or high bytes, using the colors of a `Theme`
+    #[default]
+    Class,
+    /// Byte value, on a continuous gradient from low to high values
+    Value,
+    /// Shannon entropy around each line, from uniform data (blue) to random data (red)
+    Entropy,
+}
+
+/// Class of a byte, each class has its own color in a `Theme`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteClass {
+    /// NUL (0x00), DEL (0x7F) and 0xFF
+    Sentinel,
+    /// LF, VT, FF, CR and SPACE
+    Whitespace,
+    /// Remaining control characters 0x01-0x1F
+    ControlChar,
+    /// Printable symbols like `!`, `@` or `{`
+    PrintableSymbol,
+    /// Digits and letters
+    Alphanumeric,
+    /// High bytes 0x80-0xFE
+    ExtendedAscii,
+}
+
+impl ByteClass {
+    /// All classes, in the order they are listed in reports.
+    pub const ALL: [ByteClass; 6] = [
+        ByteClass::Sentinel,
+        ByteClass::Whitespace,
+        ByteClass::ControlChar,
+        ByteClass::PrintableSymbol,
+        ByteClass::Alphanumeric,
+        ByteClass::ExtendedAscii,
+    ];
+
+    /// Returns the class of `byte`.
+    pub fn of(byte: u8) -> Self {
+        match byte {
+            // NUL, DEL, 0xff
+            0x00 | 0x7f | 0xff => ByteClass::Sentinel,
+
+            // whitespace
+            0x0a | 0x0b | 0x0c | 0x0d | 0x20 => ByteClass::Whitespace,
+
+            // symbols
+            0x01..=0x1f => ByteClass::ControlChar,
+
+            // printable symbols
+            0x21..=0x2f | 0x3a..=0x40 | 0x5b..=0x60 | 0x7b..=0x7e => ByteClass::PrintableSymbol,
+
+            // digits, uppercase and lowercase letters
+            0x30..=0x39 | 0x41..=0x5a | 0x61..=0x7a => ByteClass::Alphanumeric,
+
+            // remaining high bytes
+            0x80..=0xfe => ByteClass::ExtendedAscii,
+        }
+    }
+
+    /// Human readable name of the class.
+    pub fn name(self) -> &'static str {
+        match self {
+            ByteClass::Sentinel => "NUL, DEL, 0xff",
+            ByteClass::Whitespace => "whitespace",
+            ByteClass::ControlChar => "control chars",
+            ByteClass::PrintableSymbol => "symbols",
+            ByteClass::Alphanumeric => "letters, digits",
+            ByteClass::ExtendedAscii => "high bytes",
+        }
+    }
+}
+
+/// ANSI escape codes used for each class of bytes.
+///
+/// Digits and letters are never colored, they always use the terminal's default color.
+/// Every escape code must be at most `ByteToColor::MAX_ESCAPE_LEN` bytes long,
+/// `ByteToColor::with_theme` refuses longer ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// NUL (0x00), DEL (0x7F) and 0xFF
+    pub sentinel: &'static str,
+    /// LF, VT, FF, CR and SPACE
+    pub whitespace: &'static str,
+    /// Remaining control characters 0x01-0x1F
+    pub control_chars: &'static str,
+    /// Printable symbols like `!`, `@` or `{`
+    pub printable_symbols: &'static str,
+    /// High bytes 0x80-0xFE
+    pub extended_ascii: &'static str,
+}
+
+impl Theme {
+    /// Returns the escape code for bytes of `class`.
+    pub fn color(&self, class: ByteClass) -> &'static str {
+        match class {
+            ByteClass::Sentinel => self.sentinel,
+            ByteClass::Whitespace => self.whitespace,
+            ByteClass::ControlChar => self.control_chars,
+            ByteClass::PrintableSymbol => self.printable_symbols,
+            ByteClass::Alphanumeric => ByteToColor::RESET,
+            ByteClass::ExtendedAscii => self.extended_ascii,
+        }
+    }
+
+    /// The default 256-color theme.
+    pub const DEFAULT: Theme = Theme {
+        sentinel: ByteToColor::SENTINEL,
+        whitespace: ByteToColor::WHITESPACE,
+        control_chars: ByteToColor::CONTROL_CHARS,
+        printable_symbols: ByteToColor::PRINTABLE_SYMBOLS,
+        extended_ascii: ByteToColor::EXTENDED_ASCII,
+    };
+
+    /// Uses only the 16 standard colors, for terminals without 256-color support.
+    pub const BASIC: Theme = Theme {
+        sentinel: "\x1b[90m",
+        whitespace: "\x1b[36m",
+        control_chars: "\x1b[35m",
+        printable_symbols: "\x1b[33m",
+        extended_ascii: "\x1b[34m",
+    };
+
+    /// No colors at all, the output doesn't contain any escape codes for bytes.
+    pub const MONOCHROME: Theme = Theme {
+        sentinel: ByteToColor::RESET,
+        whitespace: ByteToColor::RESET,
+        control_chars: ByteToColor::RESET,
+        printable_symbols: ByteToColor::RESET,
+        extended_ascii: ByteToColor::RESET,
+    };
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 pub struct ByteToColor {
     color_id: [u8; 256],
-    color_bytes: [&'static [u8]; 256], // Pre-computed as_bytes() for each color
+    escapes: Vec<([u8; ByteToColor::MAX_ESCAPE_LEN], usize)>, // Zero padded escape code per color ID
+    color_by: ColorBy,
 }
 
 impl Default for ByteToColor {
@@ -22,6 +166,24 @@ impl ByteToColor {
     const PRINTABLE_SYMBOLS: &'static str = "\x1b[38;5;226m"; // 256-color bright yellow
     const RESET: &'static str = "\x1b[0m";
 
+    /// Upper bound for the length of an escape code returned by `escape`.
+    pub const MAX_ESCAPE_LEN: usize = 24;
+
+    /// Number of colors of the `Value` and `Entropy` gradients.
+    const GRADIENT_STEPS: usize = 64;
+
+    /// Number of bytes before and after a line that are included in its entropy.
+    const ENTROPY_CONTEXT: usize = 128;
+
+    /// Color stops of the gradients: blue, cyan, green, yellow, red.
+    const GRADIENT_STOPS: [(f64, [f64; 3]); 5] = [
+        (0.0, [70.0, 90.0, 220.0]),
+        (0.3, [40.0, 200.0, 210.0]),
+        (0.5, [80.0, 210.0, 80.0]),
+        (0.75, [235.0, 210.0, 50.0]),
+        (1.0, [235.0, 60.0, 50.0]),
+    ];
+
     /// Creates a new ByteToColor instance with color mappings for all 256 byte values.
     ///
     /// Color assignments:
@@ -34,55 +196,141 @@ impl ByteToColor {
     /// The color_id array stores unique identifiers for each color to enable
     /// efficient color change detection when rendering.
     pub fn new() -> Self {
-        let mut color_bytes = [Self::RESET.as_bytes(); 256];
+        Self::with_theme(&Theme::DEFAULT).expect("built-in themes are valid")
+    }
+
+    /// Creates a new ByteToColor instance that uses the colors of the given theme.
+    ///
+    /// Bytes whose color is reset get color ID 0, so with `Theme::MONOCHROME` no escape codes
+    /// are written at all.
+    ///
+    /// # Errors
+    /// Returns `EscapeTooLong` if an escape code is longer than `MAX_ESCAPE_LEN` bytes.
+    pub fn with_theme(theme: &Theme) -> Result<Self> {
+        let colors = [
+            theme.sentinel,
+            theme.whitespace,
+            theme.control_chars,
+            theme.printable_symbols,
+            theme.extended_ascii,
+        ];
+        if let Some(escape) = colors.iter().find(|c| c.len() > Self::MAX_ESCAPE_LEN) {
+            return Err(HexlerError::EscapeTooLong {
+                escape: escape.to_string(),
+                max: Self::MAX_ESCAPE_LEN,
+            });
+        }
+
         let mut color_id = [0u8; 256];
 
         let mut unique_color_count = 0u8;
         let mut color_to_id = HashMap::<&str, u8>::new();
+        let mut escapes = vec![Self::padded(Self::RESET)];
 
         // id 0 is reset!
         color_to_id.insert(Self::RESET, 0);
 
         for i in 0..=255u8 {
-            let color = match i {
-                // NUL, DEL, 0xff
-                0x00 | 0x7f | 0xff => Self::SENTINEL,
-
-                // whitespace
-                0x0a | 0x0b | 0x0c | 0x0d | 0x20 => Self::WHITESPACE,
-
-                // symbols
-                0x01..=0x1f => Self::CONTROL_CHARS,
-
-                // printable symbols
-                0x21..=0x2f | 0x3a..=0x40 | 0x5b..=0x60 | 0x7b..=0x7e => Self::PRINTABLE_SYMBOLS,
-
-                0x30..=0x39 => Self::RESET, // digits
-                0x41..=0x5a => Self::RESET, // uppercase letters
-                0x61..=0x7a => Self::RESET, // lowercase letters
-
-                // remaining high bytes
-                0x80..=0xfe => Self::EXTENDED_ASCII,
-            };
-            color_bytes[i as usize] = color.as_bytes();
+            let color = theme.color(ByteClass::of(i));
             let val = color_to_id.entry(color).or_insert_with(|| {
                 unique_color_count += 1;
+                escapes.push(Self::padded(color));
                 unique_color_count
             });
             color_id[i as usize] = *val;
         }
 
-        Self {
+        Ok(Self {
+            color_id,
+            escapes,
+            color_by: ColorBy::Class,
+        })
+    }
+
+    /// Creates a new ByteToColor with the given strategy, `theme` is only used for `Class`.
+    ///
+    /// The gradients use 24-bit colors, consecutive bytes with a similar color share a color ID
+    /// so that escape codes are only written when the color visibly changes.
+    ///
+    /// # Errors
+    /// Returns `EscapeTooLong` if an escape code of `theme` is too long, see `with_theme`.
+    pub fn with_color_by(color_by: ColorBy, theme: &Theme) -> Result<Self> {
+        if color_by == ColorBy::Class {
+            return Self::with_theme(theme);
+        }
+
+        let mut escapes = vec![Self::padded(Self::RESET)];
+        escapes.extend((0..Self::GRADIENT_STEPS).map(|step| {
+            let t = step as f64 / (Self::GRADIENT_STEPS - 1) as f64;
+            Self::padded(&Self::gradient(t))
+        }));
+
+        // Entropy colors whole lines with `line_id`, so only the value gradient needs byte IDs
+        let mut color_id = [0u8; 256];
+        if color_by == ColorBy::Value {
+            for (byte, id) in color_id.iter_mut().enumerate() {
+                *id = 1 + (byte * Self::GRADIENT_STEPS / 256) as u8;
+            }
+        }
+
+        Ok(Self {
             color_id,
-            color_bytes,
+            escapes,
+            color_by,
+        })
+    }
+
+    /// Truecolor escape code for `t` between 0 and 1 on the gradient.
+    fn gradient(t: f64) -> String {
+        let stops = &Self::GRADIENT_STOPS;
+        let upper = stops
+            .iter()
+            .position(|(pos, _)| *pos >= t)
+            .unwrap_or(1)
+            .max(1);
+        let (lo_pos, lo) = stops[upper - 1];
+        let (hi_pos, hi) = stops[upper];
+        let f = ((t - lo_pos) / (hi_pos - lo_pos)).clamp(0.0, 1.0);
+        let rgb: Vec<u8> = (0..3)
+            .map(|i| (lo[i] + (hi[i] - lo[i]) * f).round() as u8)
+            .collect();
+        format!("\x1b[38;2;{};{};{}m", rgb[0], rgb[1], rgb[2])
+    }
+
+    /// Number of bytes before and after each line that `line_id` looks at.
+    pub fn context_len(&self) -> usize {
+        match self.color_by {
+            ColorBy::Entropy => Self::ENTROPY_CONTEXT,
+            _ => 0,
+        }
+    }
+
+    /// Returns the color ID for all bytes of the line `window[line]`, for strategies that color
+    /// whole lines instead of single bytes. Returns `None` when the bytes are colored by `id`.
+    ///
+    /// `window` contains the bytes around the line, see `context_len`.
+    pub fn line_id(&self, window: &[u8], line: std::ops::Range<usize>) -> Option<u8> {
+        if self.color_by != ColorBy::Entropy {
+            return None;
         }
+        let start = line.start.saturating_sub(Self::ENTROPY_CONTEXT);
+        let end = (line.end + Self::ENTROPY_CONTEXT).min(window.len());
+        let t = entropy(&window[start..end]) / 8.0;
+        Some(1 + (t * (Self::GRADIENT_STEPS - 1) as f64).round() as u8)
+    }
+
+    fn padded(color: &str) -> ([u8; Self::MAX_ESCAPE_LEN], usize) {
+        let mut escape = [0u8; Self::MAX_ESCAPE_LEN];
+        escape[..color.len()].copy_from_slice(color.as_bytes());
+        (escape, color.len())
     }
 
     /// Returns the ANSI color escape code as bytes for the given byte.
     /// This is more efficient than calling color().as_bytes() in hot loops.
     #[inline]
-    pub fn bytes(&self, byte: u8) -> &'static [u8] {
-        self.color_bytes[byte as usize]
+    pub fn bytes(&self, byte: u8) -> &[u8] {
+        let (escape, len) = &self.escapes[self.color_id[byte as usize] as usize];
+        &escape[..*len]
     }
 
     /// Returns the color ID for the given byte.
@@ -92,6 +340,44 @@ impl ByteToColor {
     pub fn id(&self, byte: u8) -> u8 {
         self.color_id[byte as usize]
     }
+
+    /// Returns the zero padded escape code for a color ID, and its actual length.
+    ///
+    /// The fixed size lets hot loops copy the escape code without a variable length copy,
+    /// and then only advance by the returned length.
+    #[inline]
+    pub fn escape(&self, id: u8) -> (&[u8; Self::MAX_ESCAPE_LEN], usize) {
+        let (escape, len) = &self.escapes[id as usize];
+        (escape, *len)
+    }
+
+    /// Writes the color ID of each byte in `bytes` into `out`.
+    ///
+    /// `out` must be at least as long as `bytes`. Combined with `simd::transitions` this
+    /// finds all color changes of a block at once.
+    #[inline]
+    pub fn ids(&self, bytes: &[u8], out: &mut [u8]) {
+        for (id, &byte) in out.iter_mut().zip(bytes) {
+            *id = self.color_id[byte as usize];
+        }
+    }
+}
+
+/// Shannon entropy of `data` in bits per byte, between 0 and 8.
+pub fn entropy(data: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let total = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
 }
 
 #[cfg(test)]
@@ -176,6 +462,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_byte_class() {
+        assert_eq!(ByteClass::of(0x00), ByteClass::Sentinel);
+        assert_eq!(ByteClass::of(b'\n'), ByteClass::Whitespace);
+        assert_eq!(ByteClass::of(0x1b), ByteClass::ControlChar);
+        assert_eq!(ByteClass::of(b'~'), ByteClass::PrintableSymbol);
+        assert_eq!(ByteClass::of(b'Z'), ByteClass::Alphanumeric);
+        assert_eq!(ByteClass::of(0xfe), ByteClass::ExtendedAscii);
+
+        let btc = ByteToColor::new();
+        for byte in 0..=255u8 {
+            assert_eq!(color(&btc, byte), Theme::DEFAULT.color(ByteClass::of(byte)));
+        }
+    }
+
     #[test]
     fn test_color_id_consistency() {
         let btc = ByteToColor::new();
@@ -193,6 +494,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_escape_matches_bytes() {
+        let btc = ByteToColor::new();
+        for byte in 0..=255u8 {
+            let (escape, len) = btc.escape(btc.id(byte));
+            assert_eq!(&escape[..len], btc.bytes(byte));
+        }
+    }
+
+    #[test]
+    fn test_monochrome_theme_has_no_colors() {
+        let btc = ByteToColor::with_theme(&Theme::MONOCHROME).unwrap();
+        for byte in 0..=255u8 {
+            assert_eq!(btc.id(byte), 0);
+            assert_eq!(color(&btc, byte), ByteToColor::RESET);
+        }
+    }
+
+    #[test]
+    fn test_basic_theme() {
+        let btc = ByteToColor::with_theme(&Theme::BASIC).unwrap();
+        assert_eq!(color(&btc, 0x00), Theme::BASIC.sentinel);
+        assert_eq!(color(&btc, 0x80), Theme::BASIC.extended_ascii);
+        assert_eq!(color(&btc, b'A'), ByteToColor::RESET);
+        assert_eq!(btc.id(b'A'), 0);
+    }
+
+    #[test]
+    fn test_escape_too_long() {
+        let theme = Theme {
+            extended_ascii: "\x1b[1;4;38;2;255;255;255;48;2;0;0;0m",
+            ..Theme::BASIC
+        };
+        assert!(matches!(
+            ByteToColor::with_theme(&theme),
+            Err(HexlerError::EscapeTooLong { max: 24, .. })
+        ));
+        assert!(ByteToColor::with_color_by(ColorBy::Value, &theme).is_ok());
+        assert!(ByteToColor::with_color_by(ColorBy::Class, &theme).is_err());
+    }
+
     #[test]
     fn test_default_trait() {
         let btc1 = ByteToColor::new();
@@ -228,4 +570,57 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[7u8; 100]), 0.0);
+        assert_eq!(entropy(b"abab"), 1.0);
+        let all: Vec<u8> = (0..=255u8).collect();
+        assert_eq!(entropy(&all), 8.0);
+    }
+
+    #[test]
+    fn test_value_gradient() {
+        let btc = ByteToColor::with_color_by(ColorBy::Value, &Theme::DEFAULT).unwrap();
+        assert_eq!(btc.line_id(b"abc", 0..3), None);
+        assert!(color(&btc, 0x00).starts_with("\x1b[38;2;"));
+        assert_eq!(btc.id(0x00), 1);
+        assert_eq!(btc.id(0xff), ByteToColor::GRADIENT_STEPS as u8);
+
+        // Neighbouring values share a color, distant values don't
+        assert_eq!(btc.id(0x40), btc.id(0x41));
+        assert_ne!(color(&btc, 0x00), color(&btc, 0x80));
+        assert_ne!(color(&btc, 0x80), color(&btc, 0xff));
+    }
+
+    #[test]
+    fn test_entropy_gradient() {
+        let btc = ByteToColor::with_color_by(ColorBy::Entropy, &Theme::DEFAULT).unwrap();
+        assert_eq!(btc.context_len(), ByteToColor::ENTROPY_CONTEXT);
+
+        let zeros = [0u8; 64];
+        assert_eq!(btc.line_id(&zeros, 16..32), Some(1));
+
+        let all: Vec<u8> = (0..=255u8).collect();
+        assert_eq!(
+            btc.line_id(&all, 128..144),
+            Some(ByteToColor::GRADIENT_STEPS as u8)
+        );
+
+        // The context is part of the entropy
+        let mut mixed = vec![0u8; 16];
+        mixed.extend(0..=255u8);
+        assert!(btc.line_id(&mixed, 0..16).unwrap() > 1);
+    }
+
+    #[test]
+    fn test_gradient_escapes_fit() {
+        for step in 0..=100 {
+            let escape = ByteToColor::gradient(step as f64 / 100.0);
+            assert!(escape.len() <= ByteToColor::MAX_ESCAPE_LEN);
+        }
+        assert_eq!(ByteToColor::gradient(0.0), "\x1b[38;2;70;90;220m");
+        assert_eq!(ByteToColor::gradient(1.0), "\x1b[38;2;235;60;50m");
+    }
 }
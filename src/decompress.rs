@@ -0,0 +1,198 @@
+use crate::error::{HexlerError, Result};
+use std::cell::Cell;
+use std::io::{BufRead, Read};
+use std::rc::Rc;
+
+/// Compression formats that `--decompress` detects by their magic bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Codec {
+    /// All codecs, in the order they are detected.
+    pub const ALL: [Codec; 4] = [Codec::Gzip, Codec::Zstd, Codec::Xz, Codec::Bzip2];
+
+    /// Name of the codec, as shown in the title and footer.
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+            Codec::Xz => "xz",
+            Codec::Bzip2 => "bzip2",
+        }
+    }
+
+    /// Returns the codec of data that starts with `magic`, `None` when it isn't compressed.
+    pub fn detect(magic: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|codec| match codec {
+            // Deflate is the only compression method of gzip
+            Codec::Gzip => magic.starts_with(b"\x1f\x8b\x08"),
+            Codec::Zstd => magic.starts_with(b"\x28\xb5\x2f\xfd"),
+            Codec::Xz => magic.starts_with(b"\xfd7zXZ\x00"),
+            // The fourth byte is the block size, '1' to '9'
+            Codec::Bzip2 => {
+                magic.starts_with(b"BZh") && magic.get(3).is_some_and(|b| (b'1'..=b'9').contains(b))
+            }
+        })
+    }
+
+    /// Wraps `reader` in a decoder of this codec. Concatenated streams, like `cat a.gz b.gz`
+    /// produces them, are decoded as one.
+    pub fn decoder<'a, R: BufRead + 'a>(self, reader: R) -> Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Codec::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+            Codec::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+            Codec::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+            Codec::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+        })
+    }
+
+    /// Like `decoder`, and adds the number of decompressed bytes to `len` as they are read.
+    /// The formats don't store the decompressed size reliably, e.g. gzip stores it modulo
+    /// 4 GiB and only of the last stream, so it is counted while the data is dumped.
+    ///
+    /// Read errors name the codec, like `invalid gzip data: ...`.
+    pub fn counting_decoder<'a, R: BufRead + 'a>(
+        self,
+        reader: R,
+        len: Rc<Cell<u64>>,
+    ) -> Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(CountingDecoder {
+            codec: self,
+            decoder: self.decoder(reader)?,
+            len,
+        }))
+    }
+}
+
+struct CountingDecoder<'a> {
+    codec: Codec,
+    decoder: Box<dyn Read + 'a>,
+    len: Rc<Cell<u64>>,
+}
+
+impl Read for CountingDecoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.decoder.read(buf).map_err(|err| {
+            let message = err.to_string();
+            std::io::Error::new(
+                err.kind(),
+                HexlerError::Decompress {
+                    codec: self.codec.name().to_string(),
+                    message,
+                },
+            )
+        })?;
+        self.len.set(self.len.get() + len as u64);
+        Ok(len)
+    }
+}
+
+/// Detects the codec of the data in `reader` without consuming it. Only the bytes that are
+/// already buffered are looked at, the magic bytes are at most 6 bytes long.
+pub fn detect<R: BufRead>(reader: &mut R) -> Result<Option<Codec>> {
+    Ok(Codec::detect(reader.fill_buf()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn compress(codec: Codec, data: &[u8]) -> Vec<u8> {
+        match codec {
+            Codec::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Codec::Zstd => zstd::encode_all(data, 3).unwrap(),
+            Codec::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Codec::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i * i % 251) as u8).collect();
+        for codec in Codec::ALL {
+            let compressed = compress(codec, &data);
+            assert_eq!(Codec::detect(&compressed), Some(codec), "{}", codec.name());
+
+            let mut decompressed = Vec::new();
+            codec
+                .decoder(&compressed[..])
+                .unwrap()
+                .read_to_end(&mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, data, "{}", codec.name());
+
+            let len = Rc::new(Cell::new(0));
+            let mut decoder = codec
+                .counting_decoder(&compressed[..], len.clone())
+                .unwrap();
+            std::io::copy(&mut decoder, &mut std::io::sink()).unwrap();
+            assert_eq!(len.get(), 10_000);
+        }
+    }
+
+    #[test]
+    fn test_concatenated_streams() {
+        for codec in Codec::ALL {
+            let mut compressed = compress(codec, b"hello ");
+            compressed.extend(compress(codec, b"world"));
+
+            let mut decompressed = Vec::new();
+            codec
+                .decoder(&compressed[..])
+                .unwrap()
+                .read_to_end(&mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, b"hello world", "{}", codec.name());
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Codec::detect(b""), None);
+        assert_eq!(Codec::detect(b"\x1f\x8b"), None);
+        assert_eq!(Codec::detect(b"BZh0"), None);
+        assert_eq!(Codec::detect(b"BZh9"), Some(Codec::Bzip2));
+        assert_eq!(Codec::detect(b"\x7fELF\x02\x01"), None);
+
+        let compressed = compress(Codec::Xz, b"data");
+        let mut reader = &compressed[..];
+        assert_eq!(detect(&mut reader).unwrap(), Some(Codec::Xz));
+        assert_eq!(reader.len(), compressed.len());
+    }
+
+    #[test]
+    fn test_corrupt_data() {
+        let mut compressed = compress(Codec::Gzip, &[7u8; 1000]);
+        let len = compressed.len();
+        compressed.truncate(len / 2);
+
+        let len = Rc::new(Cell::new(0));
+        let mut decoder = Codec::Gzip.counting_decoder(&compressed[..], len).unwrap();
+        let err = std::io::copy(&mut decoder, &mut std::io::sink()).unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid gzip data: "),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,805 @@
+use crate::ascii_renderer::{AsciiRenderer, Encoding};
+use crate::byte_to_color::{ByteToColor, ColorBy, Theme};
+use crate::error::Result;
+use crate::hex_formatter::HexFormatter;
+use crate::highlight::{Highlight, Highlights};
+use crate::line_writer::{Border, LineWriter};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Background of the bytes that are changed but not written yet.
+pub const MODIFIED_BACKGROUND: &str = "\x1b[48;5;88m";
+
+/// Background of the byte under the cursor, in both panels.
+pub const CURSOR_BACKGROUND: &str = "\x1b[48;5;240m";
+
+/// A change of a single byte, for undo and redo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Change {
+    offset: u64,
+    before: u8,
+    after: u8,
+}
+
+/// The bytes of a file with the changes that are not written yet.
+///
+/// Only the bytes that are asked for are read, the file is never loaded completely.
+pub struct Document<R> {
+    reader: R,
+    len: u64,
+    /// Changed bytes by offset, with their original and their new value
+    changes: BTreeMap<u64, (u8, u8)>,
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+}
+
+impl<R: Read + Seek> Document<R> {
+    /// Creates a document of the data in `reader`.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            reader,
+            len,
+            changes: BTreeMap::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        })
+    }
+
+    /// Size of the document in bytes, changes never change it.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of bytes that differ from the file.
+    pub fn num_changes(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Offsets of the changed bytes within `range`.
+    pub fn changed(&self, range: std::ops::Range<u64>) -> impl Iterator<Item = u64> + '_ {
+        self.changes.range(range).map(|(&offset, _)| offset)
+    }
+
+    /// Reads up to `len` bytes at `offset`, with the changes applied.
+    pub fn read(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let end = offset.saturating_add(len as u64).min(self.len);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        let mut data = vec![0u8; (end - offset) as usize];
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let read = crate::read_full(&mut self.reader, &mut data)?;
+        data.truncate(read);
+
+        for (&pos, &(_, value)) in self.changes.range(offset..offset + read as u64) {
+            data[(pos - offset) as usize] = value;
+        }
+        Ok(data)
+    }
+
+    /// The byte at `offset`, with the changes applied.
+    pub fn byte(&mut self, offset: u64) -> Result<u8> {
+        Ok(self.read(offset, 1)?.first().copied().unwrap_or(0))
+    }
+
+    /// Changes the byte at `offset` to `value`, this can be undone.
+    pub fn set(&mut self, offset: u64, value: u8) -> Result<()> {
+        let before = self.byte(offset)?;
+        if before != value {
+            self.apply(offset, value)?;
+            self.undo.push(Change {
+                offset,
+                before,
+                after: value,
+            });
+            self.redo.clear();
+        }
+        Ok(())
+    }
+
+    /// Reverts the last change, returns its offset.
+    pub fn undo(&mut self) -> Result<Option<u64>> {
+        let Some(change) = self.undo.pop() else {
+            return Ok(None);
+        };
+        self.apply(change.offset, change.before)?;
+        self.redo.push(change);
+        Ok(Some(change.offset))
+    }
+
+    /// Applies the last undone change again, returns its offset.
+    pub fn redo(&mut self) -> Result<Option<u64>> {
+        let Some(change) = self.redo.pop() else {
+            return Ok(None);
+        };
+        self.apply(change.offset, change.after)?;
+        self.undo.push(change);
+        Ok(Some(change.offset))
+    }
+
+    /// Writes the changed bytes to `writer`, which is the same file as the reader, and
+    /// returns their number. Adjacent bytes are written at once. The history of changes is
+    /// cleared, as the file now has the new bytes.
+    pub fn write<W: Write + Seek>(&mut self, writer: &mut W) -> Result<usize> {
+        let mut runs: Vec<(u64, Vec<u8>)> = Vec::new();
+        for (&offset, &(_, value)) in &self.changes {
+            match runs.last_mut() {
+                Some((start, bytes)) if *start + bytes.len() as u64 == offset => bytes.push(value),
+                _ => runs.push((offset, vec![value])),
+            }
+        }
+        for (offset, bytes) in &runs {
+            writer.seek(SeekFrom::Start(*offset))?;
+            writer.write_all(bytes)?;
+        }
+        writer.flush()?;
+
+        let num_changes = self.changes.len();
+        self.changes.clear();
+        self.undo.clear();
+        self.redo.clear();
+        Ok(num_changes)
+    }
+
+    /// Sets the byte at `offset` without recording the change.
+    fn apply(&mut self, offset: u64, value: u8) -> Result<()> {
+        let original = match self.changes.get(&offset) {
+            Some(&(original, _)) => original,
+            None => self.byte(offset)?,
+        };
+        if value == original {
+            self.changes.remove(&offset);
+        } else {
+            self.changes.insert(offset, (original, value));
+        }
+        Ok(())
+    }
+}
+
+/// The panel the cursor is in, typing overwrites hex digits or characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Hex,
+    Text,
+}
+
+/// What the caller of `Editor::handle_key` has to do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Continue,
+    /// The user confirmed writing the changes to the file
+    Write,
+    Quit,
+}
+
+/// A question or input line shown in the status line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Prompt {
+    None,
+    GoTo(String),
+    Write,
+    Quit,
+}
+
+/// Interactive hex editor: a cursor over the hex or text panel that overwrites bytes.
+///
+/// The editor doesn't depend on a terminal, `handle_key` processes a key and `render`
+/// writes the screen. `run` connects both to the terminal.
+pub struct Editor<R> {
+    document: Document<R>,
+    title: String,
+    encoding: Encoding,
+    color_by: ColorBy,
+    bytes_per_line: usize,
+    rows: usize,
+    offset_width: usize,
+    /// Offset of the first byte on the screen, always at the start of a line
+    top: u64,
+    cursor: u64,
+    low_nibble: bool,
+    panel: Panel,
+    prompt: Prompt,
+    message: String,
+}
+
+impl<R: Read + Seek> Editor<R> {
+    /// Lines on the screen besides the data: title, borders and status line.
+    pub const EXTRA_ROWS: usize = 4;
+
+    /// Creates an editor of `document`, with `title` on top.
+    pub fn new(document: Document<R>, title: &str) -> Self {
+        // Offsets of large files have more digits, all lines need the same layout
+        let digits = (64 - document.len().leading_zeros() as usize + 3) / 4;
+        Self {
+            document,
+            title: title.to_string(),
+            encoding: Encoding::default(),
+            color_by: ColorBy::default(),
+            bytes_per_line: 16,
+            rows: 16,
+            offset_width: digits.max(HexFormatter::new().offset_width()),
+            top: 0,
+            cursor: 0,
+            low_nibble: false,
+            panel: Panel::Hex,
+            prompt: Prompt::None,
+            message: String::new(),
+        }
+    }
+
+    /// Sets the encoding of the text panel.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets what the byte colors are based on.
+    pub fn with_color_by(mut self, color_by: ColorBy) -> Self {
+        self.color_by = color_by;
+        self
+    }
+
+    /// Moves the cursor to `offset`.
+    pub fn with_cursor(mut self, offset: u64) -> Self {
+        self.move_to(offset);
+        self
+    }
+
+    pub fn document(&self) -> &Document<R> {
+        &self.document
+    }
+
+    pub fn document_mut(&mut self) -> &mut Document<R> {
+        &mut self.document
+    }
+
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    pub fn panel(&self) -> Panel {
+        self.panel
+    }
+
+    /// Sets the size of the data area, `bytes_per_line` must be a multiple of 8.
+    pub fn resize(&mut self, bytes_per_line: usize, rows: usize) {
+        self.bytes_per_line = bytes_per_line;
+        self.rows = rows.max(1);
+        self.top = self.top / bytes_per_line as u64 * bytes_per_line as u64;
+        self.scroll();
+    }
+
+    /// Shows `message` in the status line until the next key.
+    pub fn set_message(&mut self, message: String) {
+        self.message = message;
+    }
+
+    /// Processes a key press.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Result<Action> {
+        self.message.clear();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let yes = key.code == KeyCode::Char('y') && !ctrl;
+
+        match std::mem::replace(&mut self.prompt, Prompt::None) {
+            Prompt::None => {}
+            Prompt::GoTo(mut text) => {
+                match key.code {
+                    KeyCode::Enter => match crate::parse_offset(&text) {
+                        Ok(offset) => self.move_to(offset as u64),
+                        Err(err) => self.message = err,
+                    },
+                    KeyCode::Esc => {}
+                    KeyCode::Backspace => {
+                        text.pop();
+                        self.prompt = Prompt::GoTo(text);
+                    }
+                    KeyCode::Char(c) if !ctrl => {
+                        text.push(c);
+                        self.prompt = Prompt::GoTo(text);
+                    }
+                    _ => self.prompt = Prompt::GoTo(text),
+                }
+                return Ok(Action::Continue);
+            }
+            Prompt::Write if yes => return Ok(Action::Write),
+            Prompt::Quit if yes => return Ok(Action::Quit),
+            Prompt::Write | Prompt::Quit => {
+                self.message = "cancelled".to_string();
+                return Ok(Action::Continue);
+            }
+        }
+
+        let line = self.bytes_per_line as i64;
+        let page = line * self.rows as i64;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Char('c') if ctrl => return Ok(self.quit()),
+            KeyCode::Esc => return Ok(self.quit()),
+            KeyCode::Char('s') if ctrl => match self.document.num_changes() {
+                0 => self.message = "no changes to write".to_string(),
+                _ => self.prompt = Prompt::Write,
+            },
+            KeyCode::Char('z') if ctrl => match self.document.undo()? {
+                Some(offset) => self.move_to(offset),
+                None => self.message = "nothing to undo".to_string(),
+            },
+            KeyCode::Char('y') if ctrl => match self.document.redo()? {
+                Some(offset) => self.move_to(offset),
+                None => self.message = "nothing to redo".to_string(),
+            },
+            KeyCode::Char('g') if ctrl => self.prompt = Prompt::GoTo(String::new()),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.panel = match self.panel {
+                    Panel::Hex => Panel::Text,
+                    Panel::Text => Panel::Hex,
+                };
+                self.low_nibble = false;
+            }
+            KeyCode::Left => self.move_by(-1),
+            KeyCode::Right => self.move_by(1),
+            KeyCode::Up => self.move_by(-line),
+            KeyCode::Down => self.move_by(line),
+            KeyCode::PageUp => self.move_by(-page),
+            KeyCode::PageDown => self.move_by(page),
+            KeyCode::Home if ctrl => self.move_to(0),
+            KeyCode::End if ctrl => self.move_to(u64::MAX),
+            KeyCode::Home => self.move_to(self.cursor - self.cursor % line as u64),
+            KeyCode::End => self.move_to(self.cursor - self.cursor % line as u64 + line as u64 - 1),
+            KeyCode::Char(c) if !ctrl => self.type_char(c)?,
+            _ => {}
+        }
+        Ok(Action::Continue)
+    }
+
+    /// Writes the whole screen to `buffer`: title, lines, borders and the status line, and
+    /// moves the terminal cursor to the typing position.
+    pub fn render(&mut self, buffer: &mut Vec<u8>) -> Result<()> {
+        let bytes_per_line = self.bytes_per_line as u64;
+        let screen_len = self.rows as u64 * bytes_per_line;
+
+        let mut highlights = Highlights::new();
+        for offset in self.document.changed(self.top..self.top + screen_len) {
+            highlights.add(Highlight::new(offset..offset + 1, MODIFIED_BACKGROUND));
+        }
+        if !self.document.is_empty() {
+            highlights.add(Highlight::new(
+                self.cursor..self.cursor + 1,
+                CURSOR_BACKGROUND,
+            ));
+        }
+        let line_writer = LineWriter::new_bytes(self.bytes_per_line)?
+            .with_hex_formatter(HexFormatter::new().with_offset_width(self.offset_width))
+            .with_ascii_renderer(AsciiRenderer::with_encoding(self.encoding))
+            .with_byte_to_color(ByteToColor::with_color_by(self.color_by, &Theme::DEFAULT)?)
+            .with_highlights(highlights);
+
+        // The bytes on the screen, and the context around them for multibyte encodings
+        let context_len = line_writer.context_len() as u64;
+        let window_start = self.top.saturating_sub(context_len);
+        let window_len = (self.top - window_start + screen_len + context_len) as usize;
+        let window = self.document.read(window_start, window_len)?;
+
+        let mut screen = Vec::new();
+        line_writer.write_border(&mut screen, Border::Header, &self.title)?;
+        for row in 0..self.rows as u64 {
+            let start = (self.top + row * bytes_per_line - window_start) as usize;
+            let end = (start + self.bytes_per_line).min(window.len());
+            if start < end {
+                let offset = (self.top + row * bytes_per_line) as usize;
+                line_writer.write_line_in_context(&mut screen, offset, &window, start..end);
+            } else {
+                screen.push(b'\n');
+            }
+        }
+        line_writer.write_border(&mut screen, Border::Footer, "")?;
+
+        // Every line is cleared to its end, nothing may scroll the screen in raw mode
+        buffer.extend_from_slice(b"\x1b[H");
+        let screen = screen.strip_suffix(b"\n").unwrap_or(&screen);
+        for line in screen.split(|&b| b == b'\n') {
+            buffer.extend_from_slice(line);
+            buffer.extend_from_slice(b"\x1b[0m\x1b[K\r\n");
+        }
+        let status = self.status();
+        buffer.extend_from_slice(status.as_bytes());
+        buffer.extend_from_slice(b"\x1b[K\x1b[J");
+
+        let (row, column) = match self.prompt {
+            Prompt::None => self.cursor_position(),
+            _ => (self.rows + 3, status.chars().count()),
+        };
+        buffer.extend_from_slice(format!("\x1b[{};{}H", row + 1, column + 1).as_bytes());
+        Ok(())
+    }
+
+    /// Row and column of the typing position on the screen, both starting at 0.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let bytes_per_line = self.bytes_per_line;
+        let row = 2 + ((self.cursor - self.top) / bytes_per_line as u64) as usize;
+        let pos = (self.cursor % bytes_per_line as u64) as usize;
+        // Same layout as LineWriter: `offset │ xx xx ... xx  xx ... │ text`
+        let column = match self.panel {
+            Panel::Hex => self.offset_width + 3 + 3 * pos + pos / 8 + self.low_nibble as usize,
+            Panel::Text => self.offset_width + 4 + 3 * bytes_per_line + bytes_per_line / 8 + pos,
+        };
+        (row, column)
+    }
+
+    /// The status line: a prompt, a message or the position and the keys.
+    fn status(&self) -> String {
+        match &self.prompt {
+            Prompt::GoTo(text) => return format!("go to offset: {}", text),
+            Prompt::Write => {
+                return format!(
+                    "write {} to the file? (y/n) ",
+                    changed_bytes(self.document.num_changes())
+                )
+            }
+            Prompt::Quit => {
+                return format!(
+                    "quit and discard {}? (y/n) ",
+                    changed_bytes(self.document.num_changes())
+                )
+            }
+            Prompt::None => {}
+        }
+        if !self.message.is_empty() {
+            return self.message.clone();
+        }
+        format!(
+            "0x{:x} ({})  {}  {}   Tab panel  ^Z undo  ^Y redo  ^G go to  ^S write  ^Q quit",
+            self.cursor,
+            self.cursor,
+            match self.panel {
+                Panel::Hex => "hex",
+                Panel::Text => "text",
+            },
+            changed_bytes(self.document.num_changes())
+        )
+    }
+
+    fn quit(&mut self) -> Action {
+        match self.document.num_changes() {
+            0 => Action::Quit,
+            _ => {
+                self.prompt = Prompt::Quit;
+                Action::Continue
+            }
+        }
+    }
+
+    /// Overwrites the nibble or byte under the cursor with a typed character.
+    fn type_char(&mut self, c: char) -> Result<()> {
+        if self.document.is_empty() {
+            self.message = "the file is empty".to_string();
+            return Ok(());
+        }
+        match self.panel {
+            Panel::Hex => {
+                let Some(digit) = c.to_digit(16) else {
+                    self.message = "type a hex digit, Tab switches to the text panel".to_string();
+                    return Ok(());
+                };
+                let byte = self.document.byte(self.cursor)?;
+                let value = match self.low_nibble {
+                    true => byte & 0xf0 | digit as u8,
+                    false => (digit as u8) << 4 | byte & 0x0f,
+                };
+                self.document.set(self.cursor, value)?;
+                match self.low_nibble {
+                    true => self.move_by(1),
+                    false => self.low_nibble = true,
+                }
+            }
+            Panel::Text => {
+                // Stored as the byte that the text panel shows as the typed character
+                let Some(byte) = self.encoding.encode_ascii(c) else {
+                    self.message = match self.encoding {
+                        Encoding::Utf16le | Encoding::Utf16be => {
+                            "UTF-16 text can't be typed, type hex digits in the hex panel"
+                        }
+                        _ => "only ASCII characters can be typed",
+                    }
+                    .to_string();
+                    return Ok(());
+                };
+                self.document.set(self.cursor, byte)?;
+                self.move_by(1);
+            }
+        }
+        Ok(())
+    }
+
+    fn move_by(&mut self, delta: i64) {
+        let offset = match delta < 0 {
+            true => self.cursor.saturating_sub(delta.unsigned_abs()),
+            false => self.cursor.saturating_add(delta as u64),
+        };
+        self.move_to(offset);
+    }
+
+    /// Moves the cursor to `offset`, or the last byte, and scrolls it into view.
+    fn move_to(&mut self, offset: u64) {
+        self.cursor = offset.min(self.document.len().saturating_sub(1));
+        self.low_nibble = false;
+        self.scroll();
+    }
+
+    fn scroll(&mut self) {
+        let bytes_per_line = self.bytes_per_line as u64;
+        let line = self.cursor / bytes_per_line * bytes_per_line;
+        if line < self.top {
+            self.top = line;
+        } else if line >= self.top + self.rows as u64 * bytes_per_line {
+            self.top = line - (self.rows as u64 - 1) * bytes_per_line;
+        }
+    }
+}
+
+impl<R: Read + Seek> Editor<R> {
+    /// Runs the editor in the terminal until the user quits, in the alternate screen.
+    ///
+    /// The lines are `bytes_per_line` long or as long as fits into the terminal. `write` is
+    /// called to write the changes to the file.
+    pub fn run<F>(mut self, bytes_per_line: Option<usize>, mut write: F) -> Result<()>
+    where
+        F: FnMut(&mut Document<R>) -> Result<usize>,
+    {
+        use crossterm::terminal;
+
+        let mut stdout = std::io::stdout();
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(stdout, terminal::EnterAlternateScreen)?;
+
+        let result = (|| -> Result<()> {
+            loop {
+                let (width, height) = terminal::size()?;
+                let bytes_per_line = match bytes_per_line {
+                    Some(bytes_per_line) => bytes_per_line,
+                    None => {
+                        let extra_width = self.offset_width - HexFormatter::new().offset_width();
+                        let width = (width as usize).saturating_sub(extra_width);
+                        LineWriter::new_max_width(width)?.bytes_per_line()
+                    }
+                };
+                let rows = (height as usize).saturating_sub(Self::EXTRA_ROWS);
+                self.resize(bytes_per_line, rows);
+
+                let mut buffer = Vec::new();
+                self.render(&mut buffer)?;
+                stdout.write_all(&buffer)?;
+                stdout.flush()?;
+
+                let crossterm::event::Event::Key(key) = crossterm::event::read()? else {
+                    continue;
+                };
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
+                match self.handle_key(key)? {
+                    Action::Continue => {}
+                    Action::Quit => return Ok(()),
+                    Action::Write => match write(&mut self.document) {
+                        Ok(num_changes) => {
+                            self.message = format!("wrote {}", changed_bytes(num_changes))
+                        }
+                        Err(err) => self.message = format!("write failed: {}", err),
+                    },
+                }
+            }
+        })();
+
+        // The terminal is restored on errors too
+        crossterm::execute!(stdout, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+}
+
+fn changed_bytes(num: usize) -> String {
+    match num {
+        1 => "1 changed byte".to_string(),
+        _ => format!("{} changed bytes", num),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn type_text(editor: &mut Editor<Cursor<Vec<u8>>>, text: &str) {
+        for c in text.chars() {
+            assert_eq!(
+                editor.handle_key(key(KeyCode::Char(c))).unwrap(),
+                Action::Continue
+            );
+        }
+    }
+
+    fn new_editor(data: &[u8]) -> Editor<Cursor<Vec<u8>>> {
+        let mut editor = Editor::new(Document::new(Cursor::new(data.to_vec())).unwrap(), "title");
+        editor.resize(8, 2);
+        editor
+    }
+
+    fn contents(editor: &mut Editor<Cursor<Vec<u8>>>) -> Vec<u8> {
+        let len = editor.document().len() as usize;
+        editor.document_mut().read(0, len).unwrap()
+    }
+
+    #[test]
+    fn test_document_changes() {
+        let mut document = Document::new(Cursor::new(b"hello world".to_vec())).unwrap();
+        document.set(0, b'j').unwrap();
+        document.set(4, b'y').unwrap();
+        document.set(4, b'o').unwrap();
+        assert_eq!(document.read(0, 100).unwrap(), b"jello world");
+        assert_eq!(document.read(3, 3).unwrap(), b"lo ");
+        // Setting a byte back to its original value is no change
+        assert_eq!(document.num_changes(), 1);
+
+        assert_eq!(document.undo().unwrap(), Some(4));
+        assert_eq!(document.read(0, 5).unwrap(), b"jelly");
+        assert_eq!(document.undo().unwrap(), Some(4));
+        assert_eq!(document.undo().unwrap(), Some(0));
+        assert_eq!(document.undo().unwrap(), None);
+        assert_eq!(document.read(0, 5).unwrap(), b"hello");
+        assert_eq!(document.num_changes(), 0);
+
+        assert_eq!(document.redo().unwrap(), Some(0));
+        assert_eq!(document.read(0, 5).unwrap(), b"jello");
+        document.set(10, b'D').unwrap();
+        assert_eq!(document.redo().unwrap(), None);
+        assert_eq!(document.changed(0..11).collect::<Vec<_>>(), [0, 10]);
+    }
+
+    #[test]
+    fn test_document_write() {
+        let mut document = Document::new(Cursor::new(b"hello world".to_vec())).unwrap();
+        for (offset, byte) in [(0, b'H'), (1, b'E'), (6, b'W')] {
+            document.set(offset, byte).unwrap();
+        }
+
+        let mut file = Cursor::new(b"hello world".to_vec());
+        assert_eq!(document.write(&mut file).unwrap(), 3);
+        assert_eq!(file.into_inner(), b"HEllo World");
+        assert_eq!(document.num_changes(), 0);
+        assert_eq!(document.undo().unwrap(), None);
+    }
+
+    #[test]
+    fn test_type_hex() {
+        let mut editor = new_editor(b"\x00\x00\x00");
+        type_text(&mut editor, "aB1");
+        assert_eq!(contents(&mut editor), b"\xab\x10\x00");
+        assert_eq!(editor.cursor(), 1);
+        assert_eq!(editor.cursor_position(), (2, 8 + 3 + 3 + 1));
+
+        // Invalid digits change nothing, the last byte stays under the cursor
+        type_text(&mut editor, "x");
+        editor.handle_key(key(KeyCode::End)).unwrap();
+        assert_eq!(editor.cursor(), 2);
+        type_text(&mut editor, "ff");
+        assert_eq!(contents(&mut editor), b"\xab\x10\xff");
+        assert_eq!(editor.cursor(), 2);
+
+        editor.handle_key(ctrl('z')).unwrap();
+        editor.handle_key(ctrl('z')).unwrap();
+        assert_eq!(contents(&mut editor), b"\xab\x10\x00");
+        editor.handle_key(ctrl('y')).unwrap();
+        assert_eq!(contents(&mut editor), b"\xab\x10\xf0");
+    }
+
+    #[test]
+    fn test_type_text() {
+        let mut editor = new_editor(b"hello world");
+        editor.handle_key(key(KeyCode::Tab)).unwrap();
+        assert_eq!(editor.panel(), Panel::Text);
+        type_text(&mut editor, "HEL");
+        editor.handle_key(key(KeyCode::Char('ä'))).unwrap();
+        assert_eq!(contents(&mut editor), b"HELlo world");
+        assert_eq!(editor.cursor_position(), (2, 8 + 4 + 24 + 1 + 3));
+    }
+
+    #[test]
+    fn test_type_text_ebcdic() {
+        let mut editor = new_editor(b"hello world").with_encoding(Encoding::Ebcdic);
+        editor.handle_key(key(KeyCode::Tab)).unwrap();
+        type_text(&mut editor, "A.");
+        assert_eq!(&contents(&mut editor)[..3], b"\xc1\x4bl");
+
+        let mut editor = new_editor(b"hello world").with_encoding(Encoding::Utf16le);
+        editor.handle_key(key(KeyCode::Tab)).unwrap();
+        type_text(&mut editor, "A");
+        assert_eq!(contents(&mut editor), b"hello world");
+    }
+
+    #[test]
+    fn test_movement() {
+        let mut editor = new_editor(&[0u8; 100]);
+        editor.handle_key(key(KeyCode::Left)).unwrap();
+        assert_eq!(editor.cursor(), 0);
+        editor.handle_key(key(KeyCode::PageDown)).unwrap();
+        assert_eq!(editor.cursor(), 16);
+        assert_eq!(editor.cursor_position(), (3, 11));
+        editor.handle_key(key(KeyCode::Down)).unwrap();
+        assert_eq!(editor.cursor(), 24);
+        assert_eq!(editor.cursor_position(), (3, 11));
+        editor.handle_key(key(KeyCode::End)).unwrap();
+        assert_eq!(editor.cursor(), 31);
+
+        let end = KeyEvent::new(KeyCode::End, KeyModifiers::CONTROL);
+        editor.handle_key(end).unwrap();
+        assert_eq!(editor.cursor(), 99);
+        assert_eq!(editor.cursor_position(), (3, 11 + 3 * 3));
+
+        editor.handle_key(ctrl('g')).unwrap();
+        type_text(&mut editor, "0x2x");
+        editor.handle_key(key(KeyCode::Backspace)).unwrap();
+        type_text(&mut editor, "a");
+        editor.handle_key(key(KeyCode::Enter)).unwrap();
+        assert_eq!(editor.cursor(), 0x2a);
+    }
+
+    #[test]
+    fn test_confirmations() {
+        let mut editor = new_editor(b"abc");
+        assert_eq!(editor.handle_key(ctrl('s')).unwrap(), Action::Continue);
+        type_text(&mut editor, "4");
+
+        assert_eq!(editor.handle_key(ctrl('s')).unwrap(), Action::Continue);
+        assert_eq!(
+            editor.handle_key(key(KeyCode::Char('n'))).unwrap(),
+            Action::Continue
+        );
+        assert_eq!(editor.handle_key(ctrl('s')).unwrap(), Action::Continue);
+        assert_eq!(
+            editor.handle_key(key(KeyCode::Char('y'))).unwrap(),
+            Action::Write
+        );
+
+        assert_eq!(editor.handle_key(ctrl('q')).unwrap(), Action::Continue);
+        assert_eq!(
+            editor.handle_key(key(KeyCode::Char('y'))).unwrap(),
+            Action::Quit
+        );
+
+        let mut editor = new_editor(b"abc");
+        assert_eq!(editor.handle_key(key(KeyCode::Esc)).unwrap(), Action::Quit);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut editor = new_editor(b"0123456789abcdefXYZ");
+        editor.handle_key(key(KeyCode::Down)).unwrap();
+        type_text(&mut editor, "7");
+        editor.handle_key(key(KeyCode::Down)).unwrap();
+
+        let mut buffer = Vec::new();
+        editor.render(&mut buffer).unwrap();
+        let screen = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = screen.split("\r\n").collect();
+
+        assert!(lines[0].starts_with("\x1b[Htitle"));
+        // Scrolled down by one line, the changed byte has its own background
+        assert!(lines[2].contains("\x1b[0m8 │ "));
+        assert!(lines[2].contains(MODIFIED_BACKGROUND));
+        assert!(lines[3].contains(CURSOR_BACKGROUND));
+        assert!(lines[5].starts_with("0x10 (16)  hex  1 changed byte"));
+        assert!(lines[5].ends_with("\x1b[4;12H"));
+    }
+}
@@ -0,0 +1,387 @@
+use crate::error::Result;
+use crate::executable::{
+    entries, highlight_entries, highlight_fields, invalid, permissions, printable_name, read_at,
+    Executable, Fields, Section,
+};
+use crate::highlight::Highlights;
+use std::io::{Read, Seek};
+
+/// Offset and length of the fields of the 32-bit ELF header. `e_ident` is split into the
+/// magic number, class, data encoding, version, OS ABI, ABI version and padding.
+const HEADER_FIELDS_32: [(u64, u64); 20] = [
+    (0, 4),
+    (4, 1),
+    (5, 1),
+    (6, 1),
+    (7, 1),
+    (8, 1),
+    (9, 7),
+    (16, 2),
+    (18, 2),
+    (20, 4),
+    (24, 4),
+    (28, 4),
+    (32, 4),
+    (36, 4),
+    (40, 2),
+    (42, 2),
+    (44, 2),
+    (46, 2),
+    (48, 2),
+    (50, 2),
+];
+
+/// Offset and length of the fields of the 64-bit ELF header.
+const HEADER_FIELDS_64: [(u64, u64); 20] = [
+    (0, 4),
+    (4, 1),
+    (5, 1),
+    (6, 1),
+    (7, 1),
+    (8, 1),
+    (9, 7),
+    (16, 2),
+    (18, 2),
+    (20, 4),
+    (24, 8),
+    (32, 8),
+    (40, 8),
+    (48, 4),
+    (52, 2),
+    (54, 2),
+    (56, 2),
+    (58, 2),
+    (60, 2),
+    (62, 2),
+];
+
+/// Parses the ELF header, program headers and section headers.
+///
+/// # Errors
+/// Returns `InvalidExecutable` if the headers are broken or point outside of the file.
+pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<Executable> {
+    let ident = read_at(reader, 0, 16, "ELF identification")?;
+    let is_64 = match ident[4] {
+        1 => false,
+        2 => true,
+        class => return Err(invalid(format!("unknown ELF class {}", class))),
+    };
+    let little_endian = match ident[5] {
+        1 => true,
+        2 => false,
+        data => return Err(invalid(format!("unknown ELF data encoding {}", data))),
+    };
+    let fields = Fields::new(is_64, little_endian);
+
+    let header_len = if is_64 { 64 } else { 52 };
+    let header = read_at(reader, 0, header_len, "ELF header")?;
+    let description = crate::signatures::describe_elf(&header)
+        .ok_or_else(|| invalid("unsupported ELF header".to_string()))?;
+
+    let phoff = fields.word(&header, 28, 32);
+    let shoff = fields.word(&header, 32, 40);
+    let phentsize = fields.u16(&header, if is_64 { 54 } else { 42 });
+    let phnum = fields.u16(&header, if is_64 { 56 } else { 44 });
+    let shentsize = fields.u16(&header, if is_64 { 58 } else { 46 });
+    let shnum = fields.u16(&header, if is_64 { 60 } else { 48 });
+    let shstrndx = fields.u16(&header, if is_64 { 62 } else { 50 });
+
+    let mut executable = Executable {
+        description,
+        ..Executable::default()
+    };
+    let mut table_rows = vec![(0, format!("ELF header ({} bytes)", header_len))];
+    let mut highlights = Highlights::new();
+    let header_fields = if is_64 {
+        &HEADER_FIELDS_64
+    } else {
+        &HEADER_FIELDS_32
+    };
+    highlight_fields(&mut highlights, 0, header_fields);
+
+    if phnum > 0 {
+        let min_entsize = if is_64 { 56 } else { 32 };
+        if phentsize < min_entsize {
+            return Err(invalid(format!(
+                "program header entry size {} is too small",
+                phentsize
+            )));
+        }
+        let table = read_at(reader, phoff, phnum * phentsize, "program header table")?;
+        table_rows.push((phoff, format!("program headers ({})", entries(phnum))));
+        highlight_entries(&mut highlights, phoff, phnum, phentsize);
+
+        for entry in table.chunks_exact(phentsize as usize) {
+            let kind = fields.u32(entry, 0);
+            let flags = fields.u32(entry, if is_64 { 4 } else { 24 });
+            let offset = fields.word(entry, 4, 8);
+            let vaddr = fields.word(entry, 8, 16);
+            let filesz = fields.word(entry, 16, 32);
+            if filesz == 0 {
+                continue;
+            }
+            executable.rows.push((
+                offset,
+                format!(
+                    "segment {} ({}, 0x{:x} bytes at 0x{:x})",
+                    segment_type(kind),
+                    permissions(flags & 4 != 0, flags & 2 != 0, flags & 1 != 0),
+                    filesz,
+                    vaddr
+                ),
+            ));
+        }
+    }
+
+    if shnum > 0 && shoff > 0 {
+        let min_entsize = if is_64 { 64 } else { 40 };
+        if shentsize < min_entsize {
+            return Err(invalid(format!(
+                "section header entry size {} is too small",
+                shentsize
+            )));
+        }
+        let table = read_at(reader, shoff, shnum * shentsize, "section header table")?;
+        table_rows.push((shoff, format!("section headers ({})", entries(shnum))));
+        highlight_entries(&mut highlights, shoff, shnum, shentsize);
+
+        let headers: Vec<&[u8]> = table.chunks_exact(shentsize as usize).collect();
+        let names = match headers.get(shstrndx as usize) {
+            Some(entry) if shstrndx != 0 => read_at(
+                reader,
+                fields.word(entry, 16, 24),
+                fields.word(entry, 20, 32),
+                "section name table",
+            )?,
+            _ => Vec::new(),
+        };
+
+        // The first entry is always the reserved null section
+        for entry in headers.iter().skip(1) {
+            let kind = fields.u32(entry, 4);
+            let section = Section {
+                name: section_name(&names, fields.u32(entry, 0) as usize),
+                kind: section_type(kind),
+                offset: fields.word(entry, 16, 24),
+                size: match kind {
+                    SHT_NOBITS => 0,
+                    _ => fields.word(entry, 20, 32),
+                },
+                address: fields.word(entry, 12, 16),
+            };
+            if section.size > 0 {
+                executable.rows.push((
+                    section.offset,
+                    format!(
+                        "{} ({}, 0x{:x} bytes)",
+                        section.name, section.kind, section.size
+                    ),
+                ));
+            }
+            executable.sections.push(section);
+        }
+    }
+
+    // Segments contain the headers and sections, so their rows come first at the same offset
+    executable.rows.extend(table_rows);
+    executable.highlights = highlights;
+    Ok(executable)
+}
+
+/// Section type of sections without data in the file, e.g. `.bss`.
+const SHT_NOBITS: u64 = 8;
+
+fn section_name(names: &[u8], pos: usize) -> String {
+    let name = names.get(pos..).unwrap_or_default();
+    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    printable_name(&name[..len])
+}
+
+fn segment_type(kind: u64) -> String {
+    match kind {
+        0 => "NULL".to_string(),
+        1 => "LOAD".to_string(),
+        2 => "DYNAMIC".to_string(),
+        3 => "INTERP".to_string(),
+        4 => "NOTE".to_string(),
+        5 => "SHLIB".to_string(),
+        6 => "PHDR".to_string(),
+        7 => "TLS".to_string(),
+        0x6474_e550 => "GNU_EH_FRAME".to_string(),
+        0x6474_e551 => "GNU_STACK".to_string(),
+        0x6474_e552 => "GNU_RELRO".to_string(),
+        0x6474_e553 => "GNU_PROPERTY".to_string(),
+        kind => format!("0x{:x}", kind),
+    }
+}
+
+fn section_type(kind: u64) -> String {
+    match kind {
+        0 => "NULL".to_string(),
+        1 => "PROGBITS".to_string(),
+        2 => "SYMTAB".to_string(),
+        3 => "STRTAB".to_string(),
+        4 => "RELA".to_string(),
+        5 => "HASH".to_string(),
+        6 => "DYNAMIC".to_string(),
+        7 => "NOTE".to_string(),
+        SHT_NOBITS => "NOBITS".to_string(),
+        9 => "REL".to_string(),
+        11 => "DYNSYM".to_string(),
+        14 => "INIT_ARRAY".to_string(),
+        15 => "FINI_ARRAY".to_string(),
+        16 => "PREINIT_ARRAY".to_string(),
+        17 => "GROUP".to_string(),
+        18 => "SYMTAB_SHNDX".to_string(),
+        0x6fff_fff6 => "GNU_HASH".to_string(),
+        0x6fff_fffd => "VERDEF".to_string(),
+        0x6fff_fffe => "VERNEED".to_string(),
+        0x6fff_ffff => "VERSYM".to_string(),
+        kind => format!("0x{:x}", kind),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::HexlerError;
+    use std::io::Cursor;
+
+    /// Builds a tiny 64-bit little endian executable with one `LOAD` segment, a `.text`,
+    /// a `.bss` and the section name table.
+    fn tiny_elf() -> Vec<u8> {
+        let mut data = vec![0u8; 0x200];
+        data[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+        data[16..18].copy_from_slice(&2u16.to_le_bytes()); // executable
+        data[18..20].copy_from_slice(&62u16.to_le_bytes()); // x86-64
+        data[20..24].copy_from_slice(&1u32.to_le_bytes());
+        data[32..40].copy_from_slice(&0x40u64.to_le_bytes()); // phoff
+        data[40..48].copy_from_slice(&0x100u64.to_le_bytes()); // shoff
+        data[52..54].copy_from_slice(&64u16.to_le_bytes());
+        data[54..56].copy_from_slice(&56u16.to_le_bytes());
+        data[56..58].copy_from_slice(&1u16.to_le_bytes());
+        data[58..60].copy_from_slice(&64u16.to_le_bytes());
+        data[60..62].copy_from_slice(&4u16.to_le_bytes());
+        data[62..64].copy_from_slice(&3u16.to_le_bytes()); // shstrndx
+
+        // LOAD R-X covering the first 0x100 bytes
+        let ph = &mut data[0x40..0x78];
+        ph[0..4].copy_from_slice(&1u32.to_le_bytes());
+        ph[4..8].copy_from_slice(&5u32.to_le_bytes());
+        ph[16..24].copy_from_slice(&0x40_0000u64.to_le_bytes());
+        ph[32..40].copy_from_slice(&0x100u64.to_le_bytes());
+
+        data[0x80..0x90].copy_from_slice(b"\x90\x90\x90\xc3hello world!");
+        data[0xa0..0xb6].copy_from_slice(b"\0.text\0.bss\0.shstrtab\0");
+
+        let sections: [(u32, u32, u64, u64, u64); 3] = [
+            (1, 1, 0x40_0080, 0x80, 0x10),
+            (7, 8, 0x40_1000, 0x90, 0x20),
+            (12, 3, 0, 0xa0, 0x16),
+        ];
+        for (idx, (name, kind, addr, offset, size)) in sections.iter().enumerate() {
+            let sh = &mut data[0x140 + idx * 64..0x180 + idx * 64];
+            sh[0..4].copy_from_slice(&name.to_le_bytes());
+            sh[4..8].copy_from_slice(&kind.to_le_bytes());
+            sh[16..24].copy_from_slice(&addr.to_le_bytes());
+            sh[24..32].copy_from_slice(&offset.to_le_bytes());
+            sh[32..40].copy_from_slice(&size.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_tiny_elf() {
+        let executable = Executable::parse(&mut Cursor::new(tiny_elf()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(executable.description, "ELF 64-bit LSB executable, x86-64");
+
+        let names: Vec<&str> = executable
+            .sections
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(names, vec![".text", ".bss", ".shstrtab"]);
+        let text = executable.section(".text").unwrap();
+        assert_eq!(
+            (text.offset, text.size, text.address),
+            (0x80, 0x10, 0x40_0080)
+        );
+        assert_eq!(executable.section(".bss").unwrap().size, 0);
+
+        let rows: Vec<(u64, &str)> = executable
+            .rows
+            .iter()
+            .map(|(offset, label)| (*offset, label.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (0, "segment LOAD (R-X, 0x100 bytes at 0x400000)"),
+                (0x80, ".text (PROGBITS, 0x10 bytes)"),
+                (0xa0, ".shstrtab (STRTAB, 0x16 bytes)"),
+                (0, "ELF header (64 bytes)"),
+                (0x40, "program headers (1 entry)"),
+                (0x100, "section headers (4 entries)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_field_highlights() {
+        let executable = Executable::parse(&mut Cursor::new(tiny_elf()))
+            .unwrap()
+            .unwrap();
+        let highlights = executable.highlights.overlapping(0..0x40);
+        assert_eq!(highlights.len(), 20);
+        assert_eq!(highlights[0].range, 0..4);
+        assert_eq!(highlights[19].range, 62..64);
+        assert_ne!(highlights[0].background, highlights[1].background);
+
+        // Each program header is one highlight
+        assert_eq!(executable.highlights.overlapping(0x40..0x78).len(), 1);
+    }
+
+    #[test]
+    fn test_broken_headers() {
+        let mut data = tiny_elf();
+        data[40..48].copy_from_slice(&0x1000u64.to_le_bytes());
+        let error = Executable::parse(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid executable: section header table at 0x1000 ends after the end of the file"
+        );
+
+        let mut data = tiny_elf();
+        data[4] = 3;
+        let error = Executable::parse(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(error.to_string(), "invalid executable: unknown ELF class 3");
+
+        let error = Executable::parse(&mut Cursor::new(b"\x7fELF\x02".to_vec())).unwrap_err();
+        assert!(matches!(error, HexlerError::InvalidExecutable(_)));
+    }
+
+    #[test]
+    fn test_control_characters_in_names() {
+        let mut data = tiny_elf();
+        data[0xa7..0xab].copy_from_slice(b"\x1b[2J");
+        let executable = Executable::parse(&mut Cursor::new(data)).unwrap().unwrap();
+        assert_eq!(executable.sections[1].name, "\\u{1b}[2J");
+    }
+
+    #[test]
+    fn test_big_endian_32bit() {
+        let mut data = vec![0u8; 52];
+        data[..7].copy_from_slice(b"\x7fELF\x01\x02\x01");
+        data[16..18].copy_from_slice(&3u16.to_be_bytes());
+        data[18..20].copy_from_slice(&8u16.to_be_bytes());
+        let executable = Executable::parse(&mut Cursor::new(data)).unwrap().unwrap();
+        assert_eq!(executable.description, "ELF 32-bit MSB shared object, MIPS");
+        assert!(executable.sections.is_empty());
+        assert_eq!(
+            executable.rows,
+            vec![(0, "ELF header (52 bytes)".to_string())]
+        );
+    }
+}
@@ -26,6 +26,88 @@ pub enum HexlerError {
     /// to calculate the optimal bytes_per_line value for the current terminal width.
     #[error("failed to determine terminal width")]
     TerminalSizeError,
+
+    /// The encoding can't be used for the selected mode.
+    ///
+    /// E.g. `--strings` only finds ASCII, UTF-8 and UTF-16 little endian strings.
+    #[error("{mode} does not support the encoding {encoding}")]
+    UnsupportedEncoding { mode: String, encoding: String },
+
+    /// The headers of an executable file are broken, e.g. point outside of the file.
+    #[error("invalid executable: {0}")]
+    InvalidExecutable(String),
+
+    /// The section given with `--section` doesn't exist.
+    ///
+    /// `available` lists the names of all sections, separated by spaces.
+    #[error("no section {name}, the sections are: {available}")]
+    SectionNotFound { name: String, available: String },
+
+    /// A structure template can't be parsed, or can't be applied to the data.
+    #[error("invalid template, line {line}: {message}")]
+    InvalidTemplate { line: usize, message: String },
+
+    /// The input of `--decompress` starts like a compressed stream, but can't be decompressed.
+    #[error("invalid {codec} data: {message}")]
+    Decompress { codec: String, message: String },
+
+    /// The headers of a zip or tar archive are broken, or a member can't be read.
+    #[error("invalid archive: {0}")]
+    InvalidArchive(String),
+
+    /// The member given with `archive:member` doesn't exist.
+    #[error("no member {name} in {archive}")]
+    MemberNotFound { name: String, archive: String },
+
+    /// `--edit` only works on regular files.
+    #[error("can't edit {0}, --edit needs a regular file")]
+    CannotEdit(String),
+
+    /// Bytes or a patch file given to `hexler patch` can't be parsed.
+    #[error("invalid patch: {0}")]
+    InvalidPatch(String),
+
+    /// A patch would write past the end of the file without `--extend`.
+    #[error(
+        "patch ends at 0x{end:x}, past the end of the file at 0x{len:x}, --extend allows this"
+    )]
+    PatchPastEnd { end: u64, len: u64 },
+
+    /// The file doesn't have the old bytes of a hunk of `hexler applypatch`.
+    #[error(
+        "the bytes at 0x{offset:x} don't match the old bytes of the patch, nothing was written"
+    )]
+    PatchMismatch { offset: u64 },
+
+    /// A hunk of `hexler applypatch` that changes the size doesn't end at the end of the file,
+    /// e.g. because the patch was already applied.
+    #[error(
+        "the patch is for a file of {expected} bytes, the file has {actual} bytes, nothing was written"
+    )]
+    PatchLengthMismatch { expected: u64, actual: u64 },
+
+    /// A color name that isn't known.
+    ///
+    /// `available` lists the names of all colors, separated by spaces.
+    #[error("unknown color {name}, the colors are: {available}")]
+    UnknownColor { name: String, available: String },
+
+    /// A sidecar file with annotations can't be parsed.
+    #[error("invalid sidecar {path}: {message}")]
+    InvalidSidecar { path: String, message: String },
+
+    /// The process given with `--pid` doesn't exist.
+    #[error("no process with id {0}")]
+    ProcessNotFound(u32),
+
+    /// The region given with `--maps-region`, or the address given with `--addr`, isn't mapped
+    /// in the process.
+    #[error("process {pid} has no memory region {name}")]
+    RegionNotFound { pid: u32, name: String },
+
+    /// An escape code of a theme doesn't fit in the fixed size buffers of the line writer.
+    #[error("escape code {escape:?} of the theme is longer than {max} bytes")]
+    EscapeTooLong { escape: String, max: usize },
 }
 
 /// Type alias for Results that use `HexlerError` as the error type.
@@ -61,6 +143,183 @@ mod tests {
         assert!(message.contains("failed to determine terminal width"));
     }
 
+    #[test]
+    fn test_unsupported_encoding_error_message() {
+        let error = HexlerError::UnsupportedEncoding {
+            mode: "--strings".to_string(),
+            encoding: "ebcdic".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "--strings does not support the encoding ebcdic"
+        );
+    }
+
+    #[test]
+    fn test_invalid_executable_message() {
+        let error = HexlerError::InvalidExecutable("unknown ELF class 3".to_string());
+        assert_eq!(error.to_string(), "invalid executable: unknown ELF class 3");
+    }
+
+    #[test]
+    fn test_section_not_found_message() {
+        let error = HexlerError::SectionNotFound {
+            name: ".rodata".to_string(),
+            available: ".text .data".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "no section .rodata, the sections are: .text .data"
+        );
+    }
+
+    #[test]
+    fn test_invalid_template_message() {
+        let error = HexlerError::InvalidTemplate {
+            line: 3,
+            message: "unknown type u12".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "invalid template, line 3: unknown type u12"
+        );
+    }
+
+    #[test]
+    fn test_decompress_message() {
+        let error = HexlerError::Decompress {
+            codec: "gzip".to_string(),
+            message: "corrupt deflate stream".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "invalid gzip data: corrupt deflate stream"
+        );
+    }
+
+    #[test]
+    fn test_invalid_archive_message() {
+        let error = HexlerError::InvalidArchive("broken tar header at 0x200".to_string());
+        assert_eq!(
+            error.to_string(),
+            "invalid archive: broken tar header at 0x200"
+        );
+    }
+
+    #[test]
+    fn test_member_not_found_message() {
+        let error = HexlerError::MemberNotFound {
+            name: "lib/foo.so".to_string(),
+            archive: "bundle.zip".to_string(),
+        };
+        assert_eq!(error.to_string(), "no member lib/foo.so in bundle.zip");
+    }
+
+    #[test]
+    fn test_cannot_edit_message() {
+        let error = HexlerError::CannotEdit("stdin".to_string());
+        assert_eq!(
+            error.to_string(),
+            "can't edit stdin, --edit needs a regular file"
+        );
+    }
+
+    #[test]
+    fn test_invalid_patch_message() {
+        let error = HexlerError::InvalidPatch("line 2: invalid hex byte 'zz'".to_string());
+        assert_eq!(
+            error.to_string(),
+            "invalid patch: line 2: invalid hex byte 'zz'"
+        );
+    }
+
+    #[test]
+    fn test_patch_past_end_message() {
+        let error = HexlerError::PatchPastEnd {
+            end: 0x210,
+            len: 0x200,
+        };
+        assert_eq!(
+            error.to_string(),
+            "patch ends at 0x210, past the end of the file at 0x200, --extend allows this"
+        );
+    }
+
+    #[test]
+    fn test_patch_mismatch_message() {
+        let error = HexlerError::PatchMismatch { offset: 0x1f0 };
+        assert_eq!(
+            error.to_string(),
+            "the bytes at 0x1f0 don't match the old bytes of the patch, nothing was written"
+        );
+    }
+
+    #[test]
+    fn test_patch_length_mismatch_message() {
+        let error = HexlerError::PatchLengthMismatch {
+            expected: 256,
+            actual: 240,
+        };
+        assert_eq!(
+            error.to_string(),
+            "the patch is for a file of 256 bytes, the file has 240 bytes, nothing was written"
+        );
+    }
+
+    #[test]
+    fn test_unknown_color_message() {
+        let error = HexlerError::UnknownColor {
+            name: "pink".to_string(),
+            available: "red green".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "unknown color pink, the colors are: red green"
+        );
+    }
+
+    #[test]
+    fn test_invalid_sidecar_message() {
+        let error = HexlerError::InvalidSidecar {
+            path: "fw.bin.hexler.toml".to_string(),
+            message: "missing field `label`".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "invalid sidecar fw.bin.hexler.toml: missing field `label`"
+        );
+    }
+
+    #[test]
+    fn test_process_not_found_message() {
+        let error = HexlerError::ProcessNotFound(4242);
+        assert_eq!(error.to_string(), "no process with id 4242");
+    }
+
+    #[test]
+    fn test_region_not_found_message() {
+        let error = HexlerError::RegionNotFound {
+            pid: 4242,
+            name: "[heap]".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "process 4242 has no memory region [heap]"
+        );
+    }
+
+    #[test]
+    fn test_escape_too_long_message() {
+        let error = HexlerError::EscapeTooLong {
+            escape: "\x1b[1;4;38;2;255;255;255;48;2;0;0;0m".to_string(),
+            max: 24,
+        };
+        assert_eq!(
+            error.to_string(),
+            "escape code \"\\u{1b}[1;4;38;2;255;255;255;48;2;0;0;0m\" of the theme is longer than 24 bytes"
+        );
+    }
+
     #[test]
     fn test_io_error_conversion() {
         let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
@@ -0,0 +1,274 @@
+use crate::annotate::Boundaries;
+use crate::error::{HexlerError, Result};
+use crate::highlight::{Highlight, Highlights, FIELD_BACKGROUNDS};
+use std::io::{Read, Seek, SeekFrom};
+
+/// A section of an executable, e.g. `.text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Name of the section, e.g. `.rodata`
+    pub name: String,
+    /// Type of the section, e.g. `PROGBITS`
+    pub kind: String,
+    /// Offset of the section's data in the file
+    pub offset: u64,
+    /// Number of bytes of the section's data in the file
+    pub size: u64,
+    /// Virtual address the section is loaded to, 0 if it isn't loaded
+    pub address: u64,
+}
+
+/// The structure of an executable file, for annotating its hex dump.
+///
+/// Only the headers are read, so even huge executables are parsed quickly.
+#[derive(Debug, Clone, Default)]
+pub struct Executable {
+    /// Summary of the file header, e.g. `ELF 64-bit LSB executable, x86-64`
+    pub description: String,
+    /// Sections with their location in the file
+    pub sections: Vec<Section>,
+    /// Separator rows at the start of headers, segments and sections, by file offset
+    pub rows: Vec<(u64, String)>,
+    /// Background colors of header fields, by file offset
+    pub highlights: Highlights,
+}
+
+impl Executable {
+    /// Largest header table that is read, anything bigger is not a valid executable.
+    pub const MAX_TABLE_SIZE: u64 = 16 * 1024 * 1024;
+
+    /// Parses the headers of an ELF, PE or Mach-O file, or returns `None` if `reader` doesn't
+    /// start with the magic number of a supported format.
+    ///
+    /// # Errors
+    /// Returns `InvalidExecutable` if the magic number matches but the headers are broken.
+    pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<Option<Self>> {
+        let mut magic = [0u8; 8];
+        reader.seek(SeekFrom::Start(0))?;
+        let len = crate::read_full(reader, &mut magic)?;
+        let magic = &magic[..len];
+        let parse = match magic.get(..4).unwrap_or_default() {
+            b"\x7fELF" => crate::elf::parse,
+            b"\xfe\xed\xfa\xce" | b"\xfe\xed\xfa\xcf" | b"\xce\xfa\xed\xfe"
+            | b"\xcf\xfa\xed\xfe" => crate::macho::parse,
+            _ if crate::macho::is_fat_header(magic) => crate::macho::parse,
+            _ if magic.starts_with(b"MZ") => crate::pe::parse,
+            _ => return Ok(None),
+        };
+        parse(reader).map(Some)
+    }
+
+    /// Returns the section called `name`.
+    ///
+    /// # Errors
+    /// Returns `SectionNotFound` with the names of all sections if there is none.
+    pub fn section(&self, name: &str) -> Result<&Section> {
+        self.sections
+            .iter()
+            .find(|section| section.name == name)
+            .ok_or_else(|| HexlerError::SectionNotFound {
+                name: name.to_string(),
+                available: self
+                    .sections
+                    .iter()
+                    .map(|section| section.name.as_str())
+                    .filter(|name| !name.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            })
+    }
+
+    /// Returns the separator rows for a hex dump.
+    pub fn boundaries(&self) -> Boundaries {
+        Boundaries::new(
+            self.rows
+                .iter()
+                .map(|(offset, label)| (*offset as usize, label.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// Reads integers of a file's word size and byte order from its headers.
+///
+/// The positions must be inside of `data`, which is checked when reading the headers.
+pub(crate) struct Fields {
+    is_64: bool,
+    little_endian: bool,
+}
+
+impl Fields {
+    pub(crate) fn new(is_64: bool, little_endian: bool) -> Self {
+        Self {
+            is_64,
+            little_endian,
+        }
+    }
+
+    pub(crate) fn u16(&self, data: &[u8], pos: usize) -> u64 {
+        let bytes = [data[pos], data[pos + 1]];
+        u64::from(match self.little_endian {
+            true => u16::from_le_bytes(bytes),
+            false => u16::from_be_bytes(bytes),
+        })
+    }
+
+    pub(crate) fn u32(&self, data: &[u8], pos: usize) -> u64 {
+        let bytes = data[pos..pos + 4].try_into().unwrap();
+        u64::from(match self.little_endian {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    }
+
+    pub(crate) fn u64(&self, data: &[u8], pos: usize) -> u64 {
+        let bytes = data[pos..pos + 8].try_into().unwrap();
+        match self.little_endian {
+            true => u64::from_le_bytes(bytes),
+            false => u64::from_be_bytes(bytes),
+        }
+    }
+
+    /// Reads an address or offset, which is at `pos32` or `pos64` depending on the word size.
+    pub(crate) fn word(&self, data: &[u8], pos32: usize, pos64: usize) -> u64 {
+        match self.is_64 {
+            true => self.u64(data, pos64),
+            false => self.u32(data, pos32),
+        }
+    }
+}
+
+pub(crate) fn invalid(message: String) -> HexlerError {
+    HexlerError::InvalidExecutable(message)
+}
+
+/// Number of entries of a header table, e.g. `1 entry` or `12 entries`.
+pub(crate) fn entries(num: u64) -> String {
+    match num {
+        1 => "1 entry".to_string(),
+        num => format!("{} entries", num),
+    }
+}
+
+/// Text of a name from untrusted data, with control characters escaped like `\u{1b}`
+/// so that a crafted name can't send escape codes to the terminal.
+pub(crate) fn printable_name(bytes: &[u8]) -> String {
+    let mut name = String::with_capacity(bytes.len());
+    for c in String::from_utf8_lossy(bytes).chars() {
+        match c.is_control() {
+            true => name.extend(c.escape_debug()),
+            false => name.push(c),
+        }
+    }
+    name
+}
+
+/// Memory permissions like `R-X`.
+pub(crate) fn permissions(read: bool, write: bool, execute: bool) -> String {
+    [(read, 'R'), (write, 'W'), (execute, 'X')]
+        .iter()
+        .map(|&(allowed, c)| if allowed { c } else { '-' })
+        .collect()
+}
+
+/// Highlights the entries of a header table with alternating backgrounds.
+pub(crate) fn highlight_entries(highlights: &mut Highlights, offset: u64, num: u64, entsize: u64) {
+    for idx in 0..num {
+        let start = offset + idx * entsize;
+        highlights.add(Highlight::new(
+            start..start + entsize,
+            FIELD_BACKGROUNDS[idx as usize % 2],
+        ));
+    }
+}
+
+/// Highlights consecutive fields given by their offset and length, cycling through the
+/// backgrounds so that neighbouring fields are distinct.
+pub(crate) fn highlight_fields(highlights: &mut Highlights, base: u64, fields: &[(u64, u64)]) {
+    for (idx, &(pos, len)) in fields.iter().enumerate() {
+        highlights.add(Highlight::new(
+            base + pos..base + pos + len,
+            FIELD_BACKGROUNDS[idx % FIELD_BACKGROUNDS.len()],
+        ));
+    }
+}
+
+/// Reads `len` bytes at `offset`, `what` is used in the error if they are not in the file.
+pub(crate) fn read_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+    what: &str,
+) -> Result<Vec<u8>> {
+    if len > Executable::MAX_TABLE_SIZE {
+        return Err(HexlerError::InvalidExecutable(format!(
+            "{} of {} bytes is too large",
+            what, len
+        )));
+    }
+    let mut data = vec![0u8; len as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    if crate::read_full(reader, &mut data)? < data.len() {
+        return Err(HexlerError::InvalidExecutable(format!(
+            "{} at 0x{:x} ends after the end of the file",
+            what, offset
+        )));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_format() {
+        let mut reader = std::io::Cursor::new(b"just some text".to_vec());
+        assert!(Executable::parse(&mut reader).unwrap().is_none());
+
+        let mut reader = std::io::Cursor::new(b"ab".to_vec());
+        assert!(Executable::parse(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_at() {
+        let mut reader = std::io::Cursor::new(b"0123456789".to_vec());
+        assert_eq!(read_at(&mut reader, 2, 3, "table").unwrap(), b"234");
+
+        let error = read_at(&mut reader, 8, 3, "table").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid executable: table at 0x8 ends after the end of the file"
+        );
+    }
+
+    #[test]
+    fn test_printable_name() {
+        assert_eq!(printable_name(b".text"), ".text");
+        assert_eq!(printable_name("größe".as_bytes()), "größe");
+        assert_eq!(
+            printable_name(b"evil\x1b]0;pwned\x07\x1b[2J\n"),
+            "evil\\u{1b}]0;pwned\\u{7}\\u{1b}[2J\\n"
+        );
+        assert_eq!(printable_name(b"bad\xff"), "bad\u{fffd}");
+    }
+
+    #[test]
+    fn test_section_not_found() {
+        let executable = Executable {
+            sections: vec![Section {
+                name: ".text".to_string(),
+                kind: "PROGBITS".to_string(),
+                offset: 0x40,
+                size: 0x10,
+                address: 0x1040,
+            }],
+            ..Executable::default()
+        };
+        assert_eq!(executable.section(".text").unwrap().offset, 0x40);
+        assert_eq!(
+            executable.section(".data").unwrap_err().to_string(),
+            "no section .data, the sections are: .text"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+
+/// The kind of a file given on the command line. Block devices, character devices like
+/// `/dev/urandom` and FIFOs have no length in their metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Regular,
+    BlockDevice,
+    CharDevice,
+    Fifo,
+    Socket,
+    /// Directories and whatever else can't be dumped, opening or reading them fails
+    Other,
+}
+
+impl FileKind {
+    /// The kind of a file with the type `file_type`.
+    pub fn of(file_type: fs::FileType) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+
+            if file_type.is_block_device() {
+                return FileKind::BlockDevice;
+            }
+            if file_type.is_char_device() {
+                return FileKind::CharDevice;
+            }
+            if file_type.is_fifo() {
+                return FileKind::Fifo;
+            }
+            if file_type.is_socket() {
+                return FileKind::Socket;
+            }
+        }
+        match file_type.is_file() {
+            true => FileKind::Regular,
+            false => FileKind::Other,
+        }
+    }
+
+    /// Name of the kind for the title, `None` for regular files.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            FileKind::Regular => None,
+            FileKind::BlockDevice => Some("block device"),
+            FileKind::CharDevice => Some("character device"),
+            FileKind::Fifo => Some("FIFO"),
+            FileKind::Socket => Some("socket"),
+            FileKind::Other => Some("special file"),
+        }
+    }
+
+    /// Whether the file can be opened again and reads the same data, e.g. to look for archive
+    /// or executable headers before the dump. Data read from a FIFO or a character device is
+    /// gone.
+    pub fn is_rereadable(self) -> bool {
+        matches!(self, FileKind::Regular | FileKind::BlockDevice)
+    }
+}
+
+/// The number of bytes in `file` with the metadata `md`, `None` when it isn't known in advance.
+///
+/// The size of a block device is where seeking to its end ends up, its metadata says 0 bytes.
+pub fn data_len(file: &Path, md: &fs::Metadata) -> Option<u64> {
+    match FileKind::of(md.file_type()) {
+        FileKind::Regular => Some(md.len()),
+        FileKind::BlockDevice => {
+            let len = fs::File::open(file).ok()?.seek(SeekFrom::End(0)).ok()?;
+            // Platforms that can't seek to the end of a device end up at 0
+            (len > 0).then_some(len)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regular_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), b"hello").unwrap();
+        let md = fs::metadata(file.path()).unwrap();
+        assert_eq!(FileKind::of(md.file_type()), FileKind::Regular);
+        assert_eq!(FileKind::Regular.name(), None);
+        assert_eq!(data_len(file.path(), &md), Some(5));
+
+        let md = fs::metadata(std::env::temp_dir()).unwrap();
+        assert_eq!(FileKind::of(md.file_type()), FileKind::Other);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_char_device() {
+        let path = Path::new("/dev/null");
+        let md = fs::metadata(path).unwrap();
+        let kind = FileKind::of(md.file_type());
+        assert_eq!(kind, FileKind::CharDevice);
+        assert_eq!(kind.name(), Some("character device"));
+        assert!(!kind.is_rereadable());
+        assert_eq!(data_len(path, &md), None);
+    }
+}
@@ -0,0 +1,294 @@
+use sha2::Digest;
+use std::cell::RefCell;
+use std::io::Read;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Hash and checksum algorithms of `--hash`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Algorithm {
+    Crc32,
+    Md5,
+    Sha1,
+    Sha256,
+    Xxh64,
+}
+
+impl Algorithm {
+    /// Name of the algorithm, as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Crc32 => "crc32",
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Xxh64 => "xxh64",
+        }
+    }
+}
+
+/// The state of a hash or checksum while data is added.
+#[derive(Clone)]
+enum State {
+    Crc32(crc32fast::Hasher),
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Xxh64(xxhash_rust::xxh64::Xxh64),
+}
+
+impl State {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Crc32 => State::Crc32(crc32fast::Hasher::new()),
+            Algorithm::Md5 => State::Md5(md5::Md5::new()),
+            Algorithm::Sha1 => State::Sha1(sha1::Sha1::new()),
+            Algorithm::Sha256 => State::Sha256(sha2::Sha256::new()),
+            Algorithm::Xxh64 => State::Xxh64(xxhash_rust::xxh64::Xxh64::new(0)),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            State::Crc32(hasher) => hasher.update(data),
+            State::Md5(hasher) => hasher.update(data),
+            State::Sha1(hasher) => hasher.update(data),
+            State::Sha256(hasher) => hasher.update(data),
+            State::Xxh64(hasher) => hasher.update(data),
+        }
+    }
+
+    /// The digest of the data so far in hex, more data can be added afterwards.
+    fn hex(&self) -> String {
+        match self {
+            State::Crc32(hasher) => format!("{:08x}", hasher.clone().finalize()),
+            State::Md5(hasher) => format!("{:x}", hasher.clone().finalize()),
+            State::Sha1(hasher) => format!("{:x}", hasher.clone().finalize()),
+            State::Sha256(hasher) => format!("{:x}", hasher.clone().finalize()),
+            State::Xxh64(hasher) => format!("{:016x}", hasher.digest()),
+        }
+    }
+}
+
+/// Digests of a block of `--hash-per-block` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// Offsets of the block as shown in the offset column
+    pub range: Range<u64>,
+    /// The digests in the order of the algorithms
+    pub digests: Vec<String>,
+}
+
+/// Digests of all data that is added, and optionally of each block of a fixed size.
+pub struct Digests {
+    algorithms: Vec<Algorithm>,
+    states: Vec<State>,
+    start_offset: u64,
+    len: u64,
+    block_size: Option<u64>,
+    block_states: Vec<State>,
+    blocks: Vec<Block>,
+}
+
+impl Digests {
+    /// Creates digests with the given algorithms, of no data yet.
+    pub fn new(algorithms: &[Algorithm]) -> Self {
+        let states: Vec<State> = algorithms.iter().map(|&a| State::new(a)).collect();
+        Self {
+            algorithms: algorithms.to_vec(),
+            block_states: states.clone(),
+            states,
+            start_offset: 0,
+            len: 0,
+            block_size: None,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Offset of the first byte, the block ranges start there.
+    pub fn with_start_offset(mut self, start_offset: u64) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+
+    /// Additionally computes the digests of each block of `block_size` bytes.
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        self.block_size = Some(block_size);
+        self
+    }
+
+    /// Adds `data` to the digests.
+    pub fn update(&mut self, mut data: &[u8]) {
+        for state in &mut self.states {
+            state.update(data);
+        }
+        let Some(block_size) = self.block_size else {
+            self.len += data.len() as u64;
+            return;
+        };
+
+        while !data.is_empty() {
+            let in_block = self.len % block_size;
+            let take = data.len().min((block_size - in_block) as usize);
+            for state in &mut self.block_states {
+                state.update(&data[..take]);
+            }
+            self.len += take as u64;
+            data = &data[take..];
+
+            if self.len % block_size == 0 {
+                let block = self.block(block_size);
+                self.blocks.push(block);
+                self.block_states = self.algorithms.iter().map(|&a| State::new(a)).collect();
+            }
+        }
+    }
+
+    /// The block that ends at the current length.
+    fn block(&self, block_size: u64) -> Block {
+        let start = (self.len - 1) / block_size * block_size;
+        Block {
+            range: self.start_offset + start..self.start_offset + self.len,
+            digests: self.block_states.iter().map(State::hex).collect(),
+        }
+    }
+
+    /// The digests of all data so far, with their algorithm.
+    pub fn digests(&self) -> Vec<(Algorithm, String)> {
+        self.algorithms
+            .iter()
+            .zip(&self.states)
+            .map(|(&algorithm, state)| (algorithm, state.hex()))
+            .collect()
+    }
+
+    /// The digests of each block so far, the last one can be shorter than the block size.
+    pub fn blocks(&self) -> Vec<Block> {
+        let mut blocks = self.blocks.clone();
+        if let Some(block_size) = self.block_size {
+            if self.len % block_size != 0 {
+                blocks.push(self.block(block_size));
+            }
+        }
+        blocks
+    }
+
+    /// Text for the footer: the digests of all data, then one line per block.
+    pub fn footer(&self) -> String {
+        let digests = self.digests();
+        let mut lines = vec![join(
+            digests.iter().map(|(a, digest)| (*a, digest.as_str())),
+        )];
+        for block in self.blocks() {
+            let digests = self.algorithms.iter().copied().zip(block.digests.iter());
+            lines.push(format!(
+                "block 0x{:08x}..0x{:08x}   {}",
+                block.range.start,
+                block.range.end,
+                join(digests.map(|(a, digest)| (a, digest.as_str())))
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Formats digests as e.g. `crc32 0d4a1185   md5 5eb63bbb...`.
+fn join<'a>(digests: impl Iterator<Item = (Algorithm, &'a str)>) -> String {
+    digests
+        .map(|(algorithm, digest)| format!("{} {}", algorithm.name(), digest))
+        .collect::<Vec<_>>()
+        .join("   ")
+}
+
+/// Passes the data of a reader through, and adds it to shared digests on the way.
+pub struct HashingReader<R> {
+    reader: R,
+    digests: Rc<RefCell<Digests>>,
+}
+
+impl<R: Read> HashingReader<R> {
+    /// Creates a reader that adds all data read from `reader` to `digests`.
+    pub fn new(reader: R, digests: Rc<RefCell<Digests>>) -> Self {
+        Self { reader, digests }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        self.digests.borrow_mut().update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Algorithm; 5] = [
+        Algorithm::Crc32,
+        Algorithm::Md5,
+        Algorithm::Sha1,
+        Algorithm::Sha256,
+        Algorithm::Xxh64,
+    ];
+
+    #[test]
+    fn test_known_digests() {
+        let mut digests = Digests::new(&ALL);
+        digests.update(b"hello ");
+        digests.update(b"world");
+        let values: Vec<String> = digests.digests().into_iter().map(|(_, d)| d).collect();
+        assert_eq!(
+            values,
+            vec![
+                "0d4a1185",
+                "5eb63bbbe01eeed093cb22bb8f5acdc3",
+                "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
+                "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+                "45ab6734b21e6968",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let data: Vec<u8> = (0..40).collect();
+        let mut digests = Digests::new(&[Algorithm::Crc32])
+            .with_start_offset(0x100)
+            .with_block_size(16);
+        // Chunks that don't line up with the blocks
+        for chunk in data.chunks(7) {
+            digests.update(chunk);
+        }
+
+        let blocks = digests.blocks();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[1].range, 0x110..0x120);
+        assert_eq!(blocks[2].range, 0x120..0x128);
+        for (block, chunk) in blocks.iter().zip(data.chunks(16)) {
+            assert_eq!(block.digests[0], format!("{:08x}", crc32fast::hash(chunk)));
+        }
+    }
+
+    #[test]
+    fn test_hashing_reader_and_footer() {
+        let digests = Rc::new(RefCell::new(
+            Digests::new(&[Algorithm::Crc32, Algorithm::Md5]).with_block_size(8),
+        ));
+        let mut reader = HashingReader::new(&b"hello world"[..], digests.clone());
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"hello world");
+
+        let footer = digests.borrow().footer();
+        let lines: Vec<&str> = footer.lines().collect();
+        assert_eq!(
+            lines[0],
+            "crc32 0d4a1185   md5 5eb63bbbe01eeed093cb22bb8f5acdc3"
+        );
+        assert!(lines[1].starts_with("block 0x00000000..0x00000008   crc32 "));
+        assert!(lines[2].starts_with("block 0x00000008..0x0000000b   crc32 "));
+        assert_eq!(lines.len(), 3);
+    }
+}
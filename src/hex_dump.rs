@@ -0,0 +1,340 @@
+use crate::ascii_renderer::{AsciiRenderer, Encoding};
+use crate::byte_to_color::{ByteToColor, ColorBy, Theme};
+use crate::error::Result;
+use crate::hex_formatter::{HexFormatter, OffsetBase};
+use crate::line_writer::{Border, LineWriter};
+use crate::renderer::context_window;
+
+/// Formats in-memory buffers as hex dumps, for embedding hexler in other programs.
+///
+/// Unlike `dump`, this works synchronously on the calling thread and accepts any writer,
+/// which makes it useful e.g. for assertion messages in tests:
+///
+/// ```
+/// use hexler::hex_dump::HexDump;
+///
+/// let hex_dump = HexDump::builder().color(false).borders(false).build().unwrap();
+/// let data = b"hexler";
+/// assert_eq!(
+///     hex_dump.to_string(data),
+///     "00000000 │ 68 65 78 6c 65 72                                │ hexler\n"
+/// );
+/// assert!(data.len() == 6, "unexpected data:\n{}", hex_dump.display(data));
+/// ```
+pub struct HexDump {
+    line_writer: LineWriter,
+    borders: bool,
+    title: String,
+}
+
+/// Configures a `HexDump`, created with `HexDump::builder()`.
+#[derive(Debug, Clone)]
+pub struct HexDumpBuilder {
+    bytes_per_line: usize,
+    color: bool,
+    theme: Theme,
+    color_by: ColorBy,
+    encoding: Encoding,
+    offset_base: OffsetBase,
+    start_offset: usize,
+    borders: bool,
+    title: String,
+}
+
+impl Default for HexDumpBuilder {
+    fn default() -> Self {
+        Self {
+            bytes_per_line: 16,
+            color: true,
+            theme: Theme::DEFAULT,
+            color_by: ColorBy::Class,
+            encoding: Encoding::Cp437,
+            offset_base: OffsetBase::Hex,
+            start_offset: 0,
+            borders: true,
+            title: String::new(),
+        }
+    }
+}
+
+impl HexDumpBuilder {
+    /// Number of bytes per line, must be a multiple of 8. Defaults to 16.
+    pub fn bytes_per_line(mut self, bytes_per_line: usize) -> Self {
+        self.bytes_per_line = bytes_per_line;
+        self
+    }
+
+    /// Enables or disables all ANSI escape codes. Defaults to enabled.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Colors used for the byte classes when color is enabled. Defaults to `Theme::DEFAULT`.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// What the byte colors are based on when color is enabled. Defaults to `ColorBy::Class`,
+    /// which uses the colors of the theme.
+    pub fn color_by(mut self, color_by: ColorBy) -> Self {
+        self.color_by = color_by;
+        self
+    }
+
+    /// Character encoding of the text panel. Defaults to `Encoding::Cp437`.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Number system of the offset column. Defaults to `OffsetBase::Hex`.
+    pub fn offset_base(mut self, offset_base: OffsetBase) -> Self {
+        self.offset_base = offset_base;
+        self
+    }
+
+    /// Offset shown for the first byte of the buffer. Defaults to 0.
+    pub fn start_offset(mut self, start_offset: usize) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+
+    /// Enables or disables the header and footer borders. Defaults to enabled.
+    pub fn borders(mut self, borders: bool) -> Self {
+        self.borders = borders;
+        self
+    }
+
+    /// Title shown above the header border. Defaults to no title.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Creates the `HexDump`.
+    ///
+    /// # Errors
+    /// Returns `InvalidBytesPerLine` if bytes_per_line is not a multiple of 8, and
+    /// `EscapeTooLong` if an escape code of the theme is too long.
+    pub fn build(self) -> Result<HexDump> {
+        let byte_to_color = if self.color {
+            ByteToColor::with_color_by(self.color_by, &self.theme)?
+        } else {
+            ByteToColor::with_theme(&Theme::MONOCHROME)?
+        };
+        let hex_formatter = HexFormatter::new()
+            .with_offset_base(self.offset_base)
+            .with_color(self.color);
+
+        let line_writer = LineWriter::new_bytes(self.bytes_per_line)?
+            .with_hex_formatter(hex_formatter)
+            .with_ascii_renderer(AsciiRenderer::with_encoding(self.encoding))
+            .with_byte_to_color(byte_to_color)
+            .with_start_offset(self.start_offset);
+
+        Ok(HexDump {
+            line_writer,
+            borders: self.borders,
+            title: self.title,
+        })
+    }
+}
+
+impl HexDump {
+    /// Size of the internal buffer after which formatted output is passed to the writer.
+    const FLUSH_SIZE: usize = 64 * 1024;
+
+    /// Returns a builder with the default settings.
+    pub fn builder() -> HexDumpBuilder {
+        HexDumpBuilder::default()
+    }
+
+    /// Writes the hex dump of `data` to `writer`.
+    pub fn write_to<W: std::io::Write + ?Sized>(&self, writer: &mut W, data: &[u8]) -> Result<()> {
+        let bytes_per_line = self.line_writer.bytes_per_line();
+        let mut buffer = Vec::with_capacity(Self::FLUSH_SIZE);
+
+        if self.borders {
+            self.line_writer
+                .write_border(&mut buffer, Border::Header, &self.title)?;
+        }
+
+        let start_offset = self.line_writer.start_offset();
+        let context_len = self.line_writer.context_len();
+        for start in (0..data.len()).step_by(bytes_per_line) {
+            let end = (start + bytes_per_line).min(data.len());
+            let (window, line) = context_window(data, start..end, context_len);
+            self.line_writer
+                .write_line_in_context(&mut buffer, start_offset + start, window, line);
+
+            if buffer.len() >= Self::FLUSH_SIZE {
+                writer.write_all(&buffer)?;
+                buffer.clear();
+            }
+        }
+
+        if self.borders {
+            self.line_writer
+                .write_border(&mut buffer, Border::Footer, "")?;
+        }
+        writer.write_all(&buffer)?;
+        Ok(())
+    }
+
+    /// Returns the hex dump of `data` as a string.
+    pub fn to_string(&self, data: &[u8]) -> String {
+        let mut buffer = Vec::new();
+        // Writing into a Vec can't fail
+        let _ = self.write_to(&mut buffer, data);
+        // The output is always valid UTF-8, lossy conversion only avoids a panic path
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// Returns an adapter that formats `data` with `{}`, e.g. in `assert!` messages.
+    pub fn display<'a>(&'a self, data: &'a [u8]) -> HexDumpDisplay<'a> {
+        HexDumpDisplay {
+            hex_dump: self,
+            data,
+        }
+    }
+}
+
+/// Formats a buffer with `Display`, created with `HexDump::display`.
+pub struct HexDumpDisplay<'a> {
+    hex_dump: &'a HexDump,
+    data: &'a [u8],
+}
+
+impl std::fmt::Display for HexDumpDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.hex_dump.to_string(self.data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain() -> HexDumpBuilder {
+        HexDump::builder().color(false).borders(false)
+    }
+
+    #[test]
+    fn test_to_string_plain() {
+        let hex_dump = plain().bytes_per_line(8).build().unwrap();
+        let output = hex_dump.to_string(b"0123456789");
+        assert_eq!(
+            output,
+            "00000000 │ 30 31 32 33 34 35 36 37 │ 01234567\n\
+             00000008 │ 38 39                   │ 89\n"
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let hex_dump = plain().encoding(Encoding::Ascii).build().unwrap();
+        let output = hex_dump.to_string(b"a\x00\xffb");
+        assert!(output.ends_with("│ a..b\n"));
+    }
+
+    #[test]
+    fn test_invalid_bytes_per_line() {
+        assert!(HexDump::builder().bytes_per_line(12).build().is_err());
+    }
+
+    #[test]
+    fn test_start_offset_and_base() {
+        let hex_dump = plain()
+            .bytes_per_line(8)
+            .start_offset(100)
+            .offset_base(OffsetBase::Decimal)
+            .build()
+            .unwrap();
+        let output = hex_dump.to_string(&[0u8; 16]);
+        assert!(output.starts_with("0000000100 │"));
+        assert!(output.contains("\n0000000108 │"));
+    }
+
+    #[test]
+    fn test_borders_and_title() {
+        let hex_dump = HexDump::builder()
+            .color(false)
+            .title("my buffer")
+            .build()
+            .unwrap();
+        let output = hex_dump.to_string(b"abc");
+        assert!(output.starts_with("my buffer\n"));
+        assert!(output.contains('┬'));
+        assert!(output.contains('┴'));
+    }
+
+    #[test]
+    fn test_color_and_theme() {
+        let data: Vec<u8> = (0..=255u8).collect();
+
+        let colored = HexDump::builder().build().unwrap().to_string(&data);
+        assert!(colored.contains("\x1b[38;5;"));
+
+        let basic = HexDump::builder()
+            .theme(Theme::BASIC)
+            .build()
+            .unwrap()
+            .to_string(&data);
+        assert!(basic.contains(Theme::BASIC.extended_ascii));
+        assert!(!basic.contains("\x1b[38;5;"));
+
+        let plain = plain().build().unwrap().to_string(&data);
+        assert!(!plain.contains('\x1b'));
+
+        let long = Theme {
+            sentinel: "\x1b[1;4;38;2;255;255;255;48;2;0;0;0m",
+            ..Theme::DEFAULT
+        };
+        assert!(HexDump::builder().theme(long).build().is_err());
+    }
+
+    #[test]
+    fn test_color_by() {
+        let data: Vec<u8> = (0..=255u8).collect();
+
+        let value = HexDump::builder()
+            .color_by(ColorBy::Value)
+            .build()
+            .unwrap()
+            .to_string(&data);
+        assert!(value.contains("\x1b[38;2;"));
+
+        // Entropy colors whole lines, so there is one escape code per panel and line
+        let entropy = HexDump::builder()
+            .color_by(ColorBy::Entropy)
+            .build()
+            .unwrap()
+            .to_string(&data);
+        let line = entropy.lines().nth(3).unwrap();
+        assert_eq!(line.matches("\x1b[38;2;").count(), 2, "{}", line);
+
+        let plain = plain().color_by(ColorBy::Value).build().unwrap();
+        assert!(!plain.to_string(&data).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_write_to_matches_display() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let hex_dump = HexDump::builder().build().unwrap();
+
+        let mut output = Vec::new();
+        hex_dump.write_to(&mut output, &data).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}", hex_dump.display(&data))
+        );
+    }
+
+    #[test]
+    fn test_empty_without_borders() {
+        let hex_dump = plain().build().unwrap();
+        assert_eq!(hex_dump.to_string(&[]), "");
+    }
+}
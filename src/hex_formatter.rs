@@ -1,9 +1,36 @@
+use crate::simd::{self, Backend};
+
+/// Number system used for the offset column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OffsetBase {
+    #[default]
+    Hex,
+    Decimal,
+    Octal,
+}
+
+impl OffsetBase {
+    /// Number of digits needed to show any 32 bit offset in this base.
+    pub fn default_width(self) -> usize {
+        match self {
+            OffsetBase::Hex => 8,
+            OffsetBase::Decimal => 10,
+            OffsetBase::Octal => 11,
+        }
+    }
+}
+
 /// Handles hexadecimal formatting for bytes and file offsets.
 ///
 /// Uses a pre-computed lookup table for fast hex conversion of all 256 possible byte values.
 /// Each byte is formatted as two hex digits plus a trailing space (e.g., "ff ").
+/// Whole blocks of bytes are converted with the vectorized kernels from `simd`.
 pub struct HexFormatter {
     hex_lookup: [[u8; 3]; 256],
+    backend: Backend,
+    offset_base: OffsetBase,
+    offset_width: usize,
+    colored: bool,
 }
 
 impl HexFormatter {
@@ -16,13 +43,68 @@ impl HexFormatter {
     /// The lookup table contains all 256 byte values as "XX " (two hex digits + space),
     /// enabling O(1) hex conversion without runtime computation.
     pub fn new() -> Self {
+        Self::with_backend(Backend::detect())
+    }
+
+    /// Creates a new HexFormatter that uses the given backend for `write_hex_bytes`.
+    ///
+    /// Falls back to `Backend::Scalar` if the CPU doesn't support the requested backend.
+    pub fn with_backend(backend: Backend) -> Self {
+        let backend = if backend.is_supported() {
+            backend
+        } else {
+            Backend::Scalar
+        };
+
         let mut hex_lookup = [[0u8; 3]; 256];
         for (i, item) in hex_lookup.iter_mut().enumerate() {
             item[0] = Self::HEX_CHARS[i >> 4];
             item[1] = Self::HEX_CHARS[i & 0xf];
             item[2] = b' '; // space
         }
-        Self { hex_lookup }
+        Self {
+            hex_lookup,
+            backend,
+            offset_base: OffsetBase::Hex,
+            offset_width: OffsetBase::Hex.default_width(),
+            colored: true,
+        }
+    }
+
+    /// Sets the number system of the offset column, and the matching default width.
+    pub fn with_offset_base(mut self, offset_base: OffsetBase) -> Self {
+        self.offset_base = offset_base;
+        self.offset_width = offset_base.default_width();
+        self
+    }
+
+    /// Sets the minimum number of digits of the offset column.
+    ///
+    /// Offsets that need more digits are written in full, without truncation.
+    pub fn with_offset_width(mut self, offset_width: usize) -> Self {
+        self.offset_width = offset_width;
+        self
+    }
+
+    /// Enables or disables the grey color of the offset's leading zeros.
+    pub fn with_color(mut self, colored: bool) -> Self {
+        self.colored = colored;
+        self
+    }
+
+    /// Returns the minimum number of digits of the offset column.
+    pub fn offset_width(&self) -> usize {
+        self.offset_width
+    }
+
+    /// Returns whether offsets are written with ANSI escape codes.
+    pub fn is_colored(&self) -> bool {
+        self.colored
+    }
+
+    /// Returns the backend used for block conversion.
+    pub fn backend(&self) -> Backend {
+        self.backend
     }
 
     /// Returns the hex representation of a byte as a 3-byte array: "XX ".
@@ -38,6 +120,24 @@ impl HexFormatter {
         &self.hex_lookup[byte as usize]
     }
 
+    /// Writes `"XX "` for each byte of `bytes` into `out`, using the vectorized backend.
+    ///
+    /// `bytes` may contain at most `simd::BLOCK` bytes, and `out` must be exactly
+    /// `3 * bytes.len()` long.
+    ///
+    /// # Example
+    /// ```
+    /// use hexler::hex_formatter::HexFormatter;
+    /// let formatter = HexFormatter::new();
+    /// let mut out = [0u8; 6];
+    /// formatter.write_hex_bytes(&[0xde, 0xad], &mut out);
+    /// assert_eq!(&out, b"de ad ");
+    /// ```
+    #[inline]
+    pub fn write_hex_bytes(&self, bytes: &[u8], out: &mut [u8]) {
+        simd::encode_hex_spaced(self.backend, bytes, out);
+    }
+
     /// Returns three spaces "   " used for padding when a byte position is empty.
     pub fn hex_space() -> &'static [u8; 3] {
         b"   "
@@ -46,30 +146,54 @@ impl HexFormatter {
     /// Writes a byte offset directly into a `Vec<u8>` buffer.
     ///
     /// Optimized version that appends directly to a Vec without going through Write trait.
+    /// Leading zeros are grey, unless colors are disabled.
     ///
     /// # Examples
     /// - `0x00000000` → grey "00000000"
     /// - `0x00001234` → grey "0000" + "1234"
     /// - `0x12345678` → "12345678" (no leading zeros)
     pub fn write_offset(&self, buf: &mut Vec<u8>, offset: usize) {
-        let bc = offset as u32;
-        let num_leading_hex_zeroes = bc.leading_zeros() / 4;
+        let offset = offset as u64;
 
-        // Append grey color code
-        buf.extend_from_slice(Self::GREY);
+        // Significant digits, least significant first. Zero has no significant digits so it is
+        // shown entirely grey. 22 digits are enough for a 64 bit number in octal.
+        let mut digits = [0u8; 22];
+        let mut num_digits = 0;
+        match self.offset_base {
+            OffsetBase::Hex => {
+                num_digits = (64 - offset.leading_zeros() as usize + 3) / 4;
+                for (i, digit) in digits[..num_digits].iter_mut().enumerate() {
+                    *digit = Self::HEX_CHARS[((offset >> (i * 4)) & 0xf) as usize];
+                }
+            }
+            OffsetBase::Decimal | OffsetBase::Octal => {
+                let radix = if self.offset_base == OffsetBase::Decimal {
+                    10
+                } else {
+                    8
+                };
+                let mut n = offset;
+                while n != 0 {
+                    digits[num_digits] = Self::HEX_CHARS[(n % radix) as usize];
+                    n /= radix;
+                    num_digits += 1;
+                }
+            }
+        }
+        let num_leading_zeroes = self.offset_width.saturating_sub(num_digits);
 
-        // Append leading zeros efficiently
+        // Append leading zeros efficiently, in grey
+        if self.colored {
+            buf.extend_from_slice(Self::GREY);
+        }
         let start_len = buf.len();
-        buf.resize(start_len + num_leading_hex_zeroes as usize, b'0');
-
-        // Append color reset
-        buf.extend_from_slice(Self::COLOR_RESET);
-
-        // Append the remaining hex digits
-        for i in num_leading_hex_zeroes..8 {
-            let n = bc >> (32 - i * 4 - 4);
-            buf.push(Self::HEX_CHARS[n as usize & 0xf]);
+        buf.resize(start_len + num_leading_zeroes, b'0');
+        if self.colored {
+            buf.extend_from_slice(Self::COLOR_RESET);
         }
+
+        // Append the remaining digits
+        buf.extend(digits[..num_digits].iter().rev());
     }
 }
 
@@ -136,6 +260,58 @@ mod tests {
         assert!(result.contains("\x1b[90m0\x1b[0m")); // One grey zero
     }
 
+    #[test]
+    fn test_write_hex_bytes_matches_hex_byte() {
+        let bytes: Vec<u8> = (0..=255u8).collect();
+        for backend in [Backend::Scalar, Backend::Ssse3, Backend::Avx2] {
+            let formatter = HexFormatter::with_backend(backend);
+            for block in bytes.chunks(simd::BLOCK) {
+                let mut out = vec![0u8; block.len() * 3];
+                formatter.write_hex_bytes(block, &mut out);
+                let expected: Vec<u8> =
+                    block.iter().flat_map(|&b| *formatter.hex_byte(b)).collect();
+                assert_eq!(out, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_offset_larger_than_32_bit() {
+        let formatter = HexFormatter::new();
+        let mut output = Vec::new();
+        formatter.write_offset(&mut output, 0x1_2345_6789);
+        assert!(String::from_utf8_lossy(&output).ends_with("123456789"));
+    }
+
+    #[test]
+    fn test_write_offset_decimal_and_octal() {
+        let formatter = HexFormatter::new()
+            .with_offset_base(OffsetBase::Decimal)
+            .with_color(false);
+        let mut output = Vec::new();
+        formatter.write_offset(&mut output, 1234);
+        assert_eq!(output, b"0000001234");
+
+        let formatter = HexFormatter::new()
+            .with_offset_base(OffsetBase::Octal)
+            .with_color(false);
+        output.clear();
+        formatter.write_offset(&mut output, 8);
+        assert_eq!(output, b"00000000010");
+    }
+
+    #[test]
+    fn test_write_offset_without_color() {
+        let formatter = HexFormatter::new().with_color(false).with_offset_width(4);
+        let mut output = Vec::new();
+        formatter.write_offset(&mut output, 0);
+        assert_eq!(output, b"0000");
+
+        output.clear();
+        formatter.write_offset(&mut output, 0xabcde);
+        assert_eq!(output, b"abcde");
+    }
+
     #[test]
     fn test_all_bytes() {
         let formatter = HexFormatter::new();
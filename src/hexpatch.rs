@@ -0,0 +1,403 @@
+use crate::ascii_renderer::{AsciiRenderer, Encoding};
+use crate::byte_to_color::{ByteToColor, Theme};
+use crate::error::{HexlerError, Result};
+use crate::hex_formatter::HexFormatter;
+use crate::line_writer::LineWriter;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// Changed bytes that are less than this many bytes apart are put into the same hunk, so a
+/// patch doesn't fall apart into single bytes.
+const MERGE_GAP: u64 = 8;
+
+/// Bytes per line of the dumps in a patch.
+const BYTES_PER_LINE: usize = 16;
+
+/// A range of changed bytes, with the bytes of the old and the new file.
+///
+/// Both have the same length, except for a hunk at the end of the file when the new file is
+/// shorter or longer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub offset: u64,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+impl Hunk {
+    /// Whether applying the hunk changes the size of the file.
+    pub fn changes_size(&self) -> bool {
+        self.old.len() != self.new.len()
+    }
+}
+
+/// Compares `old` and `new` chunk by chunk and returns the hunks that turn `old` into `new`.
+///
+/// The files are read twice, once to find the changed ranges and once for their bytes, so
+/// they are never loaded completely.
+pub fn diff<R: Read + Seek>(old: &mut R, new: &mut R) -> Result<Vec<Hunk>> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    let old_len = old.seek(SeekFrom::End(0))?;
+    let new_len = new.seek(SeekFrom::End(0))?;
+    old.seek(SeekFrom::Start(0))?;
+    new.seek(SeekFrom::Start(0))?;
+
+    let mut ranges: Vec<Range<u64>> = Vec::new();
+    let mut add = |range: Range<u64>| match ranges.last_mut() {
+        Some(last) if range.start <= last.end + MERGE_GAP => last.end = range.end,
+        _ => ranges.push(range),
+    };
+
+    let mut old_chunk = vec![0u8; CHUNK_SIZE];
+    let mut new_chunk = vec![0u8; CHUNK_SIZE];
+    let mut offset = 0;
+    loop {
+        let old_read = crate::read_full(old, &mut old_chunk)?;
+        let new_read = crate::read_full(new, &mut new_chunk)?;
+        let len = old_read.min(new_read);
+        for (i, (a, b)) in old_chunk[..len].iter().zip(&new_chunk[..len]).enumerate() {
+            if a != b {
+                let pos = offset + i as u64;
+                add(pos..pos + 1);
+            }
+        }
+        offset += len as u64;
+        if len < CHUNK_SIZE {
+            break;
+        }
+    }
+    if old_len != new_len {
+        add(offset..old_len.max(new_len));
+    }
+
+    ranges
+        .into_iter()
+        .map(|range| {
+            Ok(Hunk {
+                offset: range.start,
+                old: read_range(old, &range)?,
+                new: read_range(new, &range)?,
+            })
+        })
+        .collect()
+}
+
+/// Reads the bytes of `range`, up to the end of the data.
+fn read_range<R: Read + Seek>(reader: &mut R, range: &Range<u64>) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; (range.end - range.start) as usize];
+    reader.seek(SeekFrom::Start(range.start))?;
+    let read = crate::read_full(reader, &mut data)?;
+    data.truncate(read);
+    Ok(data)
+}
+
+/// Writes and parses patches in a text format that can be reviewed like a diff: every hunk
+/// has a `@` line, and hex dump lines of its old bytes prefixed with `-` and its new bytes
+/// prefixed with `+`.
+///
+/// ```text
+/// @ 0x1f0, 4 bytes
+/// -000001f0 │ de ad be ef                                       │ ....
+/// +000001f0 │ 00 00 00 01                                       │ ....
+/// ```
+pub struct HexPatch {
+    line_writer: LineWriter,
+}
+
+impl Default for HexPatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HexPatch {
+    /// Creates a writer and parser of patches. The dumps have no colors and show only ASCII
+    /// characters, so patches can be read anywhere.
+    pub fn new() -> Self {
+        let line_writer = LineWriter::new_bytes(BYTES_PER_LINE)
+            .expect("16 bytes per line are valid")
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_ascii_renderer(AsciiRenderer::with_encoding(Encoding::Ascii))
+            .with_byte_to_color(
+                ByteToColor::with_theme(&Theme::MONOCHROME).expect("built-in themes are valid"),
+            );
+        Self { line_writer }
+    }
+
+    /// Writes `hunks` after the `#` comment lines of `header`.
+    pub fn write<W: Write>(&self, writer: &mut W, header: &str, hunks: &[Hunk]) -> Result<()> {
+        let mut buffer = Vec::new();
+        for line in header.lines() {
+            buffer.extend_from_slice(format!("# {}\n", line).as_bytes());
+        }
+        for hunk in hunks {
+            let size = match hunk.changes_size() {
+                false => num_bytes(hunk.new.len()),
+                true => format!(
+                    "{} -> {}",
+                    num_bytes(hunk.old.len()),
+                    num_bytes(hunk.new.len())
+                ),
+            };
+            buffer.extend_from_slice(format!("@ 0x{:x}, {}\n", hunk.offset, size).as_bytes());
+            self.write_bytes(&mut buffer, b'-', hunk.offset, &hunk.old);
+            self.write_bytes(&mut buffer, b'+', hunk.offset, &hunk.new);
+        }
+        writer.write_all(&buffer)?;
+        Ok(())
+    }
+
+    /// Writes the dump lines of `data` at `offset`, each prefixed with `prefix`.
+    fn write_bytes(&self, buffer: &mut Vec<u8>, prefix: u8, offset: u64, data: &[u8]) {
+        for (i, line) in data.chunks(BYTES_PER_LINE).enumerate() {
+            buffer.push(prefix);
+            let line_offset = offset as usize + i * BYTES_PER_LINE;
+            self.line_writer.write_line(buffer, line_offset, line);
+        }
+    }
+
+    /// Parses a patch written by `write`. Only the `@` lines and the hex bytes of the dump
+    /// lines are read, the text panel and `#` lines are ignored.
+    pub fn parse(text: &str) -> Result<Vec<Hunk>> {
+        let mut hunks: Vec<Hunk> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let invalid =
+                |message: &str| HexlerError::InvalidPatch(format!("line {}: {}", i + 1, message));
+            let Some(prefix) = line.chars().next() else {
+                continue;
+            };
+            match prefix {
+                '#' => continue,
+                '@' => {
+                    let offset = line[1..].split(',').next().unwrap_or_default();
+                    let offset = crate::parse_offset(offset).map_err(|err| invalid(&err))?;
+                    hunks.push(Hunk {
+                        offset: offset as u64,
+                        old: Vec::new(),
+                        new: Vec::new(),
+                    });
+                }
+                '-' | '+' => {
+                    let hunk = hunks
+                        .last_mut()
+                        .ok_or_else(|| invalid("bytes before the first '@' line"))?;
+                    let mut columns = line[1..].split('│');
+                    let (Some(offset), Some(hex)) = (columns.next(), columns.next()) else {
+                        return Err(invalid("expected 'offset │ hex bytes │ text'"));
+                    };
+                    let offset = u64::from_str_radix(offset.trim(), 16)
+                        .map_err(|_| invalid(&format!("invalid offset '{}'", offset.trim())))?;
+                    let bytes = crate::patch::parse_bytes(hex).map_err(|err| invalid(&err))?;
+
+                    let data = if prefix == '-' {
+                        &mut hunk.old
+                    } else {
+                        &mut hunk.new
+                    };
+                    let expected = crate::patch::check_end(hunk.offset, data.len() as u64)
+                        .map_err(|err| invalid(&err))?;
+                    if offset != expected {
+                        return Err(invalid(&format!(
+                            "expected the bytes at 0x{:x}, got 0x{:x}",
+                            expected, offset
+                        )));
+                    }
+                    data.extend(bytes);
+                    crate::patch::check_end(hunk.offset, data.len() as u64)
+                        .map_err(|err| invalid(&err))?;
+                }
+                _ => {
+                    if !line.trim().is_empty() {
+                        return Err(invalid(
+                            "expected a line starting with '#', '@', '-' or '+'",
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(hunks)
+    }
+}
+
+/// Checks that the file in `reader` of `len` bytes has the old bytes of all `hunks`, and
+/// returns the length of the file after applying them.
+///
+/// Hunks that change the size must end at the end of the file, else `PatchLengthMismatch` is
+/// returned, e.g. when the patch was already applied. The first hunk whose old bytes don't
+/// match is returned as `PatchMismatch`.
+pub fn verify<R: Read + Seek>(reader: &mut R, len: u64, hunks: &[Hunk]) -> Result<u64> {
+    let mut new_len = len;
+    for hunk in hunks {
+        let end = hunk.offset + hunk.old.len() as u64;
+        if hunk.changes_size() {
+            if end != len {
+                return Err(HexlerError::PatchLengthMismatch {
+                    expected: end,
+                    actual: len,
+                });
+            }
+            new_len = hunk.offset + hunk.new.len() as u64;
+        }
+        if read_range(reader, &(hunk.offset..end))? != hunk.old {
+            return Err(HexlerError::PatchMismatch {
+                offset: hunk.offset,
+            });
+        }
+    }
+    Ok(new_len)
+}
+
+/// The patches that write the new bytes of `hunks`.
+pub fn patches(hunks: &[Hunk]) -> Vec<crate::patch::Patch> {
+    hunks
+        .iter()
+        .map(|hunk| crate::patch::Patch::new(hunk.offset, hunk.new.clone()))
+        .collect()
+}
+
+fn num_bytes(len: usize) -> String {
+    crate::patch::num_bytes(len as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn diff_of(old: &[u8], new: &[u8]) -> Vec<Hunk> {
+        diff(
+            &mut Cursor::new(old.to_vec()),
+            &mut Cursor::new(new.to_vec()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_diff() {
+        let old: Vec<u8> = (0..100).collect();
+        let mut new = old.clone();
+        new[10] = 0xff;
+        new[14] = 0xff;
+        new[60] = 0xee;
+
+        // The bytes 10 and 14 are close together and end up in one hunk
+        let hunks = diff_of(&old, &new);
+        assert_eq!(
+            hunks,
+            vec![
+                Hunk {
+                    offset: 10,
+                    old: vec![10, 11, 12, 13, 14],
+                    new: vec![0xff, 11, 12, 13, 0xff],
+                },
+                Hunk {
+                    offset: 60,
+                    old: vec![60],
+                    new: vec![0xee],
+                },
+            ]
+        );
+        assert!(diff_of(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_diff_size_change() {
+        let hunks = diff_of(b"abcdef", b"abcdeFgh");
+        assert_eq!(
+            hunks,
+            vec![Hunk {
+                offset: 5,
+                old: b"f".to_vec(),
+                new: b"Fgh".to_vec(),
+            }]
+        );
+        assert!(hunks[0].changes_size());
+
+        let hunks = diff_of(b"abcdef", b"ab");
+        assert_eq!(hunks[0].offset, 2);
+        assert_eq!(hunks[0].old, b"cdef");
+        assert!(hunks[0].new.is_empty());
+    }
+
+    #[test]
+    fn test_write_and_parse() {
+        let old: Vec<u8> = (0..64).collect();
+        let mut new = old.clone();
+        new[0x1c..0x24].copy_from_slice(b"patched!");
+        new.extend_from_slice(b"tail");
+        let hunks = diff_of(&old, &new);
+
+        let mut text = Vec::new();
+        HexPatch::new()
+            .write(&mut text, "hexler mkpatch old new", &hunks)
+            .unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(!text.contains('\x1b'));
+        assert!(text.starts_with("# hexler mkpatch old new\n@ 0x1c, 8 bytes\n-0000001c │ "));
+        assert!(text.contains("\n@ 0x40, 0 bytes -> 4 bytes\n+00000040 │ 74 61 69 6c "));
+        assert!(text.contains("│ patched!\n"));
+
+        assert_eq!(HexPatch::parse(&text).unwrap(), hunks);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = HexPatch::parse("-00000000 │ 00\n").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("line 1: bytes before the first '@'"));
+
+        let text = "@ 0x10, 2 bytes\n-00000010 │ 00 01\n+00000011 │ 02 03\n";
+        let err = HexPatch::parse(text).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("line 3: expected the bytes at 0x10"),
+            "{}",
+            err
+        );
+
+        assert!(HexPatch::parse("hello\n").is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let hunks = diff_of(b"abcdef", b"aBcdefg");
+        assert_eq!(verify(&mut Cursor::new(b"abcdef"), 6, &hunks).unwrap(), 7);
+
+        let err = verify(&mut Cursor::new(b"axcdef"), 6, &hunks).unwrap_err();
+        assert!(matches!(err, HexlerError::PatchMismatch { offset: 1 }));
+
+        // The hunk that appends bytes doesn't match when the file is longer
+        let err = verify(&mut Cursor::new(b"abcdefg"), 7, &hunks).unwrap_err();
+        assert!(matches!(
+            err,
+            HexlerError::PatchLengthMismatch {
+                expected: 6,
+                actual: 7
+            }
+        ));
+    }
+
+    #[test]
+    fn test_verify_applied_twice() {
+        for (old, new) in [(&b"abc"[..], &b"abcxyz"[..]), (b"abcxyz", b"abc")] {
+            let hunks = diff_of(old, new);
+            let mut file = Cursor::new(old.to_vec());
+            let new_len = verify(&mut file, old.len() as u64, &hunks).unwrap();
+            crate::patch::apply(&mut file, old.len() as u64, &patches(&hunks), true).unwrap();
+            file.get_mut().truncate(new_len as usize);
+            assert_eq!(file.get_ref(), new);
+
+            let err = verify(&mut file, new.len() as u64, &hunks).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "the patch is for a file of {} bytes, the file has {} bytes, nothing was written",
+                    old.len(),
+                    new.len()
+                )
+            );
+        }
+    }
+}
@@ -0,0 +1,186 @@
+/// A background color for a range of offsets, e.g. a field of a file header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    /// Offsets as shown in the offset column
+    pub range: std::ops::Range<u64>,
+    /// ANSI escape code that sets the background color, e.g. `\x1b[48;5;17m`
+    pub background: String,
+}
+
+impl Highlight {
+    /// Creates a highlight of `range` with the background escape code `background`.
+    pub fn new(range: std::ops::Range<u64>, background: &str) -> Self {
+        Self {
+            range,
+            background: background.to_string(),
+        }
+    }
+}
+
+/// Dark background colors that keep the byte colors readable, for adjacent fields.
+pub const FIELD_BACKGROUNDS: [&str; 4] = [
+    "\x1b[48;5;17m",
+    "\x1b[48;5;22m",
+    "\x1b[48;5;52m",
+    "\x1b[48;5;54m",
+];
+
+/// Background colors by name, for ranges that the user colors. They are dark like the
+/// field backgrounds.
+pub const NAMED_BACKGROUNDS: [(&str, &str); 9] = [
+    ("red", "\x1b[48;5;52m"),
+    ("green", "\x1b[48;5;22m"),
+    ("blue", "\x1b[48;5;17m"),
+    ("yellow", "\x1b[48;5;58m"),
+    ("magenta", "\x1b[48;5;53m"),
+    ("cyan", "\x1b[48;5;23m"),
+    ("orange", "\x1b[48;5;94m"),
+    ("purple", "\x1b[48;5;54m"),
+    ("gray", "\x1b[48;5;238m"),
+];
+
+/// Returns the background escape code of the color `name`, one of `NAMED_BACKGROUNDS`.
+pub fn named_background(name: &str) -> crate::error::Result<&'static str> {
+    NAMED_BACKGROUNDS
+        .iter()
+        .find(|(color, _)| color.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, background)| *background)
+        .ok_or_else(|| crate::error::HexlerError::UnknownColor {
+            name: name.to_string(),
+            available: NAMED_BACKGROUNDS.map(|(color, _)| color).join(" "),
+        })
+}
+
+/// Non-overlapping highlights sorted by offset, for quick lookups per line.
+///
+/// Highlights that are added later are painted over the earlier ones, so e.g. the fields of
+/// a header can be highlighted on top of the whole header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Highlights {
+    highlights: Vec<Highlight>,
+}
+
+impl Highlights {
+    /// Creates an empty set of highlights.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether there are no highlights at all.
+    pub fn is_empty(&self) -> bool {
+        self.highlights.is_empty()
+    }
+
+    /// Adds `highlight` on top of the existing highlights.
+    pub fn add(&mut self, highlight: Highlight) {
+        let range = highlight.range.clone();
+        if range.is_empty() {
+            return;
+        }
+
+        // The highlights that overlap with the new one are replaced by their parts outside
+        let first = self
+            .highlights
+            .partition_point(|h| h.range.end <= range.start);
+        let last = first + self.highlights[first..].partition_point(|h| h.range.start < range.end);
+
+        let mut replacement = Vec::with_capacity(3);
+        if let Some(h) = self.highlights[first..last].first() {
+            if h.range.start < range.start {
+                replacement.push(Highlight {
+                    range: h.range.start..range.start,
+                    background: h.background.clone(),
+                });
+            }
+        }
+        replacement.push(highlight);
+        if let Some(h) = self.highlights[first..last].last() {
+            if h.range.end > range.end {
+                replacement.push(Highlight {
+                    range: range.end..h.range.end,
+                    background: h.background.clone(),
+                });
+            }
+        }
+        self.highlights.splice(first..last, replacement);
+    }
+
+    /// Returns the highlights that overlap with `range`, sorted by offset.
+    pub fn overlapping(&self, range: std::ops::Range<u64>) -> &[Highlight] {
+        let first = self
+            .highlights
+            .partition_point(|h| h.range.end <= range.start);
+        let len = self.highlights[first..].partition_point(|h| h.range.start < range.end);
+        &self.highlights[first..first + len]
+    }
+}
+
+impl FromIterator<Highlight> for Highlights {
+    fn from_iter<I: IntoIterator<Item = Highlight>>(iter: I) -> Self {
+        let mut highlights = Self::new();
+        for highlight in iter {
+            highlights.add(highlight);
+        }
+        highlights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(highlights: &[Highlight]) -> Vec<(u64, u64, &str)> {
+        highlights
+            .iter()
+            .map(|h| (h.range.start, h.range.end, h.background.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_named_background() {
+        assert_eq!(named_background("red").unwrap(), "\x1b[48;5;52m");
+        assert_eq!(named_background(" Blue").unwrap(), "\x1b[48;5;17m");
+        let err = named_background("pink").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("unknown color pink, the colors are: red green "));
+    }
+
+    #[test]
+    fn test_add_paints_over() {
+        let mut highlights = Highlights::new();
+        highlights.add(Highlight::new(10..50, "a"));
+        highlights.add(Highlight::new(20..30, "b"));
+        highlights.add(Highlight::new(45..60, "c"));
+        highlights.add(Highlight::new(0..5, "d"));
+        highlights.add(Highlight::new(7..7, "empty"));
+        assert_eq!(
+            ranges(highlights.overlapping(0..100)),
+            vec![
+                (0, 5, "d"),
+                (10, 20, "a"),
+                (20, 30, "b"),
+                (30, 45, "a"),
+                (45, 60, "c")
+            ]
+        );
+
+        // Covering several highlights completely
+        highlights.add(Highlight::new(15..55, "e"));
+        assert_eq!(
+            ranges(highlights.overlapping(0..100)),
+            vec![(0, 5, "d"), (10, 15, "a"), (15, 55, "e"), (55, 60, "c")]
+        );
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let highlights: Highlights = [Highlight::new(10..20, "a"), Highlight::new(30..40, "b")]
+            .into_iter()
+            .collect();
+        assert!(highlights.overlapping(0..10).is_empty());
+        assert!(highlights.overlapping(20..30).is_empty());
+        assert_eq!(ranges(highlights.overlapping(19..31)).len(), 2);
+        assert_eq!(ranges(highlights.overlapping(35..36)), vec![(30, 40, "b")]);
+    }
+}
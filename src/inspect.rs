@@ -0,0 +1,397 @@
+use crate::line_writer::LineWriter;
+
+/// A value decoded from the bytes at the inspected offset, in little and big endian.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value {
+    pub name: &'static str,
+    /// The value in little endian, or the only value when byte order doesn't matter. `None`
+    /// when there are not enough bytes.
+    pub little_endian: Option<String>,
+    /// The value in big endian, `None` when byte order doesn't matter or there are not enough
+    /// bytes.
+    pub big_endian: Option<String>,
+}
+
+impl Value {
+    fn single(name: &'static str, value: Option<String>) -> Self {
+        Self {
+            name,
+            little_endian: value,
+            big_endian: None,
+        }
+    }
+
+    /// Decodes the first `N` bytes of `data` in both byte orders with `format`.
+    fn both<const N: usize>(
+        name: &'static str,
+        data: &[u8],
+        format: impl Fn([u8; N], bool) -> String,
+    ) -> Self {
+        let bytes = data
+            .get(..N)
+            .map(|bytes| <[u8; N]>::try_from(bytes).unwrap());
+        Self {
+            name,
+            little_endian: bytes.map(|bytes| format(bytes, true)),
+            big_endian: bytes.map(|bytes| format(bytes, false)),
+        }
+    }
+}
+
+/// Decodes the bytes at the start of `data` as integers, floats, LEB128, Unix timestamps, a
+/// GUID and an IPv4 address. `data` should be at least `InspectReport::LEN` bytes long, values
+/// that need more bytes than available are `None`.
+pub fn decode(data: &[u8]) -> Vec<Value> {
+    macro_rules! int {
+        ($name:literal, $ty:ty, $hex:literal) => {
+            Value::both($name, data, |bytes, little_endian| {
+                let value = if little_endian {
+                    <$ty>::from_le_bytes(bytes)
+                } else {
+                    <$ty>::from_be_bytes(bytes)
+                };
+                match $hex {
+                    true => format!("{} (0x{:x})", value, value),
+                    false => value.to_string(),
+                }
+            })
+        };
+    }
+    macro_rules! float {
+        ($name:literal, $ty:ty) => {
+            Value::both($name, data, |bytes, little_endian| {
+                let value = if little_endian {
+                    <$ty>::from_le_bytes(bytes)
+                } else {
+                    <$ty>::from_be_bytes(bytes)
+                };
+                format_float(value, value.abs() as f64)
+            })
+        };
+    }
+
+    vec![
+        Value::single("u8", data.first().map(|b| format!("{} (0x{:x})", b, b))),
+        Value::single("i8", data.first().map(|&b| (b as i8).to_string())),
+        int!("u16", u16, true),
+        int!("i16", i16, false),
+        int!("u32", u32, true),
+        int!("i32", i32, false),
+        int!("u64", u64, true),
+        int!("i64", i64, false),
+        float!("f32", f32),
+        float!("f64", f64),
+        Value::single(
+            "uleb128",
+            uleb128(data).map(|(value, len)| format!("{} ({})", value, num_bytes(len))),
+        ),
+        Value::single(
+            "sleb128",
+            sleb128(data).map(|(value, len)| format!("{} ({})", value, num_bytes(len))),
+        ),
+        Value::both("unix time", data, |bytes, little_endian| {
+            let secs = match little_endian {
+                true => u32::from_le_bytes(bytes),
+                false => u32::from_be_bytes(bytes),
+            };
+            format_unix_time(secs as i64)
+        }),
+        Value::both("unix time 64", data, |bytes, little_endian| {
+            let secs = match little_endian {
+                true => i64::from_le_bytes(bytes),
+                false => i64::from_be_bytes(bytes),
+            };
+            format_unix_time(secs)
+        }),
+        Value::single("guid", data.get(..16).map(format_guid)),
+        Value::single(
+            "ipv4",
+            data.get(..4)
+                .map(|ip| format!("{}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3])),
+        ),
+    ]
+}
+
+/// Decodes an unsigned LEB128 number, returns its value and length in bytes. `None` when it
+/// doesn't end within `data` or doesn't fit into 64 bits.
+pub fn uleb128(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        let bits = (byte & 0x7f) as u64;
+        let shift = 7 * i as u32;
+        if shift == 63 && bits > 1 {
+            return None;
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// Decodes a signed LEB128 number, returns its value and length in bytes. `None` when it
+/// doesn't end within `data` or is longer than 10 bytes.
+pub fn sleb128(data: &[u8]) -> Option<(i64, usize)> {
+    let mut value = 0i64;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        let shift = 7 * i as u32;
+        value |= ((byte & 0x7f) as i64) << shift;
+        if byte & 0x80 == 0 {
+            let shift = shift + 7;
+            if shift < 64 && byte & 0x40 != 0 {
+                value |= -1i64 << shift;
+            }
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn num_bytes(len: usize) -> String {
+    match len {
+        1 => "1 byte".to_string(),
+        _ => format!("{} bytes", len),
+    }
+}
+
+/// Floats are shown in full unless their `magnitude` is very large or very small.
+fn format_float<T: std::fmt::Display + std::fmt::LowerExp>(value: T, magnitude: f64) -> String {
+    if magnitude != 0.0 && magnitude.is_finite() && !(1e-6..1e16).contains(&magnitude) {
+        format!("{:e}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn format_unix_time(secs: i64) -> String {
+    match chrono::DateTime::from_timestamp(secs, 0) {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => "out of range".to_string(),
+    }
+}
+
+/// GUIDs are stored mixed endian: the first three groups are little endian.
+fn format_guid(bytes: &[u8]) -> String {
+    format!(
+        "{{{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}}}",
+        bytes[3], bytes[2], bytes[1], bytes[0],
+        bytes[5], bytes[4],
+        bytes[7], bytes[6],
+        bytes[8], bytes[9],
+        bytes[10], bytes[11], bytes[12], bytes[13], bytes[14], bytes[15]
+    )
+}
+
+/// Shows the bytes at an offset as a hex dump line, and the values decoded from them.
+///
+/// ```text
+/// 0000001c │ 2c 01 00 00 ...
+///
+///   type          little endian                              big endian
+///   u16           300 (0x12c)                                11265 (0x2c01)
+/// ```
+pub struct InspectReport {
+    line_writer: LineWriter,
+}
+
+impl InspectReport {
+    /// Number of bytes that are decoded, enough for the longest value.
+    pub const LEN: usize = 16;
+
+    /// Creates a report that shows the inspected bytes with `line_writer`.
+    pub fn new(line_writer: LineWriter) -> Self {
+        Self { line_writer }
+    }
+
+    /// Writes the report of `data` at `offset`, with `title` on top. `data` is truncated to
+    /// `InspectReport::LEN` bytes.
+    pub fn write<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        title: &str,
+        offset: usize,
+        data: &[u8],
+    ) -> std::io::Result<()> {
+        let data = &data[..data.len().min(Self::LEN)];
+
+        writeln!(writer, "{}", title)?;
+        writeln!(
+            writer,
+            "{} at offset 0x{:x} ({})\n",
+            num_bytes(data.len()),
+            offset,
+            offset
+        )?;
+        let mut buffer = Vec::new();
+        for (i, line) in data.chunks(self.line_writer.bytes_per_line()).enumerate() {
+            let line_offset = offset + i * self.line_writer.bytes_per_line();
+            self.line_writer.write_line(&mut buffer, line_offset, line);
+        }
+        writer.write_all(&buffer)?;
+
+        writeln!(
+            writer,
+            "\n  {:<14}{:<44}big endian",
+            "type", "little endian"
+        )?;
+        for value in decode(data) {
+            let little_endian = value.little_endian.as_deref().unwrap_or("-");
+            match value.big_endian {
+                Some(big_endian) => writeln!(
+                    writer,
+                    "  {:<14}{:<44}{}",
+                    value.name, little_endian, big_endian
+                )?,
+                None => writeln!(writer, "  {:<14}{}", value.name, little_endian)?,
+            }
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_to_color::{ByteToColor, Theme};
+    use crate::hex_formatter::HexFormatter;
+
+    fn value(values: &[Value], name: &str) -> (Option<String>, Option<String>) {
+        let value = values.iter().find(|value| value.name == name).unwrap();
+        (value.little_endian.clone(), value.big_endian.clone())
+    }
+
+    fn both(little_endian: &str, big_endian: &str) -> (Option<String>, Option<String>) {
+        (
+            Some(little_endian.to_string()),
+            Some(big_endian.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_integers() {
+        let values = decode(&[0xfe, 0xff, 0x01, 0x02, 0, 0, 0, 0x80]);
+        assert_eq!(value(&values, "u8"), (Some("254 (0xfe)".to_string()), None));
+        assert_eq!(value(&values, "i8"), (Some("-2".to_string()), None));
+        assert_eq!(
+            value(&values, "u16"),
+            both("65534 (0xfffe)", "65279 (0xfeff)")
+        );
+        assert_eq!(value(&values, "i16"), both("-2", "-257"));
+        assert_eq!(
+            value(&values, "u32"),
+            both("33685502 (0x201fffe)", "4278124802 (0xfeff0102)")
+        );
+        assert_eq!(
+            value(&values, "i64"),
+            both("-9223372036821090306", "-72337960913076096")
+        );
+    }
+
+    #[test]
+    fn test_floats() {
+        let values = decode(&1.5f64.to_le_bytes());
+        assert_eq!(value(&values, "f64").0, Some("1.5".to_string()));
+
+        let values = decode(&(-1e20f32).to_be_bytes());
+        assert_eq!(value(&values, "f32").1, Some("-1e20".to_string()));
+        let values = decode(&[1, 0, 0, 0]);
+        assert_eq!(value(&values, "f32").0, Some("1e-45".to_string()));
+        assert_eq!(format_float(f64::NAN, f64::NAN), "NaN");
+        assert_eq!(format_float(0.0, 0.0), "0");
+    }
+
+    #[test]
+    fn test_leb128() {
+        assert_eq!(uleb128(&[0xac, 0x02]), Some((300, 2)));
+        assert_eq!(uleb128(&[0x7f, 0xff]), Some((127, 1)));
+        assert_eq!(uleb128(&[0xff, 0xff]), None);
+        assert_eq!(
+            uleb128(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]),
+            Some((u64::MAX, 10))
+        );
+        assert_eq!(
+            uleb128(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]),
+            None
+        );
+
+        assert_eq!(sleb128(&[0x7f]), Some((-1, 1)));
+        assert_eq!(sleb128(&[0x80, 0x7f]), Some((-128, 2)));
+        assert_eq!(sleb128(&[0x3f]), Some((63, 1)));
+        assert_eq!(
+            sleb128(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f]),
+            Some((i64::MIN, 10))
+        );
+    }
+
+    #[test]
+    fn test_time_guid_ipv4() {
+        let mut data = 1_700_000_000u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        data.extend_from_slice(&[0x9a, 0xbc, 0xde, 0xf0, 0x01, 0x02, 0x03, 0x04]);
+        let values = decode(&data);
+
+        assert_eq!(
+            value(&values, "unix time").0,
+            Some("2023-11-14 22:13:20 UTC".to_string())
+        );
+        assert_eq!(
+            value(&values, "guid").0,
+            Some("{6553f100-3412-7856-9abc-def001020304}".to_string())
+        );
+        assert_eq!(value(&values, "ipv4").0, Some("0.241.83.101".to_string()));
+
+        let values = decode(&[0xff; 8]);
+        assert_eq!(
+            value(&values, "unix time 64"),
+            both("1969-12-31 23:59:59 UTC", "1969-12-31 23:59:59 UTC")
+        );
+        let values = decode(&[0x7f; 8]);
+        assert_eq!(
+            value(&values, "unix time 64"),
+            both("out of range", "out of range")
+        );
+    }
+
+    #[test]
+    fn test_not_enough_bytes() {
+        let values = decode(&[1, 2, 3]);
+        assert_eq!(value(&values, "u16"), both("513 (0x201)", "258 (0x102)"));
+        assert_eq!(value(&values, "u32"), (None, None));
+        assert_eq!(value(&values, "guid"), (None, None));
+        assert_eq!(value(&values, "uleb128").0, Some("1 (1 byte)".to_string()));
+
+        assert!(decode(&[])
+            .iter()
+            .all(|value| value.little_endian.is_none()));
+    }
+
+    #[test]
+    fn test_report() {
+        let line_writer = LineWriter::new_bytes(8)
+            .unwrap()
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap());
+        let report = InspectReport::new(line_writer);
+
+        let mut output = Vec::new();
+        report
+            .write(
+                &mut output,
+                "title",
+                0x1c,
+                &[0x2c, 0x01, 0, 0, 0, 0, 0, 0, 0xff],
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "title");
+        assert_eq!(lines[1], "9 bytes at offset 0x1c (28)");
+        assert!(lines[3].starts_with("0000001c │ 2c 01 00 00"));
+        assert!(lines[4].starts_with("00000024 │ ff"));
+        assert!(output.contains("\n  u16           300 (0x12c)"));
+        assert!(output.contains("11265 (0x2c01)\n"));
+        assert!(output.contains("\n  guid          -\n"));
+    }
+}
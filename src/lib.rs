@@ -1,32 +1,183 @@
+pub mod annotate;
+pub mod archive;
 pub mod ascii_renderer;
 pub mod border_writer;
 pub mod byte_to_color;
+pub mod decompress;
+pub mod editor;
+pub mod elf;
 pub mod error;
+pub mod executable;
+pub mod file_kind;
+pub mod hash;
+pub mod hex_dump;
 pub mod hex_formatter;
+pub mod hexpatch;
+pub mod highlight;
+pub mod inspect;
 pub mod line_writer;
+pub mod macho;
+pub mod overview;
+pub mod patch;
+pub mod pe;
+pub mod process;
+pub mod renderer;
+pub mod sidecar;
+pub mod signatures;
+pub mod simd;
+pub mod stats;
+pub mod strings;
+pub mod template;
+
+#[cfg(test)]
+mod test_util;
 
 use chrono::{DateTime, Local};
 use pager::Pager;
 use size::Size;
+use std::cell::Cell;
 use std::fs;
+use std::rc::Rc;
 use terminal_size::terminal_size;
 
-use clap::Parser;
+use ascii_renderer::{AsciiRenderer, Encoding};
+use byte_to_color::{ByteToColor, ColorBy, Theme};
+use clap::{Parser, Subcommand};
 use error::{HexlerError, Result};
 use line_writer::LineWriter;
+use renderer::Renderer;
 
 /// Command-line arguments for hexler.
 #[derive(Parser, Debug)]
 #[command(author, version, about="A colorful hex printer with opinionated defaults", long_about = None)]
+#[command(group(clap::ArgGroup::new("memory").args(["addr", "maps_region"])))]
 pub struct Args {
     /// Number of bytes per line. Must be multiple of 8
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub num_bytes_per_line: Option<usize>,
 
     /// Disables pager and write all output to stdout
     #[arg(short, long, default_value_t = false)]
     pub stdout: bool,
 
+    /// Character encoding of the text panel
+    #[arg(long, value_enum, default_value_t = Encoding::Cp437)]
+    pub encoding: Encoding,
+
+    /// What the byte colors are based on: byte class, byte value or entropy of the surrounding data
+    #[arg(long, value_enum, default_value_t = ColorBy::Class)]
+    pub color_by: ColorBy,
+
+    /// Starts the dump at this offset, decimal or hex with a `0x` prefix
+    #[arg(long, value_parser = parse_offset, default_value = "0")]
+    pub skip: usize,
+
+    /// Decompresses gzip, zstd, xz and bzip2 input, detected by its magic bytes. --skip and the
+    /// offsets are positions in the decompressed data, other input is shown as it is
+    #[arg(long, default_value_t = false)]
+    pub decompress: bool,
+
+    /// Shows an overview map of the whole input instead of a hex dump
+    #[arg(long, default_value_t = false)]
+    pub map: bool,
+
+    /// Shows statistics of the input: byte histogram, byte classes, entropy, runs and n-grams
+    #[arg(long, default_value_t = false)]
+    pub stats: bool,
+
+    /// Length of the n-grams counted by --stats
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..=8))]
+    pub ngram_len: u8,
+
+    /// Lists strings of printable characters with their offsets instead of a hex dump, in the
+    /// encoding ascii (default), utf8 or utf16le
+    #[arg(long, default_value_t = false)]
+    pub strings: bool,
+
+    /// Minimum number of characters of the strings listed by --strings, at most 4096. Longer
+    /// runs are listed in pieces of 4096 characters
+    #[arg(long, default_value_t = 4)]
+    pub min_len: usize,
+
+    /// Shows a hex dump of each string listed by --strings, with this many lines before and after
+    #[arg(long)]
+    pub context: Option<usize>,
+
+    /// Lists embedded files found by their magic numbers (ELF, PE, PNG, JPEG, gzip, zlib, xz,
+    /// zip, squashfs, uImage, device trees, ...) instead of a hex dump
+    #[arg(long, default_value_t = false)]
+    pub scan: bool,
+
+    /// Marks the embedded files that --scan finds with separator rows in the hex dump
+    #[arg(long, default_value_t = false)]
+    pub annotate: bool,
+
+    /// Dumps only this section of an ELF, PE or Mach-O file (e.g. `.rodata` or `__TEXT,__text`),
+    /// with its virtual addresses in the offset column
+    #[arg(long)]
+    pub section: Option<String>,
+
+    /// Dumps the memory of the process with this id through /proc/<pid>/mem instead of a file,
+    /// from --addr or --maps-region, with virtual addresses in the offset column. Unreadable
+    /// pages are shown as marker rows
+    #[arg(long, requires = "memory")]
+    pub pid: Option<u32>,
+
+    /// Address where the dump of --pid starts, e.g. `0x7ffd2a000000`
+    #[arg(long, value_parser = parse_offset, requires = "pid")]
+    pub addr: Option<usize>,
+
+    /// Number of bytes of the dump of --pid, by default up to the end of the memory region
+    #[arg(long, value_parser = parse_offset, requires = "pid")]
+    pub length: Option<usize>,
+
+    /// Dumps this memory region of --pid as listed in /proc/<pid>/maps, e.g. `[heap]`, `stack` or
+    /// `libc.so.6`. Not named `--map`, which shows the overview map
+    #[arg(long, requires = "pid")]
+    pub maps_region: Option<String>,
+
+    /// Dumps executables as plain data, without section rows and header field colors, and zip
+    /// and tar archives instead of listing their files
+    #[arg(long, default_value_t = false)]
+    pub raw: bool,
+
+    /// Overlays the fields that this structure template describes on the dump, starting at
+    /// --skip: each field gets a background color, and its name and value are listed next to
+    /// the line
+    #[arg(long)]
+    pub template: Option<std::path::PathBuf>,
+
+    /// Shows the bytes at this offset decoded as integers and floats in both byte orders,
+    /// LEB128, Unix timestamps, a GUID and an IPv4 address, instead of a hex dump
+    #[arg(long, value_parser = parse_offset)]
+    pub inspect: Option<usize>,
+
+    /// Opens the file in an interactive hex editor, starting at --skip: the cursor overwrites hex
+    /// digits or characters, and the changes are written back to the file when confirmed
+    #[arg(long, default_value_t = false)]
+    pub edit: bool,
+
+    /// Gives the bytes of this range a background color in both panels, e.g. `0x100..0x140` or
+    /// `0x100..0x140:red`, and can be given several times. The colors are red, green, blue,
+    /// yellow, magenta, cyan, orange, purple and gray, marks without a color get the colors of
+    /// template fields in turn
+    #[arg(long, value_parser = parse_mark)]
+    pub mark: Vec<(std::ops::Range<u64>, Option<&'static str>)>,
+
+    /// Computes these digests of the dumped data while it is read, and shows them in the footer:
+    /// crc32, md5, sha1, sha256 and xxh64, separated by commas
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub hash: Vec<hash::Algorithm>,
+
+    /// Additionally shows the --hash digests of each block of this many bytes, counted from
+    /// --skip
+    #[arg(long, value_parser = parse_block_size, requires = "hash")]
+    pub hash_per_block: Option<u64>,
+
+    /// Collapses runs of identical lines into a single `*` line
+    #[arg(long, default_value_t = false)]
+    pub squeeze: bool,
+
     /// Writes bytes 0 to 255, only for demonstration purposes
     #[arg(long, default_value_t = false)]
     pub demo: bool,
@@ -34,9 +185,181 @@ pub struct Args {
     /// The file to display. If none is provided, the standard input (stdin) will be used instead.
     #[arg()]
     pub file: Option<std::path::PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands that change files instead of showing them.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Overwrites bytes of a file without the editor, e.g. in build scripts, and shows the
+    /// changed lines before and after
+    Patch(PatchArgs),
+
+    /// Writes the bytes that differ between two files as a patch to stdout, a hex dump of the
+    /// old and new bytes of every changed range that can be reviewed like a diff
+    Mkpatch {
+        /// The file before the changes
+        old: std::path::PathBuf,
+        /// The file after the changes
+        new: std::path::PathBuf,
+    },
+
+    /// Applies a patch made by mkpatch, after checking that the file has all of its old bytes,
+    /// and shows the changed lines before and after
+    Applypatch {
+        /// The file to patch
+        file: std::path::PathBuf,
+        /// The patch made by mkpatch
+        patch: std::path::PathBuf,
+    },
+
+    /// Adds a named range to the annotations of a file, which are kept next to it in
+    /// `<file>.hexler.toml`: dumps of the file color the range and show its label and note
+    Annotate(AnnotateArgs),
+}
+
+/// Arguments of `hexler annotate`.
+#[derive(clap::Args, Debug)]
+pub struct AnnotateArgs {
+    /// The file to annotate
+    pub file: std::path::PathBuf,
+
+    /// Range of offsets that is annotated, e.g. `0x40..0x80`
+    #[arg(long, value_parser = parse_range)]
+    pub range: std::ops::Range<u64>,
+
+    /// Name of the range, shown at its start
+    #[arg(long)]
+    pub label: String,
+
+    /// Note shown after the label
+    #[arg(long)]
+    pub note: Option<String>,
+
+    /// Background color of the range: red, green, blue, yellow, magenta, cyan, orange, purple
+    /// or gray. Ranges without a color get the colors of template fields in turn
+    #[arg(long)]
+    pub color: Option<String>,
+}
+
+/// Arguments of `hexler patch`. The patches are applied in the order --fill, --at, --apply.
+#[derive(clap::Args, Debug)]
+#[command(group(clap::ArgGroup::new("patches").required(true).multiple(true).args(["at", "fill", "apply"])))]
+pub struct PatchArgs {
+    /// The file to patch
+    pub file: std::path::PathBuf,
+
+    /// Offset where --bytes are written, decimal or hex with a `0x` prefix
+    #[arg(long, value_parser = parse_offset, requires = "bytes")]
+    pub at: Option<usize>,
+
+    /// Bytes written at --at, as hex digits like "de ad be ef"
+    #[arg(long, requires = "at")]
+    pub bytes: Option<String>,
+
+    /// Byte that --range is filled with, decimal or hex with a `0x` prefix
+    #[arg(long, value_parser = parse_byte, requires = "range")]
+    pub fill: Option<u8>,
+
+    /// Range of offsets filled with --fill, e.g. `0x100..0x200`
+    #[arg(long, value_parser = parse_range, requires = "fill")]
+    pub range: Option<std::ops::Range<u64>>,
+
+    /// Applies the patches of this file, one per line: `0x1f0: de ad be ef` writes the bytes at
+    /// the offset, `0x100..0x200: 00` fills the range with the bytes, `#` starts a comment
+    #[arg(long)]
+    pub apply: Option<std::path::PathBuf>,
+
+    /// Allows patches past the end of the file, the file grows and gaps are filled with zeros
+    #[arg(long, default_value_t = false)]
+    pub extend: bool,
+}
+
+/// Parses an offset or length given on the command line, decimal or hex with a `0x` prefix.
+pub fn parse_offset(text: &str) -> std::result::Result<usize, String> {
+    let text = text.trim().replace('_', "");
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|err| format!("invalid offset '{}': {}", text, err))
 }
 
-/// Reads data from a reader and outputs a colored hex dump.
+/// Parses a range of offsets given on the command line, e.g. `0x100..0x200`.
+pub fn parse_range(text: &str) -> std::result::Result<std::ops::Range<u64>, String> {
+    let Some((start, end)) = text.split_once("..") else {
+        return Err(format!(
+            "invalid range '{}', expected start..end",
+            text.trim()
+        ));
+    };
+    let range = parse_offset(start)? as u64..parse_offset(end)? as u64;
+    if range.start > range.end {
+        return Err(format!(
+            "invalid range '{}', the end is before the start",
+            text.trim()
+        ));
+    }
+    Ok(range)
+}
+
+/// Parses a range given with --mark and its optional color, e.g. `0x100..0x140:red`. Returns
+/// the background escape code of the color.
+pub fn parse_mark(
+    text: &str,
+) -> std::result::Result<(std::ops::Range<u64>, Option<&'static str>), String> {
+    let (range, color) = match text.rsplit_once(':') {
+        Some((range, color)) => (range, Some(color)),
+        None => (text, None),
+    };
+    let background = color
+        .map(|color| highlight::named_background(color).map_err(|err| err.to_string()))
+        .transpose()?;
+    Ok((parse_range(range)?, background))
+}
+
+/// Parses a byte value given on the command line, decimal or hex with a `0x` prefix.
+pub fn parse_byte(text: &str) -> std::result::Result<u8, String> {
+    let value = parse_offset(text)?;
+    u8::try_from(value)
+        .map_err(|_| format!("invalid byte '{}', it is larger than 0xff", text.trim()))
+}
+
+/// Parses a block size given on the command line, decimal or hex with a `0x` prefix.
+pub fn parse_block_size(text: &str) -> std::result::Result<u64, String> {
+    match parse_offset(text)? {
+        0 => Err("the block size must be at least 1".to_string()),
+        size => Ok(size as u64),
+    }
+}
+
+/// Reads until `buffer` is full or the reader is at EOF, returns the number of bytes read.
+///
+/// Only the last read of an input fills less than the whole buffer, so inputs can be processed
+/// in chunks of a fixed size regardless of how much data a single `read` returns.
+pub fn read_full<R: std::io::Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut total_read = 0;
+    while total_read < buffer.len() {
+        let bytes_read = reader.read(&mut buffer[total_read..])?;
+        if bytes_read == 0 {
+            break; // EOF reached
+        }
+        total_read += bytes_read;
+    }
+    Ok(total_read)
+}
+
+/// Whether a line is formatted, replaced by a squeeze marker, or skipped entirely.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Line,
+    Squeezed,
+    Skipped,
+}
+
+/// Reads data from a reader and outputs a hex dump in the format of the given renderer.
 ///
 /// Uses multi-threading with double buffering to overlap I/O operations: while one
 /// buffer is being written to output in a separate thread, the main thread reads and
@@ -46,12 +369,29 @@ pub struct Args {
 /// # Arguments
 /// * `title` - Header text to display (filename, "stdin", etc.)
 /// * `reader` - Data source to read from
-/// * `line_writer` - Configured line writer for output formatting
+/// * `renderer` - Output format, e.g. a configured `LineWriter` for colored terminal output
 /// * `writer` - Output writer to write the formatted data to
-pub fn dump<R: std::io::Read, W: std::io::Write + Send + 'static>(
+pub fn dump<R: std::io::Read, L: Renderer + ?Sized, W: std::io::Write + Send + 'static>(
+    title: &str,
+    reader: R,
+    renderer: &mut L,
+    writer: W,
+) -> Result<()> {
+    dump_with_footer(title, String::new, reader, renderer, writer)
+}
+
+/// Like `dump`, with the text that `footer` returns as the text of the footer border. It is
+/// called after all data is read, so the text can describe the data, e.g. its digests.
+pub fn dump_with_footer<
+    R: std::io::Read,
+    L: Renderer + ?Sized,
+    W: std::io::Write + Send + 'static,
+    F: FnOnce() -> String,
+>(
     title: &str,
+    footer: F,
     mut reader: R,
-    line_writer: &mut LineWriter,
+    renderer: &mut L,
     writer: W,
 ) -> Result<()> {
     use std::sync::mpsc;
@@ -59,11 +399,16 @@ pub fn dump<R: std::io::Read, W: std::io::Write + Send + 'static>(
 
     const MAX_READ_BUFFER_SIZE: usize = 64 * 1024; // 64KB chunks for better I/O performance
 
-    let bytes_per_line = line_writer.bytes_per_line();
+    let bytes_per_line = renderer.bytes_per_line();
+    let squeeze = renderer.squeeze();
+    let context_len = renderer.context_len();
 
-    // Make sure the buffer size is a multiple of bytes_per_line, otherwise we would print partial lines.
+    // Make sure the lines size is a multiple of bytes_per_line, otherwise we would print partial lines.
     // When reading data we make sure to always fill the buffer completely except for the last read.
-    let mut buffer = vec![0u8; (MAX_READ_BUFFER_SIZE / bytes_per_line) * bytes_per_line];
+    // The buffer has room for context_len bytes of the previous chunk in front of the lines, and
+    // context_len bytes of the next chunk after them, so lines can be rendered in context.
+    let lines_size = (MAX_READ_BUFFER_SIZE / bytes_per_line) * bytes_per_line;
+    let mut buffer = vec![0u8; context_len + lines_size + context_len];
 
     // Triple buffering: allows main thread to work on one buffer while writer processes another
     // and a third is ready for immediate swap - reduces blocking
@@ -95,7 +440,7 @@ pub fn dump<R: std::io::Read, W: std::io::Write + Send + 'static>(
     });
 
     // Use buffer A for the header
-    line_writer.write_border(&mut output_buffer_a, line_writer::Border::Header, title)?;
+    renderer.write_header(&mut output_buffer_a, title)?;
 
     // Send first buffer, get started
     if write_tx.send(output_buffer_a).is_err() {
@@ -110,28 +455,34 @@ pub fn dump<R: std::io::Read, W: std::io::Write + Send + 'static>(
     let mut current_buffer = output_buffer_b;
 
     // Track byte offset for hex display
-    let mut byte_offset = 0;
+    let mut byte_offset = renderer.start_offset();
 
     // Reusable vector for formatted lines to avoid allocations. All data in the buffer is reused so we do not need to reallocate
     let mut formatted_lines_buf: Vec<Vec<u8>> = Vec::new();
 
+    // Squeezing state: the last line of the previous chunk, and whether we are inside a run of identical lines
+    let mut line_kinds: Vec<LineKind> = Vec::new();
+    let mut previous_line: Vec<u8> = Vec::new();
+    let mut in_squeezed_run = false;
+
+    // Number of context bytes of the previous chunk in front of the lines, and the number of
+    // bytes of this chunk that were already read as context of the previous chunk
+    let mut before_len = 0;
+    let mut carried_len = 0;
+
     loop {
         // Read until buffer is full or EOF - this ensures we only get partial lines at the very end
-        let mut total_read = 0;
-        while total_read < buffer.len() {
-            let bytes_read = reader.read(&mut buffer[total_read..])?;
-            if bytes_read == 0 {
-                break; // EOF reached
-            }
-            total_read += bytes_read;
-        }
+        let read_buffer = &mut buffer[context_len + carried_len..];
+        let total_read = carried_len + read_full(&mut reader, read_buffer)?;
 
         if total_read == 0 {
             break; // Nothing read, we're at EOF
         }
 
-        // Process bytes in chunks aligned to line boundaries
-        let data = &buffer[..total_read];
+        // Process bytes in chunks aligned to line boundaries, the rest is context for the last line
+        let data_len = total_read.min(lines_size);
+        let window = &buffer[context_len - before_len..context_len + total_read];
+        let data = &window[before_len..before_len + data_len];
 
         // Batch process lines - this is the hot path. Parallel formatting: each chunk is formatted independently with its offset
         use rayon::prelude::*;
@@ -142,12 +493,48 @@ pub fn dump<R: std::io::Read, W: std::io::Write + Send + 'static>(
             formatted_lines_buf.resize(num_chunks, Vec::with_capacity(bytes_per_line * 4));
         }
 
+        // Deciding which lines are squeezed is sequential, but only a cheap comparison
+        line_kinds.clear();
+        if squeeze {
+            let mut previous = previous_line.as_slice();
+            for chunk in data.chunks(bytes_per_line) {
+                let kind = if chunk.len() != bytes_per_line || chunk != previous {
+                    in_squeezed_run = false;
+                    LineKind::Line
+                } else if in_squeezed_run {
+                    LineKind::Skipped
+                } else {
+                    in_squeezed_run = true;
+                    LineKind::Squeezed
+                };
+                line_kinds.push(kind);
+                previous = chunk;
+            }
+            let last_line = data.chunks(bytes_per_line).last().unwrap_or_default();
+            previous_line.clear();
+            previous_line.extend_from_slice(last_line);
+        } else {
+            line_kinds.resize(num_chunks, LineKind::Line);
+        }
+
+        let renderer = &*renderer;
         data.par_chunks(bytes_per_line)
             .enumerate()
             .zip(formatted_lines_buf.par_iter_mut())
-            .for_each(|((idx, chunk), line_buf)| {
+            .zip(line_kinds.par_iter())
+            .for_each(|(((idx, chunk), line_buf), kind)| {
                 line_buf.clear();
-                line_writer.write_line(line_buf, byte_offset + idx * bytes_per_line, chunk);
+                let offset = byte_offset + idx * bytes_per_line;
+                let start = before_len + idx * bytes_per_line;
+                let (line_window, line) =
+                    renderer::context_window(window, start..start + chunk.len(), context_len);
+                match kind {
+                    LineKind::Line => {
+                        renderer.write_line_in_context(line_buf, offset, line_window, line)
+                    }
+                    LineKind::Squeezed => renderer.write_squeezed(line_buf, offset),
+                    LineKind::Skipped => {}
+                }
             });
 
         for line in &formatted_lines_buf[..num_chunks] {
@@ -155,7 +542,15 @@ pub fn dump<R: std::io::Read, W: std::io::Write + Send + 'static>(
         }
 
         // Update the byte offset
-        byte_offset += data.len();
+        byte_offset += data_len;
+
+        // Keep the end of this chunk as context in front of the next one, followed by the
+        // bytes that were already read
+        carried_len = total_read - data_len;
+        let new_before_len = data_len.min(context_len);
+        let source = context_len + data_len - new_before_len..context_len + total_read;
+        buffer.copy_within(source, context_len - new_before_len);
+        before_len = new_before_len;
 
         // Send current buffer to writer thread
         if write_tx.send(current_buffer).is_err() {
@@ -173,7 +568,7 @@ pub fn dump<R: std::io::Read, W: std::io::Write + Send + 'static>(
     }
 
     // Add footer to current buffer
-    line_writer.write_border(&mut current_buffer, line_writer::Border::Footer, "")?;
+    renderer.write_footer(&mut current_buffer, &footer())?;
 
     // Send final buffer
     if write_tx.send(current_buffer).is_err() {
@@ -213,12 +608,556 @@ pub fn demo<W: std::io::Write + Send + 'static>(
 /// 1. Parses command-line arguments
 /// 2. Determines terminal width and calculates optimal bytes_per_line (unless overridden)
 /// 3. Sets up a pager (less) for interactive viewing (unless --stdout is used)
-/// 4. Reads from a file or stdin and produces the hex dump
+/// 4. Reads from a file or stdin and produces the hex dump, or the overview map with --map
 pub fn run() -> Result<()> {
     let args: Args = Args::parse();
 
     let writer = std::io::stdout();
 
+    match &args.command {
+        Some(Command::Patch(patch_args)) => return run_patch(&args, patch_args, writer),
+        Some(Command::Mkpatch { old, new }) => return run_mkpatch(old, new, writer),
+        Some(Command::Annotate(annotate_args)) => return run_annotate(annotate_args, writer),
+        Some(Command::Applypatch { file, patch }) => {
+            return run_applypatch(&args, file, patch, writer)
+        }
+        None => {}
+    }
+    if args.map {
+        return run_map(&args, writer);
+    }
+    if args.stats {
+        return run_stats(&args, writer);
+    }
+    if args.strings {
+        return run_strings(&args, writer);
+    }
+    if args.scan {
+        return run_scan(&args, writer);
+    }
+    if let Some(offset) = args.inspect {
+        return run_inspect(&args, offset, writer);
+    }
+
+    if let Some(name) = &args.section {
+        return run_section(&args, name, writer);
+    }
+    if let Some(pid) = args.pid {
+        return run_process(&args, pid, writer);
+    }
+    if args.edit {
+        return run_edit(&args);
+    }
+    if let Some((path, archive)) = archive_to_list(&args)? {
+        return run_archive_listing(&args, &path, &archive, writer);
+    }
+
+    let mut line_writer = new_line_writer(&args)?
+        .with_start_offset(args.skip)
+        .with_squeeze(args.squeeze);
+
+    // use less as the pager, much like git
+    if !args.stdout {
+        Pager::with_pager("less --raw-control-chars --quit-if-one-screen").setup();
+    }
+
+    if args.demo {
+        return demo(&mut line_writer, writer);
+    }
+
+    let mut input = open_input(&args)?;
+
+    let mut fields = Vec::new();
+    let mut highlights = highlight::Highlights::new();
+    if let Some(path) = &args.template {
+        let template = template::Template::parse(&fs::read_to_string(path)?)?;
+        fields = apply_template(&args, &template, &mut input)?;
+        highlights = template::highlights(&fields);
+    }
+
+    // Executables with broken headers are still worth a look, they are dumped as plain data.
+    // Their header field colors would mix with the ones of a template.
+    // Only files are parsed, the headers of stdin can be anywhere in the input.
+    let executable = match (&input.file, args.raw || args.template.is_some()) {
+        (Some(file), false) => parse_executable(file).unwrap_or(None),
+        _ => None,
+    };
+    let mut boundaries = annotate::Boundaries::default();
+    if let Some(executable) = executable {
+        input.title = format!("{}   {}", input.title, executable.description);
+        boundaries = executable.boundaries();
+        highlights = executable.highlights;
+    }
+
+    // The ranges of the sidecar are painted over the fields of a template or executable
+    let sidecar = match &input.file {
+        Some(file) => sidecar::Sidecar::load(file)?,
+        None => None,
+    };
+    if let Some(sidecar) = sidecar {
+        sidecar
+            .highlights()
+            .into_iter()
+            .for_each(|h| highlights.add(h));
+        for (offset, label) in sidecar.rows() {
+            boundaries.add(offset, label);
+        }
+    }
+    marks(&args).for_each(|h| highlights.add(h));
+    line_writer = line_writer.with_highlights(highlights);
+
+    let (reader, digests) = hash_input(&args, args.skip as u64, input.reader);
+    let footer = || {
+        let mut lines = Vec::new();
+        if let Some((codec, len)) = &input.decompressed {
+            lines.push(format!(
+                "decompressed from {}, {}",
+                codec.name(),
+                Size::from_bytes(len.get())
+            ));
+        }
+        if let Some(digests) = &digests {
+            lines.push(digests.borrow().footer());
+        }
+        lines.join("\n")
+    };
+
+    let scanner = args.annotate.then(signatures::SignatureScanner::new);
+    if boundaries.is_empty() && scanner.is_none() && fields.is_empty() {
+        return dump_with_footer(&input.title, footer, reader, &mut line_writer, writer);
+    }
+    let renderer = annotate::AnnotatedRenderer::new(line_writer, (boundaries, scanner));
+    let mut renderer = template::LegendRenderer::new(renderer, fields);
+    dump_with_footer(&input.title, footer, reader, &mut renderer, writer)
+}
+
+/// The highlights of --mark, on top of each other in the order they were given.
+fn marks(args: &Args) -> impl Iterator<Item = highlight::Highlight> + '_ {
+    args.mark
+        .iter()
+        .enumerate()
+        .map(|(idx, (range, background))| {
+            let default = highlight::FIELD_BACKGROUNDS[idx % highlight::FIELD_BACKGROUNDS.len()];
+            highlight::Highlight::new(range.clone(), background.unwrap_or(default))
+        })
+}
+
+/// Wraps `reader` so the digests of --hash are computed while its data is dumped, the blocks
+/// of --hash-per-block start at `start_offset`. Returns the digests for the footer.
+fn hash_input<'a>(
+    args: &Args,
+    start_offset: u64,
+    reader: Box<dyn std::io::Read + 'a>,
+) -> (
+    Box<dyn std::io::Read + 'a>,
+    Option<std::rc::Rc<std::cell::RefCell<hash::Digests>>>,
+) {
+    if args.hash.is_empty() {
+        return (reader, None);
+    }
+    let mut digests = hash::Digests::new(&args.hash).with_start_offset(start_offset);
+    if let Some(block_size) = args.hash_per_block {
+        digests = digests.with_block_size(block_size);
+    }
+    let digests = std::rc::Rc::new(std::cell::RefCell::new(digests));
+    let reader = hash::HashingReader::new(reader, digests.clone());
+    (Box::new(reader), Some(digests))
+}
+
+/// Returns the fields of the input that `template` describes.
+///
+/// Files are read a second time for this, stdin, decompressed data and archive members are read
+/// into memory and dumped from there.
+fn apply_template(
+    args: &Args,
+    template: &template::Template,
+    input: &mut Input,
+) -> Result<Vec<template::Field>> {
+    use std::io::{Read, Seek};
+
+    let skip = args.skip as u64;
+    match &input.file {
+        Some(file) => {
+            let mut f = fs::File::open(file)?;
+            f.seek(std::io::SeekFrom::Start(skip))?;
+            template.apply(f, skip)
+        }
+        None => {
+            let mut data = Vec::new();
+            input.reader.read_to_end(&mut data)?;
+            let fields = template.apply(&data[..], skip)?;
+            input.reader = Box::new(std::io::Cursor::new(data));
+            Ok(fields)
+        }
+    }
+}
+
+/// Parses the headers of `file`, if it is an executable.
+fn parse_executable(file: &std::path::Path) -> Result<Option<executable::Executable>> {
+    executable::Executable::parse(&mut fs::File::open(file)?)
+}
+
+/// Edits the file given on the command line in the terminal.
+fn run_edit(args: &Args) -> Result<()> {
+    let Some(file) = &args.file else {
+        return Err(HexlerError::CannotEdit("stdin".to_string()));
+    };
+    let md = fs::metadata(file).map_err(|err| match archive::split_member_path(file) {
+        Some(_) => HexlerError::CannotEdit(format!("the archive member {}", file.display())),
+        None => err.into(),
+    })?;
+    if !md.is_file() {
+        return Err(HexlerError::CannotEdit(file.display().to_string()));
+    }
+
+    // The file is only opened for writing when the changes are written, so read-only files
+    // can be viewed
+    let document = editor::Document::new(fs::File::open(file)?)?;
+    let editor = editor::Editor::new(document, &file_title(file, &md))
+        .with_encoding(args.encoding)
+        .with_color_by(args.color_by)
+        .with_cursor(args.skip as u64);
+    editor.run(args.num_bytes_per_line, |document| {
+        let mut f = fs::OpenOptions::new().write(true).open(file)?;
+        let num_changes = document.write(&mut f)?;
+        f.sync_all()?;
+        Ok(num_changes)
+    })
+}
+
+/// Returns the archive whose members are listed instead of a hex dump: the file given on the
+/// command line when it is a zip or tar archive, or `archive.zip:` without a member name.
+///
+/// Archives with broken headers are still worth a look, they are dumped as plain data.
+fn archive_to_list(args: &Args) -> Result<Option<(std::path::PathBuf, archive::Archive)>> {
+    let Some(file) = args
+        .file
+        .as_ref()
+        .filter(|_| !args.raw && args.template.is_none())
+    else {
+        return Ok(None);
+    };
+    match archive::split_member_path(file) {
+        Some((path, name)) if name.is_empty() => {
+            let archive = archive::Archive::parse(&mut fs::File::open(&path)?)?;
+            let archive = archive.ok_or_else(|| {
+                HexlerError::InvalidArchive(format!(
+                    "{} is not a zip or tar archive",
+                    path.display()
+                ))
+            })?;
+            Ok(Some((path, archive)))
+        }
+        Some(_) => Ok(None),
+        None => {
+            // Looking for archive headers would consume the data of a FIFO or character device
+            let rereadable = fs::metadata(file)
+                .map(|md| file_kind::FileKind::of(md.file_type()).is_rereadable())
+                .unwrap_or(false);
+            let archive = match rereadable.then(|| fs::File::open(file)) {
+                Some(Ok(mut f)) => archive::Archive::parse(&mut f).unwrap_or(None),
+                _ => None,
+            };
+            Ok(archive.map(|archive| (file.clone(), archive)))
+        }
+    }
+}
+
+/// Writes the members of an archive with the offsets of their data.
+fn run_archive_listing<W: std::io::Write>(
+    args: &Args,
+    path: &std::path::Path,
+    archive: &archive::Archive,
+    writer: W,
+) -> Result<()> {
+    let kind = match archive.kind {
+        archive::ArchiveKind::Zip => "zip",
+        archive::ArchiveKind::Tar => "tar",
+    };
+    let files = match archive.members.len() {
+        1 => "1 file".to_string(),
+        n => format!("{} files", n),
+    };
+    let title = format!(
+        "{}   {} archive, {}",
+        file_title(path, &fs::metadata(path)?),
+        kind,
+        files
+    );
+
+    if !args.stdout {
+        Pager::with_pager("less --raw-control-chars --quit-if-one-screen").setup();
+    }
+    archive::ArchiveListing::new().write(&title, archive, writer)
+}
+
+/// Dumps the section `name` of an executable, offsets are its virtual addresses.
+fn run_section<W: std::io::Write + Send + 'static>(
+    args: &Args,
+    name: &str,
+    writer: W,
+) -> Result<()> {
+    use std::io::{Read, Seek};
+
+    let Some(file) = &args.file else {
+        return Err(HexlerError::InvalidExecutable(
+            "--section needs a file, not stdin".to_string(),
+        ));
+    };
+    let executable = parse_executable(file)?.ok_or_else(|| {
+        HexlerError::InvalidExecutable(format!(
+            "{} is not an ELF, PE or Mach-O file",
+            file.display()
+        ))
+    })?;
+    let section = executable.section(name)?;
+
+    // Sections that are not loaded have no address, their file offsets are shown instead
+    let base = match section.address {
+        0 => section.offset,
+        address => address,
+    };
+    let skip = (args.skip as u64).min(section.size);
+    let mut f = fs::File::open(file)?;
+    f.seek(std::io::SeekFrom::Start(section.offset + skip))?;
+    let reader = f.take(section.size - skip);
+
+    let mut line_writer = new_line_writer(args)?
+        .with_hex_formatter(
+            hex_formatter::HexFormatter::new()
+                .with_offset_width(address_width(base + section.size)),
+        )
+        .with_start_offset((base + skip) as usize)
+        .with_squeeze(args.squeeze)
+        .with_highlights(marks(args).collect());
+    let title = format!(
+        "{}   {} ({}, {} at 0x{:x})",
+        file_title(file, &fs::metadata(file)?),
+        section.name,
+        section.kind,
+        Size::from_bytes(section.size),
+        base
+    );
+
+    if !args.stdout {
+        Pager::with_pager("less --raw-control-chars --quit-if-one-screen").setup();
+    }
+    let (reader, digests) = hash_input(args, base + skip, Box::new(reader));
+    let footer = || match &digests {
+        Some(digests) => digests.borrow().footer(),
+        None => String::new(),
+    };
+    dump_with_footer(&title, footer, reader, &mut line_writer, writer)
+}
+
+/// Width of the offset column for addresses up to `end`. Addresses are usually longer than
+/// file offsets, they all get the same width of at least the default 8 digits.
+fn address_width(end: u64) -> usize {
+    let digits = (64 - end.saturating_sub(1).leading_zeros() as usize + 3) / 4;
+    digits.max(hex_formatter::HexFormatter::new().offset_width())
+}
+
+/// Dumps memory of the process `pid`, offsets are its virtual addresses.
+fn run_process<W: std::io::Write>(args: &Args, pid: u32, mut writer: W) -> Result<()> {
+    let regions = process::regions(pid)?;
+    let not_found = |name: String| HexlerError::RegionNotFound { pid, name };
+    let (start, region_end) = match (&args.maps_region, args.addr) {
+        (Some(name), _) => {
+            let range =
+                process::find_region(&regions, name).ok_or_else(|| not_found(name.clone()))?;
+            (range.start, Some(range.end))
+        }
+        (None, Some(addr)) => {
+            let addr = addr as u64;
+            (
+                addr,
+                process::region_at(&regions, addr).map(|r| r.range.end),
+            )
+        }
+        (None, None) => unreachable!("clap requires --addr or --maps-region with --pid"),
+    };
+    let end = match (args.length, region_end) {
+        (Some(length), _) => start.saturating_add(length as u64),
+        (None, Some(end)) => end,
+        (None, None) => return Err(not_found(format!("at 0x{:x}", start))),
+    };
+
+    let region = match process::region_at(&regions, start) {
+        Some(region) if region.name.is_empty() => format!("anonymous {}   ", region.permissions),
+        Some(region) => format!("{} {}   ", region.name, region.permissions),
+        None => String::new(),
+    };
+    let title = format!(
+        "process {} ({})   {}0x{:x}..0x{:x}, {}",
+        pid,
+        process::command_name(pid).unwrap_or_default(),
+        region,
+        start,
+        end,
+        Size::from_bytes(end - start)
+    );
+
+    let line_writer = new_line_writer(args)?
+        .with_hex_formatter(
+            hex_formatter::HexFormatter::new().with_offset_width(address_width(end)),
+        )
+        .with_squeeze(args.squeeze)
+        .with_highlights(marks(args).collect());
+    let mut memory = process::open_memory(pid)?;
+
+    if !args.stdout {
+        Pager::with_pager("less --raw-control-chars --quit-if-one-screen").setup();
+    }
+    process::MemoryDump::new(line_writer).write(&mut writer, &title, &mut memory, start..end)
+}
+
+/// Patches the file of `hexler patch`, and shows the changed lines before and after.
+fn run_patch<W: std::io::Write>(args: &Args, patch_args: &PatchArgs, writer: W) -> Result<()> {
+    let mut patches = Vec::new();
+    if let (Some(fill), Some(range)) = (patch_args.fill, &patch_args.range) {
+        patches.push(patch::Patch::fill(range.clone(), &[fill]));
+    }
+    if let (Some(at), Some(bytes)) = (patch_args.at, &patch_args.bytes) {
+        let bytes = patch::parse_bytes(bytes).map_err(HexlerError::InvalidPatch)?;
+        patch::check_end(at as u64, bytes.len() as u64).map_err(HexlerError::InvalidPatch)?;
+        patches.push(patch::Patch::new(at as u64, bytes));
+    }
+    if let Some(path) = &patch_args.apply {
+        patches.extend(patch::parse_patches(&fs::read_to_string(path)?)?);
+    }
+
+    let changed: Vec<_> = patches.iter().map(patch::Patch::range).collect();
+    let num_patched = patches.iter().map(|patch| patch.len).sum();
+    patch_file(
+        args,
+        &patch_args.file,
+        &changed,
+        num_patched,
+        writer,
+        |f, len| {
+            patch::apply(f, len, &patches, patch_args.extend)?;
+            Ok(())
+        },
+    )
+}
+
+/// Writes the differences between `old` and `new` as a patch of `hexler mkpatch`.
+fn run_mkpatch<W: std::io::Write>(
+    old: &std::path::Path,
+    new: &std::path::Path,
+    mut writer: W,
+) -> Result<()> {
+    let mut old_file = fs::File::open(old)?;
+    let old_len = old_file.metadata()?.len();
+    let mut new_file = fs::File::open(new)?;
+    let new_len = new_file.metadata()?.len();
+    let hunks = hexpatch::diff(&mut old_file, &mut new_file)?;
+
+    let header = format!(
+        "hexler mkpatch {} {}\nold: {}, new: {}, {} changed",
+        old.display(),
+        new.display(),
+        patch::num_bytes(old_len),
+        patch::num_bytes(new_len),
+        match hunks.len() {
+            1 => "1 range".to_string(),
+            n => format!("{} ranges", n),
+        }
+    );
+    hexpatch::HexPatch::new().write(&mut writer, &header, &hunks)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Adds the range of `hexler annotate` to the sidecar of the file.
+fn run_annotate<W: std::io::Write>(annotate_args: &AnnotateArgs, mut writer: W) -> Result<()> {
+    if let Some(color) = &annotate_args.color {
+        highlight::named_background(color)?;
+    }
+    let range = &annotate_args.range;
+    let entry = sidecar::Entry::new(range.clone(), &annotate_args.label)
+        .with_color(annotate_args.color.clone())
+        .with_note(annotate_args.note.clone());
+    let path = sidecar::Sidecar::append(&annotate_args.file, &entry)?;
+    writeln!(
+        writer,
+        "added {} 0x{:x}..0x{:x} to {}",
+        annotate_args.label,
+        range.start,
+        range.end,
+        path.display()
+    )?;
+    Ok(())
+}
+
+/// Applies the patch of `hexler applypatch` when `file` has all of its old bytes, and shows the
+/// changed lines before and after.
+fn run_applypatch<W: std::io::Write>(
+    args: &Args,
+    file: &std::path::Path,
+    patch: &std::path::Path,
+    writer: W,
+) -> Result<()> {
+    let hunks = hexpatch::HexPatch::parse(&fs::read_to_string(patch)?)?;
+
+    // Removed bytes at the end are shown as changed in the dump before
+    let changed: Vec<_> = hunks
+        .iter()
+        .map(|hunk| hunk.offset..hunk.offset + hunk.old.len().max(hunk.new.len()) as u64)
+        .collect();
+    let num_patched = hunks.iter().map(|hunk| hunk.new.len() as u64).sum();
+    patch_file(args, file, &changed, num_patched, writer, |f, len| {
+        let new_len = hexpatch::verify(f, len, &hunks)?;
+        patch::apply(f, len, &hexpatch::patches(&hunks), true)?;
+        if new_len < len {
+            f.set_len(new_len)?;
+        }
+        Ok(())
+    })
+}
+
+/// Changes `file` with `apply`, which gets the file and its length, and writes a dump of the
+/// lines with offsets in `changed` before and after.
+fn patch_file<W: std::io::Write>(
+    args: &Args,
+    file: &std::path::Path,
+    changed: &[std::ops::Range<u64>],
+    num_patched: u64,
+    mut writer: W,
+    apply: impl FnOnce(&mut fs::File, u64) -> Result<()>,
+) -> Result<()> {
+    use std::io::Seek;
+
+    // Build scripts have no terminal, their dumps have 16 bytes per line
+    let line_writer = match (args.num_bytes_per_line, terminal_size()) {
+        (None, None) => styled_line_writer(args, LineWriter::new_bytes(16)?),
+        _ => new_line_writer(args)?,
+    }
+    .with_highlights(patch::highlights(changed, editor::MODIFIED_BACKGROUND));
+    let lines = patch::affected_lines(changed, line_writer.bytes_per_line());
+
+    let mut f = fs::OpenOptions::new().read(true).write(true).open(file)?;
+    let len = f.seek(std::io::SeekFrom::End(0))?;
+    let before_title = format!("{}   before", file_title(file, &f.metadata()?));
+    let before = patch::read_lines(&mut f, &lines)?;
+    apply(&mut f, len)?;
+    f.sync_all()?;
+    let after = patch::read_lines(&mut f, &lines)?;
+
+    let after_title = format!(
+        "{}   after, {} patched",
+        file_title(file, &f.metadata()?),
+        patch::num_bytes(num_patched)
+    );
+    patch::write_lines(&mut writer, &line_writer, &before_title, &before, changed)?;
+    patch::write_lines(&mut writer, &line_writer, &after_title, &after, changed)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Creates the `LineWriter` for the terminal width or `--num-bytes-per-line`, with the encoding
+/// and colors given on the command line.
+fn new_line_writer(args: &Args) -> Result<LineWriter> {
     // determine terminal size, and from that the number of bytes to print per line.
     let line_writer = match args.num_bytes_per_line {
         Some(num_bytes) => LineWriter::new_bytes(num_bytes),
@@ -228,47 +1167,328 @@ pub fn run() -> Result<()> {
             LineWriter::new_max_width(term_width.0 as usize)
         }
     };
+    Ok(styled_line_writer(args, line_writer?))
+}
 
-    let mut line_writer = line_writer?;
+/// Sets the encoding and colors given on the command line.
+fn styled_line_writer(args: &Args, line_writer: LineWriter) -> LineWriter {
+    line_writer
+        .with_ascii_renderer(AsciiRenderer::with_encoding(args.encoding))
+        .with_byte_to_color(
+            ByteToColor::with_color_by(args.color_by, &Theme::DEFAULT)
+                .expect("built-in themes are valid"),
+        )
+}
+
+/// The data to show, positioned at `--skip` or another offset.
+struct Input {
+    title: String,
+    reader: Box<dyn std::io::Read>,
+    /// Number of bytes after the position, if known in advance
+    len: Option<u64>,
+    /// Codec of the input when it is decompressed with `--decompress`, and the number of
+    /// decompressed bytes read so far
+    decompressed: Option<(decompress::Codec, Rc<Cell<u64>>)>,
+    /// The file the data is read from as it is, `None` for stdin, decompressed data and archive
+    /// members
+    file: Option<std::path::PathBuf>,
+}
+
+/// Opens the file given on the command line, or stdin, and skips to `--skip`.
+fn open_input(args: &Args) -> Result<Input> {
+    open_input_at(args, args.skip as u64)
+}
+
+/// Opens the file given on the command line, or stdin, and skips to `offset`.
+///
+/// A file `archive.zip:path` that doesn't exist is the member `path` of `archive.zip`.
+fn open_input_at(args: &Args, offset: u64) -> Result<Input> {
+    use std::io::Read;
+
+    match &args.file {
+        Some(file) => match archive::split_member_path(file) {
+            Some((archive, name)) => open_member(args, &archive, &name, offset),
+            None => open_file(args, file, offset),
+        },
+        None => {
+            let mut stdin = std::io::stdin().lock();
+            if let Some(codec) = detect_codec(args, &mut stdin)? {
+                return decompressed_input("stdin".to_string(), codec, stdin, offset);
+            }
+            let mut reader: Box<dyn Read> = Box::new(stdin);
+            std::io::copy(&mut (&mut reader).take(offset), &mut std::io::sink())?;
+            Ok(Input {
+                title: "stdin".to_string(),
+                reader,
+                len: None,
+                decompressed: None,
+                file: None,
+            })
+        }
+    }
+}
+
+/// Opens a file and skips to `offset`, decompressing it with `--decompress`.
+fn open_file(args: &Args, file: &std::path::Path, offset: u64) -> Result<Input> {
+    use std::io::{BufReader, Read, Seek};
+
+    // Reading from a known file, print its filename and it's last modified date
+    let md = fs::metadata(file)?;
+    let kind = file_kind::FileKind::of(md.file_type());
+    let mut f = BufReader::new(fs::File::open(file)?);
+    match detect_codec(args, &mut f)? {
+        Some(codec) => decompressed_input(file_title(file, &md), codec, f, offset),
+        None => {
+            // FIFOs can't seek, the data before the offset is read and dropped
+            if f.seek(std::io::SeekFrom::Start(offset)).is_err() {
+                std::io::copy(&mut (&mut f).take(offset), &mut std::io::sink())?;
+            }
+            Ok(Input {
+                title: file_title(file, &md),
+                reader: Box::new(f),
+                len: file_kind::data_len(file, &md).map(|len| len.saturating_sub(offset)),
+                decompressed: None,
+                file: kind.is_rereadable().then(|| file.to_path_buf()),
+            })
+        }
+    }
+}
+
+/// Opens the member `name` of an archive and skips to `offset`, decompressing it with
+/// `--decompress`.
+fn open_member(
+    args: &Args,
+    archive_path: &std::path::Path,
+    name: &str,
+    offset: u64,
+) -> Result<Input> {
+    use std::io::{BufReader, Read};
+
+    let archive =
+        archive::Archive::parse(&mut fs::File::open(archive_path)?)?.ok_or_else(|| {
+            HexlerError::InvalidArchive(format!(
+                "{} is not a zip or tar archive",
+                archive_path.display()
+            ))
+        })?;
+    let member = archive.member(name, archive_path)?;
+    let mut data = BufReader::new(archive.open_member(member, fs::File::open(archive_path)?)?);
+
+    let name = format!("{}:{}", archive_path.display(), member.name);
+    let title = title(&name, None, Some(member.size), member.modified);
+    if let Some(codec) = detect_codec(args, &mut data)? {
+        return decompressed_input(title, codec, data, offset);
+    }
+    let mut reader: Box<dyn Read> = Box::new(data);
+    std::io::copy(&mut (&mut reader).take(offset), &mut std::io::sink())?;
+    Ok(Input {
+        title,
+        reader,
+        len: Some(member.size.saturating_sub(offset)),
+        decompressed: None,
+        file: None,
+    })
+}
+
+/// The input of `reader` decompressed with `codec`, skipped to `offset` of the decompressed
+/// data. The decompressed size is only known at the end, it is shown in the footer.
+fn decompressed_input<R: std::io::BufRead + 'static>(
+    title: String,
+    codec: decompress::Codec,
+    reader: R,
+    offset: u64,
+) -> Result<Input> {
+    use std::io::Read;
+
+    let len = Rc::new(Cell::new(0));
+    let mut reader = codec.counting_decoder(reader, len.clone())?;
+    std::io::copy(&mut (&mut reader).take(offset), &mut std::io::sink())?;
+    Ok(Input {
+        title: format!("{}   {}", title, codec.name()),
+        reader,
+        len: None,
+        decompressed: Some((codec, len)),
+        file: None,
+    })
+}
+
+/// Returns the codec of the input with `--decompress`, without consuming any of it.
+fn detect_codec<R: std::io::BufRead>(
+    args: &Args,
+    reader: &mut R,
+) -> Result<Option<decompress::Codec>> {
+    match args.decompress {
+        true => decompress::detect(reader),
+        false => Ok(None),
+    }
+}
+
+/// Title of a file: its name in bold, its size and when it was last modified. Devices and
+/// FIFOs also show their kind. What the metadata doesn't have is left out.
+fn file_title(file: &std::path::Path, md: &fs::Metadata) -> String {
+    let modified_time = md.modified().ok().map(DateTime::<Local>::from);
+    title(
+        &file.display().to_string(),
+        file_kind::FileKind::of(md.file_type()).name(),
+        file_kind::data_len(file, md),
+        modified_time,
+    )
+}
+
+/// Title of a file or archive member called `name`, see `file_title`.
+fn title(
+    name: &str,
+    kind: Option<&str>,
+    len: Option<u64>,
+    modified_time: Option<DateTime<Local>>,
+) -> String {
+    let size = match (kind, len) {
+        (Some(kind), Some(len)) => format!("{}, {}", kind, Size::from_bytes(len)),
+        (Some(kind), None) => kind.to_string(),
+        (None, Some(len)) => Size::from_bytes(len).to_string(),
+        (None, None) => String::new(),
+    };
+    let mut file_name_str = name.to_string();
+    if file_name_str.contains(' ') {
+        file_name_str = format!("'{}'", file_name_str);
+    }
+
+    let mut title = format!("\x1b[1m{}\x1b[0m   {}", file_name_str, size);
+    if let Some(modified_time) = modified_time {
+        title = format!("{}   {}", title, modified_time.format("%-d %b %Y %H:%M:%S"));
+    }
+    title
+}
+
+/// Writes the statistics report of the input.
+fn run_stats<W: std::io::Write>(args: &Args, mut writer: W) -> Result<()> {
+    use stats::{StatsReport, StatsScanner};
+
+    let input = open_input(args)?;
+    let stats = StatsScanner::new()
+        .with_ngram_len(args.ngram_len as usize)
+        .with_start_offset(args.skip as u64)
+        .scan(input.reader)?;
 
-    // use less as the pager, much like git
     if !args.stdout {
         Pager::with_pager("less --raw-control-chars --quit-if-one-screen").setup();
     }
+    StatsReport::new().write(&mut writer, &input.title, &stats)?;
+    Ok(())
+}
 
-    if args.demo {
-        return demo(&mut line_writer, writer);
+/// Writes the strings of the input with their offsets, and optionally a hex dump of each.
+fn run_strings<W: std::io::Write>(args: &Args, writer: W) -> Result<()> {
+    use clap::ValueEnum;
+    use strings::{StringScanner, StringsListing};
+
+    if !StringScanner::supports(args.encoding) {
+        let encoding = args
+            .encoding
+            .to_possible_value()
+            .map(|value| value.get_name().to_string());
+        return Err(HexlerError::UnsupportedEncoding {
+            mode: "--strings".to_string(),
+            encoding: encoding.unwrap_or_default(),
+        });
     }
 
-    match args.file {
-        // Reading from a known file, print its filename and it's last modified date
-        Some(file) => {
-            let md = fs::metadata(&file)?;
-            let size = Size::from_bytes(md.len());
-            let modified_time: DateTime<Local> = md.modified().unwrap().into();
+    let scanner = StringScanner::new()
+        .with_min_len(args.min_len)
+        .with_encoding(args.encoding);
+    let mut listing = StringsListing::new(scanner).with_start_offset(args.skip);
+    if let Some(context_lines) = args.context {
+        listing = listing.with_snippets(new_line_writer(args)?, context_lines);
+    }
 
-            let mut file_name_str = format!("{}", file.display());
-            if file_name_str.contains(' ') {
-                file_name_str = format!("'{}'", file_name_str);
-            }
+    let input = open_input(args)?;
+    if !args.stdout {
+        Pager::with_pager("less --raw-control-chars --quit-if-one-screen").setup();
+    }
+    listing.write(&input.title, input.reader, writer)
+}
 
-            let title = format!(
-                "\x1b[1m{}\x1b[0m   {}   {}",
-                file_name_str,
-                size,
-                modified_time.format("%-d %b %Y %H:%M:%S")
-            );
+/// Writes the embedded files found in the input with their offsets.
+fn run_scan<W: std::io::Write>(args: &Args, writer: W) -> Result<()> {
+    let listing = signatures::ScanListing::new().with_start_offset(args.skip);
+
+    let input = open_input(args)?;
+    if !args.stdout {
+        Pager::with_pager("less --raw-control-chars --quit-if-one-screen").setup();
+    }
+    listing.write(&input.title, input.reader, writer)
+}
+
+/// Writes the bytes at `offset` of the input, and the values decoded from them.
+fn run_inspect<W: std::io::Write>(args: &Args, offset: usize, mut writer: W) -> Result<()> {
+    use inspect::InspectReport;
+    use std::io::Read;
+
+    let line_writer = LineWriter::new_bytes(InspectReport::LEN)?
+        .with_ascii_renderer(AsciiRenderer::with_encoding(args.encoding))
+        .with_byte_to_color(ByteToColor::with_color_by(args.color_by, &Theme::DEFAULT)?);
+
+    let input = open_input_at(args, offset as u64)?;
+    let mut data = Vec::with_capacity(InspectReport::LEN);
+    input
+        .reader
+        .take(InspectReport::LEN as u64)
+        .read_to_end(&mut data)?;
+
+    InspectReport::new(line_writer).write(&mut writer, &input.title, offset, &data)?;
+    Ok(())
+}
 
-            let f = std::fs::File::open(&file);
-            dump(title.as_str(), f?, &mut line_writer, writer)
+/// Writes the overview map of the input, sized to fit the terminal.
+fn run_map<W: std::io::Write>(args: &Args, writer: W) -> Result<()> {
+    use overview::OverviewMap;
+    use std::io::Read;
+
+    // The map is meant to fit on one screen, but still works when not writing to a terminal
+    let (width, height) = terminal_size()
+        .map(|(width, height)| (width.0 as usize, height.0 as usize))
+        .unwrap_or((80, 24));
+
+    let mut input = open_input(args)?;
+
+    // The map needs to know the size up front. Decompressed files and archive members are
+    // decompressed twice for this, stdin and FIFOs are read completely.
+    let rereadable = args
+        .file
+        .as_ref()
+        .is_some_and(|file| match fs::metadata(file) {
+            Ok(md) => file_kind::FileKind::of(md.file_type()).is_rereadable(),
+            // archive.zip:path
+            Err(_) => true,
+        });
+    if input.len.is_none() && input.decompressed.is_some() && rereadable {
+        input.len = Some(std::io::copy(
+            &mut open_input(args)?.reader,
+            &mut std::io::sink(),
+        )?);
+    }
+    let mut data = Vec::new();
+    let (reader, len): (Box<dyn std::io::Read>, u64) = match input.len {
+        Some(len) => (input.reader, len),
+        None => {
+            input.reader.read_to_end(&mut data)?;
+            let len = data.len() as u64;
+            (Box::new(&data[..]), len)
         }
-        None => dump("stdin", std::io::stdin().lock(), &mut line_writer, writer),
+    };
+
+    let map = OverviewMap::with_terminal_size(len, width, height).with_start_offset(args.skip);
+
+    if !args.stdout {
+        Pager::with_pager("less --raw-control-chars --quit-if-one-screen").setup();
     }
+    map.write(&input.title, reader, writer)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::SlowReader;
     use std::sync::{Arc, Mutex};
 
     /// Test helper: A thread-safe writer that stores output in a Vec<u8> for verification.
@@ -462,6 +1682,243 @@ mod tests {
         assert!(line_count >= 3); // At least header, data, and footer
     }
 
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("0"), Ok(0));
+        assert_eq!(parse_offset("4096"), Ok(4096));
+        assert_eq!(parse_offset("0x1000"), Ok(4096));
+        assert_eq!(parse_offset("0X10_00"), Ok(4096));
+        assert!(parse_offset("0x").is_err());
+        assert!(parse_offset("12k").is_err());
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("0x100..0x200"), Ok(0x100..0x200));
+        assert_eq!(parse_range("16..16"), Ok(16..16));
+        assert!(parse_range("0x100").is_err());
+        assert!(parse_range("0x200..0x100").is_err());
+    }
+
+    #[test]
+    fn test_parse_byte() {
+        assert_eq!(parse_byte("0x00"), Ok(0));
+        assert_eq!(parse_byte("255"), Ok(255));
+        assert!(parse_byte("0x100").is_err());
+    }
+
+    #[test]
+    fn test_parse_mark() {
+        assert_eq!(parse_mark("0x100..0x140"), Ok((0x100..0x140, None)));
+        assert_eq!(
+            parse_mark("0x100..0x140:red"),
+            Ok((0x100..0x140, Some("\x1b[48;5;52m")))
+        );
+        assert!(parse_mark("0x100..0x140:pink")
+            .unwrap_err()
+            .starts_with("unknown color pink"));
+        assert!(parse_mark("0x100:red").is_err());
+    }
+
+    #[test]
+    fn test_parse_block_size() {
+        assert_eq!(parse_block_size("0x1000"), Ok(4096));
+        assert!(parse_block_size("0").is_err());
+    }
+
+    #[test]
+    fn test_address_width() {
+        assert_eq!(address_width(0x1000), 8);
+        assert_eq!(address_width(0x1_0000_0000), 8);
+        assert_eq!(address_width(0x1_0000_0001), 9);
+        assert_eq!(address_width(0x7ffd_2a00_1000), 12);
+        assert_eq!(address_width(u64::MAX), 16);
+    }
+
+    #[test]
+    fn test_title() {
+        assert_eq!(
+            title("a.bin", None, Some(2048), None),
+            "\x1b[1ma.bin\x1b[0m   2.00 KiB"
+        );
+        assert_eq!(
+            title("/dev/sda", Some("block device"), Some(4096), None),
+            "\x1b[1m/dev/sda\x1b[0m   block device, 4.00 KiB"
+        );
+        assert_eq!(
+            title("/dev/zero", Some("character device"), None, None),
+            "\x1b[1m/dev/zero\x1b[0m   character device"
+        );
+    }
+
+    #[test]
+    fn test_dump_squeeze() {
+        let mut test_data = vec![b'A'; 16 * 3];
+        test_data.extend_from_slice(&[b'B'; 16]);
+        test_data.extend_from_slice(&[b'A'; 16 * 2]);
+
+        let writer = BufferWriter::new();
+        let writer_clone = writer.clone();
+        let mut line_writer = LineWriter::new_bytes(16).unwrap().with_squeeze(true);
+
+        let result = dump("Squeeze", &test_data[..], &mut line_writer, writer);
+        assert!(result.is_ok());
+
+        let output = writer_clone.get_output_as_string();
+        let lines: Vec<&str> = output.lines().collect();
+        // header title + border, A, *, B, A, *, footer border
+        assert_eq!(lines.len(), 8);
+        assert!(lines[3].contains("│ *"));
+        assert!(lines[4].contains("BBBB"));
+        assert!(lines[6].contains("│ *"));
+    }
+
+    #[test]
+    fn test_dump_squeeze_across_buffers() {
+        // Zeros span multiple read buffers, but produce a single marker
+        let mut test_data = vec![0u8; 256 * 1024];
+        test_data.extend_from_slice(b"end of the zeros");
+
+        let writer = BufferWriter::new();
+        let writer_clone = writer.clone();
+        let mut line_writer = LineWriter::new_bytes(16).unwrap().with_squeeze(true);
+
+        let result = dump("Zeros", &test_data[..], &mut line_writer, writer);
+        assert!(result.is_ok());
+
+        let output = writer_clone.get_output_as_string();
+        assert_eq!(output.matches("│ *").count(), 1);
+        assert_eq!(output.lines().count(), 6);
+        assert!(output.contains("zeros"));
+    }
+
+    #[test]
+    fn test_dump_utf8_across_buffers() {
+        // "€" starts in the last byte of the first read buffer
+        let mut test_data = vec![b'a'; 64 * 1024 - 1];
+        test_data.extend_from_slice("€ end".as_bytes());
+
+        let writer = BufferWriter::new();
+        let writer_clone = writer.clone();
+        let mut line_writer = LineWriter::new_bytes(16)
+            .unwrap()
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap())
+            .with_ascii_renderer(AsciiRenderer::with_encoding(Encoding::Utf8));
+
+        let result = dump("utf8", &test_data[..], &mut line_writer, writer);
+        assert!(result.is_ok());
+
+        let output = writer_clone.get_output_as_string();
+        assert!(output.contains("│ aaaaaaaaaaaaaaa€\n"));
+        assert!(output.contains("│ ·· end\n"));
+    }
+
+    #[test]
+    fn test_dump_annotate_across_buffers() {
+        // A PNG header that starts in the last line of the first read buffer, and is only
+        // recognized with the IHDR chunk from the next read
+        let mut test_data = vec![0u8; 64 * 1024 - 8];
+        test_data.extend_from_slice(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR");
+        test_data.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 2, 8, 2]);
+
+        let writer = BufferWriter::new();
+        let writer_clone = writer.clone();
+        let line_writer = LineWriter::new_bytes(16)
+            .unwrap()
+            .with_hex_formatter(hex_formatter::HexFormatter::new().with_color(false))
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap());
+        let scanner = signatures::SignatureScanner::new();
+        let mut renderer = annotate::AnnotatedRenderer::new(line_writer, scanner);
+
+        let result = dump("png", &test_data[..], &mut renderer, writer);
+        assert!(result.is_ok());
+
+        let output = writer_clone.get_output_as_string();
+        let row = "0000fff8 ├── png: PNG image, 1 x 2, 8-bit RGB ──";
+        let (before, after) = output.split_once(row).expect(&output);
+        assert!(before.ends_with(
+            "0000ffe0 │ 00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00 │ ⋄⋄⋄⋄⋄⋄⋄⋄⋄⋄⋄⋄⋄⋄⋄⋄\n"
+        ));
+        assert!(after.contains("\n0000fff0 │ 00"));
+        assert_eq!(output.matches("├──").count(), 1);
+    }
+
+    #[test]
+    fn test_dump_in_context_matches_hex_dump() {
+        let text = "héllo wörld, € 😀 日本語 ";
+        let mut test_data: Vec<u8> = text.bytes().cycle().take(200_000).collect();
+        test_data.extend((0..100_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8));
+
+        let settings = [
+            (Encoding::Utf8, ColorBy::Class),
+            (Encoding::Utf16le, ColorBy::Class),
+            (Encoding::Cp437, ColorBy::Entropy),
+        ];
+        for (encoding, color_by) in settings {
+            let writer = BufferWriter::new();
+            let writer_clone = writer.clone();
+            let mut line_writer = LineWriter::new_bytes(24)
+                .unwrap()
+                .with_ascii_renderer(AsciiRenderer::with_encoding(encoding))
+                .with_byte_to_color(ByteToColor::with_color_by(color_by, &Theme::DEFAULT).unwrap());
+            let reader = SlowReader {
+                data: &test_data,
+                max_read: 1000,
+            };
+            dump("text", reader, &mut line_writer, writer).unwrap();
+
+            let hex_dump = hex_dump::HexDump::builder()
+                .bytes_per_line(24)
+                .encoding(encoding)
+                .color_by(color_by)
+                .title("text")
+                .build()
+                .unwrap();
+            assert_eq!(
+                writer_clone.get_output_as_string(),
+                hex_dump.to_string(&test_data)
+            );
+        }
+    }
+
+    /// A minimal renderer in a different format, to check that dump works with any renderer.
+    struct ListRenderer;
+
+    impl Renderer for ListRenderer {
+        fn bytes_per_line(&self) -> usize {
+            4
+        }
+
+        fn write_header(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()> {
+            use std::io::Write;
+            writeln!(buffer, "[{}]", title)
+        }
+
+        fn write_line(&self, buffer: &mut Vec<u8>, byte_offset: usize, line_data: &[u8]) {
+            use std::io::Write;
+            let _ = writeln!(buffer, "{}: {:?}", byte_offset, line_data);
+        }
+
+        fn write_footer(&mut self, buffer: &mut Vec<u8>, _title: &str) -> std::io::Result<()> {
+            buffer.extend_from_slice(b"[end]\n");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_dump_custom_renderer() {
+        let writer = BufferWriter::new();
+        let writer_clone = writer.clone();
+
+        let result = dump("list", &[1u8, 2, 3, 4, 5, 6][..], &mut ListRenderer, writer);
+        assert!(result.is_ok());
+
+        assert_eq!(
+            writer_clone.get_output_as_string(),
+            "[list]\n0: [1, 2, 3, 4]\n4: [5, 6]\n[end]\n"
+        );
+    }
+
     #[test]
     fn test_exact_buffer_boundary() {
         // Test data that exactly fills the read buffer (64KB)
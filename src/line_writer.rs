@@ -3,6 +3,9 @@ use crate::border_writer::BorderWriter;
 use crate::byte_to_color::ByteToColor;
 use crate::error::{HexlerError, Result};
 use crate::hex_formatter::HexFormatter;
+use crate::highlight::{Highlight, Highlights};
+use crate::renderer::Renderer;
+use crate::simd;
 
 /// Orchestrates the hex dump line output by coordinating specialized components.
 ///
@@ -17,7 +20,10 @@ pub struct LineWriter {
     hex_formatter: HexFormatter,
     ascii_renderer: AsciiRenderer,
     byte_to_color: ByteToColor,
+    highlights: Highlights,
     bytes_per_line: usize,
+    start_offset: usize,
+    squeeze: bool,
 }
 
 /// Border type for headers and footers.
@@ -29,6 +35,11 @@ pub enum Border {
 impl LineWriter {
     const COLOR_RESET: &'static [u8] = b"\x1b[0m";
 
+    /// Worst case size of one block in either panel: a color change before every byte, plus
+    /// the group separators, plus room for the fixed size copies of padded escapes and glyphs.
+    const BLOCK_OUTPUT_SIZE: usize =
+        simd::BLOCK * (ByteToColor::MAX_ESCAPE_LEN + 4) + simd::BLOCK / 8;
+
     /// Creates a new LineWriter with a specified number of bytes per line.
     ///
     /// # Arguments
@@ -45,7 +56,10 @@ impl LineWriter {
                 hex_formatter: HexFormatter::new(),
                 ascii_renderer: AsciiRenderer::new(),
                 byte_to_color: ByteToColor::new(),
+                highlights: Highlights::new(),
                 bytes_per_line,
+                start_offset: 0,
+                squeeze: false,
             })
         }
     }
@@ -66,21 +80,81 @@ impl LineWriter {
         Self::new_bytes(num_groups_of_8 * 8)
     }
 
+    /// Replaces the hex formatter, e.g. to change the offset base or disable colors.
+    pub fn with_hex_formatter(mut self, hex_formatter: HexFormatter) -> Self {
+        self.hex_formatter = hex_formatter;
+        self
+    }
+
+    /// Replaces the text panel renderer, e.g. to use a different `Encoding`.
+    pub fn with_ascii_renderer(mut self, ascii_renderer: AsciiRenderer) -> Self {
+        self.ascii_renderer = ascii_renderer;
+        self
+    }
+
+    /// Replaces the byte colors, e.g. to use a different `Theme`.
+    pub fn with_byte_to_color(mut self, byte_to_color: ByteToColor) -> Self {
+        self.byte_to_color = byte_to_color;
+        self
+    }
+
+    /// Sets background colors for ranges of offsets, e.g. the fields of a file header. They are
+    /// only shown when the hex formatter is colored.
+    pub fn with_highlights(mut self, highlights: Highlights) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    /// Sets the offset shown for the first byte, `dump` counts up from there.
+    pub fn with_start_offset(mut self, start_offset: usize) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+
+    /// Enables collapsing runs of identical lines into a single `*` line when used with `dump`.
+    pub fn with_squeeze(mut self, squeeze: bool) -> Self {
+        self.squeeze = squeeze;
+        self
+    }
+
     /// Returns the number of bytes displayed per line.
     pub fn bytes_per_line(&self) -> usize {
         self.bytes_per_line
     }
 
+    /// Number of bytes before and after each line that `write_line_in_context` looks at.
+    pub fn context_len(&self) -> usize {
+        self.ascii_renderer
+            .context_len()
+            .max(self.byte_to_color.context_len())
+    }
+
+    /// Returns the offset shown for the first byte.
+    pub fn start_offset(&self) -> usize {
+        self.start_offset
+    }
+
     /// Writes a header or footer border with an optional title to the provided buffer.
     pub fn write_border(
-        &mut self,
+        &self,
         buffer: &mut Vec<u8>,
         border: Border,
         title: &str,
     ) -> std::io::Result<()> {
+        let offset_width = self.hex_formatter.offset_width();
         match border {
-            Border::Header => BorderWriter::write_header(buffer, title, self.bytes_per_line),
-            Border::Footer => BorderWriter::write_footer(buffer, title, self.bytes_per_line),
+            Border::Header => BorderWriter::write_header_with_offset_width(
+                buffer,
+                title,
+                self.bytes_per_line,
+                offset_width,
+            ),
+            Border::Footer => BorderWriter::write_footer_with_offset_width(
+                buffer,
+                title,
+                self.bytes_per_line,
+                offset_width,
+            ),
         }
     }
 
@@ -89,17 +163,144 @@ impl LineWriter {
     /// This is a stateless version that takes an explicit byte offset,
     /// making it suitable for parallel processing where multiple threads format lines concurrently.
     ///
+    /// The bytes are processed in blocks of `simd::BLOCK`: hex digits for the whole block are
+    /// produced at once, and the color transitions are collected into a bit mask so escape codes
+    /// are only emitted where the color actually changes. Groups of 8 bytes without a color
+    /// change are copied in one go. The output is byte-identical to `write_line_scalar`.
+    ///
     /// # Arguments
     /// * `buffer` - Output buffer to append the line to (not cleared, only appended)
     /// * `byte_offset` - The byte offset to display in the hex offset column
     /// * `line_data` - Byte slice to display (may be less than bytes_per_line for the last line)
     pub fn write_line(&self, buffer: &mut Vec<u8>, byte_offset: usize, line_data: &[u8]) {
+        self.write_line_in_context(buffer, byte_offset, line_data, 0..line_data.len());
+    }
+
+    /// Writes a line like `write_line`, where `window[line]` is the line data.
+    ///
+    /// The bytes around the line are used by the multibyte encodings of the text panel, to
+    /// decode characters that cross the line boundaries. See `Renderer::write_line_in_context`.
+    pub fn write_line_in_context(
+        &self,
+        buffer: &mut Vec<u8>,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+    ) {
+        let line_data = &window[line.clone()];
+        let line_id = self.byte_to_color.line_id(window, line.clone());
+
+        if self.hex_formatter.is_colored() && !self.highlights.is_empty() {
+            let offsets = byte_offset as u64..(byte_offset + line_data.len()) as u64;
+            let highlights = self.highlights.overlapping(offsets);
+            if !highlights.is_empty() {
+                return self.write_line_highlighted(
+                    buffer,
+                    byte_offset,
+                    window,
+                    line,
+                    line_id,
+                    highlights,
+                );
+            }
+        }
+
+        // Write hex offset
+        self.hex_formatter.write_offset(buffer, byte_offset);
+        buffer.extend_from_slice(b" \xE2\x94\x82"); // " │" in UTF-8
+
+        let backend = self.hex_formatter.backend();
+        let mut hex = [0u8; simd::BLOCK * 3];
+        let mut ids = [0u8; simd::BLOCK];
+
+        // Each block is assembled here with fixed size copies, then appended with a single copy
+        let mut out = [0u8; Self::BLOCK_OUTPUT_SIZE];
+
+        // Write hex numbers "00 01 ..."
+        let mut previous_color_id: u8 = 0;
+        for block in line_data.chunks(simd::BLOCK) {
+            let len = block.len();
+            self.hex_formatter
+                .write_hex_bytes(block, &mut hex[..len * 3]);
+            self.color_ids(line_id, block, &mut ids[..len]);
+            let changes = simd::transitions(backend, &ids[..len], previous_color_id);
+
+            let mut pos = 0;
+            for (group, group_hex) in hex[..len * 3].chunks(24).enumerate() {
+                // Add an additional space before each group of 8 bytes
+                out[pos] = b' ';
+                pos += 1;
+
+                let group_changes = (changes >> (group * 8)) & 0xff;
+                if group_changes == 0 && group_hex.len() == 24 {
+                    // Most common case: the color stays the same for the whole group
+                    out[pos..pos + 24].copy_from_slice(group_hex);
+                    pos += 24;
+                } else {
+                    for (i, hex_byte) in group_hex.chunks_exact(3).enumerate() {
+                        if group_changes & (1 << i) != 0 {
+                            pos += self.write_escape(&mut out[pos..], ids[group * 8 + i]);
+                        }
+                        out[pos..pos + 3].copy_from_slice(hex_byte);
+                        pos += 3;
+                    }
+                }
+            }
+            previous_color_id = ids[len - 1];
+            buffer.extend_from_slice(&out[..pos]);
+        }
+
+        self.write_padding(buffer, line_data.len());
+
+        // Write codepage 437 characters
+        if previous_color_id != 0 {
+            buffer.extend_from_slice(Self::COLOR_RESET);
+            previous_color_id = 0;
+        }
+        buffer.extend_from_slice(b"\xE2\x94\x82 "); // "│ " in UTF-8
+
+        if self.ascii_renderer.is_multibyte() {
+            previous_color_id = self.write_decoded_text(buffer, window, line, line_id);
+        } else {
+            for block in line_data.chunks(simd::BLOCK) {
+                let len = block.len();
+                self.color_ids(line_id, block, &mut ids[..len]);
+                let changes = simd::transitions(backend, &ids[..len], previous_color_id);
+
+                let mut pos = 0;
+                for (i, &byte) in block.iter().enumerate() {
+                    if changes & (1 << i) != 0 {
+                        pos += self.write_escape(&mut out[pos..], ids[i]);
+                    }
+                    let (glyph, glyph_len) = self.ascii_renderer.render_padded(byte);
+                    out[pos..pos + 4].copy_from_slice(glyph);
+                    pos += glyph_len;
+                }
+                previous_color_id = ids[len - 1];
+                buffer.extend_from_slice(&out[..pos]);
+            }
+        }
+
+        // Finished writing bytes, so reset color and finally go to the next line
+        if previous_color_id != 0 {
+            buffer.extend_from_slice(Self::COLOR_RESET);
+        }
+        buffer.push(b'\n');
+    }
+
+    /// Reference implementation of `write_line` that handles one byte at a time.
+    ///
+    /// Produces exactly the same output as `write_line`. It is kept for tests and benchmarks
+    /// that verify and measure the vectorized path, and is not part of the stable API.
+    #[doc(hidden)]
+    pub fn write_line_scalar(&self, buffer: &mut Vec<u8>, byte_offset: usize, line_data: &[u8]) {
         // Write hex offset
         self.hex_formatter.write_offset(buffer, byte_offset);
         buffer.extend_from_slice(b" \xE2\x94\x82"); // " │" in UTF-8
 
         // Write hex numbers "00 01 ..."
         let mut previous_color_id: u8 = 0;
+        let line_id = self.byte_to_color.line_id(line_data, 0..line_data.len());
 
         // Process actual bytes
         let mut group_counter = 0;
@@ -110,23 +311,16 @@ impl LineWriter {
             }
             group_counter = (group_counter + 1) & 7; // Faster than %8 or is_multiple_of(8)
 
-            let next_color_id = self.byte_to_color.id(byte);
+            let next_color_id = line_id.unwrap_or_else(|| self.byte_to_color.id(byte));
             if next_color_id != previous_color_id {
-                buffer.extend_from_slice(self.byte_to_color.bytes(byte));
+                let (escape, len) = self.byte_to_color.escape(next_color_id);
+                buffer.extend_from_slice(&escape[..len]);
                 previous_color_id = next_color_id;
             }
             buffer.extend_from_slice(self.hex_formatter.hex_byte(byte));
         }
 
-        // Fill remaining space with padding, most of the time this will be 0, except for the last line
-        let padding_count = self.bytes_per_line - line_data.len();
-
-        // Calculate number of separator spaces: count how many times group_counter wraps to 0
-        // line_data.len() & 7 will be 0 for multiples of 8, so it is only non-zero for the last line
-        let num_separators =
-            ((line_data.len() & 7) + padding_count) / 8 - (padding_count % 8 != 0) as usize;
-        let padding_size = num_separators + padding_count * 3;
-        buffer.resize(buffer.len() + padding_size, b' ');
+        self.write_padding(buffer, line_data.len());
 
         // Write codepage 437 characters
         if previous_color_id != 0 {
@@ -135,13 +329,19 @@ impl LineWriter {
         }
         buffer.extend_from_slice(b"\xE2\x94\x82 "); // "│ " in UTF-8
 
-        for &byte in line_data {
-            let next_color_id = self.byte_to_color.id(byte);
-            if next_color_id != previous_color_id {
-                buffer.extend_from_slice(self.byte_to_color.bytes(byte));
-                previous_color_id = next_color_id;
+        if self.ascii_renderer.is_multibyte() {
+            previous_color_id =
+                self.write_decoded_text(buffer, line_data, 0..line_data.len(), line_id);
+        } else {
+            for &byte in line_data {
+                let next_color_id = line_id.unwrap_or_else(|| self.byte_to_color.id(byte));
+                if next_color_id != previous_color_id {
+                    let (escape, len) = self.byte_to_color.escape(next_color_id);
+                    buffer.extend_from_slice(&escape[..len]);
+                    previous_color_id = next_color_id;
+                }
+                buffer.extend_from_slice(self.ascii_renderer.render_bytes(byte));
             }
-            buffer.extend_from_slice(self.ascii_renderer.render_bytes(byte));
         }
 
         // Finished writing bytes, so reset color and finally go to the next line
@@ -150,11 +350,390 @@ impl LineWriter {
         }
         buffer.push(b'\n');
     }
+
+    /// Writes a line with background colors, one byte at a time. Only used for the few lines
+    /// that overlap with a highlight.
+    ///
+    /// Every color change resets all attributes before setting the background and the byte
+    /// color, so a highlight never leaks into the following bytes. The space between two bytes
+    /// is only highlighted when both bytes have the same background.
+    fn write_line_highlighted(
+        &self,
+        buffer: &mut Vec<u8>,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+        line_id: Option<u8>,
+        highlights: &[Highlight],
+    ) {
+        let line_data = &window[line.clone()];
+        let background = |idx: usize| {
+            let offset = (byte_offset + idx) as u64;
+            highlights.iter().position(|h| h.range.contains(&offset))
+        };
+        let style = |idx: usize| {
+            let color_id = line_id.unwrap_or_else(|| self.byte_to_color.id(line_data[idx]));
+            (color_id, background(idx))
+        };
+
+        // Color ID and index of the highlight of the text written last
+        let mut current: (u8, Option<usize>) = (0, None);
+        let mut set_style = |buffer: &mut Vec<u8>, next: (u8, Option<usize>)| {
+            if next == current {
+                return;
+            }
+            if current != (0, None) {
+                buffer.extend_from_slice(Self::COLOR_RESET);
+            }
+            if let Some(highlight) = next.1 {
+                buffer.extend_from_slice(highlights[highlight].background.as_bytes());
+            }
+            if next.0 != 0 {
+                let (escape, len) = self.byte_to_color.escape(next.0);
+                buffer.extend_from_slice(&escape[..len]);
+            }
+            current = next;
+        };
+
+        // Write hex offset
+        self.hex_formatter.write_offset(buffer, byte_offset);
+        buffer.extend_from_slice(b" \xE2\x94\x82"); // " │" in UTF-8
+
+        for (idx, &byte) in line_data.iter().enumerate() {
+            if idx % 8 == 0 {
+                buffer.push(b' ');
+            }
+            let (color_id, bg) = style(idx);
+            set_style(buffer, (color_id, bg));
+            buffer.extend_from_slice(&self.hex_formatter.hex_byte(byte)[..2]);
+
+            let next_bg = if idx + 1 < line_data.len() {
+                background(idx + 1)
+            } else {
+                None
+            };
+            if bg.is_some() && next_bg != bg {
+                set_style(buffer, (0, None));
+            }
+            buffer.push(b' ');
+        }
+
+        self.write_padding(buffer, line_data.len());
+        set_style(buffer, (0, None));
+        buffer.extend_from_slice(b"\xE2\x94\x82 "); // "│ " in UTF-8
+
+        for (idx, pos) in line.clone().enumerate() {
+            if self.ascii_renderer.is_multibyte() {
+                let (glyph, glyph_len) =
+                    self.ascii_renderer
+                        .render_in_context(window, line.clone(), pos);
+                if glyph_len == 0 {
+                    continue; // Second cell of a wide character
+                }
+                set_style(buffer, style(idx));
+                buffer.extend_from_slice(&glyph[..glyph_len]);
+            } else {
+                set_style(buffer, style(idx));
+                buffer.extend_from_slice(self.ascii_renderer.render_bytes(window[pos]));
+            }
+        }
+
+        set_style(buffer, (0, None));
+        buffer.push(b'\n');
+    }
+
+    /// Writes the marker line for a run of identical lines, e.g. `00000040 │ *`.
+    ///
+    /// The marker is padded so the panel separator lines up with the regular lines.
+    pub fn write_squeezed(&self, buffer: &mut Vec<u8>, byte_offset: usize) {
+        self.hex_formatter.write_offset(buffer, byte_offset);
+        buffer.extend_from_slice(b" \xE2\x94\x82 *"); // " │ *" in UTF-8
+
+        // The hex panel is 25 characters per group of 8 bytes, 2 of them are used by " *"
+        let hex_panel_width = self.bytes_per_line / 8 * 25;
+        buffer.resize(buffer.len() + hex_panel_width - 2, b' ');
+        buffer.extend_from_slice(b"\xE2\x94\x82\n"); // "│" in UTF-8
+    }
+
+    /// Writes a separator row, e.g. `00001200 ├── gzip compressed data ────────`.
+    ///
+    /// The row is as wide as the regular lines, unless the label is longer.
+    pub fn write_separator(&self, buffer: &mut Vec<u8>, byte_offset: usize, label: &str) {
+        const BOLD: &[u8] = b"\x1b[1m";
+        const RESET: &[u8] = b"\x1b[0m";
+
+        self.hex_formatter.write_offset(buffer, byte_offset);
+        buffer.extend_from_slice(" ├── ".as_bytes());
+        if self.hex_formatter.is_colored() {
+            buffer.extend_from_slice(BOLD);
+            buffer.extend_from_slice(label.as_bytes());
+            buffer.extend_from_slice(RESET);
+        } else {
+            buffer.extend_from_slice(label.as_bytes());
+        }
+        buffer.push(b' ');
+
+        // Offset, both panels with their separators, and the text panel
+        let offset_width = self.hex_formatter.offset_width();
+        let line_width = offset_width + 4 + self.bytes_per_line / 8 * 25 + self.bytes_per_line;
+        let used = offset_width + 6 + unicode_width::UnicodeWidthStr::width(label);
+        for _ in 0..line_width.saturating_sub(used).max(2) {
+            buffer.extend_from_slice("─".as_bytes());
+        }
+        buffer.push(b'\n');
+    }
+
+    /// Writes the text panel for a multibyte encoding, returns the color id of the last byte.
+    fn write_decoded_text(
+        &self,
+        buffer: &mut Vec<u8>,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+        line_id: Option<u8>,
+    ) -> u8 {
+        let mut previous_color_id = 0;
+        for pos in line.clone() {
+            let (glyph, glyph_len) =
+                self.ascii_renderer
+                    .render_in_context(window, line.clone(), pos);
+            if glyph_len == 0 {
+                continue; // Second cell of a wide character
+            }
+            let byte = window[pos];
+            let next_color_id = line_id.unwrap_or_else(|| self.byte_to_color.id(byte));
+            if next_color_id != previous_color_id {
+                let (escape, len) = self.byte_to_color.escape(next_color_id);
+                buffer.extend_from_slice(&escape[..len]);
+                previous_color_id = next_color_id;
+            }
+            buffer.extend_from_slice(&glyph[..glyph_len]);
+        }
+        previous_color_id
+    }
+
+    /// Writes the color IDs of `block` into `out`, all set to `line_id` if the whole line has
+    /// the same color.
+    #[inline]
+    fn color_ids(&self, line_id: Option<u8>, block: &[u8], out: &mut [u8]) {
+        match line_id {
+            Some(id) => out.fill(id),
+            None => self.byte_to_color.ids(block, out),
+        }
+    }
+
+    /// Copies the padded escape code for `color_id` to the start of `out`, returns its length.
+    #[inline]
+    fn write_escape(&self, out: &mut [u8], color_id: u8) -> usize {
+        let (escape, len) = self.byte_to_color.escape(color_id);
+        out[..ByteToColor::MAX_ESCAPE_LEN].copy_from_slice(escape);
+        len
+    }
+
+    /// Fills the remaining hex panel space when `len` is less than `bytes_per_line`.
+    fn write_padding(&self, buffer: &mut Vec<u8>, len: usize) {
+        // Fill remaining space with padding, most of the time this will be 0, except for the last line
+        let padding_count = self.bytes_per_line - len;
+
+        // Calculate number of separator spaces: count how many times group_counter wraps to 0
+        // len & 7 will be 0 for multiples of 8, so it is only non-zero for the last line
+        let num_separators = ((len & 7) + padding_count) / 8 - (padding_count % 8 != 0) as usize;
+        let padding_size = num_separators + padding_count * 3;
+        buffer.resize(buffer.len() + padding_size, b' ');
+    }
+}
+
+impl Renderer for LineWriter {
+    fn bytes_per_line(&self) -> usize {
+        self.bytes_per_line
+    }
+
+    fn start_offset(&self) -> usize {
+        self.start_offset
+    }
+
+    fn squeeze(&self) -> bool {
+        self.squeeze
+    }
+
+    fn context_len(&self) -> usize {
+        LineWriter::context_len(self)
+    }
+
+    fn write_header(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()> {
+        self.write_border(buffer, Border::Header, title)
+    }
+
+    fn write_line(&self, buffer: &mut Vec<u8>, byte_offset: usize, line_data: &[u8]) {
+        LineWriter::write_line(self, buffer, byte_offset, line_data)
+    }
+
+    fn write_line_in_context(
+        &self,
+        buffer: &mut Vec<u8>,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+    ) {
+        LineWriter::write_line_in_context(self, buffer, byte_offset, window, line)
+    }
+
+    fn write_squeezed(&self, buffer: &mut Vec<u8>, byte_offset: usize) {
+        LineWriter::write_squeezed(self, buffer, byte_offset)
+    }
+
+    fn write_separator(&self, buffer: &mut Vec<u8>, byte_offset: usize, label: &str) {
+        LineWriter::write_separator(self, buffer, byte_offset, label)
+    }
+
+    fn write_footer(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()> {
+        self.write_border(buffer, Border::Footer, title)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ascii_renderer::Encoding;
+    use crate::byte_to_color::Theme;
+    use crate::simd::Backend;
+
+    /// Deterministic pseudo random bytes with long runs mixed in, to hit many color changes.
+    fn test_bytes(len: usize) -> Vec<u8> {
+        let mut state: u32 = 0x1234_5678;
+        (0..len)
+            .map(|i| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                match (i / 50) % 3 {
+                    0 => (state >> 24) as u8,
+                    1 => b"Hello, World!\n"[i % 14],
+                    _ => 0,
+                }
+            })
+            .collect()
+    }
+
+    fn line_writer_with_backend(bytes_per_line: usize, backend: Backend) -> LineWriter {
+        let mut line_writer = LineWriter::new_bytes(bytes_per_line).unwrap();
+        line_writer.hex_formatter = HexFormatter::with_backend(backend);
+        line_writer
+    }
+
+    #[test]
+    fn test_write_line_matches_scalar() {
+        let data = test_bytes(4096);
+        for backend in [Backend::Scalar, Backend::Ssse3, Backend::Avx2] {
+            for bytes_per_line in [8, 16, 24, 32, 40, 64, 128] {
+                let line_writer = line_writer_with_backend(bytes_per_line, backend);
+                for len in 0..=bytes_per_line {
+                    for start in (0..data.len() - len).step_by(97) {
+                        let line = &data[start..start + len];
+                        let mut expected = Vec::new();
+                        let mut actual = Vec::new();
+                        line_writer.write_line_scalar(&mut expected, start, line);
+                        line_writer.write_line(&mut actual, start, line);
+                        assert_eq!(
+                            String::from_utf8_lossy(&expected),
+                            String::from_utf8_lossy(&actual),
+                            "{:?} bytes_per_line={} len={} start={}",
+                            backend,
+                            bytes_per_line,
+                            len,
+                            start
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_line_all_byte_values_matches_scalar() {
+        let data: Vec<u8> = (0..=255u8).collect();
+        let line_writer = LineWriter::new_bytes(32).unwrap();
+        for (idx, line) in data.chunks(32).enumerate() {
+            let mut expected = Vec::new();
+            let mut actual = Vec::new();
+            line_writer.write_line_scalar(&mut expected, idx * 32, line);
+            line_writer.write_line(&mut actual, idx * 32, line);
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_write_line_utf8() {
+        let line_writer = LineWriter::new_bytes(8)
+            .unwrap()
+            .with_ascii_renderer(AsciiRenderer::with_encoding(Encoding::Utf8));
+        let data = "a€日\n".as_bytes();
+
+        let mut expected = Vec::new();
+        let mut actual = Vec::new();
+        line_writer.write_line_scalar(&mut expected, 0, data);
+        line_writer.write_line(&mut actual, 0, data);
+        assert_eq!(expected, actual);
+
+        // The wide character takes up two cells, so the text panel is still 8 columns wide
+        let text = strip_ansi_codes(&String::from_utf8(actual).unwrap());
+        assert!(text.ends_with("│ a€··日·◙\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_write_line_highlighted() {
+        let highlights: Highlights = [Highlight::new(0x12..0x14, "<bg>")].into_iter().collect();
+        let line_writer = LineWriter::new_bytes(8)
+            .unwrap()
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap())
+            .with_highlights(highlights);
+
+        let mut output = Vec::new();
+        line_writer.write_line(&mut output, 0x10, b"abcdefgh");
+        let output = String::from_utf8(output).unwrap();
+        let reset = "\x1b[0m";
+        assert!(
+            output.ends_with(&format!(
+                "│ 61 62 <bg>63 64{reset} 65 66 67 68 │ ab<bg>cd{reset}efgh\n"
+            )),
+            "{:?}",
+            output
+        );
+        assert_eq!(strip_ansi_codes(&output).matches("<bg>").count(), 2);
+
+        // Lines without highlights are not affected
+        let mut highlighted = Vec::new();
+        let mut plain = Vec::new();
+        line_writer.write_line(&mut highlighted, 0x20, b"abcdefgh");
+        line_writer.write_line_scalar(&mut plain, 0x20, b"abcdefgh");
+        assert_eq!(highlighted, plain);
+    }
+
+    #[test]
+    fn test_write_line_adjacent_highlights() {
+        // Across the group gap, directly followed by another one, and up to the end of the line
+        let highlights: Highlights = [
+            Highlight::new(0x06..0x0a, "<a>"),
+            Highlight::new(0x0a..0x0b, "<b>"),
+            Highlight::new(0x0e..0x20, "<c>"),
+        ]
+        .into_iter()
+        .collect();
+        let line_writer = LineWriter::new_bytes(16)
+            .unwrap()
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap())
+            .with_highlights(highlights);
+
+        let mut output = Vec::new();
+        line_writer.write_line(&mut output, 0, b"0123456789abcdef");
+        let output = String::from_utf8(output).unwrap();
+        let reset = "\x1b[0m";
+        assert!(
+            output.ends_with(&format!(
+                "│ 30 31 32 33 34 35 <a>36 37  38 39{reset} <b>61{reset} 62 63 64 <c>65 66{reset} \
+                 │ 012345<a>6789{reset}<b>a{reset}bcd<c>ef{reset}\n"
+            )),
+            "{:?}",
+            output
+        );
+    }
 
     #[test]
     fn test_new_bytes_valid() {
@@ -192,7 +771,7 @@ mod tests {
     #[test]
     fn test_write_border_header() {
         let mut buffer = Vec::new();
-        let mut line_writer = LineWriter::new_bytes(8).unwrap();
+        let line_writer = LineWriter::new_bytes(8).unwrap();
         line_writer
             .write_border(&mut buffer, Border::Header, "Test Header")
             .unwrap();
@@ -206,7 +785,7 @@ mod tests {
     #[test]
     fn test_write_border_footer() {
         let mut buffer = Vec::new();
-        let mut line_writer = LineWriter::new_bytes(8).unwrap();
+        let line_writer = LineWriter::new_bytes(8).unwrap();
         line_writer
             .write_border(&mut buffer, Border::Footer, "Test Footer")
             .unwrap();
@@ -325,6 +904,54 @@ mod tests {
         assert_eq!(line_writer.bytes_per_line() % 8, 0);
     }
 
+    #[test]
+    fn test_monochrome_has_no_escape_codes() {
+        let line_writer = LineWriter::new_bytes(16)
+            .unwrap()
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap());
+
+        let mut buffer = Vec::new();
+        let data: Vec<u8> = (0..=255u8).collect();
+        for (idx, line) in data.chunks(16).enumerate() {
+            line_writer.write_line(&mut buffer, idx * 16, line);
+        }
+        assert!(!buffer.contains(&0x1b));
+    }
+
+    #[test]
+    fn test_border_matches_offset_width() {
+        let line_writer = LineWriter::new_bytes(8)
+            .unwrap()
+            .with_hex_formatter(HexFormatter::new().with_color(false).with_offset_width(12));
+
+        let mut border = Vec::new();
+        line_writer
+            .write_border(&mut border, Border::Header, "")
+            .unwrap();
+        let mut line = Vec::new();
+        line_writer.write_line(&mut line, 0, &[0u8; 8]);
+
+        let border = String::from_utf8(border).unwrap();
+        let line = strip_ansi_codes(&String::from_utf8(line).unwrap());
+        assert_eq!(border.find('┬').unwrap() / "─".len(), 13);
+        assert_eq!(line.find('│').unwrap(), 13);
+    }
+
+    #[test]
+    fn test_write_squeezed_aligned() {
+        let line_writer = LineWriter::new_bytes(16).unwrap();
+        let mut marker = Vec::new();
+        line_writer.write_squeezed(&mut marker, 0x40);
+        let mut line = Vec::new();
+        line_writer.write_line(&mut line, 0x40, &[0x41; 16]);
+
+        let marker = strip_ansi_codes(&String::from_utf8(marker).unwrap());
+        let line = strip_ansi_codes(&String::from_utf8(line).unwrap());
+        assert!(marker.starts_with("00000040 │ *"));
+        assert_eq!(marker.rfind('│'), line.rfind('│'));
+    }
+
     #[test]
     fn test_hex_offset_leading_zeros() {
         let mut buffer = Vec::new();
@@ -0,0 +1,485 @@
+use crate::error::Result;
+use crate::executable::{
+    highlight_entries, highlight_fields, invalid, permissions, printable_name, read_at, Executable,
+    Fields, Section,
+};
+use crate::highlight::{Highlight, FIELD_BACKGROUNDS};
+use std::io::{Read, Seek};
+
+const LC_SEGMENT: u64 = 0x1;
+const LC_SYMTAB: u64 = 0x2;
+const LC_SEGMENT_64: u64 = 0x19;
+
+/// Section types without data in the file: zerofill, GB zerofill and thread local zerofill.
+const ZEROFILL_SECTION_TYPES: [u64; 3] = [0x1, 0xc, 0x12];
+
+/// Whether the 8 bytes at the start of a file are a fat header.
+///
+/// Java class files have the same magic number, followed by their version which is at least
+/// 45, while fat binaries have only a few architectures.
+pub(crate) fn is_fat_header(data: &[u8]) -> bool {
+    matches!(
+        data.get(..4),
+        Some(b"\xca\xfe\xba\xbe") | Some(b"\xca\xfe\xba\xbf")
+    ) && data.len() >= 8
+        && u32::from_be_bytes([data[4], data[5], data[6], data[7]]) < 45
+}
+
+/// Parses a Mach-O file: the fat header and its architectures, and each architecture's
+/// header, load commands, segments and sections.
+///
+/// In a fat binary the sections of all architectures are listed, so `Executable::section`
+/// finds the one of the first architecture.
+///
+/// # Errors
+/// Returns `InvalidExecutable` if the headers are broken or point outside of the file.
+pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<Executable> {
+    let mut executable = Executable::default();
+    let magic = read_at(reader, 0, 8, "Mach-O header")?;
+    if !is_fat_header(&magic) {
+        executable.description = parse_image(reader, 0, &mut executable)?;
+        return Ok(executable);
+    }
+
+    let is_64 = magic[3] == 0xbf;
+    let fields = Fields::new(is_64, false);
+    let num = fields.u32(&magic, 4);
+    let entsize = if is_64 { 32 } else { 20 };
+    let table = read_at(reader, 8, num * entsize, "fat header")?;
+
+    let mut header_rows = vec![(0, format!("fat header ({})", architectures(num)))];
+    highlight_fields(&mut executable.highlights, 0, &[(0, 4), (4, 4)]);
+    highlight_entries(&mut executable.highlights, 8, num, entsize);
+
+    let mut names = Vec::new();
+    for entry in table.chunks_exact(entsize as usize) {
+        let cpu = cpu_type(fields.u32(entry, 0));
+        let offset = fields.word(entry, 8, 8);
+        let size = fields.word(entry, 12, 16);
+        executable
+            .rows
+            .push((offset, format!("architecture {} (0x{:x} bytes)", cpu, size)));
+        parse_image(reader, offset, &mut executable)?;
+        names.push(cpu);
+    }
+
+    executable.description = format!(
+        "Mach-O universal binary with {}: {}",
+        architectures(num),
+        names.join(", ")
+    );
+    executable.rows.append(&mut header_rows);
+    Ok(executable)
+}
+
+/// Parses the image at `base`, adds its rows, highlights and sections to `executable` and
+/// returns its description.
+fn parse_image<R: Read + Seek>(
+    reader: &mut R,
+    base: u64,
+    executable: &mut Executable,
+) -> Result<String> {
+    let magic = read_at(reader, base, 4, "Mach-O header")?;
+    let (is_64, little_endian) = match magic.as_slice() {
+        b"\xfe\xed\xfa\xce" => (false, false),
+        b"\xfe\xed\xfa\xcf" => (true, false),
+        b"\xce\xfa\xed\xfe" => (false, true),
+        b"\xcf\xfa\xed\xfe" => (true, true),
+        _ => return Err(invalid(format!("no Mach-O header at 0x{:x}", base))),
+    };
+    let fields = Fields::new(is_64, little_endian);
+    let header_len = if is_64 { 32 } else { 28 };
+    let header = read_at(reader, base, header_len, "Mach-O header")?;
+    let cpu = cpu_type(fields.u32(&header, 4));
+    let file_type = file_type(fields.u32(&header, 12));
+    let num_commands = fields.u32(&header, 16);
+    let commands_len = fields.u32(&header, 20);
+
+    let mut header_rows = vec![(base, format!("Mach-O header ({} bytes)", header_len))];
+    let fields_len = header_len / 4;
+    let header_fields: Vec<(u64, u64)> = (0..fields_len).map(|idx| (idx * 4, 4)).collect();
+    highlight_fields(&mut executable.highlights, base, &header_fields);
+
+    let commands_start = base + header_len;
+    let commands = read_at(reader, commands_start, commands_len, "load commands")?;
+    if num_commands > 0 {
+        header_rows.push((
+            commands_start,
+            format!(
+                "load commands ({} commands, {} bytes)",
+                num_commands, commands_len
+            ),
+        ));
+    }
+
+    let mut pos = 0;
+    for idx in 0..num_commands as usize {
+        if pos + 8 > commands.len() {
+            return Err(invalid(format!(
+                "load command {} is outside of the load commands",
+                idx
+            )));
+        }
+        let cmd = fields.u32(&commands, pos);
+        let cmd_len = fields.u32(&commands, pos + 4) as usize;
+        if cmd_len < 8 || pos + cmd_len > commands.len() {
+            return Err(invalid(format!(
+                "load command {} has an invalid size of {} bytes",
+                idx, cmd_len
+            )));
+        }
+        let command = &commands[pos..pos + cmd_len];
+        let offset = commands_start + pos as u64;
+        executable.highlights.add(Highlight::new(
+            offset..offset + cmd_len as u64,
+            FIELD_BACKGROUNDS[idx % 2],
+        ));
+        header_rows.push((offset, format!("{} ({} bytes)", command_name(cmd), cmd_len)));
+
+        match cmd {
+            LC_SEGMENT | LC_SEGMENT_64 => {
+                parse_segment(&fields, command, base, executable)?;
+            }
+            LC_SYMTAB if cmd_len >= 24 => {
+                let num_symbols = fields.u32(command, 12);
+                let strings_len = fields.u32(command, 20);
+                if num_symbols > 0 {
+                    executable.rows.push((
+                        base + fields.u32(command, 8),
+                        format!("symbol table ({} symbols)", num_symbols),
+                    ));
+                }
+                if strings_len > 0 {
+                    executable.rows.push((
+                        base + fields.u32(command, 16),
+                        format!("string table (0x{:x} bytes)", strings_len),
+                    ));
+                }
+            }
+            _ => {
+                if let Some(name) = linkedit_data_name(cmd).filter(|_| cmd_len >= 16) {
+                    let data_len = fields.u32(command, 12);
+                    if data_len > 0 {
+                        executable.rows.push((
+                            base + fields.u32(command, 8),
+                            format!("{} (0x{:x} bytes)", name, data_len),
+                        ));
+                    }
+                }
+            }
+        }
+        pos += cmd_len;
+    }
+
+    // Segments contain the headers and sections, so their rows come first at the same offset
+    executable.rows.append(&mut header_rows);
+    Ok(format!(
+        "Mach-O {}-bit {}, {}",
+        if is_64 { 64 } else { 32 },
+        file_type,
+        cpu
+    ))
+}
+
+/// Adds the row of a segment load command and the rows and sections of its sections.
+fn parse_segment(
+    fields: &Fields,
+    command: &[u8],
+    base: u64,
+    executable: &mut Executable,
+) -> Result<()> {
+    let is_64 = fields.u32(command, 0) == LC_SEGMENT_64;
+    let (command_len, section_len) = if is_64 { (72, 80) } else { (56, 68) };
+    let segment_name = name(command.get(8..24).unwrap_or_default());
+    let num_sections = match command.len() >= command_len {
+        true => fields.u32(command, if is_64 { 64 } else { 48 }) as usize,
+        false => 0,
+    };
+    if command.len() < command_len || command.len() < command_len + num_sections * section_len {
+        return Err(invalid(format!(
+            "segment {} is larger than its load command",
+            segment_name
+        )));
+    }
+
+    let address = fields.word(command, 24, 24);
+    let file_offset = fields.word(command, 32, 40);
+    let file_len = fields.word(command, 36, 48);
+    let protection = fields.u32(command, if is_64 { 60 } else { 44 });
+    if file_len > 0 {
+        executable.rows.push((
+            base + file_offset,
+            format!(
+                "segment {} ({}, 0x{:x} bytes at 0x{:x})",
+                segment_name,
+                permissions(
+                    protection & 1 != 0,
+                    protection & 2 != 0,
+                    protection & 4 != 0
+                ),
+                file_len,
+                address
+            ),
+        ));
+    }
+
+    for entry in command[command_len..]
+        .chunks_exact(section_len)
+        .take(num_sections)
+    {
+        let kind = fields.u32(entry, if is_64 { 64 } else { 56 }) & 0xff;
+        let section = Section {
+            name: format!("{},{}", name(&entry[16..32]), name(&entry[..16])),
+            kind: section_type(kind),
+            offset: base + fields.u32(entry, if is_64 { 48 } else { 40 }),
+            size: match ZEROFILL_SECTION_TYPES.contains(&kind) {
+                true => 0,
+                false => fields.word(entry, 36, 40),
+            },
+            address: fields.word(entry, 32, 32),
+        };
+        if section.size > 0 {
+            executable.rows.push((
+                section.offset,
+                format!(
+                    "{} ({}, 0x{:x} bytes)",
+                    section.name, section.kind, section.size
+                ),
+            ));
+        }
+        executable.sections.push(section);
+    }
+    Ok(())
+}
+
+fn architectures(num: u64) -> String {
+    match num {
+        1 => "1 architecture".to_string(),
+        num => format!("{} architectures", num),
+    }
+}
+
+/// Text of a NUL padded name field of 16 bytes.
+fn name(data: &[u8]) -> String {
+    let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    printable_name(&data[..len])
+}
+
+fn cpu_type(cpu: u64) -> String {
+    match cpu {
+        7 => "x86".to_string(),
+        0x0100_0007 => "x86_64".to_string(),
+        12 => "arm".to_string(),
+        0x0100_000c => "arm64".to_string(),
+        0x0200_000c => "arm64_32".to_string(),
+        18 => "ppc".to_string(),
+        0x0100_0012 => "ppc64".to_string(),
+        cpu => format!("cpu 0x{:x}", cpu),
+    }
+}
+
+fn file_type(kind: u64) -> String {
+    match kind {
+        1 => "object".to_string(),
+        2 => "executable".to_string(),
+        4 => "core".to_string(),
+        6 => "dynamic library".to_string(),
+        7 => "dynamic linker".to_string(),
+        8 => "bundle".to_string(),
+        10 => "debug symbols".to_string(),
+        11 => "kernel extension".to_string(),
+        kind => format!("file type {}", kind),
+    }
+}
+
+fn command_name(cmd: u64) -> String {
+    match cmd {
+        LC_SEGMENT => "LC_SEGMENT".to_string(),
+        LC_SYMTAB => "LC_SYMTAB".to_string(),
+        0x4 => "LC_THREAD".to_string(),
+        0x5 => "LC_UNIXTHREAD".to_string(),
+        0xb => "LC_DYSYMTAB".to_string(),
+        0xc => "LC_LOAD_DYLIB".to_string(),
+        0xd => "LC_ID_DYLIB".to_string(),
+        0xe => "LC_LOAD_DYLINKER".to_string(),
+        LC_SEGMENT_64 => "LC_SEGMENT_64".to_string(),
+        0x1b => "LC_UUID".to_string(),
+        0x1d => "LC_CODE_SIGNATURE".to_string(),
+        0x22 => "LC_DYLD_INFO".to_string(),
+        0x24 => "LC_VERSION_MIN_MACOSX".to_string(),
+        0x26 => "LC_FUNCTION_STARTS".to_string(),
+        0x29 => "LC_DATA_IN_CODE".to_string(),
+        0x2a => "LC_SOURCE_VERSION".to_string(),
+        0x32 => "LC_BUILD_VERSION".to_string(),
+        0x8000_001c => "LC_RPATH".to_string(),
+        0x8000_0022 => "LC_DYLD_INFO_ONLY".to_string(),
+        0x8000_0028 => "LC_MAIN".to_string(),
+        0x8000_0033 => "LC_DYLD_EXPORTS_TRIE".to_string(),
+        0x8000_0034 => "LC_DYLD_CHAINED_FIXUPS".to_string(),
+        cmd => format!("load command 0x{:x}", cmd),
+    }
+}
+
+/// Name of the data that a `linkedit_data_command` points to.
+fn linkedit_data_name(cmd: u64) -> Option<&'static str> {
+    match cmd {
+        0x1d => Some("code signature"),
+        0x26 => Some("function starts"),
+        0x29 => Some("data in code"),
+        0x8000_0033 => Some("exports trie"),
+        0x8000_0034 => Some("chained fixups"),
+        _ => None,
+    }
+}
+
+fn section_type(kind: u64) -> String {
+    match kind {
+        0x0 => "REGULAR".to_string(),
+        0x1 => "ZEROFILL".to_string(),
+        0x2 => "CSTRING_LITERALS".to_string(),
+        0x3 => "4BYTE_LITERALS".to_string(),
+        0x4 => "8BYTE_LITERALS".to_string(),
+        0x5 => "LITERAL_POINTERS".to_string(),
+        0x6 => "NON_LAZY_SYMBOL_POINTERS".to_string(),
+        0x7 => "LAZY_SYMBOL_POINTERS".to_string(),
+        0x8 => "SYMBOL_STUBS".to_string(),
+        0x9 => "MOD_INIT_FUNC_POINTERS".to_string(),
+        0xa => "MOD_TERM_FUNC_POINTERS".to_string(),
+        0xb => "COALESCED".to_string(),
+        0xc => "GB_ZEROFILL".to_string(),
+        0xe => "16BYTE_LITERALS".to_string(),
+        0x11 => "THREAD_LOCAL_REGULAR".to_string(),
+        0x12 => "THREAD_LOCAL_ZEROFILL".to_string(),
+        0x13 => "THREAD_LOCAL_VARIABLES".to_string(),
+        kind => format!("0x{:x}", kind),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Builds a tiny 64-bit arm64 executable with a `__TEXT` segment that has a `__text`
+    /// section, and an `LC_MAIN` command.
+    fn tiny_macho() -> Vec<u8> {
+        let mut data = vec![0u8; 0x200];
+        let header: [u32; 8] = [0xfeed_facf, 0x0100_000c, 0, 2, 2, 176, 0, 0];
+        for (idx, value) in header.iter().enumerate() {
+            data[idx * 4..idx * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        // LC_SEGMENT_64 __TEXT with one section, 72 + 80 bytes
+        let segment = &mut data[32..184];
+        segment[0..4].copy_from_slice(&0x19u32.to_le_bytes());
+        segment[4..8].copy_from_slice(&152u32.to_le_bytes());
+        segment[8..14].copy_from_slice(b"__TEXT");
+        segment[24..32].copy_from_slice(&0x1_0000_0000u64.to_le_bytes());
+        segment[32..40].copy_from_slice(&0x200u64.to_le_bytes());
+        segment[48..56].copy_from_slice(&0x200u64.to_le_bytes());
+        segment[56..60].copy_from_slice(&5u32.to_le_bytes());
+        segment[60..64].copy_from_slice(&5u32.to_le_bytes());
+        segment[64..68].copy_from_slice(&1u32.to_le_bytes());
+        let section = &mut segment[72..152];
+        section[..6].copy_from_slice(b"__text");
+        section[16..22].copy_from_slice(b"__TEXT");
+        section[32..40].copy_from_slice(&0x1_0000_0100u64.to_le_bytes());
+        section[40..48].copy_from_slice(&0x40u64.to_le_bytes());
+        section[48..52].copy_from_slice(&0x100u32.to_le_bytes());
+        section[64..68].copy_from_slice(&0x8000_0400u32.to_le_bytes());
+
+        // LC_MAIN, 24 bytes
+        data[184..188].copy_from_slice(&0x8000_0028u32.to_le_bytes());
+        data[188..192].copy_from_slice(&24u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_parse_tiny_macho() {
+        let executable = Executable::parse(&mut Cursor::new(tiny_macho()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(executable.description, "Mach-O 64-bit executable, arm64");
+
+        let text = executable.section("__TEXT,__text").unwrap();
+        assert_eq!(text.kind, "REGULAR");
+        assert_eq!(
+            (text.offset, text.size, text.address),
+            (0x100, 0x40, 0x1_0000_0100)
+        );
+
+        let rows: Vec<(u64, &str)> = executable
+            .rows
+            .iter()
+            .map(|(offset, label)| (*offset, label.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (0, "segment __TEXT (R-X, 0x200 bytes at 0x100000000)"),
+                (0x100, "__TEXT,__text (REGULAR, 0x40 bytes)"),
+                (0, "Mach-O header (32 bytes)"),
+                (32, "load commands (2 commands, 176 bytes)"),
+                (32, "LC_SEGMENT_64 (152 bytes)"),
+                (184, "LC_MAIN (24 bytes)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fat_binary() {
+        let image = tiny_macho();
+        let mut data = vec![0u8; 0x1000];
+        data[..8].copy_from_slice(b"\xca\xfe\xba\xbe\0\0\0\x01");
+        data[8..12].copy_from_slice(&0x0100_000cu32.to_be_bytes());
+        data[16..20].copy_from_slice(&0x1000u32.to_be_bytes());
+        data[20..24].copy_from_slice(&(image.len() as u32).to_be_bytes());
+        data.extend_from_slice(&image);
+
+        let executable = Executable::parse(&mut Cursor::new(data)).unwrap().unwrap();
+        assert_eq!(
+            executable.description,
+            "Mach-O universal binary with 1 architecture: arm64"
+        );
+        assert_eq!(executable.section("__TEXT,__text").unwrap().offset, 0x1100);
+        assert!(executable
+            .rows
+            .contains(&(0x1000, "architecture arm64 (0x200 bytes)".to_string())));
+        assert!(executable
+            .rows
+            .contains(&(0, "fat header (1 architecture)".to_string())));
+    }
+
+    #[test]
+    fn test_java_class_is_not_fat() {
+        let class = b"\xca\xfe\xba\xbe\x00\x00\x00\x34rest of the class file";
+        assert!(!is_fat_header(class));
+        assert!(Executable::parse(&mut Cursor::new(class.to_vec()))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_control_characters_in_names() {
+        let mut data = tiny_macho();
+        data[40..46].copy_from_slice(b"\x1b[2J\0\0");
+        data[104..110].copy_from_slice(b"a\x07b\0\0\0");
+        let executable = Executable::parse(&mut Cursor::new(data)).unwrap().unwrap();
+        assert_eq!(executable.sections[0].name, "__TEXT,a\\u{7}b");
+        assert_eq!(
+            executable.rows[0].1,
+            "segment \\u{1b}[2J (R-X, 0x200 bytes at 0x100000000)"
+        );
+    }
+
+    #[test]
+    fn test_broken_load_command() {
+        let mut data = tiny_macho();
+        data[36..40].copy_from_slice(&4u32.to_le_bytes());
+        let error = Executable::parse(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid executable: load command 0 has an invalid size of 4 bytes"
+        );
+    }
+}
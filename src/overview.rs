@@ -0,0 +1,297 @@
+use crate::byte_to_color::{entropy, ByteToColor};
+use crate::error::Result;
+use crate::hex_formatter::HexFormatter;
+
+/// Summary of a block of the input, shown as a single cell of the overview map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockSummary {
+    /// Shannon entropy in bits per byte, between 0 and 8
+    pub entropy: f64,
+    /// Color ID of the most common byte class, see `ByteToColor::id`
+    pub dominant_color_id: u8,
+    /// Every byte of the block is 0x00
+    pub all_zero: bool,
+    /// Every byte of the block is 0xFF
+    pub all_ff: bool,
+}
+
+impl BlockSummary {
+    /// Summarizes `block`, which must not be empty.
+    pub fn of(block: &[u8], byte_to_color: &ByteToColor) -> Self {
+        let mut id_counts = [0usize; 256];
+        for &byte in block {
+            id_counts[byte_to_color.id(byte) as usize] += 1;
+        }
+        // Ties are resolved in favor of the lower color ID
+        let dominant_color_id = (0..=255u8)
+            .rev()
+            .max_by_key(|&id| id_counts[id as usize])
+            .unwrap_or(0);
+
+        Self {
+            entropy: entropy(block),
+            dominant_color_id,
+            all_zero: block.iter().all(|&byte| byte == 0x00),
+            all_ff: block.iter().all(|&byte| byte == 0xff),
+        }
+    }
+
+    /// Returns the character of the cell: the entropy as bar height, or a marker for blocks
+    /// that consist of a single sentinel value.
+    pub fn glyph(&self) -> &'static str {
+        const LEVELS: [&str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+        if self.all_zero {
+            OverviewMap::ALL_ZERO
+        } else if self.all_ff {
+            OverviewMap::ALL_FF
+        } else {
+            LEVELS[((self.entropy / 8.0 * 8.0) as usize).min(7)]
+        }
+    }
+}
+
+/// Prints a compact map of the whole input, where each cell summarizes a block of bytes.
+///
+/// Each row starts with the offset of its first block, so interesting regions can be dumped
+/// with `--skip`:
+///
+/// ```text
+/// 00000000 │ ▃▅▆▆▆▅████████████▇▆▃▃▂····························
+/// 00080000 │ ·······································▅▆▆▅▅▄▄▃▂▂▁▁
+/// ```
+pub struct OverviewMap {
+    hex_formatter: HexFormatter,
+    byte_to_color: ByteToColor,
+    block_size: usize,
+    columns: usize,
+    start_offset: usize,
+}
+
+impl OverviewMap {
+    /// Cell of a block where every byte is 0x00.
+    pub const ALL_ZERO: &'static str = "·";
+
+    /// Cell of a block where every byte is 0xFF.
+    pub const ALL_FF: &'static str = "×";
+
+    /// Upper limit for the number of cells per row, more get hard to follow.
+    pub const MAX_COLUMNS: usize = 64;
+
+    /// Creates a map with `columns` cells per row that each summarize `block_size` bytes.
+    pub fn new(block_size: usize, columns: usize) -> Self {
+        Self {
+            hex_formatter: HexFormatter::new(),
+            byte_to_color: ByteToColor::new(),
+            block_size: block_size.max(1),
+            columns: columns.max(1),
+            start_offset: 0,
+        }
+    }
+
+    /// Creates a map that shows `input_size` bytes in at most `width` x `height` characters.
+    ///
+    /// The block size is a power of two, so the row offsets are round numbers.
+    pub fn with_terminal_size(input_size: u64, width: usize, height: usize) -> Self {
+        let hex_formatter = HexFormatter::new();
+
+        // Offset, " │ " and the cells; title and legend take up 3 lines
+        let available = width.saturating_sub(hex_formatter.offset_width() + 3);
+        let columns = (available / 8 * 8).clamp(8, Self::MAX_COLUMNS);
+        let rows = height.saturating_sub(3).max(1) as u64;
+
+        let cells = rows * columns as u64;
+        let block_size = ((input_size + cells - 1) / cells)
+            .next_power_of_two()
+            .max(16);
+        Self::new(block_size as usize, columns)
+    }
+
+    /// Replaces the hex formatter used for the row offsets.
+    pub fn with_hex_formatter(mut self, hex_formatter: HexFormatter) -> Self {
+        self.hex_formatter = hex_formatter;
+        self
+    }
+
+    /// Replaces the byte colors, the cells use the color of their most common byte class.
+    pub fn with_byte_to_color(mut self, byte_to_color: ByteToColor) -> Self {
+        self.byte_to_color = byte_to_color;
+        self
+    }
+
+    /// Sets the offset shown for the first block.
+    pub fn with_start_offset(mut self, start_offset: usize) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+
+    /// Returns the number of bytes summarized by a single cell.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Summarizes all blocks of `reader`, in parallel.
+    pub fn summarize<R: std::io::Read>(&self, mut reader: R) -> Result<Vec<BlockSummary>> {
+        use rayon::prelude::*;
+
+        const MIN_READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+        let blocks_per_read = (MIN_READ_BUFFER_SIZE + self.block_size - 1) / self.block_size;
+        let mut buffer = vec![0u8; blocks_per_read * self.block_size];
+        let mut summaries = Vec::new();
+
+        loop {
+            let total_read = crate::read_full(&mut reader, &mut buffer)?;
+            if total_read == 0 {
+                break;
+            }
+
+            let chunk_summaries: Vec<BlockSummary> = buffer[..total_read]
+                .par_chunks(self.block_size)
+                .map(|block| BlockSummary::of(block, &self.byte_to_color))
+                .collect();
+            summaries.extend(chunk_summaries);
+
+            if total_read < buffer.len() {
+                break;
+            }
+        }
+        Ok(summaries)
+    }
+
+    /// Writes the map of all data in `reader`: the title, one row per `columns` blocks, and
+    /// a legend.
+    pub fn write<R: std::io::Read, W: std::io::Write>(
+        &self,
+        title: &str,
+        reader: R,
+        mut writer: W,
+    ) -> Result<()> {
+        let summaries = self.summarize(reader)?;
+
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(title.as_bytes());
+        buffer.push(b'\n');
+
+        for (row, cells) in summaries.chunks(self.columns).enumerate() {
+            let byte_offset = self.start_offset + row * self.columns * self.block_size;
+            self.write_row(&mut buffer, byte_offset, cells);
+        }
+
+        self.write_legend(&mut buffer);
+        writer.write_all(&buffer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes a single row of cells, starting at `byte_offset`.
+    fn write_row(&self, buffer: &mut Vec<u8>, byte_offset: usize, cells: &[BlockSummary]) {
+        self.hex_formatter.write_offset(buffer, byte_offset);
+        buffer.extend_from_slice(" │ ".as_bytes());
+
+        let mut previous_color_id = 0;
+        for cell in cells {
+            // Zeros and 0xFF are sentinels, they always use the sentinel color
+            let color_id = if cell.all_zero || cell.all_ff {
+                self.byte_to_color.id(0x00)
+            } else {
+                cell.dominant_color_id
+            };
+            if color_id != previous_color_id {
+                let (escape, len) = self.byte_to_color.escape(color_id);
+                buffer.extend_from_slice(&escape[..len]);
+                previous_color_id = color_id;
+            }
+            buffer.extend_from_slice(cell.glyph().as_bytes());
+        }
+        if previous_color_id != 0 {
+            let (escape, len) = self.byte_to_color.escape(0);
+            buffer.extend_from_slice(&escape[..len]);
+        }
+        buffer.push(b'\n');
+    }
+
+    fn write_legend(&self, buffer: &mut Vec<u8>) {
+        let legend = format!(
+            "1 cell = {}   ▁▂▃▄▅▆▇█ entropy   {} all 0x00   {} all 0xff   color: most common byte class\n",
+            size::Size::from_bytes(self.block_size),
+            Self::ALL_ZERO,
+            Self::ALL_FF
+        );
+        buffer.extend_from_slice(legend.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_to_color::Theme;
+
+    fn plain_map(block_size: usize, columns: usize) -> OverviewMap {
+        OverviewMap::new(block_size, columns)
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap())
+    }
+
+    #[test]
+    fn test_block_summary() {
+        let btc = ByteToColor::new();
+
+        let zeros = BlockSummary::of(&[0u8; 32], &btc);
+        assert!(zeros.all_zero && !zeros.all_ff);
+        assert_eq!(zeros.glyph(), OverviewMap::ALL_ZERO);
+
+        let ff = BlockSummary::of(&[0xffu8; 32], &btc);
+        assert!(ff.all_ff && !ff.all_zero);
+        assert_eq!(ff.glyph(), OverviewMap::ALL_FF);
+
+        let text = BlockSummary::of(b"hello, world of text", &btc);
+        assert_eq!(text.dominant_color_id, btc.id(b'a'));
+
+        let all: Vec<u8> = (0..=255u8).collect();
+        let random = BlockSummary::of(&all, &btc);
+        assert_eq!(random.entropy, 8.0);
+        assert_eq!(random.dominant_color_id, btc.id(0x80));
+        assert_eq!(random.glyph(), "█");
+    }
+
+    #[test]
+    fn test_with_terminal_size() {
+        // 80 - 11 = 69 characters for cells, so 64 columns; 21 rows
+        let map = OverviewMap::with_terminal_size(1024 * 1024, 80, 24);
+        assert_eq!(map.columns, 64);
+        assert_eq!(map.block_size(), 1024);
+
+        // Small inputs and terminals
+        let map = OverviewMap::with_terminal_size(100, 20, 2);
+        assert_eq!(map.columns, 8);
+        assert_eq!(map.block_size(), 16);
+    }
+
+    #[test]
+    fn test_summarize_across_reads() {
+        let data: Vec<u8> = (0..3 * 1024 * 1024 + 10).map(|i| (i % 7) as u8).collect();
+        let map = plain_map(4096, 8);
+        let summaries = map.summarize(&data[..]).unwrap();
+        assert_eq!(summaries.len(), (data.len() + 4095) / 4096);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut data = vec![0u8; 64];
+        data.extend_from_slice(&[0xff; 32]);
+        data.extend(0..=255u8);
+
+        let mut output = Vec::new();
+        plain_map(32, 4)
+            .write("title", &data[..], &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "title");
+        assert_eq!(lines[1], "00000000 │ ··×▆");
+        assert_eq!(lines[2], "00000080 │ ▆▆▆▆");
+        assert_eq!(lines[3], "00000100 │ ▆▆▆");
+        assert!(lines[4].starts_with("1 cell = 32 bytes"));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::error::{HexlerError, Result};
+use crate::highlight::{Highlight, Highlights};
+use crate::line_writer::{Border, LineWriter};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// Fills are written in chunks of about this many bytes.
+const WRITE_CHUNK_LEN: usize = 64 * 1024;
+
+/// Changed ranges of more lines are shown with their first and last lines only.
+const MAX_SHOWN_LINES: u64 = 32;
+
+/// Bytes that are written at an offset of a file: `pattern`, repeated until `len` bytes are
+/// written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub offset: u64,
+    pub len: u64,
+    pub pattern: Vec<u8>,
+}
+
+impl Patch {
+    /// Creates a patch that writes `bytes` at `offset`.
+    pub fn new(offset: u64, bytes: Vec<u8>) -> Self {
+        Self {
+            offset,
+            len: bytes.len() as u64,
+            pattern: bytes,
+        }
+    }
+
+    /// Creates a patch that fills `range` with `pattern`, repeated as often as needed. The
+    /// bytes are only made while they are written, so the range can be larger than memory.
+    pub fn fill(range: Range<u64>, pattern: &[u8]) -> Self {
+        Self {
+            offset: range.start,
+            len: range.end.saturating_sub(range.start),
+            pattern: pattern.to_vec(),
+        }
+    }
+
+    /// Offset after the last byte of the patch, see `check_end` for patches of user input.
+    pub fn end(&self) -> u64 {
+        self.offset.saturating_add(self.len)
+    }
+
+    /// Offsets of the bytes that the patch writes.
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.end()
+    }
+}
+
+/// Checks that `len` bytes at `offset` end at an offset that fits in 64 bits.
+pub fn check_end(offset: u64, len: u64) -> std::result::Result<u64, String> {
+    offset.checked_add(len).ok_or_else(|| {
+        format!(
+            "{} at 0x{:x} end past the largest possible offset",
+            num_bytes(len),
+            offset
+        )
+    })
+}
+
+/// Parses bytes given as hex digits, e.g. `de ad be ef` or `deadbeef`.
+pub fn parse_bytes(text: &str) -> std::result::Result<Vec<u8>, String> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() {
+        return Err("no bytes given".to_string());
+    }
+    if digits.len() % 2 != 0 {
+        return Err(format!("odd number of hex digits in '{}'", text.trim()));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let byte: String = pair.iter().collect();
+            u8::from_str_radix(&byte, 16).map_err(|_| format!("invalid hex byte '{}'", byte))
+        })
+        .collect()
+}
+
+/// Parses a patch file: one patch per line, an offset and the hex bytes written there, or a
+/// range and the bytes it is filled with. `#` starts a comment.
+///
+/// ```text
+/// # serial number
+/// 0x1f0: de ad be ef
+/// 0x100..0x200: 00
+/// ```
+pub fn parse_patches(text: &str) -> Result<Vec<Patch>> {
+    let mut patches = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid =
+            |message: String| HexlerError::InvalidPatch(format!("line {}: {}", i + 1, message));
+
+        let Some((position, bytes)) = line.split_once(':') else {
+            return Err(invalid(format!(
+                "expected 'offset: bytes' or 'start..end: bytes', got '{}'",
+                line
+            )));
+        };
+        let bytes = parse_bytes(bytes).map_err(invalid)?;
+        let patch = if position.contains("..") {
+            Patch::fill(crate::parse_range(position).map_err(invalid)?, &bytes)
+        } else {
+            let offset = crate::parse_offset(position).map_err(invalid)? as u64;
+            check_end(offset, bytes.len() as u64).map_err(invalid)?;
+            Patch::new(offset, bytes)
+        };
+        patches.push(patch);
+    }
+    Ok(patches)
+}
+
+/// Writes `patches` in their order to `file` of `len` bytes, and returns the new length.
+///
+/// Nothing is written when a patch ends past the end of the file, unless `extend` allows the
+/// file to grow. A gap between the end of the file and a patch is filled with zeros.
+pub fn apply<F: Write + Seek>(
+    file: &mut F,
+    len: u64,
+    patches: &[Patch],
+    extend: bool,
+) -> Result<u64> {
+    let end = patches.iter().map(Patch::end).max().unwrap_or(0);
+    if end > len && !extend {
+        return Err(HexlerError::PatchPastEnd { end, len });
+    }
+    for patch in patches.iter().filter(|patch| !patch.pattern.is_empty()) {
+        // Whole patterns per chunk, so every chunk starts with the first byte of the pattern
+        let pattern_len = patch.pattern.len();
+        let chunk_len = pattern_len * (WRITE_CHUNK_LEN / pattern_len).max(1);
+        let chunk: Vec<u8> = patch
+            .pattern
+            .iter()
+            .copied()
+            .cycle()
+            .take(chunk_len.min(patch.len as usize))
+            .collect();
+
+        file.seek(SeekFrom::Start(patch.offset))?;
+        let mut remaining = patch.len;
+        while remaining > 0 {
+            let take = remaining.min(chunk.len() as u64) as usize;
+            file.write_all(&chunk[..take])?;
+            remaining -= take as u64;
+        }
+    }
+    file.flush()?;
+    Ok(len.max(end))
+}
+
+/// The lines of `bytes_per_line` bytes with offsets in `changed`, adjacent lines are merged.
+///
+/// Of more than `MAX_SHOWN_LINES` adjacent lines only the first and last lines are kept, so
+/// large fills don't make a large dump.
+pub fn affected_lines(changed: &[Range<u64>], bytes_per_line: usize) -> Vec<Range<u64>> {
+    let line = bytes_per_line as u64;
+    // The last line can end past the largest offset, it is cut there
+    let line_end = |end: u64| match end % line {
+        0 => end,
+        rest => end.saturating_add(line - rest),
+    };
+    let mut ranges: Vec<Range<u64>> = changed
+        .iter()
+        .filter(|range| !range.is_empty())
+        .map(|range| range.start / line * line..line_end(range.end))
+        .collect();
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<u64>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    let half = MAX_SHOWN_LINES / 2 * line;
+    merged
+        .into_iter()
+        .flat_map(|range| match range.end - range.start > 2 * half {
+            true => vec![range.start..range.start + half, range.end - half..range.end],
+            false => vec![range],
+        })
+        .collect()
+}
+
+/// Highlights of the offsets in `changed`, with `background`.
+pub fn highlights(changed: &[Range<u64>], background: &str) -> Highlights {
+    let mut highlights = Highlights::new();
+    for range in changed.iter().filter(|range| !range.is_empty()) {
+        highlights.add(Highlight::new(range.clone(), background));
+    }
+    highlights
+}
+
+/// Reads the data of `lines` from `reader`, up to its end.
+pub fn read_lines<R: Read + Seek>(
+    reader: &mut R,
+    lines: &[Range<u64>],
+) -> Result<Vec<(u64, Vec<u8>)>> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let mut data = Vec::with_capacity(lines.len());
+    for range in lines {
+        // Only the bytes up to the end are read, the lines can be far past it
+        let mut bytes = Vec::new();
+        if range.start >= len {
+            data.push((range.start, bytes));
+            continue;
+        }
+        reader.seek(SeekFrom::Start(range.start))?;
+        reader
+            .by_ref()
+            .take(range.end - range.start)
+            .read_to_end(&mut bytes)?;
+        data.push((range.start, bytes));
+    }
+    Ok(data)
+}
+
+/// Writes a hex dump of `lines` that are read with `read_lines`, between borders with `title`.
+/// A separator row with the number of skipped bytes is written between lines that aren't
+/// adjacent, the bytes are unchanged unless they overlap `changed`.
+pub fn write_lines<W: Write>(
+    writer: &mut W,
+    line_writer: &LineWriter,
+    title: &str,
+    lines: &[(u64, Vec<u8>)],
+    changed: &[Range<u64>],
+) -> Result<()> {
+    let bytes_per_line = line_writer.bytes_per_line();
+    let mut buffer = Vec::new();
+    line_writer.write_border(&mut buffer, Border::Header, title)?;
+
+    let mut previous_end = None;
+    for (offset, data) in lines.iter().filter(|(_, data)| !data.is_empty()) {
+        if let Some(end) = previous_end.filter(|&end| end < *offset) {
+            let skipped_changes = changed
+                .iter()
+                .any(|range| range.start < *offset && end < range.end);
+            let label = match skipped_changes {
+                true => format!("{} not shown", num_bytes(offset - end)),
+                false => format!("{} unchanged", num_bytes(offset - end)),
+            };
+            line_writer.write_separator(&mut buffer, *offset as usize, &label);
+        }
+        for (i, line) in data.chunks(bytes_per_line).enumerate() {
+            line_writer.write_line(&mut buffer, *offset as usize + i * bytes_per_line, line);
+        }
+        previous_end = Some(offset + data.len() as u64);
+    }
+
+    line_writer.write_border(&mut buffer, Border::Footer, "")?;
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+/// Formats a number of bytes, e.g. "1 byte" or "16 bytes".
+pub fn num_bytes(len: u64) -> String {
+    match len {
+        1 => "1 byte".to_string(),
+        _ => format!("{} bytes", len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("de ad be ef"), Ok(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(
+            parse_bytes(" DEAD\tbeef "),
+            Ok(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(parse_bytes("00"), Ok(vec![0]));
+        assert!(parse_bytes("").is_err());
+        assert!(parse_bytes("abc").unwrap_err().contains("odd number"));
+        assert!(parse_bytes("zz").unwrap_err().contains("'zz'"));
+    }
+
+    #[test]
+    fn test_parse_patches() {
+        let text = "# serial number\n0x1f0: de ad be ef\n\n0x4..0x9: 12 34  # pattern\n16: ff\n";
+        let patches = parse_patches(text).unwrap();
+        assert_eq!(
+            patches,
+            vec![
+                Patch::new(0x1f0, vec![0xde, 0xad, 0xbe, 0xef]),
+                Patch::fill(4..9, &[0x12, 0x34]),
+                Patch::new(16, vec![0xff]),
+            ]
+        );
+
+        let err = parse_patches("0x10: 00\n0x20 00\n").unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid patch: line 2: "),
+            "{}",
+            err
+        );
+        let err = parse_patches("0x10: 0\n").unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid patch: line 1: "),
+            "{}",
+            err
+        );
+        let err = parse_patches("0xffffffffffffffff: 00 00\n").unwrap_err();
+        assert!(
+            err.to_string().contains("past the largest possible offset"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut file = Cursor::new(b"0123456789".to_vec());
+        let patches = vec![Patch::fill(2..6, b"\0"), Patch::new(4, b"ab".to_vec())];
+        assert_eq!(apply(&mut file, 10, &patches, false).unwrap(), 10);
+        assert_eq!(file.get_ref(), b"01\0\0ab6789");
+    }
+
+    #[test]
+    fn test_apply_large_fill() {
+        // Chunks of whole patterns keep the pattern going across chunks
+        let len = 3 * WRITE_CHUNK_LEN as u64 + 5;
+        let mut file = Cursor::new(Vec::new());
+        let patches = vec![Patch::fill(1..1 + len, b"abc")];
+        assert_eq!(apply(&mut file, 0, &patches, true).unwrap(), 1 + len);
+        let data = file.get_ref();
+        assert_eq!(data[0], 0);
+        assert!(data[1..].chunks(3).all(|c| c == &b"abc"[..c.len()]));
+
+        // A fill past the end is refused before anything is made or written
+        let patches = vec![Patch::fill(0..0x1000_0000_0000, b"\0")];
+        let err = apply(&mut file, 4, &patches, false).unwrap_err();
+        assert!(matches!(err, HexlerError::PatchPastEnd { .. }));
+    }
+
+    #[test]
+    fn test_apply_past_end() {
+        let mut file = Cursor::new(b"0123".to_vec());
+        let patches = vec![Patch::new(0, b"x".to_vec()), Patch::new(6, b"yz".to_vec())];
+        let err = apply(&mut file, 4, &patches, false).unwrap_err();
+        assert!(matches!(err, HexlerError::PatchPastEnd { end: 8, len: 4 }));
+        assert_eq!(file.get_ref(), b"0123");
+
+        assert_eq!(apply(&mut file, 4, &patches, true).unwrap(), 8);
+        assert_eq!(file.get_ref(), b"x123\0\0yz");
+    }
+
+    #[test]
+    fn test_affected_lines() {
+        let changed = vec![0x1f0..0x1f4, 0x12..0x32, 0x3c..0x44, 0x100..0x100];
+        assert_eq!(affected_lines(&changed, 16), vec![0x10..0x50, 0x1f0..0x200]);
+        assert_eq!(affected_lines(&changed, 8), vec![0x10..0x48, 0x1f0..0x1f8]);
+
+        // Long ranges keep their first and last lines, the last line can't end past u64::MAX
+        assert_eq!(
+            affected_lines(&[0x8..0x10_0000, u64::MAX - 2..u64::MAX], 16),
+            vec![0..0x100, 0xfff00..0x10_0000, u64::MAX - 15..u64::MAX]
+        );
+    }
+
+    #[test]
+    fn test_write_lines() {
+        let line_writer = LineWriter::new_bytes(8).unwrap();
+        let mut file = Cursor::new((0..40u8).collect::<Vec<u8>>());
+        let lines = read_lines(&mut file, &[0..8, 32..48]).unwrap();
+        assert_eq!(lines[1], (32, (32..40).collect()));
+
+        let mut out = Vec::new();
+        write_lines(&mut out, &line_writer, "before", &lines, &[]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("before\n"), "{}", out);
+        assert!(out.contains("24 bytes unchanged"), "{}", out);
+        assert_eq!(out.lines().count(), 6, "{}", out);
+
+        let mut out = Vec::new();
+        write_lines(&mut out, &line_writer, "before", &lines, &[4..12, 30..34]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("24 bytes not shown"), "{}", out);
+    }
+}
@@ -0,0 +1,403 @@
+use crate::error::Result;
+use crate::executable::{
+    entries, highlight_entries, highlight_fields, invalid, permissions, printable_name, read_at,
+    Executable, Fields, Section,
+};
+use crate::highlight::Highlights;
+use std::io::{Read, Seek};
+
+/// Offset and length of the fields of the DOS header that matter: `e_magic` and `e_lfanew`.
+const DOS_HEADER_FIELDS: [(u64, u64); 2] = [(0, 2), (0x3c, 4)];
+
+/// Offset and length of the PE signature and the fields of the COFF file header.
+const NT_HEADER_FIELDS: [(u64, u64); 8] = [
+    (0, 4),
+    (4, 2),
+    (6, 2),
+    (8, 4),
+    (12, 4),
+    (16, 4),
+    (20, 2),
+    (22, 2),
+];
+
+/// Offset and length of the fields of the PE32 optional header, up to the data directories.
+const OPTIONAL_HEADER_FIELDS_32: [(u64, u64); 30] = [
+    (0, 2),
+    (2, 1),
+    (3, 1),
+    (4, 4),
+    (8, 4),
+    (12, 4),
+    (16, 4),
+    (20, 4),
+    (24, 4),
+    (28, 4),
+    (32, 4),
+    (36, 4),
+    (40, 2),
+    (42, 2),
+    (44, 2),
+    (46, 2),
+    (48, 2),
+    (50, 2),
+    (52, 4),
+    (56, 4),
+    (60, 4),
+    (64, 4),
+    (68, 2),
+    (70, 2),
+    (72, 4),
+    (76, 4),
+    (80, 4),
+    (84, 4),
+    (88, 4),
+    (92, 4),
+];
+
+/// Offset and length of the fields of the PE32+ optional header, up to the data directories.
+const OPTIONAL_HEADER_FIELDS_64: [(u64, u64); 29] = [
+    (0, 2),
+    (2, 1),
+    (3, 1),
+    (4, 4),
+    (8, 4),
+    (12, 4),
+    (16, 4),
+    (20, 4),
+    (24, 8),
+    (32, 4),
+    (36, 4),
+    (40, 2),
+    (42, 2),
+    (44, 2),
+    (46, 2),
+    (48, 2),
+    (50, 2),
+    (52, 4),
+    (56, 4),
+    (60, 4),
+    (64, 4),
+    (68, 2),
+    (70, 2),
+    (72, 8),
+    (80, 8),
+    (88, 8),
+    (96, 8),
+    (104, 4),
+    (108, 4),
+];
+
+/// Names of the data directories, by their index.
+const DATA_DIRECTORIES: [&str; 16] = [
+    "export",
+    "import",
+    "resource",
+    "exception",
+    "certificate",
+    "base relocation",
+    "debug",
+    "architecture",
+    "global pointer",
+    "TLS",
+    "load config",
+    "bound import",
+    "import address",
+    "delay import",
+    "CLR runtime",
+    "reserved",
+];
+
+/// Index of the certificate directory, the only one with a file offset instead of an address.
+const CERTIFICATE_DIRECTORY: usize = 4;
+
+/// Size of an entry of the section table.
+const SECTION_HEADER_LEN: u64 = 40;
+
+/// Parses the DOS header, NT headers, data directories and section table.
+///
+/// # Errors
+/// Returns `InvalidExecutable` if there are no NT headers, e.g. in a plain DOS executable, or
+/// the headers point outside of the file.
+pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<Executable> {
+    let fields = Fields::new(false, true);
+    let dos = read_at(reader, 0, 64, "DOS header")?;
+    let pe = fields.u32(&dos, 0x3c);
+
+    let nt = read_at(reader, pe, 24, "NT headers")?;
+    if &nt[..4] != b"PE\0\0" {
+        return Err(invalid(format!("no PE signature at 0x{:x}", pe)));
+    }
+    let num_sections = fields.u16(&nt, 6);
+    let optional_len = fields.u16(&nt, 20);
+
+    // All headers are next to each other, so they are read at once
+    let optional_start = pe + 24;
+    let table_start = optional_start + optional_len;
+    let headers = read_at(
+        reader,
+        0,
+        table_start + num_sections * SECTION_HEADER_LEN,
+        "section table",
+    )?;
+    let description = crate::signatures::describe_pe(&headers)
+        .ok_or_else(|| invalid("unsupported PE header".to_string()))?;
+    let optional = &headers[optional_start as usize..table_start as usize];
+
+    let mut highlights = Highlights::new();
+    let mut header_rows = vec![(0, "DOS header (64 bytes)".to_string())];
+    highlight_fields(&mut highlights, 0, &DOS_HEADER_FIELDS);
+    if pe > 64 {
+        header_rows.push((64, format!("DOS stub ({} bytes)", pe - 64)));
+    }
+    header_rows.push((
+        pe,
+        "NT headers (signature and COFF file header)".to_string(),
+    ));
+    highlight_fields(&mut highlights, pe, &NT_HEADER_FIELDS);
+
+    // Without an optional header, e.g. in object files, there is no image base and no
+    // data directories
+    let mut image_base = 0;
+    let mut directories = &optional[..0];
+    if optional_len >= 2 {
+        let (format, header_fields, directories_start): (_, &[(u64, u64)], u64) =
+            match fields.u16(optional, 0) {
+                0x20b => ("PE32+", &OPTIONAL_HEADER_FIELDS_64, 112),
+                _ => ("PE32", &OPTIONAL_HEADER_FIELDS_32, 96),
+            };
+        header_rows.push((
+            optional_start,
+            format!("optional header ({}, {} bytes)", format, optional_len),
+        ));
+        let header_fields: Vec<(u64, u64)> = header_fields
+            .iter()
+            .copied()
+            .filter(|&(pos, len)| pos + len <= optional_len)
+            .collect();
+        highlight_fields(&mut highlights, optional_start, &header_fields);
+
+        if optional_len >= directories_start {
+            image_base = match format {
+                "PE32+" => fields.u64(optional, 24),
+                _ => fields.u32(optional, 28),
+            };
+            let num = fields
+                .u32(optional, directories_start as usize - 4)
+                .min((optional_len - directories_start) / 8)
+                .min(DATA_DIRECTORIES.len() as u64);
+            directories = &optional[directories_start as usize..][..num as usize * 8];
+            header_rows.push((
+                optional_start + directories_start,
+                format!("data directories ({})", entries(num)),
+            ));
+            highlight_entries(&mut highlights, optional_start + directories_start, num, 8);
+        }
+    }
+
+    let mut executable = Executable {
+        description,
+        ..Executable::default()
+    };
+    let mut ranges = Vec::new();
+    if num_sections > 0 {
+        header_rows.push((
+            table_start,
+            format!("section table ({})", entries(num_sections)),
+        ));
+        highlight_entries(
+            &mut highlights,
+            table_start,
+            num_sections,
+            SECTION_HEADER_LEN,
+        );
+    }
+    for entry in headers[table_start as usize..].chunks_exact(SECTION_HEADER_LEN as usize) {
+        let name_len = entry[..8].iter().position(|&b| b == 0).unwrap_or(8);
+        let virtual_size = fields.u32(entry, 8);
+        let address = fields.u32(entry, 12);
+        let raw_size = fields.u32(entry, 16);
+        let raw_offset = fields.u32(entry, 20);
+        let characteristics = fields.u32(entry, 36);
+
+        let content = match characteristics {
+            c if c & 0x20 != 0 => "code",
+            c if c & 0x40 != 0 => "data",
+            c if c & 0x80 != 0 => "uninitialized data",
+            _ => "other",
+        };
+        let section = Section {
+            name: printable_name(&entry[..name_len]),
+            kind: format!(
+                "{}, {}",
+                content,
+                permissions(
+                    characteristics & 0x4000_0000 != 0,
+                    characteristics & 0x8000_0000 != 0,
+                    characteristics & 0x2000_0000 != 0
+                )
+            ),
+            offset: raw_offset,
+            size: if raw_offset == 0 { 0 } else { raw_size },
+            address: image_base + address,
+        };
+        if section.size > 0 {
+            executable.rows.push((
+                section.offset,
+                format!(
+                    "{} ({}, 0x{:x} bytes)",
+                    section.name, section.kind, section.size
+                ),
+            ));
+            ranges.push((address, virtual_size.max(raw_size), raw_offset));
+        }
+        executable.sections.push(section);
+    }
+
+    // Data directories are given by their address, which is in one of the sections
+    for (idx, entry) in directories.chunks_exact(8).enumerate() {
+        let address = fields.u32(entry, 0);
+        let size = fields.u32(entry, 4);
+        if address == 0 || size == 0 {
+            continue;
+        }
+        let offset = match idx {
+            CERTIFICATE_DIRECTORY => Some(address),
+            _ => ranges
+                .iter()
+                .find(|(start, len, _)| (*start..start + len).contains(&address))
+                .map(|(start, _, raw_offset)| raw_offset + address - start),
+        };
+        if let Some(offset) = offset {
+            executable.rows.push((
+                offset,
+                format!("{} directory (0x{:x} bytes)", DATA_DIRECTORIES[idx], size),
+            ));
+        }
+    }
+
+    executable.rows.extend(header_rows);
+    executable.highlights = highlights;
+    Ok(executable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Builds a tiny PE32+ executable with a `.text` and a `.rdata` section that contains
+    /// the import directory.
+    fn tiny_pe() -> Vec<u8> {
+        let mut data = vec![0u8; 0x600];
+        data[..2].copy_from_slice(b"MZ");
+        data[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+
+        let pe = 0x80;
+        data[pe..pe + 4].copy_from_slice(b"PE\0\0");
+        data[pe + 4..pe + 6].copy_from_slice(&0x8664u16.to_le_bytes());
+        data[pe + 6..pe + 8].copy_from_slice(&2u16.to_le_bytes());
+        data[pe + 20..pe + 22].copy_from_slice(&0xf0u16.to_le_bytes());
+        data[pe + 22..pe + 24].copy_from_slice(&0x22u16.to_le_bytes());
+
+        let optional = pe + 24;
+        data[optional..optional + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+        data[optional + 24..optional + 32].copy_from_slice(&0x1_4000_0000u64.to_le_bytes());
+        data[optional + 108..optional + 112].copy_from_slice(&16u32.to_le_bytes());
+        // import directory at 0x2010, inside of .rdata
+        data[optional + 120..optional + 124].copy_from_slice(&0x2010u32.to_le_bytes());
+        data[optional + 124..optional + 128].copy_from_slice(&0x28u32.to_le_bytes());
+
+        let sections = [
+            (
+                ".text",
+                0x1f0u32,
+                0x1000u32,
+                0x200u32,
+                0x200u32,
+                0x6000_0020u32,
+            ),
+            (".rdata", 0x80, 0x2000, 0x200, 0x400, 0x4000_0040),
+        ];
+        for (idx, (name, vsize, address, raw_size, raw_offset, characteristics)) in
+            sections.iter().enumerate()
+        {
+            let entry = &mut data[0x188 + idx * 40..0x188 + (idx + 1) * 40];
+            entry[..name.len()].copy_from_slice(name.as_bytes());
+            entry[8..12].copy_from_slice(&vsize.to_le_bytes());
+            entry[12..16].copy_from_slice(&address.to_le_bytes());
+            entry[16..20].copy_from_slice(&raw_size.to_le_bytes());
+            entry[20..24].copy_from_slice(&raw_offset.to_le_bytes());
+            entry[36..40].copy_from_slice(&characteristics.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_tiny_pe() {
+        let executable = Executable::parse(&mut Cursor::new(tiny_pe()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            executable.description,
+            "PE32+ executable, x86-64, 2 sections"
+        );
+
+        let text = executable.section(".text").unwrap();
+        assert_eq!(text.kind, "code, R-X");
+        assert_eq!(
+            (text.offset, text.size, text.address),
+            (0x200, 0x200, 0x1_4000_1000)
+        );
+
+        let rows: Vec<(u64, &str)> = executable
+            .rows
+            .iter()
+            .map(|(offset, label)| (*offset, label.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (0x200, ".text (code, R-X, 0x200 bytes)"),
+                (0x400, ".rdata (data, R--, 0x200 bytes)"),
+                (0x410, "import directory (0x28 bytes)"),
+                (0, "DOS header (64 bytes)"),
+                (0x40, "DOS stub (64 bytes)"),
+                (0x80, "NT headers (signature and COFF file header)"),
+                (0x98, "optional header (PE32+, 240 bytes)"),
+                (0x108, "data directories (16 entries)"),
+                (0x188, "section table (2 entries)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_highlights() {
+        let executable = Executable::parse(&mut Cursor::new(tiny_pe()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(executable.highlights.overlapping(0..0x40).len(), 2);
+        assert_eq!(executable.highlights.overlapping(0x80..0x98).len(), 8);
+        assert_eq!(executable.highlights.overlapping(0x108..0x188).len(), 16);
+        assert_eq!(executable.highlights.overlapping(0x188..0x1d8).len(), 2);
+    }
+
+    #[test]
+    fn test_control_characters_in_names() {
+        let mut data = tiny_pe();
+        data[0x188..0x18d].copy_from_slice(b"\x1b[2J\x07");
+        let executable = Executable::parse(&mut Cursor::new(data)).unwrap().unwrap();
+        assert_eq!(executable.sections[0].name, "\\u{1b}[2J\\u{7}");
+    }
+
+    #[test]
+    fn test_dos_executable() {
+        let mut data = tiny_pe();
+        data[0x80..0x84].copy_from_slice(b"\0\0\0\0");
+        let error = Executable::parse(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid executable: no PE signature at 0x80"
+        );
+    }
+}
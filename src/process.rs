@@ -0,0 +1,400 @@
+use crate::error::{HexlerError, Result};
+use crate::executable::printable_name;
+use crate::line_writer::{Border, LineWriter};
+use crate::renderer::Renderer;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// Unreadable memory is skipped in pages of this size, the smallest page size of common
+/// platforms.
+const PAGE_SIZE: u64 = 4096;
+
+/// Memory is read in chunks of this size, as long as it is readable.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// A mapping of a process, a line of `/proc/<pid>/maps`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub range: Range<u64>,
+    /// Permissions like `rw-p`
+    pub permissions: String,
+    /// The mapped file or a name like `[heap]`, empty for anonymous mappings
+    pub name: String,
+}
+
+/// Parses the text of `/proc/<pid>/maps`, lines that can't be parsed are skipped.
+pub fn parse_maps(text: &str) -> Vec<Region> {
+    text.lines()
+        .filter_map(|line| {
+            // address perms offset dev inode name, the name can contain spaces
+            let mut fields = line.splitn(6, ' ');
+            let (start, end) = fields.next()?.split_once('-')?;
+            let permissions = fields.next()?.to_string();
+            let name = printable_name(fields.nth(3).unwrap_or_default().trim().as_bytes());
+            Some(Region {
+                range: u64::from_str_radix(start, 16).ok()?..u64::from_str_radix(end, 16).ok()?,
+                permissions,
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Reads the mappings of the process `pid`.
+pub fn regions(pid: u32) -> Result<Vec<Region>> {
+    match std::fs::read_to_string(format!("/proc/{}/maps", pid)) {
+        Ok(text) => Ok(parse_maps(&text)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            Err(HexlerError::ProcessNotFound(pid))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Opens the memory of the process `pid` for reading, with the addresses as offsets.
+pub fn open_memory(pid: u32) -> Result<std::fs::File> {
+    Ok(std::fs::File::open(format!("/proc/{}/mem", pid))?)
+}
+
+/// The name of the process `pid`, e.g. `bash`. The process sets it itself, so control
+/// characters are escaped.
+pub fn command_name(pid: u32) -> Option<String> {
+    let name = std::fs::read(format!("/proc/{}/comm", pid)).ok()?;
+    Some(printable_name(name.strip_suffix(b"\n").unwrap_or(&name)))
+}
+
+/// The addresses of the mappings named `name`, e.g. `[heap]`, `heap`, `libc.so.6` or a full
+/// path. A file that is mapped several times is covered from its first to its last mapping.
+pub fn find_region(regions: &[Region], name: &str) -> Option<Range<u64>> {
+    let matches = |region: &&Region| {
+        !region.name.is_empty()
+            && (region.name == name
+                || region.name == format!("[{}]", name)
+                || region.name.rsplit('/').next() == Some(name))
+    };
+    let start = regions
+        .iter()
+        .filter(matches)
+        .map(|r| r.range.start)
+        .min()?;
+    let end = regions.iter().filter(matches).map(|r| r.range.end).max()?;
+    Some(start..end)
+}
+
+/// The mapping that contains `addr`.
+pub fn region_at(regions: &[Region], addr: u64) -> Option<&Region> {
+    regions.iter().find(|region| region.range.contains(&addr))
+}
+
+/// Hex dump of memory that can contain unreadable pages, e.g. of a process through
+/// `/proc/<pid>/mem`. Runs of unreadable pages are shown as a single marker row, and runs of
+/// identical lines are squeezed when the `LineWriter` squeezes.
+///
+/// ```text
+/// 7ffd2a000ff0 │ 00 00 00 00 00 00 00 00  2f 75 73 72 2f 62 69 6e │ ⋄⋄⋄⋄⋄⋄⋄⋄/usr/bin
+/// 7ffd2a001000 ├── unreadable, 2 pages ──────────────────────────────────────
+/// ```
+pub struct MemoryDump {
+    line_writer: LineWriter,
+}
+
+impl MemoryDump {
+    /// Creates a dump with the lines of `line_writer`, the offset column shows the addresses.
+    pub fn new(line_writer: LineWriter) -> Self {
+        Self { line_writer }
+    }
+
+    /// Dumps the addresses `range` of `memory`, which is read at the addresses as offsets.
+    pub fn write<M: Read + Seek, W: Write>(
+        &self,
+        writer: &mut W,
+        title: &str,
+        memory: &mut M,
+        range: Range<u64>,
+    ) -> Result<()> {
+        let mut lines = Lines {
+            line_writer: &self.line_writer,
+            squeeze: self.line_writer.squeeze(),
+            buffer: Vec::new(),
+            line: Vec::new(),
+            line_addr: range.start,
+            previous: None,
+            in_squeezed_run: false,
+        };
+        self.line_writer
+            .write_border(&mut lines.buffer, Border::Header, title)?;
+
+        let mut chunk = vec![0u8; CHUNK_SIZE as usize];
+        let mut unreadable_start = None;
+        let mut addr = range.start;
+        while addr < range.end {
+            // A read stops before the first unreadable page, and fails when it starts there
+            let chunk_end = next_page(addr)
+                .saturating_add(CHUNK_SIZE - PAGE_SIZE)
+                .min(range.end);
+            let len = (chunk_end - addr) as usize;
+            let read = memory
+                .seek(SeekFrom::Start(addr))
+                .and_then(|_| memory.read(&mut chunk[..len]));
+            match read {
+                Ok(read) if read > 0 => {
+                    if let Some(start) = unreadable_start.take() {
+                        lines.unreadable(start, addr);
+                    }
+                    lines.add(addr, &chunk[..read]);
+                    addr += read as u64;
+                }
+                // Reading past a mapping fails with an error or returns nothing
+                _ => {
+                    lines.flush();
+                    unreadable_start.get_or_insert(addr);
+                    addr = next_page(addr).min(range.end);
+                }
+            }
+            writer.write_all(&lines.buffer)?;
+            lines.buffer.clear();
+        }
+        lines.flush();
+        if let Some(start) = unreadable_start {
+            lines.unreadable(start, range.end);
+        }
+
+        self.line_writer
+            .write_border(&mut lines.buffer, Border::Footer, "")?;
+        writer.write_all(&lines.buffer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// The start of the page after the one of `addr`, the highest page has none.
+fn next_page(addr: u64) -> u64 {
+    (addr / PAGE_SIZE * PAGE_SIZE).saturating_add(PAGE_SIZE)
+}
+
+/// Collects readable bytes into lines and writes them.
+struct Lines<'a> {
+    line_writer: &'a LineWriter,
+    squeeze: bool,
+    buffer: Vec<u8>,
+    /// Bytes of the current line, and the address of its first byte
+    line: Vec<u8>,
+    line_addr: u64,
+    previous: Option<Vec<u8>>,
+    in_squeezed_run: bool,
+}
+
+impl Lines<'_> {
+    /// Adds the bytes `data` that were read at `addr`.
+    fn add(&mut self, mut addr: u64, mut data: &[u8]) {
+        let bytes_per_line = self.line_writer.bytes_per_line();
+        while !data.is_empty() {
+            if self.line.is_empty() {
+                self.line_addr = addr;
+            }
+            let take = data.len().min(bytes_per_line - self.line.len());
+            self.line.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            addr += take as u64;
+            if self.line.len() == bytes_per_line {
+                self.flush();
+            }
+        }
+    }
+
+    /// Writes the current line, also when it isn't complete.
+    fn flush(&mut self) {
+        if self.line.is_empty() {
+            return;
+        }
+        let addr = self.line_addr as usize;
+        let full = self.line.len() == self.line_writer.bytes_per_line();
+        if self.squeeze && full && self.previous.as_ref() == Some(&self.line) {
+            if !self.in_squeezed_run {
+                self.line_writer.write_squeezed(&mut self.buffer, addr);
+                self.in_squeezed_run = true;
+            }
+        } else {
+            self.line_writer
+                .write_line(&mut self.buffer, addr, &self.line);
+            self.in_squeezed_run = false;
+        }
+        self.previous = Some(std::mem::take(&mut self.line));
+    }
+
+    /// Writes the marker row of the unreadable addresses `start..end`.
+    fn unreadable(&mut self, start: u64, end: u64) {
+        let pages = (end - start + PAGE_SIZE - 1) / PAGE_SIZE;
+        let label = match pages {
+            1 => "unreadable, 1 page".to_string(),
+            _ => format!("unreadable, {} pages", pages),
+        };
+        self.line_writer
+            .write_separator(&mut self.buffer, start as usize, &label);
+        self.previous = None;
+        self.in_squeezed_run = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_to_color::{ByteToColor, Theme};
+    use crate::hex_formatter::HexFormatter;
+    use std::io::Cursor;
+
+    const MAPS: &str = "\
+55d4c8a00000-55d4c8a21000 rw-p 00000000 00:00 0                          [heap]
+7f1c2a000000-7f1c2a028000 r--p 00000000 08:01 1234                       /usr/lib/libc.so.6
+7f1c2a028000-7f1c2a1bd000 r-xp 00028000 08:01 1234                       /usr/lib/libc.so.6
+7f1c2a1bd000-7f1c2a215000 r--p 001bd000 08:01 1234                       /usr/lib/libc.so.6
+7f1c2a215000-7f1c2a219000 rw-p 00000000 00:00 0
+7ffd29fe0000-7ffd2a001000 rw-p 00000000 00:00 0                          [stack]
+7ffd2a1f0000-7ffd2a1f4000 r--p 00000000 08:01 99                         /tmp/my file
+";
+
+    fn monochrome(bytes_per_line: usize) -> LineWriter {
+        LineWriter::new_bytes(bytes_per_line)
+            .unwrap()
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap())
+    }
+
+    #[test]
+    fn test_parse_maps() {
+        let regions = parse_maps(MAPS);
+        assert_eq!(regions.len(), 7);
+        assert_eq!(regions[0].range, 0x55d4c8a00000..0x55d4c8a21000);
+        assert_eq!(regions[0].permissions, "rw-p");
+        assert_eq!(regions[0].name, "[heap]");
+        assert_eq!(regions[4].name, "");
+        assert_eq!(regions[6].name, "/tmp/my file");
+
+        let regions = parse_maps("7f00-7f01 r--p 00000000 08:01 42 /tmp/\x1b[2J.so\n");
+        assert_eq!(regions[0].name, "/tmp/\\u{1b}[2J.so");
+    }
+
+    #[test]
+    fn test_find_region() {
+        let regions = parse_maps(MAPS);
+        assert_eq!(
+            find_region(&regions, "[heap]"),
+            Some(0x55d4c8a00000..0x55d4c8a21000)
+        );
+        assert_eq!(
+            find_region(&regions, "stack"),
+            Some(0x7ffd29fe0000..0x7ffd2a001000)
+        );
+        assert_eq!(
+            find_region(&regions, "libc.so.6"),
+            Some(0x7f1c2a000000..0x7f1c2a215000)
+        );
+        assert_eq!(
+            find_region(&regions, "/tmp/my file"),
+            Some(0x7ffd2a1f0000..0x7ffd2a1f4000)
+        );
+        assert_eq!(find_region(&regions, "vdso"), None);
+        assert_eq!(find_region(&regions, ""), None);
+
+        let region = region_at(&regions, 0x7f1c2a028010).unwrap();
+        assert_eq!(region.permissions, "r-xp");
+        assert!(region_at(&regions, 0x1000).is_none());
+    }
+
+    /// Memory of 5 pages at 0x10000 where the pages 1 and 2 can't be read.
+    struct TestMemory {
+        data: Cursor<Vec<u8>>,
+    }
+
+    impl Read for TestMemory {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let page = (self.data.position() - 0x10000) / PAGE_SIZE;
+            if page == 1 || page == 2 {
+                return Err(std::io::Error::from_raw_os_error(5));
+            }
+            // A read stops at an unreadable page
+            let len = buf
+                .len()
+                .min((PAGE_SIZE - self.data.position() % PAGE_SIZE) as usize);
+            let offset = self.data.position() as usize - 0x10000;
+            buf[..len].copy_from_slice(&self.data.get_ref()[offset..offset + len]);
+            self.data.set_position(self.data.position() + len as u64);
+            Ok(len)
+        }
+    }
+
+    impl Seek for TestMemory {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.data.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_unreadable_pages() {
+        let mut memory = TestMemory {
+            data: Cursor::new((0..5 * PAGE_SIZE).map(|i| (i / PAGE_SIZE) as u8).collect()),
+        };
+        let dump = MemoryDump::new(monochrome(16).with_squeeze(true));
+        let mut out = Vec::new();
+        dump.write(&mut out, "memory", &mut memory, 0x10ff8..0x13010)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[0], "memory");
+        assert!(lines[2].starts_with("00010ff8 │ 00 00 00 00 00 00 00 00"));
+        assert!(lines[3].starts_with("00011000 ├── unreadable, 2 pages ──"));
+        assert!(lines[4].starts_with("00013000 │ 03 03 03 03"));
+        assert_eq!(lines.len(), 6, "{}", out);
+    }
+
+    #[test]
+    fn test_squeeze_and_line_addresses() {
+        let mut memory = TestMemory {
+            data: Cursor::new(vec![7u8; 5 * PAGE_SIZE as usize]),
+        };
+        let dump = MemoryDump::new(monochrome(16).with_squeeze(true));
+        let mut out = Vec::new();
+        dump.write(&mut out, "memory", &mut memory, 0x10008..0x10100)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[2].starts_with("00010008 │ 07 07"));
+        assert!(lines[3].starts_with("00010018 │ *"));
+        assert!(lines[4].starts_with("000100f8 │ 07 07 07 07 07 07 07 07"));
+        assert_eq!(lines.len(), 6, "{}", out);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_own_memory() {
+        let data: Vec<u8> = (0..64).collect();
+        let addr = data.as_ptr() as u64;
+        let mut memory = open_memory(std::process::id()).unwrap();
+
+        let mut out = Vec::new();
+        MemoryDump::new(monochrome(16))
+            .write(&mut out, "self", &mut memory, addr..addr + 64)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.contains(&format!("{:x} │ 00 01 02 03", addr)),
+            "{}",
+            out
+        );
+        assert!(
+            out.contains(&format!("{:x} │ 30 31 32 33", addr + 48)),
+            "{}",
+            out
+        );
+
+        // The first page is never mapped
+        let mut out = Vec::new();
+        MemoryDump::new(monochrome(16))
+            .write(&mut out, "self", &mut memory, 0..0x2000)
+            .unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("unreadable, 2 pages"));
+    }
+}
@@ -0,0 +1,85 @@
+/// Returns the part of `data` around `line` that is passed to `write_line_in_context`, with up
+/// to `context_len` bytes before and after the line, and the range of the line within it.
+pub fn context_window(
+    data: &[u8],
+    line: std::ops::Range<usize>,
+    context_len: usize,
+) -> (&[u8], std::ops::Range<usize>) {
+    let start = line.start.saturating_sub(context_len);
+    let end = (line.end + context_len).min(data.len());
+    (&data[start..end], line.start - start..line.end - start)
+}
+
+/// Output format driven by `dump`.
+///
+/// `dump` takes care of reading, splitting the input into lines, formatting lines in parallel
+/// and writing the result in a separate thread. A renderer only decides how each part looks,
+/// so new output formats can reuse the same buffered pipeline. `LineWriter` is the
+/// implementation for the colored terminal output.
+///
+/// All methods append to a buffer that is later written to the output. `write_line` and
+/// `write_squeezed` are called concurrently from multiple threads, and the order of these
+/// calls is not the order of the output, so they must not depend on any state.
+pub trait Renderer: Sync {
+    /// Number of bytes passed to each `write_line` call, except for the last line.
+    fn bytes_per_line(&self) -> usize;
+
+    /// Offset of the first byte, the offsets passed to `write_line` count up from here.
+    fn start_offset(&self) -> usize {
+        0
+    }
+
+    /// Number of bytes before and after each line that `write_line_in_context` needs to see.
+    fn context_len(&self) -> usize {
+        0
+    }
+
+    /// Whether runs of identical lines are collapsed into a single `write_squeezed` call.
+    fn squeeze(&self) -> bool {
+        false
+    }
+
+    /// Writes everything that comes before the first line, called exactly once.
+    fn write_header(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()>;
+
+    /// Writes a single line.
+    ///
+    /// # Arguments
+    /// * `buffer` - Output buffer to append the line to (not cleared, only appended)
+    /// * `byte_offset` - The byte offset of the first byte in `line_data`
+    /// * `line_data` - Byte slice to display (may be less than bytes_per_line for the last line)
+    fn write_line(&self, buffer: &mut Vec<u8>, byte_offset: usize, line_data: &[u8]);
+
+    /// Writes a single line, with access to the bytes around it.
+    ///
+    /// `window[line]` is the line data, and `window` additionally contains up to
+    /// `context_len` bytes before and after it; fewer at the start and end of the input. This
+    /// is what `dump` calls, the default ignores the context and calls `write_line`.
+    fn write_line_in_context(
+        &self,
+        buffer: &mut Vec<u8>,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+    ) {
+        self.write_line(buffer, byte_offset, &window[line]);
+    }
+
+    /// Writes the marker for a run of lines identical to the previous line.
+    ///
+    /// Only called when `squeeze` returns true; `byte_offset` is the offset of the first
+    /// skipped line. The run ends before the next line written with `write_line`.
+    fn write_squeezed(&self, buffer: &mut Vec<u8>, byte_offset: usize) {
+        let _ = (buffer, byte_offset);
+    }
+
+    /// Writes a separator row labelled `label` that refers to `byte_offset`, e.g. the start of
+    /// an embedded file. Called in front of the line that contains `byte_offset`.
+    fn write_separator(&self, buffer: &mut Vec<u8>, byte_offset: usize, label: &str) {
+        let _ = byte_offset;
+        buffer.extend_from_slice(format!("── {} ──\n", label).as_bytes());
+    }
+
+    /// Writes everything that comes after the last line, called exactly once.
+    fn write_footer(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()>;
+}
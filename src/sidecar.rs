@@ -0,0 +1,227 @@
+use crate::error::{HexlerError, Result};
+use crate::highlight::{self, Highlight};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A named range of a file, with an optional color and note.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Offsets like `0x40..0x80`
+    pub range: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Entry {
+    /// Creates an entry of `range` named `label`.
+    pub fn new(range: Range<u64>, label: &str) -> Self {
+        Self {
+            range: format!("0x{:x}..0x{:x}", range.start, range.end),
+            label: label.to_string(),
+            color: None,
+            note: None,
+        }
+    }
+
+    /// Colors the range with one of the colors of `highlight::NAMED_BACKGROUNDS`.
+    pub fn with_color(mut self, color: Option<String>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Adds a note that is shown below the label.
+    pub fn with_note(mut self, note: Option<String>) -> Self {
+        self.note = note;
+        self
+    }
+
+    /// The offsets of the range.
+    pub fn offsets(&self) -> std::result::Result<Range<u64>, String> {
+        crate::parse_range(&self.range)
+    }
+}
+
+/// Annotations of a file that are kept next to it in `<file>.hexler.toml`, e.g. findings while
+/// reverse engineering. hexler loads the sidecar of a file automatically.
+///
+/// ```toml
+/// [[range]]
+/// range = "0x40..0x80"
+/// label = "header"
+/// color = "blue"
+/// note = "checksum at the end"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sidecar {
+    #[serde(default, rename = "range")]
+    pub entries: Vec<Entry>,
+}
+
+impl Sidecar {
+    /// The path of the sidecar of `file`.
+    pub fn path(file: &Path) -> PathBuf {
+        let mut path = file.as_os_str().to_owned();
+        path.push(".hexler.toml");
+        PathBuf::from(path)
+    }
+
+    /// Loads the sidecar of `file`, `None` when there is none.
+    pub fn load(file: &Path) -> Result<Option<Self>> {
+        let path = Self::path(file);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Self::parse(&text)
+            .map(Some)
+            .map_err(|message| HexlerError::InvalidSidecar {
+                path: path.display().to_string(),
+                message,
+            })
+    }
+
+    /// Parses the text of a sidecar, and checks its ranges and colors.
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let sidecar: Self = toml::from_str(text).map_err(|err| err.message().to_string())?;
+        for entry in &sidecar.entries {
+            let context = |message: String| format!("range {}: {}", entry.label, message);
+            entry.offsets().map_err(context)?;
+            if let Some(color) = &entry.color {
+                highlight::named_background(color).map_err(|err| context(err.to_string()))?;
+            }
+        }
+        Ok(sidecar)
+    }
+
+    /// Adds `entry` to the sidecar of `file`, which is created if needed. The entry is
+    /// appended to the text, so comments and formatting of the file are kept.
+    pub fn append(file: &Path, entry: &Entry) -> Result<PathBuf> {
+        use std::io::Write;
+
+        // An invalid sidecar isn't made worse
+        let existing = Self::load(file)?;
+        let text = toml::to_string(&Self {
+            entries: vec![entry.clone()],
+        })
+        .map_err(|err| HexlerError::InvalidSidecar {
+            path: Self::path(file).display().to_string(),
+            message: err.to_string(),
+        })?;
+
+        let path = Self::path(file);
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        if existing.is_some() {
+            f.write_all(b"\n")?;
+        }
+        f.write_all(text.as_bytes())?;
+        Ok(path)
+    }
+
+    /// Backgrounds of the ranges, ranges without a color get the field colors in turn.
+    pub fn highlights(&self) -> Vec<Highlight> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| {
+                let background = match &entry.color {
+                    Some(color) => highlight::named_background(color).ok()?,
+                    None => highlight::FIELD_BACKGROUNDS[idx % highlight::FIELD_BACKGROUNDS.len()],
+                };
+                Some(Highlight::new(entry.offsets().ok()?, background))
+            })
+            .collect()
+    }
+
+    /// Separator rows at the start of the ranges, with their label and note.
+    pub fn rows(&self) -> Vec<(usize, String)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let label = match &entry.note {
+                    Some(note) => format!("{}: {}", entry.label, note),
+                    None => entry.label.clone(),
+                };
+                Some((entry.offsets().ok()?.start as usize, label))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = r#"
+# found in the boot loader
+[[range]]
+range = "0x40..0x80"
+label = "header"
+color = "blue"
+note = "checksum at the end"
+
+[[range]]
+range = "0x100..0x104"
+label = "version"
+"#;
+
+    #[test]
+    fn test_parse() {
+        let sidecar = Sidecar::parse(TEXT).unwrap();
+        assert_eq!(sidecar.entries.len(), 2);
+        assert_eq!(sidecar.entries[0].offsets(), Ok(0x40..0x80));
+        assert_eq!(sidecar.entries[1].color, None);
+
+        assert_eq!(
+            sidecar.highlights(),
+            vec![
+                Highlight::new(0x40..0x80, "\x1b[48;5;17m"),
+                Highlight::new(0x100..0x104, highlight::FIELD_BACKGROUNDS[1]),
+            ]
+        );
+        assert_eq!(
+            sidecar.rows(),
+            vec![
+                (0x40, "header: checksum at the end".to_string()),
+                (0x100, "version".to_string()),
+            ]
+        );
+        assert_eq!(Sidecar::parse("").unwrap(), Sidecar::default());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = Sidecar::parse("[[range]]\nrange = \"0x40\"\nlabel = \"x\"\n").unwrap_err();
+        assert!(err.starts_with("range x: invalid range '0x40'"), "{}", err);
+
+        let text = "[[range]]\nrange = \"0..4\"\nlabel = \"x\"\ncolor = \"pink\"\n";
+        let err = Sidecar::parse(text).unwrap_err();
+        assert!(err.starts_with("range x: unknown color pink"), "{}", err);
+
+        let err = Sidecar::parse("[[range]]\nrange = \"0..4\"\n").unwrap_err();
+        assert!(err.contains("label"), "{}", err);
+    }
+
+    #[test]
+    fn test_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("firmware.bin");
+        assert_eq!(Sidecar::load(&file).unwrap(), None);
+
+        let entry = Entry::new(0x40..0x80, "header").with_note(Some("say \"hi\"".to_string()));
+        let path = Sidecar::append(&file, &entry).unwrap();
+        assert_eq!(path, dir.path().join("firmware.bin.hexler.toml"));
+        let entry2 = Entry::new(0..4, "magic").with_color(Some("red".to_string()));
+        Sidecar::append(&file, &entry2).unwrap();
+
+        let sidecar = Sidecar::load(&file).unwrap().unwrap();
+        assert_eq!(sidecar.entries, vec![entry, entry2]);
+    }
+}
@@ -0,0 +1,865 @@
+use crate::annotate::Annotator;
+use crate::error::Result;
+use crate::hex_formatter::HexFormatter;
+use size::Size;
+
+/// Magic number of a file format, and how to describe its header.
+pub struct Signature {
+    /// Short name of the format, e.g. `gzip`
+    pub name: &'static str,
+    /// Bytes at the start of the format
+    pub magic: &'static [u8],
+    /// Describes the header that starts with `magic`, or rejects it as a false positive by
+    /// returning `None`. Gets up to `SignatureScanner::MAX_HEADER_LEN` bytes.
+    pub describe: fn(&[u8]) -> Option<String>,
+}
+
+/// An embedded file found by `SignatureScanner`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    /// Offset of the magic number, relative to the start of the scanned input
+    pub offset: u64,
+    /// Name of the format, see `Signature::name`
+    pub name: &'static str,
+    /// Fields parsed from the header
+    pub description: String,
+}
+
+/// All known signatures.
+pub const SIGNATURES: &[Signature] = &[
+    Signature {
+        name: "elf",
+        magic: b"\x7fELF",
+        describe: describe_elf,
+    },
+    Signature {
+        name: "pe",
+        magic: b"MZ",
+        describe: describe_pe,
+    },
+    Signature {
+        name: "png",
+        magic: b"\x89PNG\r\n\x1a\n",
+        describe: describe_png,
+    },
+    Signature {
+        name: "jpeg",
+        magic: b"\xff\xd8\xff",
+        describe: describe_jpeg,
+    },
+    Signature {
+        name: "gzip",
+        magic: b"\x1f\x8b\x08",
+        describe: describe_gzip,
+    },
+    Signature {
+        name: "zlib",
+        magic: b"\x78",
+        describe: describe_zlib,
+    },
+    Signature {
+        name: "xz",
+        magic: b"\xfd7zXZ\x00",
+        describe: describe_xz,
+    },
+    Signature {
+        name: "bzip2",
+        magic: b"BZh",
+        describe: describe_bzip2,
+    },
+    Signature {
+        name: "zstd",
+        magic: b"\x28\xb5\x2f\xfd",
+        describe: describe_zstd,
+    },
+    Signature {
+        name: "7z",
+        magic: b"7z\xbc\xaf\x27\x1c",
+        describe: describe_7z,
+    },
+    Signature {
+        name: "zip",
+        magic: b"PK\x03\x04",
+        describe: describe_zip,
+    },
+    Signature {
+        name: "squashfs",
+        magic: b"hsqs",
+        describe: describe_squashfs,
+    },
+    Signature {
+        name: "uimage",
+        magic: b"\x27\x05\x19\x56",
+        describe: describe_uimage,
+    },
+    Signature {
+        name: "dtb",
+        magic: b"\xd0\x0d\xfe\xed",
+        describe: describe_dtb,
+    },
+];
+
+/// Finds embedded files by their magic numbers at any offset, like binwalk.
+///
+/// Every hit is validated by parsing its header, which rules out most random matches of short
+/// magic numbers. Some remain for zlib with its single byte magic number.
+pub struct SignatureScanner {
+    /// Indices into `SIGNATURES`, by the first byte of their magic number
+    candidates: Vec<Vec<usize>>,
+    read_buffer_size: usize,
+}
+
+impl Default for SignatureScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignatureScanner {
+    /// Number of bytes from the magic number on that are parsed for the description.
+    pub const MAX_HEADER_LEN: usize = 1024;
+
+    /// Creates a scanner for all known signatures.
+    pub fn new() -> Self {
+        let mut candidates = vec![Vec::new(); 256];
+        for (idx, signature) in SIGNATURES.iter().enumerate() {
+            candidates[signature.magic[0] as usize].push(idx);
+        }
+        Self {
+            candidates,
+            read_buffer_size: 1024 * 1024,
+        }
+    }
+
+    /// Returns the signature at the start of `data` and its description, if there is one.
+    /// `data` should contain `MAX_HEADER_LEN` bytes unless the input ends earlier.
+    pub fn find_at(&self, data: &[u8]) -> Option<(&'static Signature, String)> {
+        let first = *data.first()?;
+        self.candidates[first as usize].iter().find_map(|&idx| {
+            let signature = &SIGNATURES[idx];
+            if !data.starts_with(signature.magic) {
+                return None;
+            }
+            let header = &data[..data.len().min(Self::MAX_HEADER_LEN)];
+            (signature.describe)(header).map(|description| (signature, description))
+        })
+    }
+
+    /// Reads `reader` until EOF and calls `found` for each hit, in order of their offsets.
+    pub fn scan<R, F>(&self, mut reader: R, mut found: F) -> Result<()>
+    where
+        R: std::io::Read,
+        F: FnMut(Hit) -> Result<()>,
+    {
+        // The last MAX_HEADER_LEN bytes of each read are only scanned with the next one, so
+        // every header can be parsed completely.
+        let mut buffer = vec![0u8; Self::MAX_HEADER_LEN + self.read_buffer_size];
+        let mut carried_len = 0;
+        let mut offset = 0u64;
+
+        loop {
+            let bytes_read = crate::read_full(&mut reader, &mut buffer[carried_len..])?;
+            let total = carried_len + bytes_read;
+            let eof = total < buffer.len();
+            let scan_len = if eof {
+                total
+            } else {
+                total - Self::MAX_HEADER_LEN
+            };
+
+            for pos in 0..scan_len {
+                if let Some((signature, description)) = self.find_at(&buffer[pos..total]) {
+                    found(Hit {
+                        offset: offset + pos as u64,
+                        name: signature.name,
+                        description,
+                    })?;
+                }
+            }
+            if eof {
+                return Ok(());
+            }
+
+            buffer.copy_within(scan_len..total, 0);
+            carried_len = total - scan_len;
+            offset += scan_len as u64;
+        }
+    }
+}
+
+impl Annotator for SignatureScanner {
+    fn context_len(&self) -> usize {
+        Self::MAX_HEADER_LEN
+    }
+
+    fn annotate(
+        &self,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+        rows: &mut Vec<(usize, String)>,
+    ) {
+        for pos in line.clone() {
+            if let Some((signature, description)) = self.find_at(&window[pos..]) {
+                let offset = byte_offset + pos - line.start;
+                rows.push((offset, format!("{}: {}", signature.name, description)));
+            }
+        }
+    }
+}
+
+/// Prints the hits of a `SignatureScanner` with their offsets.
+///
+/// ```text
+/// 00000000 │ uimage    uImage "Linux-6.1", ARM kernel, gzip, 4.20 MiB, load 0x80008000, ...
+/// 00000040 │ gzip      gzip compressed data, name "zImage"
+/// ```
+pub struct ScanListing {
+    scanner: SignatureScanner,
+    hex_formatter: HexFormatter,
+    start_offset: usize,
+}
+
+impl Default for ScanListing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScanListing {
+    /// Creates a listing of all known signatures.
+    pub fn new() -> Self {
+        Self {
+            scanner: SignatureScanner::new(),
+            hex_formatter: HexFormatter::new(),
+            start_offset: 0,
+        }
+    }
+
+    /// Replaces the hex formatter used for the offsets.
+    pub fn with_hex_formatter(mut self, hex_formatter: HexFormatter) -> Self {
+        self.hex_formatter = hex_formatter;
+        self
+    }
+
+    /// Sets the offset of the first byte of the input.
+    pub fn with_start_offset(mut self, start_offset: usize) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+
+    /// Writes the title and the hits of all data in `reader`, or a note that there are none.
+    pub fn write<R: std::io::Read, W: std::io::Write>(
+        &self,
+        title: &str,
+        reader: R,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(title.as_bytes());
+        buffer.push(b'\n');
+
+        let mut num_hits = 0;
+        self.scanner.scan(reader, |hit| {
+            num_hits += 1;
+            let byte_offset = self.start_offset + hit.offset as usize;
+            self.hex_formatter.write_offset(&mut buffer, byte_offset);
+            let row = format!(" │ {:<9} {}\n", hit.name, hit.description);
+            buffer.extend_from_slice(row.as_bytes());
+            Ok(())
+        })?;
+        if num_hits == 0 {
+            buffer.extend_from_slice(b"no known signatures found\n");
+        }
+
+        writer.write_all(&buffer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn bytes<const N: usize>(data: &[u8], pos: usize) -> Option<[u8; N]> {
+    data.get(pos..pos + N)?.try_into().ok()
+}
+
+fn le16(data: &[u8], pos: usize) -> Option<u16> {
+    bytes(data, pos).map(u16::from_le_bytes)
+}
+
+fn le32(data: &[u8], pos: usize) -> Option<u32> {
+    bytes(data, pos).map(u32::from_le_bytes)
+}
+
+fn le64(data: &[u8], pos: usize) -> Option<u64> {
+    bytes(data, pos).map(u64::from_le_bytes)
+}
+
+fn be32(data: &[u8], pos: usize) -> Option<u32> {
+    bytes(data, pos).map(u32::from_be_bytes)
+}
+
+/// Text of a NUL terminated or padded string field, `None` if it isn't printable.
+fn text(data: &[u8]) -> Option<String> {
+    let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    let text = std::str::from_utf8(&data[..len]).ok()?;
+    if text.chars().any(char::is_control) {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+pub(crate) fn describe_elf(data: &[u8]) -> Option<String> {
+    let class = match data.get(4)? {
+        1 => "32-bit",
+        2 => "64-bit",
+        _ => return None,
+    };
+    let little_endian = match data.get(5)? {
+        1 => true,
+        2 => false,
+        _ => return None,
+    };
+    if *data.get(6)? != 1 {
+        return None;
+    }
+    let half = |pos| {
+        let bytes = bytes(data, pos)?;
+        Some(match little_endian {
+            true => u16::from_le_bytes(bytes),
+            false => u16::from_be_bytes(bytes),
+        })
+    };
+
+    let file_type = match half(16)? {
+        1 => "relocatable",
+        2 => "executable",
+        3 => "shared object",
+        4 => "core file",
+        _ => return None,
+    };
+    let machine = match half(18)? {
+        3 => "x86".to_string(),
+        8 => "MIPS".to_string(),
+        20 => "PowerPC".to_string(),
+        21 => "PowerPC64".to_string(),
+        40 => "ARM".to_string(),
+        62 => "x86-64".to_string(),
+        183 => "AArch64".to_string(),
+        243 => "RISC-V".to_string(),
+        machine => format!("machine {}", machine),
+    };
+    let endian = if little_endian { "LSB" } else { "MSB" };
+    Some(format!(
+        "ELF {} {} {}, {}",
+        class, endian, file_type, machine
+    ))
+}
+
+pub(crate) fn describe_pe(data: &[u8]) -> Option<String> {
+    // A plain DOS executable is too likely to be a false positive of the two byte magic
+    let pe = le32(data, 0x3c)? as usize;
+    if pe < 0x40 || data.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+
+    let machine = match le16(data, pe + 4)? {
+        0x014c => "i386".to_string(),
+        0x8664 => "x86-64".to_string(),
+        0x01c0 | 0x01c4 => "ARM".to_string(),
+        0xaa64 => "ARM64".to_string(),
+        machine => format!("machine 0x{:04x}", machine),
+    };
+    let num_sections = le16(data, pe + 6)?;
+    let characteristics = le16(data, pe + 22)?;
+    let format = match le16(data, pe + 24) {
+        Some(0x20b) => "PE32+",
+        _ => "PE32",
+    };
+    let kind = if characteristics & 0x2000 != 0 {
+        "DLL"
+    } else {
+        "executable"
+    };
+    Some(format!(
+        "{} {}, {}, {} sections",
+        format, kind, machine, num_sections
+    ))
+}
+
+fn describe_png(data: &[u8]) -> Option<String> {
+    if data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = be32(data, 16)?;
+    let height = be32(data, 20)?;
+    let bit_depth = *data.get(24)?;
+    let color_type = match data.get(25)? {
+        0 => "grayscale",
+        2 => "RGB",
+        3 => "palette",
+        4 => "grayscale with alpha",
+        6 => "RGBA",
+        _ => return None,
+    };
+    Some(format!(
+        "PNG image, {} x {}, {}-bit {}",
+        width, height, bit_depth, color_type
+    ))
+}
+
+fn describe_jpeg(data: &[u8]) -> Option<String> {
+    match data.get(3)? {
+        0xe0..=0xef | 0xdb => {}
+        _ => return None,
+    }
+    let format = match data.get(6..10) {
+        Some(b"JFIF") => ", JFIF",
+        Some(b"Exif") => ", Exif",
+        _ => "",
+    };
+    Some(format!("JPEG image{}", format))
+}
+
+fn describe_gzip(data: &[u8]) -> Option<String> {
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+
+    let flags = *data.get(3)?;
+    if flags & 0xe0 != 0 {
+        return None;
+    }
+    let mut description = "gzip compressed data".to_string();
+
+    if flags & FNAME != 0 {
+        let mut pos = 10;
+        if flags & FEXTRA != 0 {
+            pos += 2 + le16(data, 10)? as usize;
+        }
+        if let Some(name) = data.get(pos..).and_then(text) {
+            description += &format!(", name \"{}\"", name);
+        }
+    }
+    let mtime = le32(data, 4)?;
+    if let Some(modified) = chrono::DateTime::from_timestamp(mtime as i64, 0).filter(|_| mtime > 0)
+    {
+        description += &format!(", modified {}", modified.format("%Y-%m-%d %H:%M:%S"));
+    }
+    Some(description)
+}
+
+fn describe_zlib(data: &[u8]) -> Option<String> {
+    let level = match data.get(1)? {
+        0x01 => "fastest",
+        0x5e => "fast",
+        0x9c => "default",
+        0xda => "best",
+        _ => return None,
+    };
+    if !is_deflate_block(data.get(2..)?) {
+        return None;
+    }
+    Some(format!("zlib compressed data, {} compression", level))
+}
+
+/// Checks the header of the first block of a deflate stream, as far as possible without
+/// decompressing it.
+fn is_deflate_block(data: &[u8]) -> bool {
+    // Deflate packs bits starting with the least significant bit
+    let bits = |start: usize, len: usize| -> Option<u32> {
+        (0..len).try_fold(0, |value, i| {
+            let pos = start + i;
+            let bit = (data.get(pos / 8)? >> (pos % 8)) & 1;
+            Some(value | (bit as u32) << i)
+        })
+    };
+
+    match bits(1, 2) {
+        // Stored: the length follows at the next byte, and then its complement
+        Some(0) => match (le16(data, 1), le16(data, 3)) {
+            (Some(len), Some(nlen)) => len == !nlen,
+            _ => false,
+        },
+        // Fixed Huffman codes: every bit pattern is a valid start
+        Some(1) => true,
+        // Dynamic Huffman codes: the code for the code lengths must be complete
+        Some(2) => {
+            let (Some(hlit), Some(hdist), Some(hclen)) = (bits(3, 5), bits(8, 5), bits(13, 4))
+            else {
+                return false;
+            };
+            if hlit > 29 || hdist > 29 {
+                return false;
+            }
+            let mut kraft = 0;
+            for i in 0..hclen as usize + 4 {
+                match bits(17 + 3 * i, 3) {
+                    Some(0) => {}
+                    Some(len) => kraft += 1 << (7 - len),
+                    None => return false,
+                }
+            }
+            kraft == 1 << 7
+        }
+        _ => false,
+    }
+}
+
+fn describe_xz(data: &[u8]) -> Option<String> {
+    if *data.get(6)? != 0 {
+        return None;
+    }
+    let check = match data.get(7)? {
+        0x00 => "no",
+        0x01 => "CRC32",
+        0x04 => "CRC64",
+        0x0a => "SHA-256",
+        _ => return None,
+    };
+    Some(format!("xz compressed data, {} check", check))
+}
+
+fn describe_bzip2(data: &[u8]) -> Option<String> {
+    let level = match data.get(3)? {
+        level @ b'1'..=b'9' => level - b'0',
+        _ => return None,
+    };
+    // The magic of the first block, or of the end of stream for empty data
+    match data.get(4..10)? {
+        b"\x31\x41\x59\x26\x53\x59" | b"\x17\x72\x45\x38\x50\x90" => {}
+        _ => return None,
+    }
+    Some(format!("bzip2 compressed data, block size {}00k", level))
+}
+
+fn describe_zstd(data: &[u8]) -> Option<String> {
+    // The reserved bit of the frame header descriptor
+    if data.get(4)? & 0x08 != 0 {
+        return None;
+    }
+    Some("zstd compressed data".to_string())
+}
+
+fn describe_7z(data: &[u8]) -> Option<String> {
+    let major = *data.get(6)?;
+    let minor = *data.get(7)?;
+    if major != 0 {
+        return None;
+    }
+    Some(format!("7-zip archive, version {}.{}", major, minor))
+}
+
+fn describe_zip(data: &[u8]) -> Option<String> {
+    let method = match le16(data, 8)? {
+        0 => "stored".to_string(),
+        8 => "deflate".to_string(),
+        9 => "deflate64".to_string(),
+        12 => "bzip2".to_string(),
+        14 => "lzma".to_string(),
+        93 => "zstd".to_string(),
+        95 => "xz".to_string(),
+        99 => "AES encrypted".to_string(),
+        method if method < 20 => format!("method {}", method),
+        _ => return None,
+    };
+    let compressed = le32(data, 18)?;
+    let uncompressed = le32(data, 22)?;
+    let name_len = le16(data, 26)? as usize;
+    if name_len == 0 {
+        return None;
+    }
+
+    let mut description = "Zip archive entry".to_string();
+    if let Some(name) = data.get(30..30 + name_len).and_then(text) {
+        description += &format!(" \"{}\"", name);
+    }
+    description += &format!(", {}", method);
+    // Sizes are only known up front unless the data descriptor flag is set
+    if le16(data, 6)? & 0x08 == 0 {
+        description += &format!(
+            ", {} compressed, {} uncompressed",
+            Size::from_bytes(compressed),
+            Size::from_bytes(uncompressed)
+        );
+    }
+    Some(description)
+}
+
+fn describe_squashfs(data: &[u8]) -> Option<String> {
+    let major = le16(data, 28)?;
+    let minor = le16(data, 30)?;
+    if major != 4 {
+        return None;
+    }
+    let inodes = le32(data, 4)?;
+    let block_size = le32(data, 12)?;
+    let compression = match le16(data, 20)? {
+        1 => "gzip",
+        2 => "lzma",
+        3 => "lzo",
+        4 => "xz",
+        5 => "lz4",
+        6 => "zstd",
+        _ => return None,
+    };
+    let bytes_used = le64(data, 40)?;
+    Some(format!(
+        "Squashfs filesystem {}.{}, {}, {}, {} inodes, block size {}",
+        major,
+        minor,
+        compression,
+        Size::from_bytes(bytes_used),
+        inodes,
+        Size::from_bytes(block_size)
+    ))
+}
+
+fn describe_uimage(data: &[u8]) -> Option<String> {
+    let size = be32(data, 12)?;
+    let load = be32(data, 16)?;
+    let entry = be32(data, 20)?;
+    let arch = match data.get(29)? {
+        2 => "ARM",
+        3 => "x86",
+        5 => "MIPS",
+        7 => "PowerPC",
+        22 => "ARM64",
+        26 => "RISC-V",
+        _ => "unknown architecture",
+    };
+    let image_type = match data.get(30)? {
+        1 => "standalone program",
+        2 => "kernel",
+        3 => "ramdisk",
+        4 => "multi-file image",
+        5 => "firmware",
+        6 => "script",
+        8 => "flattened device tree",
+        _ => return None,
+    };
+    let compression = match data.get(31)? {
+        0 => "uncompressed",
+        1 => "gzip",
+        2 => "bzip2",
+        3 => "lzma",
+        4 => "lzo",
+        5 => "lz4",
+        6 => "zstd",
+        _ => return None,
+    };
+    let name = text(data.get(32..64)?)?;
+    Some(format!(
+        "uImage \"{}\", {} {}, {}, {}, load 0x{:08x}, entry 0x{:08x}",
+        name,
+        arch,
+        image_type,
+        compression,
+        Size::from_bytes(size),
+        load,
+        entry
+    ))
+}
+
+fn describe_dtb(data: &[u8]) -> Option<String> {
+    let total_size = be32(data, 4)?;
+    let version = be32(data, 20)?;
+    let last_compatible_version = be32(data, 24)?;
+    if total_size < 40 || !(1..=17).contains(&version) || last_compatible_version > version {
+        return None;
+    }
+    Some(format!(
+        "device tree blob, version {}, {}",
+        version,
+        Size::from_bytes(total_size)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(data: &[u8]) -> Option<String> {
+        SignatureScanner::new()
+            .find_at(data)
+            .map(|(_, description)| description)
+    }
+
+    fn elf_header() -> Vec<u8> {
+        let mut elf = vec![0u8; 64];
+        elf[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+        elf[16..20].copy_from_slice(&[3, 0, 62, 0]);
+        elf
+    }
+
+    #[test]
+    fn test_elf() {
+        assert_eq!(
+            describe(&elf_header()).unwrap(),
+            "ELF 64-bit LSB shared object, x86-64"
+        );
+        assert_eq!(describe(b"\x7fELF\x05\x01\x01"), None);
+    }
+
+    #[test]
+    fn test_pe() {
+        let mut pe = vec![0u8; 0x100];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        pe[0x80..0x84].copy_from_slice(b"PE\0\0");
+        pe[0x84..0x86].copy_from_slice(&0x8664u16.to_le_bytes());
+        pe[0x86..0x88].copy_from_slice(&5u16.to_le_bytes());
+        pe[0x96..0x98].copy_from_slice(&0x2022u16.to_le_bytes());
+        pe[0x98..0x9a].copy_from_slice(&0x20bu16.to_le_bytes());
+        assert_eq!(describe(&pe).unwrap(), "PE32+ DLL, x86-64, 5 sections");
+
+        // Without the PE header, MZ is just two letters
+        assert_eq!(describe(b"MZ and more text"), None);
+    }
+
+    #[test]
+    fn test_png_and_jpeg() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        png.extend_from_slice(&[8, 6]);
+        assert_eq!(describe(&png).unwrap(), "PNG image, 640 x 480, 8-bit RGBA");
+
+        assert_eq!(
+            describe(b"\xff\xd8\xff\xe0\x00\x10JFIF\x00").unwrap(),
+            "JPEG image, JFIF"
+        );
+        assert_eq!(describe(b"\xff\xd8\xff\xff"), None);
+    }
+
+    #[test]
+    fn test_compressed() {
+        let gzip = b"\x1f\x8b\x08\x08\x00\x00\x00\x00\x00\x03notes.txt\x00";
+        assert_eq!(
+            describe(gzip).unwrap(),
+            "gzip compressed data, name \"notes.txt\""
+        );
+        assert_eq!(
+            describe(b"\x78\x9c\x4b\xcc").unwrap(),
+            "zlib compressed data, default compression"
+        );
+        assert_eq!(describe(b"\x78\x9c\x06"), None);
+        // Stored block of 5 bytes, and with a wrong complement of the length
+        assert!(describe(b"\x78\x01\x01\x05\x00\xfa\xffhello").is_some());
+        assert_eq!(describe(b"\x78\x01\x01\x05\x00\xfa\xfehello"), None);
+        assert_eq!(describe(b"xylophone"), None);
+        assert_eq!(
+            describe(b"\xfd7zXZ\x00\x00\x04").unwrap(),
+            "xz compressed data, CRC64 check"
+        );
+        assert_eq!(
+            describe(b"BZh91AY&SY").unwrap(),
+            "bzip2 compressed data, block size 900k"
+        );
+        assert_eq!(describe(b"BZh9 and text"), None);
+        assert_eq!(
+            describe(b"\x28\xb5\x2f\xfd\x24").unwrap(),
+            "zstd compressed data"
+        );
+    }
+
+    #[test]
+    fn test_zip() {
+        let mut zip = b"PK\x03\x04\x14\x00\x00\x00\x08\x00".to_vec();
+        zip.extend_from_slice(&[0; 8]);
+        zip.extend_from_slice(&100u32.to_le_bytes());
+        zip.extend_from_slice(&300u32.to_le_bytes());
+        zip.extend_from_slice(&5u16.to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip.extend_from_slice(b"a.txt");
+        assert_eq!(
+            describe(&zip).unwrap(),
+            "Zip archive entry \"a.txt\", deflate, 100 bytes compressed, 300 bytes uncompressed"
+        );
+    }
+
+    #[test]
+    fn test_firmware() {
+        let mut squashfs = vec![0u8; 96];
+        squashfs[..4].copy_from_slice(b"hsqs");
+        squashfs[4..8].copy_from_slice(&57u32.to_le_bytes());
+        squashfs[12..16].copy_from_slice(&131_072u32.to_le_bytes());
+        squashfs[20..22].copy_from_slice(&4u16.to_le_bytes());
+        squashfs[28..30].copy_from_slice(&4u16.to_le_bytes());
+        squashfs[40..48].copy_from_slice(&4096u64.to_le_bytes());
+        assert_eq!(
+            describe(&squashfs).unwrap(),
+            "Squashfs filesystem 4.0, xz, 4.00 KiB, 57 inodes, block size 128 KiB"
+        );
+
+        let mut uimage = vec![0u8; 64];
+        uimage[..4].copy_from_slice(b"\x27\x05\x19\x56");
+        uimage[12..16].copy_from_slice(&2048u32.to_be_bytes());
+        uimage[16..20].copy_from_slice(&0x8000_8000u32.to_be_bytes());
+        uimage[20..24].copy_from_slice(&0x8000_8000u32.to_be_bytes());
+        uimage[28..32].copy_from_slice(&[5, 2, 2, 1]);
+        uimage[32..41].copy_from_slice(b"Linux-6.1");
+        assert_eq!(
+            describe(&uimage).unwrap(),
+            "uImage \"Linux-6.1\", ARM kernel, gzip, 2.00 KiB, load 0x80008000, entry 0x80008000"
+        );
+
+        let mut dtb = vec![0u8; 40];
+        dtb[..4].copy_from_slice(b"\xd0\x0d\xfe\xed");
+        dtb[4..8].copy_from_slice(&1234u32.to_be_bytes());
+        dtb[20..24].copy_from_slice(&17u32.to_be_bytes());
+        dtb[24..28].copy_from_slice(&16u32.to_be_bytes());
+        assert_eq!(
+            describe(&dtb).unwrap(),
+            "device tree blob, version 17, 1.21 KiB"
+        );
+    }
+
+    #[test]
+    fn test_scan_across_reads() {
+        let mut data = vec![0u8; 5000];
+        let offsets = [0, 996, 1000, 2047, 4990];
+        for &offset in &offsets {
+            data[offset..offset + 4].copy_from_slice(b"\x78\x9c\x4b\xcc");
+        }
+        let elf = elf_header();
+        data.extend_from_slice(&elf);
+
+        let scanner = SignatureScanner {
+            read_buffer_size: 1000,
+            ..SignatureScanner::new()
+        };
+        let mut hits = Vec::new();
+        scanner
+            .scan(&data[..], |hit| {
+                hits.push((hit.offset, hit.name));
+                Ok(())
+            })
+            .unwrap();
+
+        let mut expected: Vec<(u64, &str)> = offsets.iter().map(|&o| (o as u64, "zlib")).collect();
+        expected.push((5000, "elf"));
+        assert_eq!(hits, expected);
+    }
+
+    #[test]
+    fn test_listing() {
+        let mut data = vec![0u8; 32];
+        data.extend_from_slice(b"\xfd7zXZ\x00\x00\x01");
+        let mut output = Vec::new();
+        ScanListing::new()
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .write("title", &data[..], &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "title\n00000020 │ xz        xz compressed data, CRC32 check\n"
+        );
+
+        let mut output = Vec::new();
+        ScanListing::new()
+            .write("title", &[0u8; 10][..], &mut output)
+            .unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .ends_with("no known signatures found\n"));
+    }
+}
@@ -0,0 +1,335 @@
+//! Vectorized kernels for the hot loop in `LineWriter::write_line`.
+//!
+//! Two operations are accelerated:
+//! - Hex encoding: converts a block of bytes into the `"xx "` layout used by the hex panel.
+//! - Class transitions: compares consecutive color ids to find where an escape code is needed.
+//!
+//! The implementation is selected at runtime with `Backend::detect()`. On x86_64 an AVX2
+//! path processes 32 bytes at once and an SSSE3 path 16 bytes (byte shuffles need SSSE3,
+//! plain SSE2 can't place the digits and spaces without a scalar scatter). Everywhere else
+//! the portable lookup table implementation is used, which is also the reference for tests.
+
+/// Maximum number of bytes processed by one call to the kernels.
+pub const BLOCK: usize = 32;
+
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+
+/// Implementation used for the vectorized kernels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Portable lookup table implementation, available everywhere.
+    Scalar,
+    /// 16 bytes per step using SSSE3 shuffles.
+    Ssse3,
+    /// 32 bytes per step using AVX2 shuffles.
+    Avx2,
+}
+
+impl Backend {
+    /// Returns the fastest backend supported by the running CPU.
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Backend::Avx2;
+            }
+            if is_x86_feature_detected!("ssse3") {
+                return Backend::Ssse3;
+            }
+        }
+        Backend::Scalar
+    }
+
+    /// Returns true if this backend can run on the current CPU.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Backend::Ssse3 => is_x86_feature_detected!("ssse3"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+}
+
+/// Writes `"xx "` for each byte in `input` into `out`.
+///
+/// `input` may hold at most `BLOCK` bytes, and `out` must be exactly `3 * input.len()` long.
+/// A backend the CPU doesn't support falls back to the scalar implementation.
+#[inline]
+pub fn encode_hex_spaced(backend: Backend, input: &[u8], out: &mut [u8]) {
+    // The vectorized stores rely on these, so they are checked in release builds too
+    assert!(input.len() <= BLOCK);
+    assert_eq!(out.len(), input.len() * 3);
+
+    let done = match backend {
+        // SAFETY: the guards check that the CPU supports the feature, the detection result
+        // is cached so this is cheap.
+        #[cfg(target_arch = "x86_64")]
+        Backend::Avx2 if is_x86_feature_detected!("avx2") => unsafe {
+            x86::encode_hex_spaced_avx2(input, out)
+        },
+        #[cfg(target_arch = "x86_64")]
+        Backend::Ssse3 if is_x86_feature_detected!("ssse3") => unsafe {
+            x86::encode_hex_spaced_ssse3(input, out)
+        },
+        _ => 0,
+    };
+    encode_hex_spaced_scalar(&input[done..], &mut out[done * 3..]);
+}
+
+/// Returns a bit mask where bit `i` is set when `ids[i]` differs from its predecessor.
+///
+/// The predecessor of `ids[0]` is `previous_id`. `ids` may hold at most `BLOCK` entries.
+#[inline]
+pub fn transitions(backend: Backend, ids: &[u8], previous_id: u8) -> u32 {
+    debug_assert!(ids.len() <= BLOCK);
+    if ids.is_empty() {
+        return 0;
+    }
+
+    // shifted[i] is the predecessor of ids[i]
+    let mut shifted = [0u8; BLOCK + 1];
+    shifted[0] = previous_id;
+    shifted[1..=ids.len()].copy_from_slice(ids);
+
+    match backend {
+        Backend::Scalar => transitions_scalar(ids, &shifted),
+        // SAFETY: SSE2 is part of the x86_64 baseline, every x86_64 CPU supports it.
+        #[cfg(target_arch = "x86_64")]
+        Backend::Avx2 | Backend::Ssse3 => unsafe { x86::transitions_sse2(ids, &shifted) },
+        #[cfg(not(target_arch = "x86_64"))]
+        _ => transitions_scalar(ids, &shifted),
+    }
+}
+
+fn encode_hex_spaced_scalar(input: &[u8], out: &mut [u8]) {
+    for (&byte, o) in input.iter().zip(out.chunks_exact_mut(3)) {
+        o[0] = HEX_CHARS[(byte >> 4) as usize];
+        o[1] = HEX_CHARS[(byte & 0xf) as usize];
+        o[2] = b' ';
+    }
+}
+
+fn transitions_scalar(ids: &[u8], shifted: &[u8]) -> u32 {
+    let mut mask = 0;
+    for (i, (&id, &prev)) in ids.iter().zip(shifted).enumerate() {
+        mask |= ((id != prev) as u32) << i;
+    }
+    mask
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::HEX_CHARS;
+    use std::arch::x86_64::*;
+
+    /// Shuffle masks that spread 16 hex digit pairs over three 16 byte registers.
+    ///
+    /// Output position `p` belongs to byte `p / 3`; `p % 3 == 0` takes the high digit,
+    /// `p % 3 == 1` the low digit and `p % 3 == 2` is a space. A mask value of 0x80 makes
+    /// `pshufb` write a zero, so the high, low and space parts can be or-ed together.
+    const fn shuffle_masks(digit: usize) -> [[u8; 16]; 3] {
+        let mut masks = [[0x80u8; 16]; 3];
+        let mut p = 0;
+        while p < 48 {
+            if p % 3 == digit {
+                masks[p / 16][p % 16] = (p / 3) as u8;
+            }
+            p += 1;
+        }
+        masks
+    }
+
+    const fn space_masks() -> [[u8; 16]; 3] {
+        let mut masks = [[0u8; 16]; 3];
+        let mut p = 0;
+        while p < 48 {
+            if p % 3 == 2 {
+                masks[p / 16][p % 16] = b' ';
+            }
+            p += 1;
+        }
+        masks
+    }
+
+    const HIGH_MASKS: [[u8; 16]; 3] = shuffle_masks(0);
+    const LOW_MASKS: [[u8; 16]; 3] = shuffle_masks(1);
+    const SPACES: [[u8; 16]; 3] = space_masks();
+
+    #[inline(always)]
+    unsafe fn load128(bytes: &[u8; 16]) -> __m128i {
+        _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+    }
+
+    /// Encodes as many 16 byte steps as possible, returns the number of bytes consumed.
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn encode_hex_spaced_ssse3(input: &[u8], out: &mut [u8]) -> usize {
+        let lut = load128(HEX_CHARS);
+        let nibble = _mm_set1_epi8(0x0f);
+
+        let mut done = 0;
+        while done + 16 <= input.len() {
+            let v = _mm_loadu_si128(input.as_ptr().add(done) as *const __m128i);
+            let hi = _mm_shuffle_epi8(lut, _mm_and_si128(_mm_srli_epi16(v, 4), nibble));
+            let lo = _mm_shuffle_epi8(lut, _mm_and_si128(v, nibble));
+
+            let dst = out.as_mut_ptr().add(done * 3) as *mut __m128i;
+            for k in 0..3 {
+                let r = _mm_or_si128(
+                    _mm_or_si128(
+                        _mm_shuffle_epi8(hi, load128(&HIGH_MASKS[k])),
+                        _mm_shuffle_epi8(lo, load128(&LOW_MASKS[k])),
+                    ),
+                    load128(&SPACES[k]),
+                );
+                _mm_storeu_si128(dst.add(k), r);
+            }
+            done += 16;
+        }
+        done
+    }
+
+    /// Encodes as many 32 byte steps as possible, returns the number of bytes consumed.
+    ///
+    /// `vpshufb` works within 128 bit lanes, so each lane produces 48 output bytes with the
+    /// same masks as the SSSE3 version.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn encode_hex_spaced_avx2(input: &[u8], out: &mut [u8]) -> usize {
+        let lut = _mm256_broadcastsi128_si256(load128(HEX_CHARS));
+        let nibble = _mm256_set1_epi8(0x0f);
+
+        let mut done = 0;
+        while done + 32 <= input.len() {
+            let v = _mm256_loadu_si256(input.as_ptr().add(done) as *const __m256i);
+            let hi = _mm256_shuffle_epi8(lut, _mm256_and_si256(_mm256_srli_epi16(v, 4), nibble));
+            let lo = _mm256_shuffle_epi8(lut, _mm256_and_si256(v, nibble));
+
+            let dst = out.as_mut_ptr().add(done * 3) as *mut __m128i;
+            for k in 0..3 {
+                let r = _mm256_or_si256(
+                    _mm256_or_si256(
+                        _mm256_shuffle_epi8(
+                            hi,
+                            _mm256_broadcastsi128_si256(load128(&HIGH_MASKS[k])),
+                        ),
+                        _mm256_shuffle_epi8(
+                            lo,
+                            _mm256_broadcastsi128_si256(load128(&LOW_MASKS[k])),
+                        ),
+                    ),
+                    _mm256_broadcastsi128_si256(load128(&SPACES[k])),
+                );
+                _mm_storeu_si128(dst.add(k), _mm256_castsi256_si128(r));
+                _mm_storeu_si128(dst.add(k + 3), _mm256_extracti128_si256(r, 1));
+            }
+            done += 32;
+        }
+        done + encode_hex_spaced_ssse3(&input[done..], &mut out[done * 3..])
+    }
+
+    /// Compares ids with their predecessors 16 at a time, SSE2 is always present on x86_64.
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn transitions_sse2(ids: &[u8], shifted: &[u8; super::BLOCK + 1]) -> u32 {
+        let mut padded = [0u8; super::BLOCK];
+        padded[..ids.len()].copy_from_slice(ids);
+
+        let mut mask = 0u32;
+        for half in 0..2 {
+            let current = _mm_loadu_si128(padded.as_ptr().add(half * 16) as *const __m128i);
+            let previous = _mm_loadu_si128(shifted.as_ptr().add(half * 16) as *const __m128i);
+            let equal = _mm_movemask_epi8(_mm_cmpeq_epi8(current, previous)) as u32;
+            mask |= (!equal & 0xffff) << (half * 16);
+        }
+
+        // Only the first ids.len() bits are meaningful
+        mask & (u64::MAX >> (64 - ids.len())) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_backends() -> Vec<Backend> {
+        [Backend::Scalar, Backend::Ssse3, Backend::Avx2]
+            .into_iter()
+            .filter(|b| b.is_supported())
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_is_supported() {
+        assert!(Backend::detect().is_supported());
+        assert!(Backend::Scalar.is_supported());
+    }
+
+    #[test]
+    fn test_encode_hex_spaced_matches_scalar() {
+        let input: Vec<u8> = (0..=255u8).rev().collect();
+        for backend in all_backends() {
+            for len in 0..=BLOCK {
+                for start in [0, 1, 7, 200] {
+                    let data = &input[start..start + len];
+                    let mut expected = vec![0u8; len * 3];
+                    let mut actual = vec![0u8; len * 3];
+                    encode_hex_spaced_scalar(data, &mut expected);
+                    encode_hex_spaced(backend, data, &mut actual);
+                    assert_eq!(
+                        expected, actual,
+                        "{:?} len={} start={}",
+                        backend, len, start
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_hex_spaced_format() {
+        let mut out = [0u8; 9];
+        encode_hex_spaced(Backend::detect(), &[0x00, 0xab, 0xff], &mut out);
+        assert_eq!(&out, b"00 ab ff ");
+    }
+
+    #[test]
+    fn test_encode_hex_spaced_any_backend() {
+        // Unsupported backends must fall back instead of running unavailable instructions
+        let input: Vec<u8> = (0..BLOCK as u8).collect();
+        let mut expected = vec![0u8; BLOCK * 3];
+        encode_hex_spaced_scalar(&input, &mut expected);
+        for backend in [Backend::Scalar, Backend::Ssse3, Backend::Avx2] {
+            let mut actual = vec![0u8; BLOCK * 3];
+            encode_hex_spaced(backend, &input, &mut actual);
+            assert_eq!(expected, actual, "{:?}", backend);
+        }
+    }
+
+    #[test]
+    fn test_transitions_matches_scalar() {
+        let ids: Vec<u8> = (0..BLOCK as u8).map(|i| (i / 3) % 4).collect();
+        for backend in all_backends() {
+            for len in 0..=BLOCK {
+                for previous in 0..4 {
+                    let mut shifted = [0u8; BLOCK + 1];
+                    shifted[0] = previous;
+                    shifted[1..=len].copy_from_slice(&ids[..len]);
+                    let expected = transitions_scalar(&ids[..len], &shifted);
+                    let actual = transitions(backend, &ids[..len], previous);
+                    assert_eq!(expected, actual, "{:?} len={}", backend, len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_transitions_bits() {
+        let mask = transitions(Backend::detect(), &[0, 0, 1, 1, 0], 0);
+        assert_eq!(mask, 0b10100);
+
+        let mask = transitions(Backend::detect(), &[3, 3], 0);
+        assert_eq!(mask, 0b1);
+    }
+}
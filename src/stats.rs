@@ -0,0 +1,744 @@
+use crate::byte_to_color::{ByteClass, ByteToColor, Theme};
+use crate::error::Result;
+
+/// A run of identical bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Run {
+    pub offset: u64,
+    pub len: u64,
+    pub byte: u8,
+}
+
+/// Statistics of the whole input, collected by `StatsScanner`.
+#[derive(Debug, Clone)]
+pub struct ByteStats {
+    /// Number of occurrences of each byte value
+    pub counts: [u64; 256],
+    /// Total number of bytes
+    pub len: u64,
+    /// Longest runs of identical bytes, longest first
+    pub longest_runs: Vec<Run>,
+    /// Most frequent n-grams with their number of occurrences, most frequent first. Counts of
+    /// n-grams longer than 2 bytes are lower bounds, see `StatsScanner`.
+    pub ngrams: Vec<(Vec<u8>, u64)>,
+}
+
+impl ByteStats {
+    /// Shannon entropy of the whole input in bits per byte, between 0 and 8.
+    pub fn entropy(&self) -> f64 {
+        let total = self.len as f64;
+        self.counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// Number of bytes in each class, in the order of `ByteClass::ALL`.
+    pub fn class_counts(&self) -> [(ByteClass, u64); 6] {
+        let mut class_counts = ByteClass::ALL.map(|class| (class, 0));
+        for (byte, &count) in self.counts.iter().enumerate() {
+            let class = ByteClass::of(byte as u8);
+            if let Some(entry) = class_counts.iter_mut().find(|(c, _)| *c == class) {
+                entry.1 += count;
+            }
+        }
+        class_counts
+    }
+}
+
+/// Statistics of a chunk of the input, merged into `ByteStats`.
+struct ChunkStats {
+    counts: [u64; 256],
+    runs: Vec<Run>,
+    ngrams: NgramCounts,
+}
+
+/// Number of occurrences of n-grams, keyed by the n-gram bytes in big endian.
+enum NgramCounts {
+    /// Exact counts of all n-grams up to 2 bytes, indexed by the key
+    Table(Vec<u64>),
+    /// Approximate counts of the most frequent longer n-grams, a fixed number of slots
+    /// indexed by the hash of the key
+    HeavyHitters(Vec<(u64, u64)>),
+}
+
+impl NgramCounts {
+    /// Slots for long n-grams of the whole input, 1M slots take up 16 MB.
+    const TOTAL_SLOTS_BITS: u32 = 20;
+
+    /// Slots for long n-grams of a chunk, fewer so allocating and merging them is cheap.
+    const CHUNK_SLOTS_BITS: u32 = 16;
+
+    fn new(ngram_len: usize, slots_bits: u32) -> Self {
+        if ngram_len <= 2 {
+            Self::Table(vec![0; 1 << (8 * ngram_len)])
+        } else {
+            Self::HeavyHitters(vec![(0, 0); 1 << slots_bits])
+        }
+    }
+
+    /// Adds `count` occurrences of `key`.
+    ///
+    /// Each slot holds a single candidate, like the Misra-Gries algorithm with one counter. A
+    /// different key in the slot cancels out occurrences, so frequent n-grams survive while
+    /// the memory stays bounded even for random data.
+    fn add(&mut self, key: u64, count: u64) {
+        match self {
+            Self::Table(table) => table[key as usize] += count,
+            Self::HeavyHitters(slots) => {
+                let bits = slots.len().trailing_zeros();
+                let idx = (key.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (64 - bits)) as usize;
+                let slot = &mut slots[idx];
+                if slot.0 == key || slot.1 == 0 {
+                    *slot = (key, slot.1 + count);
+                } else if slot.1 >= count {
+                    slot.1 -= count;
+                } else {
+                    *slot = (key, count - slot.1);
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: NgramCounts) {
+        match (self, other) {
+            (Self::Table(table), Self::Table(other)) => {
+                for (total, count) in table.iter_mut().zip(other) {
+                    *total += count;
+                }
+            }
+            (this, other) => {
+                for (key, count) in other.into_counts() {
+                    this.add(key, count);
+                }
+            }
+        }
+    }
+
+    /// All n-grams with a nonzero count, in no particular order.
+    fn into_counts(self) -> Box<dyn Iterator<Item = (u64, u64)>> {
+        match self {
+            Self::Table(table) => Box::new(
+                table
+                    .into_iter()
+                    .enumerate()
+                    .map(|(key, count)| (key as u64, count))
+                    .filter(|&(_, count)| count > 0),
+            ),
+            Self::HeavyHitters(slots) => {
+                Box::new(slots.into_iter().filter(|&(_, count)| count > 0))
+            }
+        }
+    }
+}
+
+/// Collects `ByteStats` of an input, in parallel.
+///
+/// The input is read in large chunks like in `dump`, and each chunk is split up further so
+/// all cores are busy. Runs and n-grams that cross chunk boundaries are counted correctly.
+///
+/// Byte values, runs and n-grams of up to 2 bytes are counted exactly. Longer n-grams have
+/// far too many distinct values to count them all, so only frequent ones are tracked in a
+/// fixed amount of memory and their counts are lower bounds.
+pub struct StatsScanner {
+    ngram_len: usize,
+    top: usize,
+    start_offset: u64,
+    read_buffer_size: usize,
+    chunk_size: usize,
+}
+
+impl Default for StatsScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatsScanner {
+    /// Longest supported n-gram, so an n-gram fits into a `u64`.
+    pub const MAX_NGRAM_LEN: usize = 8;
+
+    /// Creates a scanner for byte pairs that reports the top 10 runs and n-grams.
+    pub fn new() -> Self {
+        Self {
+            ngram_len: 2,
+            top: 10,
+            start_offset: 0,
+            read_buffer_size: 8 * 1024 * 1024,
+            chunk_size: 1024 * 1024,
+        }
+    }
+
+    /// Sets the length of the counted n-grams, clamped to 1..=MAX_NGRAM_LEN.
+    pub fn with_ngram_len(mut self, ngram_len: usize) -> Self {
+        self.ngram_len = ngram_len.clamp(1, Self::MAX_NGRAM_LEN);
+        self
+    }
+
+    /// Sets the number of runs and n-grams that are reported.
+    pub fn with_top(mut self, top: usize) -> Self {
+        self.top = top;
+        self
+    }
+
+    /// Sets the offset of the first byte of the input, e.g. after `--skip`, so the offsets of
+    /// the runs are offsets in the file.
+    pub fn with_start_offset(mut self, start_offset: u64) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+
+    /// Reads `reader` until EOF and collects its statistics.
+    pub fn scan<R: std::io::Read>(&self, mut reader: R) -> Result<ByteStats> {
+        use rayon::prelude::*;
+
+        // The last ngram_len - 1 bytes of the previous read are kept in front of the new data,
+        // so n-grams that start there are counted with the next read.
+        let tail_len = self.ngram_len - 1;
+        let mut buffer = vec![0u8; tail_len + self.read_buffer_size];
+        let mut kept = 0;
+
+        let mut counts = [0u64; 256];
+        let mut ngrams = NgramCounts::new(self.ngram_len, NgramCounts::TOTAL_SLOTS_BITS);
+        let mut longest_runs: Vec<Run> = Vec::new();
+        let mut open_run: Option<Run> = None;
+        let mut offset = self.start_offset;
+
+        loop {
+            let total_read = crate::read_full(&mut reader, &mut buffer[tail_len..])?;
+            if total_read == 0 {
+                break;
+            }
+            let window = &buffer[tail_len - kept..tail_len + total_read];
+            let data = &window[kept..];
+
+            let chunk_stats: Vec<ChunkStats> = data
+                .par_chunks(self.chunk_size)
+                .enumerate()
+                .map(|(idx, chunk)| {
+                    let start = idx * self.chunk_size;
+                    // n-grams starting in this chunk, including the kept bytes for the first one
+                    let gram_start = if idx == 0 { 0 } else { kept + start };
+                    let gram_end = (kept + start + chunk.len() + tail_len).min(window.len());
+                    ChunkStats {
+                        counts: Self::count(chunk),
+                        runs: self.runs(data, start..start + chunk.len(), offset),
+                        ngrams: self.count_ngrams(&window[gram_start..gram_end]),
+                    }
+                })
+                .collect();
+
+            for chunk in chunk_stats {
+                for (total, count) in counts.iter_mut().zip(chunk.counts) {
+                    *total += count;
+                }
+                ngrams.merge(chunk.ngrams);
+                for run in chunk.runs {
+                    self.insert_run(&mut longest_runs, run);
+                }
+            }
+
+            // The run at the start continues the open run of the previous read
+            let first_len = data.iter().take_while(|&&b| b == data[0]).count() as u64;
+            let first = match open_run {
+                Some(run) if run.byte == data[0] => Run {
+                    len: run.len + first_len,
+                    ..run
+                },
+                Some(run) => {
+                    self.insert_run(&mut longest_runs, run);
+                    Run {
+                        offset,
+                        len: first_len,
+                        byte: data[0],
+                    }
+                }
+                None => Run {
+                    offset,
+                    len: first_len,
+                    byte: data[0],
+                },
+            };
+
+            // The run at the end may continue with the next read
+            let last_byte = data[data.len() - 1];
+            open_run = Some(if first_len == data.len() as u64 {
+                first
+            } else {
+                self.insert_run(&mut longest_runs, first);
+                let last_len = data.iter().rev().take_while(|&&b| b == last_byte).count() as u64;
+                Run {
+                    offset: offset + data.len() as u64 - last_len,
+                    len: last_len,
+                    byte: last_byte,
+                }
+            });
+
+            offset += data.len() as u64;
+            kept = tail_len.min(kept + data.len());
+            let end = tail_len + total_read;
+            buffer.copy_within(end - kept..end, tail_len - kept);
+            if total_read < self.read_buffer_size {
+                break;
+            }
+        }
+        if let Some(run) = open_run {
+            self.insert_run(&mut longest_runs, run);
+        }
+
+        let mut ngrams: Vec<(Vec<u8>, u64)> = ngrams
+            .into_counts()
+            .map(|(gram, count)| (gram.to_be_bytes()[8 - self.ngram_len..].to_vec(), count))
+            .collect();
+        ngrams.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ngrams.truncate(self.top);
+
+        Ok(ByteStats {
+            counts,
+            len: offset - self.start_offset,
+            longest_runs,
+            ngrams,
+        })
+    }
+
+    fn count(chunk: &[u8]) -> [u64; 256] {
+        let mut counts = [0u64; 256];
+        for &byte in chunk {
+            counts[byte as usize] += 1;
+        }
+        counts
+    }
+
+    /// Finds the longest runs that start in `data[range]` and don't touch the start or end of
+    /// `data`; those are handled across reads in `scan`.
+    fn runs(&self, data: &[u8], range: std::ops::Range<usize>, offset: u64) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        let mut pos = range.start.max(1);
+        while pos < range.end {
+            let byte = data[pos];
+            if byte == data[pos - 1] {
+                pos += 1;
+                continue;
+            }
+            let mut end = pos + 1;
+            while end < data.len() && data[end] == byte {
+                end += 1;
+            }
+
+            // Most runs are short, skip them as soon as enough longer ones were found
+            let len = (end - pos) as u64;
+            let is_candidate = runs.len() < self.top || runs.last().is_some_and(|r| r.len < len);
+            if end < data.len() && is_candidate {
+                let run = Run {
+                    offset: offset + pos as u64,
+                    len,
+                    byte,
+                };
+                self.insert_run(&mut runs, run);
+            }
+            pos = end;
+        }
+        runs
+    }
+
+    /// Inserts `run` into `runs` if it is one of the `top` longest, earlier runs win ties.
+    fn insert_run(&self, runs: &mut Vec<Run>, run: Run) {
+        let key = |r: &Run| (std::cmp::Reverse(r.len), r.offset);
+        let idx = runs.partition_point(|r| key(r) < key(&run));
+        if idx < self.top {
+            runs.insert(idx, run);
+            runs.truncate(self.top);
+        }
+    }
+
+    /// Counts all n-grams that fit completely into `data`.
+    fn count_ngrams(&self, data: &[u8]) -> NgramCounts {
+        let key = |gram: &[u8]| gram.iter().fold(0u64, |acc, &b| acc << 8 | b as u64);
+        let mut ngrams = NgramCounts::new(self.ngram_len, NgramCounts::CHUNK_SLOTS_BITS);
+        for gram in data.windows(self.ngram_len) {
+            ngrams.add(key(gram), 1);
+        }
+        ngrams
+    }
+}
+
+/// Prints `ByteStats` as colored histograms, with the colors of the hex dump.
+pub struct StatsReport {
+    theme: Theme,
+    byte_to_color: ByteToColor,
+    bar_width: usize,
+}
+
+impl Default for StatsReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatsReport {
+    const COLOR_RESET: &'static str = "\x1b[0m";
+    const BARS: [&'static str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+    /// Creates a report with the default theme.
+    pub fn new() -> Self {
+        Self::with_theme(Theme::DEFAULT).expect("built-in themes are valid")
+    }
+
+    /// Creates a report with the colors of `theme`.
+    ///
+    /// # Errors
+    /// Returns `EscapeTooLong` if an escape code of the theme is too long.
+    pub fn with_theme(theme: Theme) -> Result<Self> {
+        Ok(Self {
+            theme,
+            byte_to_color: ByteToColor::with_theme(&theme)?,
+            bar_width: 40,
+        })
+    }
+
+    /// Writes the report of `stats`, with `title` on top.
+    pub fn write<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        title: &str,
+        stats: &ByteStats,
+    ) -> std::io::Result<()> {
+        writeln!(writer, "{}", title)?;
+        writeln!(
+            writer,
+            "{} bytes, entropy {:.3} bits per byte",
+            stats.len,
+            stats.entropy()
+        )?;
+
+        writeln!(writer, "\nbyte classes")?;
+        for (class, count) in stats.class_counts() {
+            let color = self.theme.color(class);
+            writeln!(
+                writer,
+                "  {:<16}{:>14} {:>6.2}% {}{}{}",
+                class.name(),
+                count,
+                Self::percent(count, stats.len),
+                color,
+                self.bar(count, stats.len),
+                Self::COLOR_RESET
+            )?;
+        }
+
+        self.write_byte_histogram(writer, stats)?;
+
+        writeln!(writer, "\nmost common bytes")?;
+        let mut by_count: Vec<(usize, u64)> = stats.counts.iter().copied().enumerate().collect();
+        by_count.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let max = by_count.first().map_or(0, |&(_, count)| count);
+        for &(byte, count) in by_count.iter().take(10).filter(|(_, count)| *count > 0) {
+            let color = self.color(byte as u8);
+            writeln!(
+                writer,
+                "  {}{:02x}{}{:>28} {:>6.2}% {}{}{}",
+                color,
+                byte,
+                Self::COLOR_RESET,
+                count,
+                Self::percent(count, stats.len),
+                color,
+                self.bar(count, max),
+                Self::COLOR_RESET
+            )?;
+        }
+
+        if !stats.longest_runs.is_empty() {
+            writeln!(writer, "\nlongest runs")?;
+            for run in &stats.longest_runs {
+                writeln!(
+                    writer,
+                    "  {}{:02x}{} x {:<12} at 0x{:08x}",
+                    self.color(run.byte),
+                    run.byte,
+                    Self::COLOR_RESET,
+                    run.len,
+                    run.offset
+                )?;
+            }
+        }
+
+        if !stats.ngrams.is_empty() {
+            writeln!(writer, "\nmost common {}-grams", stats.ngrams[0].0.len())?;
+            for (gram, count) in &stats.ngrams {
+                let mut hex = String::new();
+                for &byte in gram {
+                    hex.push_str(&format!(
+                        "{}{:02x}{} ",
+                        self.color(byte),
+                        byte,
+                        Self::COLOR_RESET
+                    ));
+                }
+                writeln!(writer, "  {}{:>12}", hex, count)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a 16 x 16 grid with a bar per byte value, relative to the most common byte.
+    ///
+    /// The bars use a log scale, otherwise a single dominant byte like 0x00 flattens all
+    /// others.
+    fn write_byte_histogram<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        stats: &ByteStats,
+    ) -> std::io::Result<()> {
+        let max = stats.counts.iter().copied().max().unwrap_or(0);
+        writeln!(
+            writer,
+            "\nbyte values (bar height: log scale relative to the most common byte)"
+        )?;
+        writeln!(writer, "     0 1 2 3 4 5 6 7 8 9 a b c d e f")?;
+        for row in 0..16 {
+            write!(writer, "  {:x}_", row)?;
+            for col in 0..16 {
+                let byte = (row * 16 + col) as u8;
+                let count = stats.counts[byte as usize];
+                let bar = if count == 0 {
+                    " "
+                } else {
+                    let scale = ((count + 1) as f64).ln() / ((max + 1) as f64).ln();
+                    let level = (scale * 7.0).round() as usize;
+                    Self::BARS[level]
+                };
+                write!(writer, " {}{}", self.color(byte), bar)?;
+            }
+            writeln!(writer, "{}", Self::COLOR_RESET)?;
+        }
+        Ok(())
+    }
+
+    fn color(&self, byte: u8) -> &str {
+        // Escape codes are always valid UTF-8
+        std::str::from_utf8(self.byte_to_color.bytes(byte)).unwrap_or_default()
+    }
+
+    fn bar(&self, count: u64, max: u64) -> String {
+        if max == 0 {
+            return String::new();
+        }
+        let eighths = (count as f64 / max as f64 * (self.bar_width * 8) as f64).round() as usize;
+        let mut bar = Self::BARS[7].repeat(eighths / 8);
+        if eighths % 8 != 0 {
+            // Partial blocks grow from the left
+            bar.push_str(["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"][eighths % 8]);
+        }
+        bar
+    }
+
+    fn percent(count: u64, total: u64) -> f64 {
+        if total == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / total as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SlowReader;
+
+    /// Straightforward single threaded reference for the runs.
+    fn reference_runs(data: &[u8], top: usize) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        let mut start = 0;
+        for pos in 1..=data.len() {
+            if pos == data.len() || data[pos] != data[start] {
+                runs.push(Run {
+                    offset: start as u64,
+                    len: (pos - start) as u64,
+                    byte: data[start],
+                });
+                start = pos;
+            }
+        }
+        runs.sort_by(|a, b| b.len.cmp(&a.len).then(a.offset.cmp(&b.offset)));
+        runs.truncate(top);
+        runs
+    }
+
+    /// Scanner with tiny buffers, so short inputs cross many chunk and read boundaries.
+    fn small_scanner() -> StatsScanner {
+        StatsScanner {
+            read_buffer_size: 4000,
+            chunk_size: 1000,
+            ..StatsScanner::new()
+        }
+    }
+
+    #[test]
+    fn test_counts_and_entropy() {
+        let stats = StatsScanner::new().scan(&b"aabbbc"[..]).unwrap();
+        assert_eq!(stats.len, 6);
+        assert_eq!(stats.counts[b'a' as usize], 2);
+        assert_eq!(stats.counts[b'b' as usize], 3);
+        assert_eq!(stats.counts[b'c' as usize], 1);
+        assert!((stats.entropy() - 1.459).abs() < 0.001);
+
+        let classes = stats.class_counts();
+        assert_eq!(classes[4], (ByteClass::Alphanumeric, 6));
+        assert_eq!(classes[0], (ByteClass::Sentinel, 0));
+    }
+
+    #[test]
+    fn test_empty() {
+        let stats = StatsScanner::new().scan(&b""[..]).unwrap();
+        assert_eq!(stats.len, 0);
+        assert_eq!(stats.entropy(), 0.0);
+        assert!(stats.longest_runs.is_empty());
+        assert!(stats.ngrams.is_empty());
+    }
+
+    #[test]
+    fn test_runs() {
+        let stats = StatsScanner::new()
+            .with_top(3)
+            .scan(&b"abbbcdddde"[..])
+            .unwrap();
+        assert_eq!(
+            stats.longest_runs,
+            vec![
+                Run {
+                    offset: 5,
+                    len: 4,
+                    byte: b'd'
+                },
+                Run {
+                    offset: 1,
+                    len: 3,
+                    byte: b'b'
+                },
+                Run {
+                    offset: 0,
+                    len: 1,
+                    byte: b'a'
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_runs_across_reads() {
+        // Runs that cross chunk and read boundaries, and a run that spans several reads
+        let mut data: Vec<u8> = (0..50_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 28) as u8)
+            .collect();
+        data[990..1010].fill(7);
+        data[3990..4010].fill(9);
+        data[9_000..18_000].fill(0);
+
+        let reader = SlowReader {
+            data: &data,
+            max_read: 700,
+        };
+        let stats = small_scanner().with_top(5).scan(reader).unwrap();
+        assert_eq!(stats.longest_runs, reference_runs(&data, 5));
+        assert_eq!(stats.longest_runs[0].len, 9_000);
+    }
+
+    #[test]
+    fn test_start_offset() {
+        let data = b"ab\x00\x00\x00c";
+        let stats = StatsScanner::new()
+            .with_start_offset(0x100)
+            .scan(&data[..])
+            .unwrap();
+        assert_eq!(stats.len, 6);
+        assert_eq!(
+            stats.longest_runs[0],
+            Run {
+                offset: 0x102,
+                len: 3,
+                byte: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_ngrams() {
+        let stats = StatsScanner::new()
+            .with_top(2)
+            .scan(&b"abcabcab"[..])
+            .unwrap();
+        assert_eq!(stats.ngrams, vec![(b"ab".to_vec(), 3), (b"bc".to_vec(), 2)]);
+
+        let stats = StatsScanner::new()
+            .with_ngram_len(3)
+            .with_top(1)
+            .scan(&b"abcabcab"[..])
+            .unwrap();
+        assert_eq!(stats.ngrams, vec![(b"abc".to_vec(), 2)]);
+    }
+
+    #[test]
+    fn test_long_ngrams_bounded() {
+        // Mostly distinct 4-grams, with one that is frequent
+        let mut data: Vec<u8> = (0..400_000u32)
+            .flat_map(|i| i.wrapping_mul(2_654_435_761).to_le_bytes())
+            .collect();
+        for pos in (0..data.len()).step_by(64) {
+            data[pos..pos + 4].copy_from_slice(b"ELF!");
+        }
+        let stats = small_scanner()
+            .with_ngram_len(4)
+            .with_top(1)
+            .scan(&data[..])
+            .unwrap();
+        assert_eq!(stats.ngrams[0].0, b"ELF!");
+        assert!(stats.ngrams[0].1 <= (data.len() / 64) as u64);
+        assert!(stats.ngrams[0].1 > (data.len() / 128) as u64);
+    }
+
+    #[test]
+    fn test_ngrams_across_reads() {
+        let data: Vec<u8> = b"0123".iter().copied().cycle().take(20_001).collect();
+        for ngram_len in [2, 4] {
+            let stats = small_scanner()
+                .with_ngram_len(ngram_len)
+                .scan(&data[..])
+                .unwrap();
+            let total: u64 = stats.ngrams.iter().map(|(_, count)| count).sum();
+            assert_eq!(total, (data.len() - ngram_len + 1) as u64);
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let stats = StatsScanner::new()
+            .scan(&b"hello,\x00\x00\x00"[..])
+            .unwrap();
+        let mut output = Vec::new();
+        StatsReport::new()
+            .write(&mut output, "title", &stats)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with("title\n9 bytes, entropy"));
+        assert!(output.contains("byte classes"));
+        assert!(output.contains("most common bytes"));
+        assert!(output.contains("longest runs"));
+        assert!(output.contains("most common 2-grams"));
+        assert!(output.contains(Theme::DEFAULT.sentinel));
+    }
+
+    #[test]
+    fn test_bar() {
+        let report = StatsReport::new();
+        assert_eq!(report.bar(0, 0), "");
+        assert_eq!(report.bar(1, 1).chars().count(), 40);
+        assert_eq!(report.bar(1, 80), "▌");
+    }
+}
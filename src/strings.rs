@@ -0,0 +1,558 @@
+use crate::ascii_renderer::Encoding;
+use crate::error::Result;
+use crate::hex_formatter::HexFormatter;
+use crate::line_writer::LineWriter;
+use crate::renderer::context_window;
+use std::collections::VecDeque;
+
+/// A run of printable characters, found by `StringScanner`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundString {
+    /// Offset of the first byte, relative to the start of the scanned input
+    pub offset: u64,
+    /// Number of bytes, which is more than the number of characters for UTF-8 and UTF-16
+    pub len: usize,
+    /// The decoded characters
+    pub text: String,
+}
+
+/// What `StringScanner` finds at a position of the input.
+enum Scanned {
+    /// A printable character of this many bytes
+    Printable(usize),
+    /// Anything else, scanning continues at the next byte
+    Other,
+    /// The input ends in the middle of a character, more data is needed to decide
+    Incomplete,
+}
+
+/// Finds runs of printable characters in an input, like `strings`.
+///
+/// Printable are the characters 0x20 to 0x7e and tab. With UTF-8 also all other valid
+/// characters except control characters; with UTF-16 the code units of printable ASCII and
+/// the Latin-1 letters 0xa0 to 0xff, since almost any pair of random bytes is a valid UTF-16
+/// code unit.
+///
+/// Scanning continues one byte after each unprintable character, so UTF-16 strings are found
+/// regardless of their alignment. Runs longer than `MAX_STRING_LEN` characters are reported
+/// in pieces, so a huge text file doesn't have to fit into memory.
+pub struct StringScanner {
+    min_len: usize,
+    encoding: Encoding,
+    before: usize,
+    after: usize,
+    read_buffer_size: usize,
+}
+
+impl Default for StringScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StringScanner {
+    /// Maximum number of characters of a reported string, longer runs are split up.
+    pub const MAX_STRING_LEN: usize = 4096;
+
+    /// Creates a scanner for ASCII strings of at least 4 characters.
+    pub fn new() -> Self {
+        Self {
+            min_len: 4,
+            encoding: Encoding::Ascii,
+            before: 0,
+            after: 0,
+            read_buffer_size: 64 * 1024,
+        }
+    }
+
+    /// Whether strings in `encoding` can be found, see `with_encoding`.
+    pub fn supports(encoding: Encoding) -> bool {
+        matches!(
+            encoding,
+            Encoding::Cp437 | Encoding::Ascii | Encoding::Utf8 | Encoding::Utf16le
+        )
+    }
+
+    /// Sets the minimum number of characters of a string, clamped to 1..=MAX_STRING_LEN.
+    pub fn with_min_len(mut self, min_len: usize) -> Self {
+        self.min_len = min_len.clamp(1, Self::MAX_STRING_LEN);
+        self
+    }
+
+    /// Sets the encoding of the strings. UTF-8 and UTF-16 little endian are decoded, all other
+    /// encodings find ASCII strings.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the number of bytes before and after each string that are passed along with it.
+    pub fn with_context(mut self, before: usize, after: usize) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    /// Reads `reader` until EOF and calls `found` for each string, in order of their offsets.
+    ///
+    /// Besides the string, `found` gets the bytes around it as configured with `with_context`
+    /// (fewer at the start and end of the input), and the offset of these bytes.
+    pub fn scan<R, F>(&self, mut reader: R, mut found: F) -> Result<()>
+    where
+        R: std::io::Read,
+        F: FnMut(&FoundString, &[u8], u64) -> Result<()>,
+    {
+        // Bytes of the input starting at `base`. Everything from the earliest byte that is
+        // still needed is kept: the context of pending strings, and the start of the open string.
+        let mut history: Vec<u8> = Vec::with_capacity(2 * self.read_buffer_size);
+        let mut base = 0u64;
+        let mut read_buffer = vec![0u8; self.read_buffer_size];
+
+        // Next position to scan, and start and number of characters of the current string,
+        // and whether it continues a run that was split up
+        let mut pos = 0u64;
+        let mut open: Option<(u64, usize, bool)> = None;
+
+        // Strings whose context after them was not read yet
+        let mut pending: VecDeque<FoundString> = VecDeque::new();
+
+        loop {
+            let bytes_read = crate::read_full(&mut reader, &mut read_buffer)?;
+            history.extend_from_slice(&read_buffer[..bytes_read]);
+            let eof = bytes_read < read_buffer.len();
+
+            loop {
+                let idx = (pos - base) as usize;
+                if idx >= history.len() {
+                    break;
+                }
+                match self.scan_at(&history[idx..], eof) {
+                    Scanned::Printable(len) => {
+                        if open.is_some_and(|(_, num_chars, _)| num_chars == Self::MAX_STRING_LEN) {
+                            self.close(&mut open, pos, &history, base, &mut pending);
+                            open = Some((pos, 0, true));
+                        }
+                        open.get_or_insert((pos, 0, false)).1 += 1;
+                        pos += len as u64;
+                    }
+                    Scanned::Other => {
+                        self.close(&mut open, pos, &history, base, &mut pending);
+                        pos += 1;
+                    }
+                    Scanned::Incomplete => break,
+                }
+            }
+            let end = base + history.len() as u64;
+            if eof {
+                self.close(&mut open, end, &history, base, &mut pending);
+            }
+
+            while let Some(string) = pending.front() {
+                let string_end = string.offset + string.len as u64;
+                if !eof && string_end + self.after as u64 > end {
+                    break;
+                }
+                let window_start = string.offset.saturating_sub(self.before as u64).max(base);
+                let window_end = (string_end + self.after as u64).min(end);
+                let window = &history[(window_start - base) as usize..(window_end - base) as usize];
+                found(string, window, window_start)?;
+                pending.pop_front();
+            }
+            if eof {
+                return Ok(());
+            }
+
+            let keep_from = pending
+                .front()
+                .map(|string| string.offset)
+                .into_iter()
+                .chain(open.map(|(start, _, _)| start))
+                .fold(pos, u64::min)
+                .saturating_sub(self.before as u64)
+                .max(base);
+            history.drain(..(keep_from - base) as usize);
+            base = keep_from;
+        }
+    }
+
+    /// Ends the open string at `end`, it is found if it is long enough or the rest of a long
+    /// run.
+    fn close(
+        &self,
+        open: &mut Option<(u64, usize, bool)>,
+        end: u64,
+        history: &[u8],
+        base: u64,
+        pending: &mut VecDeque<FoundString>,
+    ) {
+        let Some((start, num_chars, continued)) = open.take() else {
+            return;
+        };
+        if num_chars < self.min_len && !continued {
+            return;
+        }
+        let bytes = &history[(start - base) as usize..(end - base) as usize];
+        let text = match self.encoding {
+            Encoding::Utf16le => bytes
+                .chunks_exact(2)
+                .map(|unit| char::from(unit[0]))
+                .collect(),
+            // Only valid UTF-8 is found, and ASCII is valid UTF-8
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        };
+        pending.push_back(FoundString {
+            offset: start,
+            len: bytes.len(),
+            text,
+        });
+    }
+
+    /// Decides whether a printable character starts at `data[0]`.
+    fn scan_at(&self, data: &[u8], eof: bool) -> Scanned {
+        let is_printable_ascii = |byte: u8| (0x20..=0x7e).contains(&byte) || byte == b'\t';
+        let incomplete = if eof {
+            Scanned::Other
+        } else {
+            Scanned::Incomplete
+        };
+
+        match self.encoding {
+            Encoding::Utf8 => {
+                let len = match data[0] {
+                    byte if byte < 0x80 => {
+                        return match is_printable_ascii(byte) {
+                            true => Scanned::Printable(1),
+                            false => Scanned::Other,
+                        }
+                    }
+                    0xc2..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf4 => 4,
+                    _ => return Scanned::Other,
+                };
+                let Some(sequence) = data.get(..len) else {
+                    return incomplete;
+                };
+                match std::str::from_utf8(sequence).map(|text| text.chars().next()) {
+                    Ok(Some(ch)) if !ch.is_control() => Scanned::Printable(len),
+                    _ => Scanned::Other,
+                }
+            }
+            Encoding::Utf16le => match data.get(..2) {
+                None => incomplete,
+                Some(&[low, 0]) if is_printable_ascii(low) || low >= 0xa0 => Scanned::Printable(2),
+                Some(_) => Scanned::Other,
+            },
+            _ => match is_printable_ascii(data[0]) {
+                true => Scanned::Printable(1),
+                false => Scanned::Other,
+            },
+        }
+    }
+}
+
+/// Prints the strings of an input with their offsets, optionally with a hex dump of each.
+///
+/// ```text
+/// 00000040 │ GCC: (GNU) 13.2.1
+/// 00000053 │ .shstrtab
+/// ```
+pub struct StringsListing {
+    scanner: StringScanner,
+    hex_formatter: HexFormatter,
+    start_offset: usize,
+    snippet: Option<(LineWriter, usize)>,
+}
+
+impl StringsListing {
+    /// Size of the internal buffer after which formatted output is passed to the writer.
+    const FLUSH_SIZE: usize = 64 * 1024;
+
+    /// Maximum number of lines of a long string that are shown in its hex dump.
+    pub const MAX_SNIPPET_STRING_LINES: usize = 4;
+
+    /// Creates a listing of the strings that `scanner` finds.
+    pub fn new(scanner: StringScanner) -> Self {
+        Self {
+            scanner,
+            hex_formatter: HexFormatter::new(),
+            start_offset: 0,
+            snippet: None,
+        }
+    }
+
+    /// Replaces the hex formatter used for the offsets.
+    pub fn with_hex_formatter(mut self, hex_formatter: HexFormatter) -> Self {
+        self.hex_formatter = hex_formatter;
+        self
+    }
+
+    /// Sets the offset of the first byte of the input.
+    pub fn with_start_offset(mut self, start_offset: usize) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+
+    /// Shows a hex dump of each string below it, formatted by `line_writer`, with
+    /// `context_lines` additional lines before and after the string.
+    ///
+    /// The start offset of `line_writer` is ignored, lines are aligned to the offsets of
+    /// the listing.
+    pub fn with_snippets(mut self, line_writer: LineWriter, context_lines: usize) -> Self {
+        // One more line on each side, as the lines are aligned to multiples of bytes_per_line
+        let context_len =
+            (context_lines + 1) * line_writer.bytes_per_line() + line_writer.context_len();
+        self.scanner = self.scanner.with_context(context_len, context_len);
+        self.snippet = Some((line_writer, context_lines));
+        self
+    }
+
+    /// Writes the title and the strings of all data in `reader`.
+    pub fn write<R: std::io::Read, W: std::io::Write>(
+        &self,
+        title: &str,
+        reader: R,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut buffer = Vec::with_capacity(Self::FLUSH_SIZE);
+        buffer.extend_from_slice(title.as_bytes());
+        buffer.push(b'\n');
+
+        self.scanner.scan(reader, |string, window, window_offset| {
+            let byte_offset = self.start_offset + string.offset as usize;
+            self.hex_formatter.write_offset(&mut buffer, byte_offset);
+            buffer.extend_from_slice(" │ ".as_bytes());
+            buffer.extend_from_slice(string.text.as_bytes());
+            buffer.push(b'\n');
+
+            if let Some((line_writer, context_lines)) = &self.snippet {
+                self.write_snippet(
+                    &mut buffer,
+                    line_writer,
+                    *context_lines,
+                    string,
+                    window,
+                    window_offset,
+                );
+            }
+            if buffer.len() >= Self::FLUSH_SIZE {
+                writer.write_all(&buffer)?;
+                buffer.clear();
+            }
+            Ok(())
+        })?;
+
+        writer.write_all(&buffer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the hex dump lines around `string`, followed by an empty line.
+    fn write_snippet(
+        &self,
+        buffer: &mut Vec<u8>,
+        line_writer: &LineWriter,
+        context_lines: usize,
+        string: &FoundString,
+        window: &[u8],
+        window_offset: u64,
+    ) {
+        let bytes_per_line = line_writer.bytes_per_line();
+
+        // Positions within window, lines start at multiples of bytes_per_line in the listing
+        let byte_offset = |pos: usize| self.start_offset + window_offset as usize + pos;
+        let line_start = |pos: usize| pos.saturating_sub(byte_offset(pos) % bytes_per_line);
+        let string_start = (string.offset - window_offset) as usize;
+        let first_line = line_start(string_start);
+        let last_line = line_start(string_start + string.len - 1)
+            .min(first_line + (Self::MAX_SNIPPET_STRING_LINES - 1) * bytes_per_line);
+
+        let start = first_line.saturating_sub(context_lines * bytes_per_line);
+        let end = (last_line + (context_lines + 1) * bytes_per_line).min(window.len());
+
+        let context_len = line_writer.context_len();
+        let mut line = start;
+        while line < end {
+            let line_end = (line + bytes_per_line - byte_offset(line) % bytes_per_line).min(end);
+            let (line_window, line_range) = context_window(window, line..line_end, context_len);
+            line_writer.write_line_in_context(buffer, byte_offset(line), line_window, line_range);
+            line = line_end;
+        }
+        buffer.push(b'\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_to_color::{ByteToColor, Theme};
+    use crate::test_util::SlowReader;
+
+    fn find(scanner: &StringScanner, data: &[u8]) -> Vec<(u64, String)> {
+        let mut strings = Vec::new();
+        scanner
+            .scan(data, |string, _, _| {
+                strings.push((string.offset, string.text.clone()));
+                Ok(())
+            })
+            .unwrap();
+        strings
+    }
+
+    fn plain_line_writer(bytes_per_line: usize) -> LineWriter {
+        LineWriter::new_bytes(bytes_per_line)
+            .unwrap()
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap())
+    }
+
+    #[test]
+    fn test_ascii() {
+        let scanner = StringScanner::new();
+        let data = b"\x00\x01hello\x02abc\x7fworld\ttab\xff";
+        assert_eq!(
+            find(&scanner, data),
+            vec![(2, "hello".to_string()), (12, "world\ttab".to_string())]
+        );
+
+        // Strings at the very start and end, and shorter minimum length
+        let scanner = scanner.with_min_len(3);
+        assert_eq!(
+            find(&scanner, b"abc\x00def"),
+            vec![(0, "abc".to_string()), (4, "def".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_long_run_in_pieces() {
+        let max = StringScanner::MAX_STRING_LEN;
+        let mut data = b"\x00".to_vec();
+        data.resize(1 + 2 * max + 5, b'A');
+        data.push(0);
+        let scanner = StringScanner::new().with_min_len(10);
+        let pieces: Vec<(u64, usize)> = find(&scanner, &data)
+            .into_iter()
+            .map(|(offset, text)| (offset, text.len()))
+            .collect();
+        assert_eq!(
+            pieces,
+            vec![(1, max), (1 + max as u64, max), (1 + 2 * max as u64, 5)]
+        );
+
+        // The same pieces when the input arrives in small reads
+        let reader = SlowReader {
+            data: &data,
+            max_read: 100,
+        };
+        let mut found = Vec::new();
+        scanner
+            .scan(reader, |string, _, _| {
+                found.push((string.offset, string.len));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(found, pieces);
+    }
+
+    #[test]
+    fn test_utf8() {
+        let scanner = StringScanner::new().with_encoding(Encoding::Utf8);
+        let data = "\u{1}grüße, 世界\u{85}ok".as_bytes();
+        assert_eq!(find(&scanner, data), vec![(1, "grüße, 世界".to_string())]);
+
+        // Invalid and truncated sequences end a string
+        let data = b"abcd\xc3(efgh\xe4\xb8";
+        assert_eq!(
+            find(&scanner, data),
+            vec![(0, "abcd".to_string()), (5, "(efgh".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_utf16le() {
+        let scanner = StringScanner::new().with_encoding(Encoding::Utf16le);
+        let mut data = vec![0xffu8];
+        data.extend("Grüße".encode_utf16().flat_map(u16::to_le_bytes));
+        data.extend([0x00, 0xd8]);
+        data.extend("ab".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(find(&scanner, &data), vec![(1, "Grüße".to_string())]);
+
+        // ASCII strings are not UTF-16
+        assert!(find(&scanner, b"hello world").is_empty());
+    }
+
+    #[test]
+    fn test_across_reads() {
+        let mut data = Vec::new();
+        let mut expected = Vec::new();
+        for i in 0..2000 {
+            let text = format!("string {} ✓", i);
+            expected.push((data.len() as u64, text.clone()));
+            data.extend_from_slice(text.as_bytes());
+            data.extend_from_slice(&[0, 1, 2][..i % 3 + 1]);
+        }
+
+        for read_buffer_size in [5, 64, 1000] {
+            let scanner = StringScanner {
+                read_buffer_size,
+                ..StringScanner::new().with_encoding(Encoding::Utf8)
+            }
+            .with_context(7, 9);
+            let reader = SlowReader {
+                data: &data,
+                max_read: 3,
+            };
+
+            let mut strings = Vec::new();
+            scanner
+                .scan(reader, |string, window, window_offset| {
+                    let start = string.offset.saturating_sub(7);
+                    let end = (string.offset + string.len as u64 + 9).min(data.len() as u64);
+                    assert_eq!(window_offset, start);
+                    assert_eq!(window, &data[start as usize..end as usize]);
+                    strings.push((string.offset, string.text.clone()));
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(strings, expected);
+        }
+    }
+
+    #[test]
+    fn test_listing() {
+        let listing = StringsListing::new(StringScanner::new())
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_start_offset(0x100);
+
+        let mut output = Vec::new();
+        listing
+            .write("title", &b"\x00\x00hello\x00\x00world"[..], &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "title\n00000102 │ hello\n00000109 │ world\n"
+        );
+    }
+
+    #[test]
+    fn test_listing_snippets() {
+        let mut data = [0u8; 64];
+        data[20..26].copy_from_slice(b"needle");
+        let listing = StringsListing::new(StringScanner::new())
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_snippets(plain_line_writer(8), 1);
+
+        let mut output = Vec::new();
+        listing.write("title", &data[..], &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[1], "00000014 │ needle");
+        assert!(lines[2].starts_with("00000008 │"));
+        assert!(lines[3].starts_with("00000010 │"));
+        assert!(lines[4].starts_with("00000018 │"));
+        assert!(lines[5].starts_with("00000020 │"));
+        assert_eq!(lines[6], "");
+        assert_eq!(lines.len(), 7);
+    }
+}
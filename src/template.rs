@@ -0,0 +1,1105 @@
+use crate::error::{HexlerError, Result};
+use crate::highlight::{Highlight, Highlights, FIELD_BACKGROUNDS};
+use crate::renderer::Renderer;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Most fields that are shown, the rest of the data is dumped without a template.
+const MAX_FIELDS: usize = 100_000;
+
+/// Deepest nesting of structs, to stop templates where a struct contains itself.
+const MAX_DEPTH: usize = 64;
+
+/// Number of elements shown for arrays of numbers and strings.
+const MAX_SHOWN_ELEMENTS: usize = 8;
+
+/// Number of bytes shown of strings.
+const MAX_SHOWN_TEXT: usize = 64;
+
+/// Number of bytes shown of `bytes` fields.
+const MAX_SHOWN_BYTES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarKind {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+/// Number type of a field, e.g. `u16be`. Without a suffix numbers are little endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Scalar {
+    kind: ScalarKind,
+    little_endian: bool,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        use ScalarKind::*;
+
+        let (base, little_endian) = match (name.strip_suffix("le"), name.strip_suffix("be")) {
+            (Some(base), _) => (base, true),
+            (_, Some(base)) => (base, false),
+            _ => (name, true),
+        };
+        let kind = match base {
+            "u8" => U8,
+            "i8" => I8,
+            "u16" => U16,
+            "i16" => I16,
+            "u32" => U32,
+            "i32" => I32,
+            "u64" => U64,
+            "i64" => I64,
+            "f32" => F32,
+            "f64" => F64,
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            little_endian,
+        })
+    }
+
+    fn len(&self) -> usize {
+        use ScalarKind::*;
+
+        match self.kind {
+            U8 | I8 => 1,
+            U16 | I16 => 2,
+            U32 | I32 | F32 => 4,
+            U64 | I64 | F64 => 8,
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        !matches!(self.kind, ScalarKind::F32 | ScalarKind::F64)
+    }
+
+    fn raw(&self, bytes: &[u8]) -> u64 {
+        let fold = |raw: u64, &byte: &u8| raw << 8 | u64::from(byte);
+        match self.little_endian {
+            true => bytes.iter().rev().fold(0, fold),
+            false => bytes.iter().fold(0, fold),
+        }
+    }
+
+    /// Value of an integer field, floats are truncated.
+    fn integer(&self, bytes: &[u8]) -> i128 {
+        use ScalarKind::*;
+
+        let raw = self.raw(bytes);
+        match self.kind {
+            I8 => i128::from(raw as u8 as i8),
+            I16 => i128::from(raw as u16 as i16),
+            I32 => i128::from(raw as u32 as i32),
+            I64 => i128::from(raw as i64),
+            F32 => f32::from_bits(raw as u32) as i128,
+            F64 => f64::from_bits(raw) as i128,
+            U8 | U16 | U32 | U64 => i128::from(raw),
+        }
+    }
+
+    fn format(&self, bytes: &[u8]) -> String {
+        match self.kind {
+            ScalarKind::F32 => f32::from_bits(self.raw(bytes) as u32).to_string(),
+            ScalarKind::F64 => f64::from_bits(self.raw(bytes)).to_string(),
+            _ => format_integer(self.integer(bytes)),
+        }
+    }
+}
+
+/// Integers are shown in decimal, and also in hex unless they are single digits.
+fn format_integer(value: i128) -> String {
+    match value {
+        0..=9 => value.to_string(),
+        10.. => format!("{} (0x{:x})", value, value),
+        _ => value.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Type {
+    Scalar(Scalar),
+    /// Index into `Template::enums`
+    Enum(usize),
+    /// Index into `Template::structs`
+    Struct(usize),
+    String,
+    CString,
+    Bytes,
+    /// Name of an enum or struct, before they are resolved
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Length {
+    Fixed(u64),
+    /// Value of an earlier field of the same struct
+    Field(String),
+}
+
+#[derive(Debug, Clone)]
+struct FieldDef {
+    name: String,
+    ty: Type,
+    len: Option<Length>,
+    line: usize,
+}
+
+#[derive(Debug, Clone)]
+struct StructDef {
+    name: String,
+    fields: Vec<FieldDef>,
+    line: usize,
+}
+
+#[derive(Debug, Clone)]
+struct EnumDef {
+    name: String,
+    scalar: Scalar,
+    values: Vec<(i128, String)>,
+    line: usize,
+}
+
+impl EnumDef {
+    fn format(&self, value: i128) -> String {
+        match self.values.iter().find(|(v, _)| *v == value) {
+            Some((_, name)) => format!("{} ({})", name, value),
+            None => format!("{} (not a {})", value, self.name),
+        }
+    }
+}
+
+/// A field of the data that a template describes, as shown in the legend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Path of the field, e.g. `entries[2].id`
+    pub name: String,
+    /// Offsets of the field's bytes, as shown in the offset column
+    pub range: std::ops::Range<u64>,
+    /// The decoded value, e.g. `Response (2)` for an enum
+    pub value: String,
+}
+
+/// Declarative description of a binary layout, applied to the data to find its fields.
+///
+/// ```text
+/// # Comments start with # or //
+/// enum Kind : u8 {
+///     Request = 1,
+///     Response = 2,
+/// }
+///
+/// struct Message {              # the first struct describes the whole data
+///     magic: string[2]
+///     kind: Kind
+///     count: u16be              # numbers are little endian without a le/be suffix
+///     entries: Entry[count]     # lengths are numbers or earlier fields
+///     name: cstring             # NUL terminated
+/// }
+///
+/// struct Entry {
+///     id: u32
+///     weight: f32
+///     tag: bytes[4]
+/// }
+/// ```
+///
+/// Numbers are `u8`, `i8`, `u16`, `i16`, `u32`, `i32`, `u64`, `i64`, `f32` and `f64`.
+#[derive(Debug, Clone)]
+pub struct Template {
+    structs: Vec<StructDef>,
+    enums: Vec<EnumDef>,
+}
+
+impl Template {
+    /// Parses a template.
+    ///
+    /// # Errors
+    /// Returns `InvalidTemplate` with the line of the first error, e.g. an unknown type or a
+    /// length that refers to a later field.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+        };
+        let mut template = Template {
+            structs: Vec::new(),
+            enums: Vec::new(),
+        };
+        while let Some(token) = parser.peek() {
+            match token {
+                Token::Ident(keyword) if keyword == "struct" => {
+                    template.structs.push(parser.struct_def()?);
+                }
+                Token::Ident(keyword) if keyword == "enum" => {
+                    template.enums.push(parser.enum_def()?);
+                }
+                _ => return Err(parser.error("expected `struct` or `enum`")),
+            }
+        }
+        template.resolve()?;
+        Ok(template)
+    }
+
+    /// Checks the names, and replaces the names of types with their index.
+    fn resolve(&mut self) -> Result<()> {
+        if self.structs.is_empty() {
+            return Err(invalid(1, "the template has no struct".to_string()));
+        }
+        for (idx, def) in self.structs.iter().enumerate() {
+            let is_duplicate = self.structs[..idx].iter().any(|s| s.name == def.name)
+                || self.enums.iter().any(|e| e.name == def.name);
+            if is_duplicate {
+                return Err(invalid(def.line, format!("{} is defined twice", def.name)));
+            }
+        }
+        for (idx, def) in self.enums.iter().enumerate() {
+            if self.enums[..idx].iter().any(|e| e.name == def.name) {
+                return Err(invalid(def.line, format!("{} is defined twice", def.name)));
+            }
+        }
+
+        let struct_names: Vec<String> = self.structs.iter().map(|s| s.name.clone()).collect();
+        let enum_names: Vec<String> = self.enums.iter().map(|e| e.name.clone()).collect();
+        for def in &mut self.structs {
+            for idx in 0..def.fields.len() {
+                let (earlier, rest) = def.fields.split_at_mut(idx);
+                let field = &mut rest[0];
+                if earlier.iter().any(|f| f.name == field.name) {
+                    let message = format!("{} has two fields {}", def.name, field.name);
+                    return Err(invalid(field.line, message));
+                }
+
+                if let Type::Named(name) = &field.ty {
+                    field.ty = if let Some(idx) = enum_names.iter().position(|n| n == name) {
+                        Type::Enum(idx)
+                    } else if let Some(idx) = struct_names.iter().position(|n| n == name) {
+                        Type::Struct(idx)
+                    } else {
+                        return Err(invalid(field.line, format!("unknown type {}", name)));
+                    };
+                }
+
+                let needs_length = matches!(field.ty, Type::String | Type::Bytes);
+                if needs_length && field.len.is_none() {
+                    let message = format!("{} needs a length, e.g. [4]", field.name);
+                    return Err(invalid(field.line, message));
+                }
+                if let Some(Length::Field(name)) = &field.len {
+                    let is_number = |f: &&FieldDef| match f.ty {
+                        Type::Scalar(scalar) => scalar.is_integer() && f.len.is_none(),
+                        Type::Enum(_) => f.len.is_none(),
+                        _ => false,
+                    };
+                    if !earlier.iter().filter(is_number).any(|f| &f.name == name) {
+                        let message = format!(
+                            "the length {} of {} is not an earlier integer field",
+                            name, field.name
+                        );
+                        return Err(invalid(field.line, message));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the first struct of the template to the data of `reader`, and returns its
+    /// fields. `start_offset` is the offset of the first byte in the offset column.
+    ///
+    /// Stops at the end of the data, the field that doesn't fit is not returned.
+    ///
+    /// # Errors
+    /// Returns `InvalidTemplate` if structs are nested too deep, e.g. a struct contains itself.
+    pub fn apply<R: Read>(&self, reader: R, start_offset: u64) -> Result<Vec<Field>> {
+        let mut evaluator = Evaluator {
+            template: self,
+            input: std::io::BufReader::new(reader),
+            offset: start_offset,
+            fields: Vec::new(),
+            elements: 0,
+        };
+        evaluator.eval_struct(0, "", 0)?;
+        Ok(evaluator.fields)
+    }
+}
+
+fn invalid(line: usize, message: String) -> HexlerError {
+    HexlerError::InvalidTemplate { line, message }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i128),
+    Punct(char),
+}
+
+/// Splits the template into tokens with their line numbers.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let end = [line.find('#'), line.find("//")]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(line.len());
+        let chars: Vec<char> = line[..end].chars().collect();
+
+        let mut pos = 0;
+        while pos < chars.len() {
+            let c = chars[pos];
+            let word_len = chars[pos..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .count();
+            if c.is_whitespace() {
+                pos += 1;
+                continue;
+            } else if c.is_ascii_digit() {
+                let word: String = chars[pos..pos + word_len].iter().collect();
+                let digits = word.replace('_', "");
+                let value = match digits.strip_prefix("0x") {
+                    Some(hex) => i128::from_str_radix(hex, 16),
+                    None => digits.parse(),
+                };
+                let value =
+                    value.map_err(|_| invalid(line_no, format!("invalid number {}", word)))?;
+                tokens.push((Token::Int(value), line_no));
+                pos += word_len;
+            } else if word_len > 0 {
+                let word = chars[pos..pos + word_len].iter().collect();
+                tokens.push((Token::Ident(word), line_no));
+                pos += word_len;
+            } else if "{}[]:;,=-".contains(c) {
+                tokens.push((Token::Punct(c), line_no));
+                pos += 1;
+            } else {
+                return Err(invalid(line_no, format!("unexpected character '{}'", c)));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, message: &str) -> HexlerError {
+        invalid(self.line(), message.to_string())
+    }
+
+    /// Consumes `c` if it is the next token.
+    fn eat(&mut self, c: char) -> bool {
+        let is_next = self.peek() == Some(&Token::Punct(c));
+        if is_next {
+            self.pos += 1;
+        }
+        is_next
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.error(&format!("expected '{}'", c))),
+        }
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String> {
+        match self.peek() {
+            Some(Token::Ident(ident)) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                Ok(ident)
+            }
+            _ => Err(self.error(&format!("expected {}", what))),
+        }
+    }
+
+    fn int(&mut self) -> Result<i128> {
+        let negative = self.eat('-');
+        match self.peek() {
+            Some(&Token::Int(value)) => {
+                self.pos += 1;
+                Ok(if negative { -value } else { value })
+            }
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    /// Parses `struct Name { field: type ... }`.
+    fn struct_def(&mut self) -> Result<StructDef> {
+        let line = self.line();
+        self.next();
+        let name = self.ident("a struct name")?;
+        self.expect('{')?;
+
+        let mut fields = Vec::new();
+        while !self.eat('}') {
+            if self.peek().is_none() {
+                return Err(self.error(&format!("missing '}}' of {}", name)));
+            }
+            let line = self.line();
+            let name = self.ident("a field name")?;
+            self.expect(':')?;
+            let type_name = self.ident("a type")?;
+            let ty = match type_name.as_str() {
+                "string" => Type::String,
+                "cstring" => Type::CString,
+                "bytes" => Type::Bytes,
+                _ => Scalar::parse(&type_name).map_or(Type::Named(type_name), Type::Scalar),
+            };
+            let len = match self.eat('[') {
+                true => {
+                    let len = match self.next() {
+                        Some(Token::Int(len)) if len >= 0 => Length::Fixed(len as u64),
+                        Some(Token::Ident(field)) => Length::Field(field),
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.error("expected a length"));
+                        }
+                    };
+                    self.expect(']')?;
+                    Some(len)
+                }
+                false => None,
+            };
+            let _ = self.eat(';') || self.eat(',');
+            fields.push(FieldDef {
+                name,
+                ty,
+                len,
+                line,
+            });
+        }
+        Ok(StructDef { name, fields, line })
+    }
+
+    /// Parses `enum Name : type { Value = 1, ... }`.
+    fn enum_def(&mut self) -> Result<EnumDef> {
+        let line = self.line();
+        self.next();
+        let name = self.ident("an enum name")?;
+        self.expect(':')?;
+        let scalar = Scalar::parse(&self.ident("an integer type")?)
+            .filter(Scalar::is_integer)
+            .ok_or_else(|| invalid(line, format!("{} needs an integer type", name)))?;
+        self.expect('{')?;
+
+        let mut values = Vec::new();
+        while !self.eat('}') {
+            if self.peek().is_none() {
+                return Err(self.error(&format!("missing '}}' of {}", name)));
+            }
+            let value_name = self.ident("a value name")?;
+            self.expect('=')?;
+            values.push((self.int()?, value_name));
+            let _ = self.eat(',');
+        }
+        Ok(EnumDef {
+            name,
+            scalar,
+            values,
+            line,
+        })
+    }
+}
+
+/// Type of a number field, plain or an enum.
+enum Number<'a> {
+    Scalar(Scalar),
+    Enum(&'a EnumDef),
+}
+
+impl Number<'_> {
+    fn scalar(&self) -> Scalar {
+        match self {
+            Number::Scalar(scalar) => *scalar,
+            Number::Enum(def) => def.scalar,
+        }
+    }
+
+    fn format(&self, bytes: &[u8]) -> String {
+        match self {
+            Number::Scalar(scalar) => scalar.format(bytes),
+            Number::Enum(def) => def.format(def.scalar.integer(bytes)),
+        }
+    }
+}
+
+struct Evaluator<'a, R> {
+    template: &'a Template,
+    input: std::io::BufReader<R>,
+    offset: u64,
+    fields: Vec<Field>,
+    /// Number of struct array elements so far, limited like the fields because elements of
+    /// empty structs add no fields
+    elements: usize,
+}
+
+impl<'a, R: Read> Evaluator<'a, R> {
+    /// Reads the fields of a struct, returns false when the data or the field limit ended.
+    fn eval_struct(&mut self, idx: usize, prefix: &str, depth: usize) -> Result<bool> {
+        let def = &self.template.structs[idx];
+        if depth > MAX_DEPTH {
+            let message = format!("{} is nested more than {} deep", def.name, MAX_DEPTH);
+            return Err(invalid(def.line, message));
+        }
+
+        let mut values: HashMap<&'a str, i128> = HashMap::new();
+        for field in &def.fields {
+            let name = match prefix {
+                "" => field.name.clone(),
+                _ => format!("{}.{}", prefix, field.name),
+            };
+            let count = field.len.as_ref().map(|len| match len {
+                Length::Fixed(count) => *count,
+                Length::Field(field) => {
+                    let value = values.get(field.as_str()).copied().unwrap_or(0);
+                    value.clamp(0, i128::from(u64::MAX)) as u64
+                }
+            });
+
+            let start = self.offset;
+            let complete = match (&field.ty, count) {
+                (Type::Struct(idx), None) => self.eval_struct(*idx, &name, depth + 1)?,
+                (Type::Struct(idx), Some(count)) => {
+                    let mut complete = true;
+                    for element in 0..count {
+                        self.elements += 1;
+                        if self.elements > MAX_FIELDS {
+                            complete = false;
+                            break;
+                        }
+                        let name = format!("{}[{}]", name, element);
+                        if !self.eval_struct(*idx, &name, depth + 1)? {
+                            complete = false;
+                            break;
+                        }
+                    }
+                    complete
+                }
+                (Type::Scalar(_) | Type::Enum(_), None) => {
+                    let number = self.number(&field.ty);
+                    let len = number.scalar().len();
+                    match self.read(len as u64, len)? {
+                        Some(bytes) => {
+                            values.insert(&field.name, number.scalar().integer(&bytes));
+                            self.push(name, start, number.format(&bytes));
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                (Type::Scalar(_) | Type::Enum(_), Some(count)) => {
+                    let number = self.number(&field.ty);
+                    let len = number.scalar().len();
+                    // A count too large for a 64 bit length runs out of data like a large one
+                    let bytes = match (len as u64).checked_mul(count) {
+                        Some(total) => self.read(total, len * MAX_SHOWN_ELEMENTS)?,
+                        None => None,
+                    };
+                    match bytes {
+                        Some(bytes) => {
+                            let elements = bytes.chunks(len).map(|b| number.format(b));
+                            self.push(name, start, format_list(elements, count));
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                (Type::String, Some(len)) => match self.read(len, MAX_SHOWN_TEXT)? {
+                    Some(bytes) => {
+                        self.push(name, start, format_text(&bytes, len));
+                        true
+                    }
+                    None => false,
+                },
+                (Type::Bytes, Some(len)) => match self.read(len, MAX_SHOWN_BYTES)? {
+                    Some(bytes) => {
+                        self.push(name, start, format_bytes(&bytes, len));
+                        true
+                    }
+                    None => false,
+                },
+                (Type::CString, None) => match self.read_cstring()? {
+                    Some((bytes, len)) => {
+                        self.push(name, start, format_text(&bytes, len));
+                        true
+                    }
+                    None => false,
+                },
+                (Type::CString, Some(count)) => {
+                    let mut elements = Vec::new();
+                    let mut complete = true;
+                    for _ in 0..count {
+                        match self.read_cstring()? {
+                            Some((bytes, len)) => {
+                                if elements.len() < MAX_SHOWN_ELEMENTS {
+                                    elements.push(format_text(&bytes, len));
+                                }
+                            }
+                            None => {
+                                complete = false;
+                                break;
+                            }
+                        }
+                    }
+                    if complete {
+                        self.push(name, start, format_list(elements.into_iter(), count));
+                    }
+                    complete
+                }
+                (Type::String | Type::Bytes, None) | (Type::Named(_), _) => {
+                    unreachable!("checked by Template::parse")
+                }
+            };
+            if !complete || self.fields.len() >= MAX_FIELDS {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The number type of a scalar or enum field.
+    fn number(&self, ty: &Type) -> Number<'a> {
+        match ty {
+            Type::Enum(idx) => Number::Enum(&self.template.enums[*idx]),
+            Type::Scalar(scalar) => Number::Scalar(*scalar),
+            _ => unreachable!("only called for numbers"),
+        }
+    }
+
+    fn push(&mut self, name: String, start: u64, value: String) {
+        self.fields.push(Field {
+            name,
+            range: start..self.offset,
+            value,
+        });
+    }
+
+    /// Reads `len` bytes and returns the first `keep` of them, `None` at the end of the data.
+    fn read(&mut self, len: u64, keep: usize) -> Result<Option<Vec<u8>>> {
+        let mut bytes = vec![0u8; (keep as u64).min(len) as usize];
+        if crate::read_full(&mut self.input, &mut bytes)? < bytes.len() {
+            return Ok(None);
+        }
+        let rest = len - bytes.len() as u64;
+        if std::io::copy(&mut (&mut self.input).take(rest), &mut std::io::sink())? < rest {
+            return Ok(None);
+        }
+        self.offset += len;
+        Ok(Some(bytes))
+    }
+
+    /// Reads a NUL terminated string, returns its first bytes and its length without the NUL.
+    fn read_cstring(&mut self) -> Result<Option<(Vec<u8>, u64)>> {
+        let mut bytes = Vec::new();
+        let mut len = 0;
+        let mut byte = [0u8];
+        loop {
+            if self.input.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] == 0 {
+                break;
+            }
+            if bytes.len() < MAX_SHOWN_TEXT {
+                bytes.push(byte[0]);
+            }
+            len += 1;
+        }
+        self.offset += len + 1;
+        Ok(Some((bytes, len)))
+    }
+}
+
+fn format_list<I: Iterator<Item = String>>(elements: I, count: u64) -> String {
+    let mut elements: Vec<String> = elements.take(MAX_SHOWN_ELEMENTS).collect();
+    if count > elements.len() as u64 {
+        elements.push("…".to_string());
+    }
+    format!("[{}]", elements.join(", "))
+}
+
+/// Quoted text, `bytes` are the first bytes of a string of `len` bytes.
+fn format_text(bytes: &[u8], len: u64) -> String {
+    // Fixed size strings are often padded with NUL
+    let text_len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let text = String::from_utf8_lossy(&bytes[..text_len]);
+    let ellipsis = if len > bytes.len() as u64 { "…" } else { "" };
+    format!("\"{}\"{}", text.escape_debug(), ellipsis)
+}
+
+fn format_bytes(bytes: &[u8], len: u64) -> String {
+    let mut hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    if len > bytes.len() as u64 {
+        hex.push("…".to_string());
+    }
+    hex.join(" ")
+}
+
+/// Background colors of the fields, the same ones as in the legend of `LegendRenderer`.
+pub fn highlights(fields: &[Field]) -> Highlights {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| Highlight::new(field.range.clone(), background(idx)))
+        .collect()
+}
+
+fn background(field_idx: usize) -> &'static str {
+    FIELD_BACKGROUNDS[field_idx % FIELD_BACKGROUNDS.len()]
+}
+
+/// Renderer that prints the fields of a template next to the lines of another renderer.
+///
+/// Each field that starts in a line is listed after it, with the background color of its
+/// bytes:
+///
+/// ```text
+/// 00000000 │ 48 58 02 00 03 ... │ HX☻⋄♥...  magic="HX"  kind=Response (2)  count=3
+/// ```
+pub struct LegendRenderer<R> {
+    renderer: R,
+    fields: Vec<Field>,
+    color: bool,
+}
+
+impl<R: Renderer> LegendRenderer<R> {
+    /// Creates a renderer that lists `fields` after the lines of `renderer`.
+    pub fn new(renderer: R, fields: Vec<Field>) -> Self {
+        Self {
+            renderer,
+            fields,
+            color: true,
+        }
+    }
+
+    /// Whether the field names have the background colors of their bytes.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+impl<R: Renderer> Renderer for LegendRenderer<R> {
+    fn bytes_per_line(&self) -> usize {
+        self.renderer.bytes_per_line()
+    }
+
+    fn start_offset(&self) -> usize {
+        self.renderer.start_offset()
+    }
+
+    fn context_len(&self) -> usize {
+        self.renderer.context_len()
+    }
+
+    fn squeeze(&self) -> bool {
+        self.renderer.squeeze()
+    }
+
+    fn write_header(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()> {
+        self.renderer.write_header(buffer, title)
+    }
+
+    fn write_line(&self, buffer: &mut Vec<u8>, byte_offset: usize, line_data: &[u8]) {
+        self.write_line_in_context(buffer, byte_offset, line_data, 0..line_data.len());
+    }
+
+    fn write_line_in_context(
+        &self,
+        buffer: &mut Vec<u8>,
+        byte_offset: usize,
+        window: &[u8],
+        line: std::ops::Range<usize>,
+    ) {
+        let line_len = line.len();
+        self.renderer
+            .write_line_in_context(buffer, byte_offset, window, line);
+
+        let start = byte_offset as u64;
+        let end = start + line_len as u64;
+        let first = self.fields.partition_point(|f| f.range.start < start);
+        let len = self.fields[first..].partition_point(|f| f.range.start < end);
+        if len == 0 {
+            return;
+        }
+
+        // The legend goes at the end of the line, aligned even after a short last line
+        if buffer.last() == Some(&b'\n') {
+            buffer.pop();
+        }
+        let padding = self.renderer.bytes_per_line().saturating_sub(line_len);
+        buffer.resize(buffer.len() + padding, b' ');
+        for (idx, field) in self.fields[first..first + len].iter().enumerate() {
+            buffer.extend_from_slice(b"  ");
+            if self.color {
+                buffer.extend_from_slice(background(first + idx).as_bytes());
+                buffer.extend_from_slice(field.name.as_bytes());
+                buffer.extend_from_slice(b"\x1b[0m");
+            } else {
+                buffer.extend_from_slice(field.name.as_bytes());
+            }
+            buffer.push(b'=');
+            buffer.extend_from_slice(field.value.as_bytes());
+        }
+        buffer.push(b'\n');
+    }
+
+    fn write_squeezed(&self, buffer: &mut Vec<u8>, byte_offset: usize) {
+        self.renderer.write_squeezed(buffer, byte_offset)
+    }
+
+    fn write_separator(&self, buffer: &mut Vec<u8>, byte_offset: usize, label: &str) {
+        self.renderer.write_separator(buffer, byte_offset, label)
+    }
+
+    fn write_footer(&mut self, buffer: &mut Vec<u8>, title: &str) -> std::io::Result<()> {
+        self.renderer.write_footer(buffer, title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_to_color::{ByteToColor, Theme};
+    use crate::hex_formatter::HexFormatter;
+    use crate::line_writer::LineWriter;
+
+    const MESSAGE: &str = "
+        # A message of a made up protocol
+        struct Message {
+            magic: string[2]
+            kind: Kind
+            count: u16be
+            entries: Entry[count]
+            name: cstring
+            checksum: u32
+        }
+
+        enum Kind : u8 { Request = 1, Response = 2 }
+
+        struct Entry {
+            id: i16;
+            weight: f32;
+        }
+    ";
+
+    fn message() -> Vec<u8> {
+        let mut data = b"HX\x02\x00\x02".to_vec();
+        data.extend_from_slice(&(-2i16).to_le_bytes());
+        data.extend_from_slice(&1.5f32.to_le_bytes());
+        data.extend_from_slice(&300i16.to_le_bytes());
+        data.extend_from_slice(&0.25f32.to_le_bytes());
+        data.extend_from_slice(b"hexler\0");
+        data.extend_from_slice(&0xdead_beefu32.to_le_bytes());
+        data
+    }
+
+    fn fields(template: &str, data: &[u8]) -> Vec<(String, u64, u64, String)> {
+        Template::parse(template)
+            .unwrap()
+            .apply(data, 0x100)
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.name, f.range.start, f.range.end, f.value))
+            .collect()
+    }
+
+    fn field(name: &str, start: u64, end: u64, value: &str) -> (String, u64, u64, String) {
+        (name.to_string(), start, end, value.to_string())
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(
+            fields(MESSAGE, &message()),
+            vec![
+                field("magic", 0x100, 0x102, "\"HX\""),
+                field("kind", 0x102, 0x103, "Response (2)"),
+                field("count", 0x103, 0x105, "2"),
+                field("entries[0].id", 0x105, 0x107, "-2"),
+                field("entries[0].weight", 0x107, 0x10b, "1.5"),
+                field("entries[1].id", 0x10b, 0x10d, "300 (0x12c)"),
+                field("entries[1].weight", 0x10d, 0x111, "0.25"),
+                field("name", 0x111, 0x118, "\"hexler\""),
+                field("checksum", 0x118, 0x11c, "3735928559 (0xdeadbeef)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arrays_and_enums() {
+        let template = "
+            enum Color : u16be { Red = 0xff00, Blue = 0x00ff }
+            struct Image {
+                len: u8
+                palette: Color[2]
+                pixels: u8[len]
+                tag: bytes[10]
+                names: cstring[2]
+                missing: Color
+            }";
+        let mut data = b"\x0a\xff\x00\x12\x34".to_vec();
+        data.extend_from_slice(&[7; 10]);
+        data.extend_from_slice(&(0..10).collect::<Vec<u8>>());
+        data.extend_from_slice(b"a\0bc\0\x00\x01");
+        assert_eq!(
+            fields(template, &data),
+            vec![
+                field("len", 0x100, 0x101, "10 (0xa)"),
+                field("palette", 0x101, 0x105, "[Red (65280), 4660 (not a Color)]"),
+                field("pixels", 0x105, 0x10f, "[7, 7, 7, 7, 7, 7, 7, 7, …]"),
+                field("tag", 0x10f, 0x119, "00 01 02 03 04 05 06 07 …"),
+                field("names", 0x119, 0x11e, "[\"a\", \"bc\"]"),
+                field("missing", 0x11e, 0x120, "1 (not a Color)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stops_at_end_of_data() {
+        let data = message();
+        let names: Vec<String> = fields(MESSAGE, &data[..9])
+            .into_iter()
+            .map(|(name, ..)| name)
+            .collect();
+        assert_eq!(names, vec!["magic", "kind", "count", "entries[0].id"]);
+
+        assert!(fields(MESSAGE, b"").is_empty());
+
+        // Counts from the data whose length doesn't fit in 64 bits
+        let template = "struct A {\n  n: u64\n  d: u32[n]\n}";
+        for n in [u64::MAX, 0x4000_0000_0000_0000] {
+            let data = n.to_le_bytes();
+            let names: Vec<String> = fields(template, &data)
+                .into_iter()
+                .map(|(name, ..)| name)
+                .collect();
+            assert_eq!(names, vec!["n"]);
+        }
+    }
+
+    #[test]
+    fn test_array_of_empty_structs() {
+        let template = "struct A {\n  n: u64\n  e: Empty[n]\n  b: u8\n}\nstruct Empty {\n}";
+        let mut data = u64::MAX.to_le_bytes().to_vec();
+        data.push(7);
+        let names: Vec<String> = fields(template, &data)
+            .into_iter()
+            .map(|(name, ..)| name)
+            .collect();
+        assert_eq!(names, vec!["n"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |template: &str| Template::parse(template).unwrap_err().to_string();
+
+        assert_eq!(
+            error("struct A {\n  a: u12\n}"),
+            "invalid template, line 2: unknown type u12"
+        );
+        assert_eq!(
+            error("struct A {\n  a: string\n}"),
+            "invalid template, line 2: a needs a length, e.g. [4]"
+        );
+        assert_eq!(
+            error("struct A {\n  a: u8[b]\n  b: u8\n}"),
+            "invalid template, line 2: the length b of a is not an earlier integer field"
+        );
+        assert_eq!(
+            error("struct A { a: u8 }\nstruct A { b: u8 }"),
+            "invalid template, line 2: A is defined twice"
+        );
+        assert_eq!(
+            error("struct A {\n  a: u8 $\n}"),
+            "invalid template, line 2: unexpected character '$'"
+        );
+        assert_eq!(
+            error("struct A {\n  a: u8\n"),
+            "invalid template, line 2: missing '}' of A"
+        );
+        assert_eq!(
+            error("enum E : f32 { A = 1 }"),
+            "invalid template, line 1: E needs an integer type"
+        );
+        assert_eq!(
+            error("# nothing"),
+            "invalid template, line 1: the template has no struct"
+        );
+    }
+
+    #[test]
+    fn test_recursive_struct() {
+        let template = Template::parse("struct Node { value: u8\n next: Node }").unwrap();
+        let error = template.apply(&[0u8; 100][..], 0).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid template, line 1: Node is nested more than 64 deep"
+        );
+    }
+
+    #[test]
+    fn test_legend() {
+        let template = Template::parse(MESSAGE).unwrap();
+        let data = message();
+        let fields = template.apply(&data[..], 0).unwrap();
+        let line_writer = LineWriter::new_bytes(8)
+            .unwrap()
+            .with_hex_formatter(HexFormatter::new().with_color(false))
+            .with_byte_to_color(ByteToColor::with_theme(&Theme::MONOCHROME).unwrap())
+            .with_highlights(highlights(&fields));
+        let renderer = LegendRenderer::new(line_writer, fields).with_color(false);
+
+        let mut output = Vec::new();
+        for (idx, line) in data.chunks(8).enumerate() {
+            renderer.write_line(&mut output, idx * 8, line);
+        }
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with(
+            "  magic=\"HX\"  kind=Response (2)  count=2  entries[0].id=-2  entries[0].weight=1.5"
+        ));
+        assert!(lines[1].ends_with("  entries[1].id=300 (0x12c)  entries[1].weight=0.25"));
+        assert!(lines[2].ends_with("  name=\"hexler\""));
+        // The short last line is padded, so the legend is aligned with the others
+        let column =
+            |line: &str, pattern: &str| line[..line.find(pattern).unwrap()].chars().count();
+        assert_eq!(column(lines[3], "  checksum="), column(lines[2], "  name="));
+    }
+}
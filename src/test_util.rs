@@ -0,0 +1,16 @@
+//! Helpers shared by the unit tests.
+
+/// Reader that returns at most `max_read` bytes per call, to exercise partial reads.
+pub struct SlowReader<'a> {
+    pub data: &'a [u8],
+    pub max_read: usize,
+}
+
+impl std::io::Read for SlowReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.max_read).min(self.data.len());
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
@@ -142,3 +142,831 @@ fn test_binary_data() {
     assert!(stdout.contains("ff")); // 0xff
     assert!(stdout.contains("7f")); // 0x7f
 }
+
+#[test]
+fn test_squeeze() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--squeeze")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+
+    cmd.stdin
+        .as_mut()
+        .unwrap()
+        .write_all(&[0u8; 4096])
+        .expect("Failed to write to stdin");
+
+    let output = cmd.wait_with_output().expect("Failed to read output");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // header title + border, first line, marker, footer border
+    assert_eq!(stdout.lines().count(), 5);
+    assert!(stdout.contains("│ *"));
+}
+
+#[test]
+fn test_encoding_ebcdic() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--encoding")
+        .arg("ebcdic")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+
+    // "Hello" in EBCDIC code page 037
+    cmd.stdin
+        .as_mut()
+        .unwrap()
+        .write_all(&[0xc8, 0x85, 0x93, 0x93, 0x96])
+        .expect("Failed to write to stdin");
+
+    let output = cmd.wait_with_output().expect("Failed to read output");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Hello"));
+}
+
+#[test]
+fn test_encoding_utf16le() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--encoding")
+        .arg("utf16le")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+
+    let text: Vec<u8> = "Grüße".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    cmd.stdin
+        .as_mut()
+        .unwrap()
+        .write_all(&text)
+        .expect("Failed to write to stdin");
+
+    let output = cmd.wait_with_output().expect("Failed to read output");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains('ü'));
+    assert!(stdout.contains('ß'));
+}
+
+#[test]
+fn test_color_by_value() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--color-by")
+        .arg("value")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+
+    let data: Vec<u8> = (0..=255u8).collect();
+    cmd.stdin
+        .as_mut()
+        .unwrap()
+        .write_all(&data)
+        .expect("Failed to write to stdin");
+
+    let output = cmd.wait_with_output().expect("Failed to read output");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[38;2;"));
+}
+
+#[test]
+fn test_map() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--map")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+
+    let mut data = vec![0u8; 4096];
+    data.extend(std::iter::repeat(0xff).take(4096));
+    cmd.stdin
+        .as_mut()
+        .unwrap()
+        .write_all(&data)
+        .expect("Failed to write to stdin");
+
+    let output = cmd.wait_with_output().expect("Failed to read output");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains('·'));
+    assert!(stdout.contains('×'));
+    assert!(stdout.contains("1 cell = "));
+}
+
+#[test]
+fn test_skip() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--skip")
+        .arg("0x10")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+
+    cmd.stdin
+        .as_mut()
+        .unwrap()
+        .write_all(b"skipped, 16 byteshown")
+        .expect("Failed to write to stdin");
+
+    let output = cmd.wait_with_output().expect("Failed to read output");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("shown"));
+    assert!(!stdout.contains("skipped"));
+    assert!(stdout.contains("10 │"));
+}
+
+#[test]
+fn test_stats() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--stats")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+
+    let mut data = b"hello hello hello".to_vec();
+    data.extend(std::iter::repeat(0u8).take(1000));
+    cmd.stdin
+        .as_mut()
+        .unwrap()
+        .write_all(&data)
+        .expect("Failed to write to stdin");
+
+    let output = cmd.wait_with_output().expect("Failed to read output");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("entropy"));
+    assert!(stdout.contains("byte classes"));
+    assert!(stdout.contains("1000"));
+}
+
+#[test]
+fn test_strings() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--strings")
+        .arg("--context")
+        .arg("0")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+
+    cmd.stdin
+        .as_mut()
+        .unwrap()
+        .write_all(b"\x00\x01\x02abc\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00found it\x00")
+        .expect("Failed to write to stdin");
+
+    let output = cmd.wait_with_output().expect("Failed to read output");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("12 │ found it\n"));
+    assert!(!stdout.contains("abc\n"));
+    // The hex dump of the line containing the string
+    assert!(stdout.contains("66 6f 75 6e 64"));
+}
+
+#[test]
+fn test_scan_and_annotate() {
+    let mut data = vec![0u8; 40];
+    data.extend_from_slice(b"\x1f\x8b\x08\x08\x00\x00\x00\x00\x00\x03notes.txt\x00");
+    data.extend_from_slice(&[0u8; 40]);
+
+    for mode in ["--scan", "--annotate"] {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_hexler"))
+            .arg("--stdout")
+            .arg(mode)
+            .arg("--num-bytes-per-line")
+            .arg("16")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn hexler");
+
+        cmd.stdin
+            .as_mut()
+            .unwrap()
+            .write_all(&data)
+            .expect("Failed to write to stdin");
+
+        let output = cmd.wait_with_output().expect("Failed to read output");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("gzip compressed data, name \"notes.txt\""));
+        assert!(stdout.contains("28 │") || stdout.contains("28 ├──"));
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_elf_sections() {
+    use std::io::BufRead;
+
+    // The hexler binary itself is an ELF file on Linux. It is large, so only the start of the
+    // dump is read
+    let binary = env!("CARGO_BIN_EXE_hexler");
+    let head = |args: &[&str]| {
+        let mut child = Command::new(binary)
+            .arg("--stdout")
+            .arg("--num-bytes-per-line")
+            .arg("16")
+            .args(args)
+            .arg(binary)
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn hexler");
+        let stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+        let lines: Vec<String> = stdout.lines().take(100).map(|line| line.unwrap()).collect();
+        let _ = child.kill();
+        let _ = child.wait();
+        lines.join("\n")
+    };
+
+    let stdout = head(&[]);
+    assert!(stdout.contains("ELF header (64 bytes)"));
+    assert!(stdout.contains("program headers ("));
+    assert!(stdout.contains("segment LOAD ("));
+
+    let stdout = head(&["--raw"]);
+    assert!(stdout.contains("45 4c 46"));
+    assert!(!stdout.contains("├──"));
+
+    let output = Command::new(binary)
+        .args(["--stdout", "-n", "16", "--section", ".interp", binary])
+        .output()
+        .expect("Failed to run hexler");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(".interp (PROGBITS, "));
+    assert!(stdout.contains("ld"));
+    assert!(!stdout.contains("ELF header"));
+
+    let output = Command::new(binary)
+        .args([
+            "--stdout",
+            "-n",
+            "16",
+            "--section",
+            ".does-not-exist",
+            binary,
+        ])
+        .output()
+        .expect("Failed to run hexler");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_template() {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    let mut template = NamedTempFile::new().expect("Failed to create temp file");
+    template
+        .write_all(b"struct Packet {\n  len: u16be\n  payload: string[len]\n  crc: u32\n}\n")
+        .expect("Failed to write template");
+    template.flush().expect("Failed to flush");
+
+    let mut data = NamedTempFile::new().expect("Failed to create temp file");
+    data.write_all(b"\x00\x05hello\x78\x56\x34\x12")
+        .expect("Failed to write test data");
+    data.flush().expect("Failed to flush");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg("--template")
+        .arg(template.path())
+        .arg(data.path())
+        .output()
+        .expect("Failed to execute hexler");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("=5  "));
+    assert!(stdout.contains("=\"hello\"  "));
+    assert!(stdout.contains("=305419896 (0x12345678)"));
+
+    // Errors in the template are reported with their line
+    template
+        .write_all(b"struct Broken { x: u24 }\n")
+        .expect("Failed to write template");
+    template.flush().expect("Failed to flush");
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg("--template")
+        .arg(template.path())
+        .arg(data.path())
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 6: unknown type u24"));
+}
+
+#[test]
+fn test_inspect() {
+    use tempfile::NamedTempFile;
+
+    let mut data = NamedTempFile::new().expect("Failed to create temp file");
+    data.write_all(b"skip\x2c\x01\x00\x00\xc0\xa8\x00\x01")
+        .expect("Failed to write test data");
+    data.flush().expect("Failed to flush");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--inspect")
+        .arg("0x4")
+        .arg(data.path())
+        .output()
+        .expect("Failed to execute hexler");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("8 bytes at offset 0x4 (4)"));
+    assert!(stdout.contains("  u16           300 (0x12c)"));
+    assert!(stdout.contains("  uleb128       44 (1 byte)"));
+    assert!(stdout.contains("  unix time     1970-01-01 00:05:00 UTC"));
+    assert!(stdout.contains("  ipv4          44.1.0.0"));
+}
+
+#[test]
+fn test_decompress() {
+    use tempfile::NamedTempFile;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(&[b'x'; 1000])
+        .expect("Failed to compress test data");
+    let compressed = encoder.finish().expect("Failed to compress test data");
+
+    let mut data = NamedTempFile::new().expect("Failed to create temp file");
+    data.write_all(&compressed)
+        .expect("Failed to write test data");
+    data.flush().expect("Failed to flush");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg("--decompress")
+        .arg("--skip")
+        .arg("992")
+        .arg(data.path())
+        .output()
+        .expect("Failed to execute hexler");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().next().unwrap().ends_with("gzip"));
+    assert!(stdout.contains("78 78 78 78 78 78 78 78  "));
+    assert!(stdout.ends_with("decompressed from gzip, 1000 bytes\n"));
+
+    // Truncated data can't be decompressed
+    let data = NamedTempFile::new().expect("Failed to create temp file");
+    std::fs::write(data.path(), &compressed[..compressed.len() / 2])
+        .expect("Failed to write test data");
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg("--decompress")
+        .arg(data.path())
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid gzip data"));
+}
+
+#[test]
+fn test_archive_member() {
+    use tempfile::NamedTempFile;
+
+    // A tar archive with a single file
+    let content = b"root:x:0:0:root:/root:/bin/sh\n";
+    let mut header = vec![0u8; 512];
+    header[..10].copy_from_slice(b"etc/passwd");
+    header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+    header[136..148].copy_from_slice(b"14524220400\0");
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[148..156].copy_from_slice(b"        ");
+    let sum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+
+    let mut archive = NamedTempFile::new().expect("Failed to create temp file");
+    archive.write_all(&header).expect("Failed to write archive");
+    archive.write_all(content).expect("Failed to write archive");
+    archive
+        .write_all(&[0u8; 512 * 3 - 30])
+        .expect("Failed to write archive");
+    archive.flush().expect("Failed to flush");
+
+    let hexler = |path: &str| {
+        Command::new(env!("CARGO_BIN_EXE_hexler"))
+            .arg("--stdout")
+            .arg("--num-bytes-per-line")
+            .arg("16")
+            .arg(path)
+            .output()
+            .expect("Failed to execute hexler")
+    };
+    let path = archive.path().display().to_string();
+
+    let output = hexler(&path);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("tar archive, 1 file\n"));
+    assert!(stdout.contains("│     30 bytes  "));
+    assert!(stdout.contains("  stored    etc/passwd\n"));
+
+    let output = hexler(&format!("{}:etc/passwd", path));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(":etc/passwd\x1b[0m   30 bytes   "));
+    assert!(stdout.contains("72 6f 6f 74"));
+
+    let output = hexler(&format!("{}:etc/shadow", path));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no member etc/shadow in "));
+}
+
+#[test]
+fn test_edit_needs_a_file() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--edit")
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hexler");
+    drop(child.stdin.take());
+    let output = child.wait_with_output().expect("Failed to wait for hexler");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("can't edit stdin, --edit needs a regular file"));
+}
+
+#[test]
+fn test_patch() {
+    use tempfile::NamedTempFile;
+
+    let mut file = NamedTempFile::new().expect("Failed to create temp file");
+    file.write_all(&[0x11u8; 0x40])
+        .expect("Failed to write file");
+    file.flush().expect("Failed to flush");
+
+    let mut patches = NamedTempFile::new().expect("Failed to create temp file");
+    patches
+        .write_all(b"# version\n0x3e: 01 02\n")
+        .expect("Failed to write patches");
+    patches.flush().expect("Failed to flush");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("patch")
+        .arg(file.path())
+        .arg("--at")
+        .arg("0x4")
+        .arg("--bytes")
+        .arg("de ad be ef")
+        .arg("--fill")
+        .arg("0")
+        .arg("--range")
+        .arg("0x20..0x30")
+        .arg("--apply")
+        .arg(patches.path())
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("   before\n"));
+    assert!(stdout.contains("   after, 22 bytes patched\n"));
+    assert!(stdout.contains("de ad be ef"));
+
+    let data = std::fs::read(file.path()).expect("Failed to read file");
+    assert_eq!(&data[..8], b"\x11\x11\x11\x11\xde\xad\xbe\xef");
+    assert_eq!(&data[0x20..0x30], &[0u8; 16]);
+    assert_eq!(&data[0x3c..], b"\x11\x11\x01\x02");
+
+    // Nothing is written past the end without --extend
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("patch")
+        .arg(file.path())
+        .arg("--at")
+        .arg("0x3f")
+        .arg("--bytes")
+        .arg("ff ff")
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--extend allows this"));
+    assert_eq!(
+        std::fs::read(file.path()).expect("Failed to read file"),
+        data
+    );
+
+    // A huge fill is refused before its bytes are made
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("patch")
+        .arg(file.path())
+        .arg("--fill")
+        .arg("0")
+        .arg("--range")
+        .arg("0..0x100000000000")
+        .output()
+        .expect("Failed to execute hexler");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--extend allows this"));
+
+    // Patches must end at an offset that fits in 64 bits
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("patch")
+        .arg(file.path())
+        .arg("--at")
+        .arg("0xffffffffffffffff")
+        .arg("--bytes")
+        .arg("00")
+        .output()
+        .expect("Failed to execute hexler");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid patch"));
+    assert_eq!(
+        std::fs::read(file.path()).expect("Failed to read file"),
+        data
+    );
+}
+
+#[test]
+fn test_mkpatch_applypatch() {
+    use tempfile::NamedTempFile;
+
+    let old_data: Vec<u8> = (0..=255u8).collect();
+    let mut new_data = old_data.clone();
+    new_data[0x40..0x44].copy_from_slice(b"\xde\xad\xbe\xef");
+    new_data.truncate(0xf0);
+
+    let mut old = NamedTempFile::new().expect("Failed to create temp file");
+    old.write_all(&old_data).expect("Failed to write file");
+    old.flush().expect("Failed to flush");
+    let mut new = NamedTempFile::new().expect("Failed to create temp file");
+    new.write_all(&new_data).expect("Failed to write file");
+    new.flush().expect("Failed to flush");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("mkpatch")
+        .arg(old.path())
+        .arg(new.path())
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(output.status.success());
+    let patch_text = String::from_utf8(output.stdout).expect("Patch is not UTF-8");
+    assert!(patch_text.contains("\n@ 0x40, 4 bytes\n-00000040 │ 40 41 42 43 "));
+    assert!(patch_text.contains("\n+00000040 │ de ad be ef "));
+    assert!(patch_text.contains("\n@ 0xf0, 16 bytes -> 0 bytes\n"));
+
+    let mut patch = NamedTempFile::new().expect("Failed to create temp file");
+    patch
+        .write_all(patch_text.as_bytes())
+        .expect("Failed to write patch");
+    patch.flush().expect("Failed to flush");
+
+    let applypatch = || {
+        Command::new(env!("CARGO_BIN_EXE_hexler"))
+            .arg("applypatch")
+            .arg(old.path())
+            .arg(patch.path())
+            .arg("--num-bytes-per-line")
+            .arg("16")
+            .output()
+            .expect("Failed to execute hexler")
+    };
+    let output = applypatch();
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read(old.path()).expect("Failed to read file"),
+        new_data
+    );
+
+    // The old bytes are verified, a patch is only applied once
+    let output = applypatch();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("the bytes at 0x40 don't match the old bytes of the patch"));
+}
+
+#[test]
+fn test_hash() {
+    use tempfile::NamedTempFile;
+
+    let mut file = NamedTempFile::new().expect("Failed to create temp file");
+    file.write_all(b"0123456789hello world")
+        .expect("Failed to write file");
+    file.flush().expect("Failed to flush");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg("--skip")
+        .arg("10")
+        .arg("--hash")
+        .arg("crc32,md5")
+        .arg("--hash-per-block")
+        .arg("8")
+        .arg(file.path())
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Only the dumped bytes "hello world" are hashed
+    assert!(stdout.contains("\ncrc32 0d4a1185   md5 5eb63bbbe01eeed093cb22bb8f5acdc3\n"));
+    assert!(stdout.contains("\nblock 0x0000000a..0x00000012   crc32 "));
+    assert!(stdout.contains("\nblock 0x00000012..0x00000015   crc32 "));
+}
+
+#[test]
+fn test_annotate_sidecar() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let file = dir.path().join("firmware.bin");
+    std::fs::write(&file, [0x5au8; 0x40]).expect("Failed to write file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("annotate")
+        .arg(&file)
+        .arg("--range")
+        .arg("0x10..0x20")
+        .arg("--label")
+        .arg("header")
+        .arg("--note")
+        .arg("checksum at the end")
+        .arg("--color")
+        .arg("blue")
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(output.status.success());
+    let sidecar = std::fs::read_to_string(dir.path().join("firmware.bin.hexler.toml"))
+        .expect("Failed to read sidecar");
+    assert!(sidecar.contains("range = \"0x10..0x20\"\nlabel = \"header\"\n"));
+
+    // The sidecar is loaded automatically
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg(&file)
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("header: checksum at the end"));
+    assert!(stdout.contains("\x1b[48;5;17m"));
+}
+
+#[test]
+fn test_mark() {
+    use tempfile::NamedTempFile;
+
+    let mut file = NamedTempFile::new().expect("Failed to create temp file");
+    file.write_all(b"0123456789abcdef0123456789abcdef")
+        .expect("Failed to write file");
+    file.flush().expect("Failed to flush");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg("--mark")
+        .arg("0x4..0x8:red")
+        .arg("--mark")
+        .arg("0x14..0x16")
+        .arg(file.path())
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Both panels of the marked bytes get the background
+    assert!(stdout.contains("\x1b[48;5;52m34 35 36 37\x1b[0m "));
+    assert!(stdout.contains("\x1b[48;5;52m4567\x1b[0m"));
+    assert!(stdout.contains("\x1b[48;5;22m34 35\x1b[0m "));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--mark")
+        .arg("0x4..0x8:pink")
+        .arg(file.path())
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown color pink"));
+}
+
+#[test]
+fn test_pid_errors() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--pid")
+        .arg("4000000000")
+        .arg("--maps-region")
+        .arg("stack")
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no process with id 4000000000"));
+
+    // --pid needs to know where to start
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--pid")
+        .arg("1")
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--maps-region"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_special_files() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg("/dev/null")
+        .output()
+        .expect("Failed to execute hexler");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("character device"));
+
+    // A FIFO is read once, --skip reads over the start
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let fifo = dir.path().join("fifo");
+    let status = Command::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .expect("Failed to execute mkfifo");
+    assert!(status.success());
+    let writer = {
+        let fifo = fifo.clone();
+        std::thread::spawn(move || std::fs::write(fifo, b"0123456789abcdef0123456789abcdef"))
+    };
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hexler"))
+        .arg("--stdout")
+        .arg("--num-bytes-per-line")
+        .arg("16")
+        .arg("--skip")
+        .arg("4")
+        .arg(&fifo)
+        .output()
+        .expect("Failed to execute hexler");
+    writer.join().unwrap().expect("Failed to write fifo");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("FIFO"));
+    assert!(stdout.contains("456789abcdef0123"));
+}
@@ -0,0 +1,60 @@
+//! Throughput regression tests for `dump`.
+//!
+//! These are ignored by default because timings are only meaningful in release builds:
+//! `cargo test --release --test throughput_test -- --ignored`
+//!
+//! The minimum throughput in MiB/s can be adjusted with `HEXLER_MIN_THROUGHPUT`.
+
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use hexler::line_writer::LineWriter;
+use std::time::Instant;
+
+const LEN: usize = 16 * 1024 * 1024;
+
+fn min_throughput() -> f64 {
+    std::env::var("HEXLER_MIN_THROUGHPUT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(20.0)
+}
+
+/// Runs `dump` a few times and returns the best throughput in MiB/s.
+fn measure(data: &[u8], bytes_per_line: usize) -> f64 {
+    let mut line_writer = LineWriter::new_bytes(bytes_per_line).unwrap();
+    let mut best = f64::MAX;
+    for _ in 0..3 {
+        let start = Instant::now();
+        hexler::dump(
+            "throughput",
+            std::io::Cursor::new(data),
+            &mut line_writer,
+            std::io::sink(),
+        )
+        .unwrap();
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    data.len() as f64 / (1024.0 * 1024.0) / best
+}
+
+fn check(name: &str, data: &[u8]) {
+    for bytes_per_line in [16, 32] {
+        let throughput = measure(data, bytes_per_line);
+        println!("{name} {bytes_per_line}: {throughput:.1} MiB/s");
+        assert!(
+            throughput >= min_throughput(),
+            "{name} with {bytes_per_line} bytes per line: {throughput:.1} MiB/s is below {} MiB/s",
+            min_throughput()
+        );
+    }
+}
+
+/// Uses the inputs of the benchmarks, so the tests check what `cargo bench` measures.
+#[test]
+#[ignore]
+fn test_throughput() {
+    for (name, data) in common::inputs(LEN) {
+        check(name, &data);
+    }
+}